@@ -11,6 +11,19 @@ window.initializeActionItemSearch = function () {
 
   let activeMenu = null;
 
+  const buildSearchParams = (input, query) => {
+    const params = new URLSearchParams({ q: query });
+    table
+      .querySelectorAll("tr:not(.template) .js-action-item-input")
+      .forEach((other) => {
+        const value = other.value.trim();
+        if (other !== input && value) {
+          params.append("exclude", value);
+        }
+      });
+    return params;
+  };
+
   const closeMenu = (menu) => {
     if (!menu) {
       return;
@@ -112,7 +125,7 @@ window.initializeActionItemSearch = function () {
 
       const token = ++requestToken;
       try {
-        const response = await fetch(`${searchUrl}?q=${encodeURIComponent(query)}`);
+        const response = await fetch(`${searchUrl}?${buildSearchParams(input, query)}`);
         if (!response.ok) {
           closeMenu(menu);
           return;
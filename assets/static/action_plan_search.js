@@ -0,0 +1,163 @@
+window.initializePlanNameSuggestions = function () {
+  const container = document.querySelector(".js-plan-name-suggestions");
+  const input = document.getElementById("name");
+  if (!container || !input) {
+    return;
+  }
+
+  const searchUrl = container.getAttribute("data-search-url");
+  const planUrl = container.getAttribute("data-plan-url");
+  const excludeId = container.getAttribute("data-exclude-id");
+  const list = container.querySelector(".js-plan-name-suggestion-list");
+  let debounceId = null;
+  let requestToken = 0;
+
+  const render = (plans) => {
+    list.innerHTML = "";
+    plans.forEach((plan) => {
+      const item = document.createElement("li");
+      const link = document.createElement("a");
+      link.href = `${planUrl}/${plan.id}`;
+      link.target = "_blank";
+      link.textContent = plan.name;
+      item.appendChild(link);
+      list.appendChild(item);
+    });
+    container.hidden = plans.length === 0;
+  };
+
+  const search = async () => {
+    const query = input.value.trim();
+    if (query.length < 2) {
+      render([]);
+      return;
+    }
+
+    const params = new URLSearchParams({ q: query });
+    if (excludeId) {
+      params.append("exclude_id", excludeId);
+    }
+    const token = ++requestToken;
+    try {
+      const response = await fetch(`${searchUrl}?${params}`);
+      if (!response.ok) {
+        return;
+      }
+      const plans = await response.json();
+      if (token === requestToken) {
+        render(Array.isArray(plans) ? plans : []);
+      }
+    } catch (error) {
+      render([]);
+    }
+  };
+
+  input.addEventListener("input", function () {
+    if (debounceId) {
+      window.clearTimeout(debounceId);
+    }
+    debounceId = window.setTimeout(search, 200);
+  });
+};
+
+window.initializePlanItemCopy = function () {
+  const panel = document.querySelector(".js-plan-item-copy");
+  if (!panel) {
+    return;
+  }
+
+  const searchUrl = panel.getAttribute("data-search-url");
+  const excludeId = panel.getAttribute("data-exclude-id");
+  const input = panel.querySelector(".js-plan-item-copy-input");
+  const results = panel.querySelector(".js-plan-item-copy-results");
+  let debounceId = null;
+  let requestToken = 0;
+
+  const renderPlan = (plan) => {
+    const block = document.createElement("div");
+    block.className = "item-copy-plan";
+
+    const title = document.createElement("strong");
+    title.textContent = plan.name;
+    block.appendChild(title);
+
+    const checkboxes = plan.items.map((name) => {
+      const label = document.createElement("label");
+      label.className = "item-copy-option";
+      const checkbox = document.createElement("input");
+      checkbox.type = "checkbox";
+      checkbox.checked = true;
+      checkbox.value = name;
+      label.appendChild(checkbox);
+      label.appendChild(document.createTextNode(` ${name}`));
+      block.appendChild(label);
+      return checkbox;
+    });
+
+    const button = document.createElement("button");
+    button.type = "button";
+    button.className = "btn";
+    button.textContent = "Add Selected Items";
+    button.addEventListener("click", function () {
+      checkboxes
+        .filter((checkbox) => checkbox.checked)
+        .forEach((checkbox) => window.addActionPlanItemRow(checkbox.value));
+    });
+    block.appendChild(button);
+    return block;
+  };
+
+  const render = (plans) => {
+    results.innerHTML = "";
+    if (!plans.length && input.value.trim()) {
+      const empty = document.createElement("p");
+      empty.className = "muted";
+      empty.textContent = "No other plan matches.";
+      results.appendChild(empty);
+      return;
+    }
+    plans
+      .filter((plan) => plan.items.length)
+      .forEach((plan) => results.appendChild(renderPlan(plan)));
+  };
+
+  const search = async () => {
+    const query = input.value.trim();
+    if (!query) {
+      render([]);
+      return;
+    }
+
+    const params = new URLSearchParams({ q: query });
+    if (excludeId) {
+      params.append("exclude_id", excludeId);
+    }
+    const token = ++requestToken;
+    try {
+      const response = await fetch(`${searchUrl}?${params}`);
+      if (!response.ok) {
+        return;
+      }
+      const plans = await response.json();
+      if (token === requestToken) {
+        render(Array.isArray(plans) ? plans : []);
+      }
+    } catch (error) {
+      render([]);
+    }
+  };
+
+  input.addEventListener("input", function () {
+    if (debounceId) {
+      window.clearTimeout(debounceId);
+    }
+    debounceId = window.setTimeout(search, 250);
+  });
+  // Enter would otherwise submit the plan form.
+  input.addEventListener("keydown", function (event) {
+    if (event.key === "Enter") {
+      event.preventDefault();
+      search();
+    }
+  });
+};
@@ -10,6 +10,59 @@ window.onload = function () {
   if (typeof window.initializeActionItemSearch === "function") {
     bindActionItemSearchInput = window.initializeActionItemSearch() || bindActionItemSearchInput;
   }
+  if (typeof window.initializePlanNameSuggestions === "function") {
+    window.initializePlanNameSuggestions();
+  }
+  if (typeof window.initializePlanItemCopy === "function") {
+    window.initializePlanItemCopy();
+  }
+
+  const executionPacing = document.querySelector(".js-execution-pacing");
+  const estimatedRemaining = document.querySelector(".js-estimated-remaining");
+
+  const formatDuration = (seconds) => {
+    const minutes = Math.floor(Math.abs(seconds) / 60);
+    return minutes < 60 ? `${minutes} min` : `${Math.floor(minutes / 60)} h ${minutes % 60} min`;
+  };
+
+  // Compares the estimates of the items checked off so far with the time since the start.
+  const updateExecutionPacing = () => {
+    if (!executionPacing) {
+      return;
+    }
+
+    const started = Number(executionPacing.dataset.started);
+    const elapsed = Math.max(0, Math.floor(Date.now() / 1000) - started);
+    let expected = 0;
+    document.querySelectorAll(".execution-item-toggle:checked").forEach((checkbox) => {
+      const row = checkbox.closest("tr");
+      expected += Number((row && row.dataset.estimatedSeconds) || 0);
+    });
+
+    const difference = elapsed - expected;
+    let pace = "on schedule";
+    if (difference >= 60) {
+      pace = `${formatDuration(difference)} behind`;
+    } else if (difference <= -60) {
+      pace = `${formatDuration(difference)} ahead`;
+    }
+    executionPacing.textContent =
+      `Expected so far: ${formatDuration(expected)} · Elapsed: ${formatDuration(elapsed)} · ${pace}`;
+    executionPacing.hidden = false;
+
+    if (estimatedRemaining) {
+      // Skipped and failed items and items whose condition is not met are not left to do.
+      let remaining = 0;
+      document.querySelectorAll(".execution-item-toggle:not(:checked)").forEach((checkbox) => {
+        const row = checkbox.closest("tr");
+        if (!row || row.hidden || checkbox.dataset.skipped || checkbox.dataset.failed) {
+          return;
+        }
+        remaining += Number(row.dataset.estimatedSeconds || 0);
+      });
+      estimatedRemaining.textContent = formatDuration(remaining);
+    }
+  };
 
   const updateCompleteExecutionLinkState = () => {
     if (!completeExecutionLink) {
@@ -17,9 +70,52 @@ window.onload = function () {
     }
 
     const checkboxes = Array.from(document.querySelectorAll(".execution-item-toggle"));
-    const allChecked = checkboxes.length > 0 && checkboxes.every((checkbox) => checkbox.checked);
-    completeExecutionLink.classList.toggle("is-disabled", !allChecked);
-    completeExecutionLink.setAttribute("aria-disabled", allChecked ? "false" : "true");
+    // Blocked, skipped and failed items and items whose condition is not met do not hold up
+    // completing the execution.
+    const allChecked =
+      checkboxes.length > 0 &&
+      checkboxes.every(
+        (checkbox) =>
+          checkbox.checked ||
+          checkbox.dataset.blocked === "true" ||
+          checkbox.dataset.skipped === "true" ||
+          checkbox.dataset.failed === "true" ||
+          checkbox.closest("tr").hidden,
+      );
+    const canComplete = allChecked && completeExecutionLink.dataset.noteMissing !== "true";
+    completeExecutionLink.classList.toggle("is-disabled", !canComplete);
+    completeExecutionLink.setAttribute("aria-disabled", canComplete ? "false" : "true");
+  };
+
+  // Shows the items whose earlier step was answered or marked failed as their condition asks,
+  // like the server does when rendering. Items only depend on earlier ones, so one pass in order is enough.
+  const updateConditionalItems = () => {
+    const rows = Array.from(document.querySelectorAll("tr[data-order-index]")).sort(
+      (a, b) => Number(a.dataset.orderIndex) - Number(b.dataset.orderIndex),
+    );
+    const satisfied = new Map();
+    rows.forEach((row) => {
+      let applies = true;
+      if (row.dataset.conditionIndex !== undefined) {
+        const other = satisfied.get(row.dataset.conditionIndex);
+        const expected = (row.dataset.conditionValue || "").trim().toLowerCase();
+        if (row.dataset.conditionFailed === "true") {
+          applies = Boolean(other) && other.failed;
+        } else {
+          applies = Boolean(other) && other.finished && (!expected || other.value === expected);
+        }
+        row.hidden = !applies;
+      }
+      if (applies) {
+        const checkbox = row.querySelector(".execution-item-toggle");
+        const valueInput = row.querySelector(".js-item-value");
+        satisfied.set(row.dataset.orderIndex, {
+          finished: Boolean(checkbox && checkbox.checked),
+          failed: Boolean(checkbox && checkbox.dataset.failed === "true"),
+          value: valueInput ? valueInput.value.trim().toLowerCase() : "",
+        });
+      }
+    });
   };
 
   const bindRemoveButton = (button) => {
@@ -35,6 +131,34 @@ window.onload = function () {
     });
   };
 
+  const addRow = (table, value) => {
+    const tableBody = table.tBodies[0] || table;
+    const templateRow = tableBody.querySelector(".template");
+    if (!templateRow) {
+      return null;
+    }
+
+    const newRow = templateRow.cloneNode(true);
+    newRow.classList.remove("template");
+    newRow.querySelectorAll(".action-search-menu").forEach((menu) => menu.remove());
+    newRow.querySelectorAll("input, select, textarea").forEach((input) => {
+      input.removeAttribute("form");
+      delete input.dataset.searchBound;
+    });
+    tableBody.appendChild(newRow);
+    bindRemoveButton(newRow.querySelector(".remove"));
+    const actionItemInput = newRow.querySelector(".js-action-item-input");
+    if (actionItemInput) {
+      bindActionItemSearchInput(actionItemInput);
+      if (value === undefined) {
+        actionItemInput.focus();
+      } else {
+        actionItemInput.value = value;
+      }
+    }
+    return newRow;
+  };
+
   const initializeDynamicRows = () => {
     document.querySelectorAll(".add-row").forEach((button) => {
       button.addEventListener("click", function () {
@@ -43,38 +167,40 @@ window.onload = function () {
         if (!table) {
           return;
         }
-
-        const tableBody = table.tBodies[0] || table;
-        const templateRow = tableBody.querySelector(".template");
-        if (!templateRow) {
-          return;
-        }
-
-        const newRow = templateRow.cloneNode(true);
-        newRow.classList.remove("template");
-        newRow.querySelectorAll(".action-search-menu").forEach((menu) => menu.remove());
-        newRow.querySelectorAll("input").forEach((input) => {
-          input.removeAttribute("form");
-          delete input.dataset.searchBound;
-        });
-        tableBody.appendChild(newRow);
-        bindRemoveButton(newRow.querySelector(".remove"));
-        const actionItemInput = newRow.querySelector(".js-action-item-input");
-        if (actionItemInput) {
-          bindActionItemSearchInput(actionItemInput);
-          actionItemInput.focus();
-        }
+        addRow(table);
       });
     });
 
     document.querySelectorAll(".remove").forEach(bindRemoveButton);
   };
 
+  // Lets the item copy panel append filled-in rows to the plan editor.
+  window.addActionPlanItemRow = (value) => {
+    const table = document.getElementById("items");
+    if (table) {
+      addRow(table, value);
+    }
+  };
+
+  const setOutOfSpec = (row, outOfSpec) => {
+    const marker = row ? row.querySelector(".js-out-of-spec") : null;
+    if (marker) {
+      marker.hidden = !outOfSpec;
+    }
+  };
+
   const initializeExecutionItemToggles = () => {
     document.querySelectorAll(".execution-item-toggle").forEach((checkbox) => {
       checkbox.addEventListener("change", async function () {
         const previousChecked = !this.checked;
         const url = this.getAttribute("data-url");
+        const row = this.closest("tr");
+        const valueInput = row ? row.querySelector(".js-item-value") : null;
+        if (this.checked && valueInput && !valueInput.value.trim()) {
+          this.checked = false;
+          valueInput.focus();
+          return;
+        }
         this.disabled = true;
 
         try {
@@ -83,21 +209,36 @@ window.onload = function () {
             headers: {
               "Content-Type": "application/json",
             },
-            body: JSON.stringify({ finished: this.checked }),
+            body: JSON.stringify(
+              valueInput
+                ? { finished: this.checked, value: valueInput.value }
+                : { finished: this.checked },
+            ),
           });
 
           if (!response.ok) {
             this.checked = previousChecked;
-            alert("Could not update item status.");
+            alert(
+              response.status === 409 && this.dataset.conflictMessage
+                ? this.dataset.conflictMessage
+                : "Could not update item status.",
+            );
             return;
           }
 
           const payload = await response.json();
-          const row = this.closest("tr");
+          if (valueInput) {
+            // Unchecking keeps the entered value around for correcting it.
+            if (payload.value) {
+              valueInput.value = payload.value;
+            }
+            valueInput.disabled = Boolean(payload.finished_display);
+          }
+          setOutOfSpec(row, payload.out_of_spec);
           const finishedAt = row ? row.querySelector(".finished-at") : null;
           if (finishedAt) {
             finishedAt.textContent = payload.finished_display
-              ? `Finished: ${payload.finished_display}`
+              ? `Finished: ${payload.finished_display}${payload.finished_by_name ? ` by ${payload.finished_by_name}` : ""}`
               : "";
           }
         } catch (error) {
@@ -105,7 +246,349 @@ window.onload = function () {
           alert("Could not update item status.");
         } finally {
           this.disabled = false;
+          updateConditionalItems();
           updateCompleteExecutionLinkState();
+          updateExecutionPacing();
+        }
+      });
+    });
+  };
+
+  // Shows a note and updates the label of its edit button.
+  const showItemNote = (container, note) => {
+    const text = container.querySelector(".js-item-note-text");
+    const editButton = container.querySelector(".js-item-note-edit");
+    const input = container.querySelector(".js-item-note-input");
+    text.textContent = note || "";
+    text.hidden = !note;
+    if (editButton) {
+      editButton.textContent = note ? "Edit Note" : "Add Note";
+    }
+    if (input) {
+      input.value = note || "";
+    }
+  };
+
+  const initializeItemNotes = () => {
+    document.querySelectorAll(".js-item-note").forEach((container) => {
+      const editButton = container.querySelector(".js-item-note-edit");
+      const editor = container.querySelector(".js-item-note-editor");
+      if (!editButton || !editor) {
+        return;
+      }
+      const input = editor.querySelector(".js-item-note-input");
+      const saveButton = editor.querySelector(".js-item-note-save");
+      const cancelButton = editor.querySelector(".js-item-note-cancel");
+      const text = container.querySelector(".js-item-note-text");
+
+      const closeEditor = () => {
+        editor.hidden = true;
+        editButton.hidden = false;
+      };
+
+      editButton.addEventListener("click", () => {
+        input.value = text.textContent;
+        editor.hidden = false;
+        editButton.hidden = true;
+        input.focus();
+      });
+      cancelButton.addEventListener("click", closeEditor);
+      saveButton.addEventListener("click", async () => {
+        saveButton.disabled = true;
+        try {
+          const response = await fetch(container.getAttribute("data-url"), {
+            method: "POST",
+            headers: {
+              "Content-Type": "application/json",
+            },
+            body: JSON.stringify({ note: input.value }),
+          });
+          if (!response.ok) {
+            alert("Could not save the note.");
+            return;
+          }
+          const payload = await response.json();
+          showItemNote(container, payload.note);
+          closeEditor();
+        } catch (error) {
+          alert("Could not save the note.");
+        } finally {
+          saveButton.disabled = false;
+        }
+      });
+    });
+  };
+
+  const initializeExecutionLiveUpdates = () => {
+    const table = document.querySelector("[data-events-url]");
+    if (!table) {
+      return;
+    }
+
+    const viewersLabel = document.querySelector(".js-execution-viewers");
+    let ownConnectionId = null;
+    let socket = null;
+    let focusedItemId = null;
+
+    const applyItemFinished = (payload) => {
+      const checkbox = table.querySelector(
+        `.execution-item-toggle[data-item-id="${payload.item_id}"]`
+      );
+      if (!checkbox || checkbox.disabled) {
+        return;
+      }
+
+      checkbox.checked = payload.is_finished;
+      const row = checkbox.closest("tr");
+      const valueInput = row ? row.querySelector(".js-item-value") : null;
+      if (valueInput) {
+        if (payload.value) {
+          valueInput.value = payload.value;
+        }
+        valueInput.disabled = payload.is_finished;
+      }
+      setOutOfSpec(row, payload.out_of_spec);
+      const finishedAt = row ? row.querySelector(".finished-at") : null;
+      if (finishedAt) {
+        finishedAt.textContent = payload.finished_display
+          ? `Finished: ${payload.finished_display}${payload.finished_by_name ? ` by ${payload.finished_by_name}` : ""}`
+          : "";
+      }
+      updateConditionalItems();
+      updateCompleteExecutionLinkState();
+      updateExecutionPacing();
+    };
+
+    const applyItemNote = (payload) => {
+      const row = table.querySelector(`tr[data-item-id="${payload.item_id}"]`);
+      const container = row ? row.querySelector(".js-item-note") : null;
+      const editor = container ? container.querySelector(".js-item-note-editor") : null;
+      // Someone editing the same note keeps their draft.
+      if (!container || (editor && !editor.hidden)) {
+        return;
+      }
+      showItemNote(container, payload.note);
+    };
+
+    const applyPresence = (payload) => {
+      const others = (payload.viewers || []).filter(
+        (viewer) => viewer.connection_id !== ownConnectionId
+      );
+
+      if (viewersLabel) {
+        const names = Array.from(new Set(others.map((viewer) => viewer.user_name)));
+        viewersLabel.textContent = names.length ? `Also viewing: ${names.join(", ")}` : "";
+        viewersLabel.hidden = names.length === 0;
+      }
+
+      table.querySelectorAll(".js-item-viewers").forEach((badges) => {
+        badges.innerHTML = "";
+      });
+      others.forEach((viewer) => {
+        if (!viewer.focused_item_id) {
+          return;
+        }
+        const row = table.querySelector(`tr[data-item-id="${viewer.focused_item_id}"]`);
+        const badges = row ? row.querySelector(".js-item-viewers") : null;
+        if (!badges) {
+          return;
+        }
+        const badge = document.createElement("span");
+        badge.className = "viewer-badge";
+        badge.textContent = viewer.user_name;
+        badges.appendChild(badge);
+      });
+    };
+
+    const applyEvent = (payload) => {
+      switch (payload.type) {
+        case "welcome":
+          ownConnectionId = payload.connection_id;
+          break;
+        case "item_finished":
+          applyItemFinished(payload);
+          break;
+        case "item_note":
+          applyItemNote(payload);
+          break;
+        case "presence":
+          applyPresence(payload);
+          break;
+        case "completed":
+        case "reopened":
+          window.location.reload();
+          break;
+        case "deleted":
+          window.location.assign(table.getAttribute("data-executions-url"));
+          break;
+        default:
+          break;
+      }
+    };
+
+    const parseEvent = (data) => {
+      try {
+        applyEvent(JSON.parse(data));
+      } catch (error) {
+        // Ignore malformed messages.
+      }
+    };
+
+    const sendFocus = (itemId) => {
+      if (itemId === focusedItemId || !socket || socket.readyState !== WebSocket.OPEN) {
+        return;
+      }
+      focusedItemId = itemId;
+      socket.send(JSON.stringify({ type: "focus", item_id: itemId }));
+    };
+
+    if (typeof window.WebSocket === "function") {
+      const wsUrl = new URL(table.getAttribute("data-ws-url"), window.location.href);
+      wsUrl.protocol = wsUrl.protocol === "https:" ? "wss:" : "ws:";
+      socket = new WebSocket(wsUrl);
+      socket.addEventListener("message", (event) => parseEvent(event.data));
+      window.addEventListener("beforeunload", () => socket.close());
+
+      table.querySelectorAll("tr[data-item-id]").forEach((row) => {
+        const itemId = row.getAttribute("data-item-id");
+        row.addEventListener("mouseenter", () => sendFocus(itemId));
+        row.addEventListener("focusin", () => sendFocus(itemId));
+      });
+      table.addEventListener("mouseleave", () => sendFocus(null));
+    } else if (typeof window.EventSource === "function") {
+      const source = new EventSource(table.getAttribute("data-events-url"));
+      source.addEventListener("message", (event) => parseEvent(event.data));
+      window.addEventListener("beforeunload", () => source.close());
+    }
+  };
+
+  // Counts down locally between the server's reports, which also pick up activity in other tabs.
+  const initializeAutoLogout = () => {
+    const banner = document.querySelector(".js-auto-logout");
+    if (!banner) {
+      return;
+    }
+
+    const limit = Number(banner.dataset.seconds);
+    const remainingLabel = banner.querySelector(".js-auto-logout-remaining");
+    const keepAliveButton = banner.querySelector("button");
+    let remaining = limit;
+
+    const logOut = () => {
+      window.location.href = banner.dataset.loginUrl;
+    };
+    const render = () => {
+      remainingLabel.textContent = Math.max(remaining, 0);
+      banner.hidden = remaining === null || remaining > 60;
+    };
+
+    window.setInterval(() => {
+      if (remaining === null) {
+        return;
+      }
+      remaining -= 1;
+      if (remaining <= 0) {
+        logOut();
+        return;
+      }
+      render();
+    }, 1000);
+
+    keepAliveButton.addEventListener("click", async () => {
+      const response = await fetch(banner.dataset.keepAliveUrl, { method: "POST" });
+      if (!response.ok || response.redirected) {
+        logOut();
+        return;
+      }
+      remaining = limit;
+      render();
+    });
+
+    if (typeof window.EventSource !== "function") {
+      return;
+    }
+    const source = new EventSource(banner.dataset.eventsUrl);
+    source.addEventListener("message", (event) => {
+      let payload;
+      try {
+        payload = JSON.parse(event.data);
+      } catch (error) {
+        return;
+      }
+      if (payload.logged_out) {
+        source.close();
+        logOut();
+        return;
+      }
+      remaining = payload.remaining_seconds;
+      render();
+    });
+    window.addEventListener("beforeunload", () => source.close());
+  };
+
+  const initializeExecutionCounters = () => {
+    const counters = document.querySelector(".js-execution-counts");
+    if (!counters || typeof window.EventSource !== "function") {
+      return;
+    }
+
+    const openCount = counters.querySelector(".js-open-count");
+    const finishedCount = counters.querySelector(".js-finished-count");
+    const changedLink = counters.querySelector(".js-executions-changed");
+    const source = new EventSource(counters.getAttribute("data-events-url"));
+    source.addEventListener("message", (event) => {
+      let payload;
+      try {
+        payload = JSON.parse(event.data);
+      } catch (error) {
+        return;
+      }
+      openCount.textContent = payload.open_count;
+      finishedCount.textContent = payload.finished_count;
+      changedLink.hidden = false;
+    });
+    window.addEventListener("beforeunload", () => source.close());
+  };
+
+  const initializeSelectOnClick = () => {
+    document.querySelectorAll(".js-select-on-click").forEach((input) => {
+      input.addEventListener("click", () => input.select());
+    });
+  };
+
+  // Copies an export of the checklist. HTML is also put on the clipboard as rich text, so it
+  // pastes as a table into editors that support it.
+  const initializeCopyExport = () => {
+    const status = document.querySelector(".js-copy-export-status");
+    const showStatus = (message) => {
+      if (status) {
+        status.textContent = message;
+        status.hidden = false;
+      }
+    };
+
+    document.querySelectorAll(".js-copy-export").forEach((button) => {
+      button.addEventListener("click", async () => {
+        try {
+          const response = await fetch(button.dataset.url, { credentials: "same-origin" });
+          if (!response.ok) {
+            throw new Error(`HTTP ${response.status}`);
+          }
+          const text = await response.text();
+          if (button.dataset.format === "html" && typeof ClipboardItem === "function") {
+            await navigator.clipboard.write([
+              new ClipboardItem({
+                "text/html": new Blob([text], { type: "text/html" }),
+                "text/plain": new Blob([text], { type: "text/plain" }),
+              }),
+            ]);
+          } else {
+            await navigator.clipboard.writeText(text);
+          }
+          showStatus(`Copied as ${button.textContent}.`);
+        } catch (error) {
+          // Without clipboard access (e.g. plain HTTP), open the export to copy it by hand.
+          window.open(button.dataset.url, "_blank");
         }
       });
     });
@@ -125,7 +608,23 @@ window.onload = function () {
     updateCompleteExecutionLinkState();
   };
 
+  const initializeExecutionPacing = () => {
+    if (!executionPacing) {
+      return;
+    }
+
+    updateExecutionPacing();
+    window.setInterval(updateExecutionPacing, 30000);
+  };
+
   initializeDynamicRows();
   initializeExecutionItemToggles();
+  initializeItemNotes();
+  initializeExecutionPacing();
+  initializeExecutionLiveUpdates();
+  initializeExecutionCounters();
+  initializeAutoLogout();
+  initializeSelectOnClick();
+  initializeCopyExport();
   initializeCompletionLink();
 };
@@ -0,0 +1,3 @@
+ALTER TABLE users ADD COLUMN calendar_token BLOB;
+
+CREATE UNIQUE INDEX users_calendar_token_idx ON users(calendar_token);
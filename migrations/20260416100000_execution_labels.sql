@@ -0,0 +1,18 @@
+ALTER TABLE action_plan_executions
+ADD COLUMN label TEXT;
+
+ALTER TABLE action_plan_executions
+ADD COLUMN run_number INTEGER;
+
+UPDATE action_plan_executions
+SET run_number = (
+    SELECT COUNT(*)
+    FROM action_plan_executions AS earlier
+    WHERE earlier.action_plan = action_plan_executions.action_plan
+        AND (
+            earlier.started < action_plan_executions.started
+            OR (earlier.started = action_plan_executions.started AND earlier.id <= action_plan_executions.id)
+        )
+);
+
+CREATE INDEX action_plan_executions_plan_run_idx ON action_plan_executions(action_plan, run_number);
@@ -0,0 +1,9 @@
+CREATE TABLE server_secrets (
+    name TEXT PRIMARY KEY NOT NULL,
+    value BLOB NOT NULL
+);
+
+CREATE TABLE revoked_tokens (
+    id BLOB PRIMARY KEY NOT NULL,
+    revoked INTEGER NOT NULL
+);
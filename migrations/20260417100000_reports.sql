@@ -0,0 +1,8 @@
+CREATE TABLE reports (
+    id BLOB PRIMARY KEY NOT NULL,
+    period_start INTEGER NOT NULL UNIQUE,
+    period_end INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    generated INTEGER NOT NULL,
+    content TEXT NOT NULL
+);
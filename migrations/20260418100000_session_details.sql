@@ -0,0 +1,5 @@
+ALTER TABLE user_sessions ADD COLUMN last_seen INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE user_sessions ADD COLUMN ip_address TEXT;
+ALTER TABLE user_sessions ADD COLUMN user_agent TEXT;
+
+UPDATE user_sessions SET last_seen = created_at;
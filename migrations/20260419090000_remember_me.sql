@@ -0,0 +1,2 @@
+-- Sessions created before this change were all long-lived.
+ALTER TABLE user_sessions ADD COLUMN remember_me INTEGER NOT NULL DEFAULT 1;
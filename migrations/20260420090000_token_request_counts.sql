@@ -0,0 +1,6 @@
+CREATE TABLE token_request_counts (
+    token_id BLOB PRIMARY KEY NOT NULL,
+    window_start INTEGER NOT NULL,
+    request_count INTEGER NOT NULL,
+    locked_until INTEGER
+);
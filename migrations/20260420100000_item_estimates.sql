@@ -0,0 +1,5 @@
+ALTER TABLE action_items ADD COLUMN estimated_minutes INTEGER;
+-- Copied from the plan item when the execution starts, so later plan edits keep past pacing.
+ALTER TABLE action_item_executions ADD COLUMN estimated_minutes INTEGER;
+-- Seconds since the previous item (or the start) when this item was checked off.
+ALTER TABLE action_item_executions ADD COLUMN actual_seconds INTEGER;
@@ -0,0 +1,2 @@
+-- The plan an execution belonged to before that plan was merged into its current one.
+ALTER TABLE action_plan_executions ADD COLUMN merged_from BLOB;
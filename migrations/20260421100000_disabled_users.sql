@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN is_disabled INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,21 @@
+-- Current human-readable URL name. Every slug a plan ever had stays in action_plan_slugs,
+-- so links and printed codes keep working after a rename.
+ALTER TABLE action_plans ADD COLUMN slug TEXT;
+
+CREATE TABLE action_plan_slugs (
+    slug TEXT PRIMARY KEY NOT NULL,
+    action_plan BLOB NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (action_plan) REFERENCES action_plans(id)
+);
+CREATE INDEX action_plan_slugs_plan_idx ON action_plan_slugs(action_plan);
+
+CREATE TABLE action_plan_renames (
+    id BLOB PRIMARY KEY NOT NULL,
+    action_plan BLOB NOT NULL,
+    old_name TEXT NOT NULL,
+    new_name TEXT NOT NULL,
+    renamed_at INTEGER NOT NULL,
+    FOREIGN KEY (action_plan) REFERENCES action_plans(id)
+);
+CREATE INDEX action_plan_renames_plan_idx ON action_plan_renames(action_plan);
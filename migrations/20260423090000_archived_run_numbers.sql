@@ -0,0 +1,3 @@
+-- Highest run number moved to the archive database, so new runs keep counting up after
+-- every earlier run of a plan has been archived.
+ALTER TABLE action_plans ADD COLUMN archived_run_number INTEGER NOT NULL DEFAULT 0;
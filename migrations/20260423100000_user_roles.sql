@@ -0,0 +1,5 @@
+-- Viewers only read, operators also run executions, admins manage everything. Existing
+-- non-admin users become operators.
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'operator' CHECK (role IN ('viewer', 'operator', 'admin'));
+UPDATE users SET role = 'admin' WHERE is_admin = 1;
+ALTER TABLE users DROP COLUMN is_admin;
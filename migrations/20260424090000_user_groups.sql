@@ -0,0 +1,18 @@
+-- Named sets of users such as "Electricians" or "Night shift", so features can address a team
+-- instead of listing people one by one.
+CREATE TABLE user_groups (
+    id BLOB PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX user_groups_name_unique_idx ON user_groups(name COLLATE NOCASE);
+
+CREATE TABLE user_group_members (
+    user_group BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    PRIMARY KEY (user_group, user_id)
+);
+
+CREATE INDEX user_group_members_user_idx ON user_group_members(user_id);
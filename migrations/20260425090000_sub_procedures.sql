@@ -0,0 +1,7 @@
+-- An item can stand for running another plan. Checking it off in an execution needs a
+-- completed run of that plan, which is kept in `linked_execution`.
+ALTER TABLE action_items ADD COLUMN sub_plan BLOB;
+ALTER TABLE action_item_executions ADD COLUMN sub_plan BLOB;
+ALTER TABLE action_item_executions ADD COLUMN linked_execution BLOB;
+
+CREATE INDEX action_item_executions_linked_execution_idx ON action_item_executions(linked_execution);
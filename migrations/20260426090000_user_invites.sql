@@ -0,0 +1,14 @@
+-- Single-use links that let a new user pick their own password. The account is created with
+-- the name and role chosen by the admin when the link is used.
+CREATE TABLE user_invites (
+    id BLOB PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('viewer', 'operator', 'admin')),
+    created_by BLOB,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    accepted_at INTEGER,
+    accepted_user BLOB
+);
+
+CREATE INDEX user_invites_expires_at_idx ON user_invites(expires_at);
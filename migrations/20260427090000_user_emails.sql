@@ -0,0 +1,9 @@
+-- Email addresses for notifications. Only an address with email_verified_at set may be mailed
+-- anything beyond its verification link. email_verification_id is the id of the latest
+-- verification token; entering a new address replaces it, so older links stop working.
+ALTER TABLE users ADD COLUMN email TEXT;
+ALTER TABLE users ADD COLUMN email_verified_at INTEGER;
+ALTER TABLE users ADD COLUMN email_verification_id BLOB;
+
+-- Address entered by the admin when inviting, set on the account once the invite is used.
+ALTER TABLE user_invites ADD COLUMN email TEXT;
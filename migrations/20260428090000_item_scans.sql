@@ -0,0 +1,3 @@
+-- The user whose scan of the code printed at the maintenance point checked the item off.
+-- Cleared when the item is unchecked or checked off by hand.
+ALTER TABLE action_item_executions ADD COLUMN scanned_by BLOB;
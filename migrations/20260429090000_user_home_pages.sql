@@ -0,0 +1,3 @@
+-- The page `/` opens for the user. See `users::HomePage` for the values.
+ALTER TABLE users ADD COLUMN home_page TEXT NOT NULL DEFAULT 'plans'
+    CHECK (home_page IN ('plans', 'executions', 'calendar', 'reports'));
@@ -0,0 +1,7 @@
+-- Optional TOTP second factor. Secrets are encrypted with a key derived from the token signing
+-- key. totp_pending_secret holds a secret shown during enrollment until a code confirms it.
+-- totp_last_step is the time step of the last accepted code, so a code cannot be used twice.
+ALTER TABLE users ADD COLUMN totp_secret BLOB;
+ALTER TABLE users ADD COLUMN totp_pending_secret BLOB;
+ALTER TABLE users ADD COLUMN totp_enabled_at INTEGER;
+ALTER TABLE users ADD COLUMN totp_last_step INTEGER;
@@ -0,0 +1,10 @@
+-- Single-use codes for logging in when the authenticator app is lost. Only a SHA-256 hash of
+-- each code is stored; the codes themselves are shown once when they are generated.
+CREATE TABLE user_recovery_codes (
+    id BLOB PRIMARY KEY NOT NULL,
+    user_id BLOB NOT NULL,
+    code_hash TEXT NOT NULL,
+    used_at INTEGER
+);
+
+CREATE INDEX user_recovery_codes_user_idx ON user_recovery_codes(user_id);
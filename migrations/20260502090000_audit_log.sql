@@ -0,0 +1,28 @@
+-- Who changed what and when, as evidence for audits. Names are copied so entries stay readable
+-- after users or plans are deleted. Entries can only be added; the triggers reject edits.
+CREATE TABLE audit_log (
+    id BLOB PRIMARY KEY NOT NULL,
+    created_at INTEGER NOT NULL,
+    user_id BLOB,
+    user_name TEXT NOT NULL,
+    ip_address TEXT,
+    entity_type TEXT NOT NULL,
+    entity_id BLOB,
+    action TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    before_state TEXT,
+    after_state TEXT
+);
+
+CREATE INDEX audit_log_created_at_idx ON audit_log(created_at);
+CREATE INDEX audit_log_entity_idx ON audit_log(entity_type, entity_id);
+
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit log entries cannot be changed');
+END;
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit log entries cannot be deleted');
+END;
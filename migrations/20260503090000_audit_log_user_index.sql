@@ -0,0 +1,2 @@
+-- The activity feed filters the audit log by user.
+CREATE INDEX audit_log_user_idx ON audit_log(user_id);
@@ -0,0 +1,6 @@
+-- A single row rewritten while in read-only mode, to find out when the database can be
+-- written again.
+CREATE TABLE storage_probe (
+    id INTEGER PRIMARY KEY NOT NULL,
+    checked_at INTEGER NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Who started and who completed a run. Left as they are when the user is deleted; the pages
+-- then show no name.
+ALTER TABLE action_plan_executions ADD COLUMN started_by BLOB;
+ALTER TABLE action_plan_executions ADD COLUMN finished_by BLOB;
@@ -0,0 +1,20 @@
+-- Who is responsible for an open execution. Both empty means whoever started it.
+ALTER TABLE action_plan_executions ADD COLUMN assigned_user BLOB;
+ALTER TABLE action_plan_executions ADD COLUMN assigned_group BLOB;
+
+-- Every handover of an execution with its note. Names are kept as they were at the time, so the
+-- chain stays readable after users or groups are renamed or deleted.
+CREATE TABLE execution_transfers (
+    id BLOB PRIMARY KEY NOT NULL,
+    action_plan_execution BLOB NOT NULL,
+    transferred_at INTEGER NOT NULL,
+    transferred_by BLOB NOT NULL,
+    transferred_by_name TEXT NOT NULL,
+    from_name TEXT NOT NULL,
+    to_user BLOB,
+    to_group BLOB,
+    to_name TEXT NOT NULL,
+    note TEXT NOT NULL
+);
+
+CREATE INDEX execution_transfers_execution_idx ON execution_transfers(action_plan_execution, transferred_at);
@@ -0,0 +1,2 @@
+-- Who checked off an item, so it stays traceable across shift handovers.
+ALTER TABLE action_item_executions ADD COLUMN finished_by BLOB;
@@ -0,0 +1,4 @@
+-- Who should take care of an item, so a team can split up a large execution.
+ALTER TABLE action_item_executions ADD COLUMN assigned_to BLOB;
+
+CREATE INDEX action_item_executions_assigned_to_idx ON action_item_executions(assigned_to);
@@ -0,0 +1,2 @@
+-- What was found or done at a single step, e.g. "replaced filter, part #123".
+ALTER TABLE action_item_executions ADD COLUMN note TEXT;
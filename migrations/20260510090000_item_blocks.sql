@@ -0,0 +1,10 @@
+-- Items that cannot be done in a run, e.g. because a part is missing. A blocked item does not
+-- hold up completing its execution and stays listed until someone resolves it.
+ALTER TABLE action_item_executions ADD COLUMN blocked_reason TEXT;
+ALTER TABLE action_item_executions ADD COLUMN blocked_at INTEGER;
+ALTER TABLE action_item_executions ADD COLUMN blocked_by BLOB;
+ALTER TABLE action_item_executions ADD COLUMN block_resolved_at INTEGER;
+ALTER TABLE action_item_executions ADD COLUMN block_resolved_by BLOB;
+ALTER TABLE action_item_executions ADD COLUMN block_resolution TEXT;
+
+CREATE INDEX action_item_executions_blocked_at_idx ON action_item_executions(blocked_at);
@@ -0,0 +1,22 @@
+-- Plans pulled from a central instance. `sync_source_id` is the plan's id there and
+-- `sync_source_revision` the central revision last applied. `sync_local_revision` is the
+-- revision of the local copy right after applying it, so a plan whose content no longer
+-- matches it was changed here and is not overwritten by later pulls. The central revision
+-- held back because of that is kept in `sync_pending_revision`.
+ALTER TABLE action_plans ADD COLUMN sync_source_id BLOB;
+ALTER TABLE action_plans ADD COLUMN sync_source_revision TEXT;
+ALTER TABLE action_plans ADD COLUMN sync_local_revision TEXT;
+ALTER TABLE action_plans ADD COLUMN sync_pending_revision TEXT;
+ALTER TABLE action_plans ADD COLUMN synced_at INTEGER;
+
+CREATE UNIQUE INDEX action_plans_sync_source_id_idx ON action_plans(sync_source_id);
+
+-- Outcome of each pull, for the admin page.
+CREATE TABLE plan_sync_runs (
+    id BLOB PRIMARY KEY NOT NULL,
+    ran_at INTEGER NOT NULL,
+    succeeded INTEGER NOT NULL,
+    summary TEXT NOT NULL
+);
+
+CREATE INDEX plan_sync_runs_ran_at_idx ON plan_sync_runs(ran_at);
@@ -0,0 +1,15 @@
+-- Comments people leave on an execution, e.g. "waiting on spare part". Replies point at the
+-- comment they answer. The author's name is kept as it was, so comments stay readable after the
+-- user is renamed or deleted. Deleted comments that have replies stay as a placeholder.
+CREATE TABLE execution_comments (
+    id BLOB PRIMARY KEY NOT NULL,
+    action_plan_execution BLOB NOT NULL,
+    parent BLOB,
+    author BLOB NOT NULL,
+    author_name TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    deleted_at INTEGER
+);
+
+CREATE INDEX execution_comments_execution_idx ON execution_comments(action_plan_execution, created_at);
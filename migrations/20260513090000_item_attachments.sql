@@ -0,0 +1,17 @@
+-- Photos and files attached to execution items, e.g. as evidence for an audit. The files live
+-- in `attachments_dir` under the attachment id, with `<id>.thumb.jpg` next to images. The
+-- execution is kept as well, so attachments stay reachable after the items are archived.
+CREATE TABLE execution_item_attachments (
+    id BLOB PRIMARY KEY NOT NULL,
+    action_item_execution BLOB NOT NULL,
+    action_plan_execution BLOB NOT NULL,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    has_thumbnail INTEGER NOT NULL DEFAULT 0,
+    uploaded_at INTEGER NOT NULL,
+    uploaded_by BLOB NOT NULL,
+    uploaded_by_name TEXT NOT NULL
+);
+
+CREATE INDEX execution_item_attachments_execution_idx ON execution_item_attachments(action_plan_execution, uploaded_at);
@@ -0,0 +1,14 @@
+-- Reference documents attached to an action plan, like manuals or wiring diagrams. The files
+-- live in `attachments_dir` under the attachment id, next to the execution item attachments.
+-- Only the uploader's name is kept, as it is all a restored backup knows about them.
+CREATE TABLE plan_attachments (
+    id BLOB PRIMARY KEY NOT NULL,
+    action_plan BLOB NOT NULL,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    uploaded_at INTEGER NOT NULL,
+    uploaded_by_name TEXT NOT NULL
+);
+
+CREATE INDEX plan_attachments_plan_idx ON plan_attachments(action_plan, uploaded_at);
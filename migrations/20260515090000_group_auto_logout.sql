@@ -0,0 +1,3 @@
+-- Minutes without activity after which members of the group are logged out, e.g. for a group
+-- of shop-floor terminal accounts. NULL leaves it to the instance setting.
+ALTER TABLE user_groups ADD COLUMN auto_logout_minutes INTEGER;
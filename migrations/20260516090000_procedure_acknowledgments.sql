@@ -0,0 +1,9 @@
+-- The procedure of a plan each user last confirmed having reviewed, as the item names one per
+-- line. Executions whose items differ from it point the user at what changed.
+CREATE TABLE procedure_acknowledgments (
+    action_plan BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    items TEXT NOT NULL,
+    acknowledged_at INTEGER NOT NULL,
+    PRIMARY KEY (action_plan, user_id)
+);
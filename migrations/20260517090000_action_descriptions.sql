@@ -0,0 +1,2 @@
+-- Instructions for an action as Markdown, shared by every plan using it.
+ALTER TABLE actions ADD COLUMN description TEXT;
@@ -0,0 +1,3 @@
+-- Instructions for an item that only apply in its plan, copied to the items of each run.
+ALTER TABLE action_items ADD COLUMN instructions TEXT;
+ALTER TABLE action_item_executions ADD COLUMN instructions TEXT;
@@ -0,0 +1,12 @@
+-- Reference links of an action, like vendor documentation or wiki pages. Without a label the
+-- URL itself is shown.
+CREATE TABLE action_links (
+    id BLOB PRIMARY KEY NOT NULL,
+    action BLOB NOT NULL,
+    label TEXT,
+    url TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (action) REFERENCES actions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX action_links_action_idx ON action_links(action, created_at);
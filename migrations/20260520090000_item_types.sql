@@ -0,0 +1,7 @@
+-- What an item captures when it is checked off: `checkbox`, `text` or `number`. Runs copy the
+-- type and unit of their plan's items and keep the value that was entered.
+ALTER TABLE action_items ADD COLUMN item_type TEXT NOT NULL DEFAULT 'checkbox';
+ALTER TABLE action_items ADD COLUMN unit TEXT;
+ALTER TABLE action_item_executions ADD COLUMN item_type TEXT NOT NULL DEFAULT 'checkbox';
+ALTER TABLE action_item_executions ADD COLUMN unit TEXT;
+ALTER TABLE action_item_executions ADD COLUMN value TEXT;
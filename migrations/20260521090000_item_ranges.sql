@@ -0,0 +1,7 @@
+-- Expected range of numeric readings. Either bound may be missing. Runs copy the range and
+-- flag readings outside of it when they are checked off.
+ALTER TABLE action_items ADD COLUMN min_value REAL;
+ALTER TABLE action_items ADD COLUMN max_value REAL;
+ALTER TABLE action_item_executions ADD COLUMN min_value REAL;
+ALTER TABLE action_item_executions ADD COLUMN max_value REAL;
+ALTER TABLE action_item_executions ADD COLUMN out_of_spec INTEGER NOT NULL DEFAULT 0;
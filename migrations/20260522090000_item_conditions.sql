@@ -0,0 +1,6 @@
+-- Items can depend on an earlier item of their plan, referenced by its order index, and then
+-- only apply once that item was checked off, with `condition_value` as its answer if set.
+ALTER TABLE action_items ADD COLUMN condition_index INTEGER;
+ALTER TABLE action_items ADD COLUMN condition_value TEXT;
+ALTER TABLE action_item_executions ADD COLUMN condition_index INTEGER;
+ALTER TABLE action_item_executions ADD COLUMN condition_value TEXT;
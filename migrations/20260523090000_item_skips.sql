@@ -0,0 +1,5 @@
+-- Items deliberately left out of a run, with the reason. A skipped item counts as dealt with
+-- when completing the execution.
+ALTER TABLE action_item_executions ADD COLUMN skip_reason TEXT;
+ALTER TABLE action_item_executions ADD COLUMN skipped_at INTEGER;
+ALTER TABLE action_item_executions ADD COLUMN skipped_by BLOB;
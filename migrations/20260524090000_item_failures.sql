@@ -0,0 +1,23 @@
+-- Items that were done but did not pass, e.g. a pump that fails its pressure test. A failed item
+-- counts as dealt with when completing the execution, and a follow-up task keeps the defect on
+-- record until someone takes care of it.
+ALTER TABLE action_item_executions ADD COLUMN failure_note TEXT;
+ALTER TABLE action_item_executions ADD COLUMN failed_at INTEGER;
+ALTER TABLE action_item_executions ADD COLUMN failed_by BLOB;
+
+-- Follow-up work found during a run. The plan, run and item are kept by name as well, so tasks
+-- stay readable after the run is archived or the plan is edited.
+CREATE TABLE tasks (
+    id BLOB PRIMARY KEY NOT NULL,
+    title TEXT NOT NULL,
+    note TEXT NOT NULL,
+    action_plan_execution BLOB NOT NULL,
+    action_item_execution BLOB,
+    action_plan_name TEXT NOT NULL,
+    run_number INTEGER NOT NULL,
+    item_name TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    created_by BLOB NOT NULL
+);
+
+CREATE INDEX tasks_execution_item_idx ON tasks(action_item_execution);
@@ -0,0 +1,8 @@
+-- Who takes care of a follow-up task, and when it was closed. Closed tasks stay listed on the
+-- task page for reference.
+ALTER TABLE tasks ADD COLUMN assigned_to BLOB;
+ALTER TABLE tasks ADD COLUMN closed_at INTEGER;
+ALTER TABLE tasks ADD COLUMN closed_by BLOB;
+ALTER TABLE tasks ADD COLUMN resolution TEXT;
+
+CREATE INDEX tasks_closed_at_idx ON tasks(closed_at, created_at);
@@ -0,0 +1,5 @@
+-- Section headings like "Before shutdown" that start a new part of a plan. A heading is shown
+-- above the item it is stored with, so headings follow the items' order indexes and do not
+-- count as steps themselves.
+ALTER TABLE action_items ADD COLUMN section TEXT;
+ALTER TABLE action_item_executions ADD COLUMN section TEXT;
@@ -0,0 +1,5 @@
+-- Sub-items, like the single bolts of "Tighten flange". The parent is referenced by its order
+-- index like conditions are, as item ids change when a plan is edited during a run. Only one
+-- level of nesting is allowed, so a parent never has a parent itself.
+ALTER TABLE action_items ADD COLUMN parent_index INTEGER;
+ALTER TABLE action_item_executions ADD COLUMN parent_index INTEGER;
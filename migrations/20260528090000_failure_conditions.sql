@@ -0,0 +1,4 @@
+-- Conditions on an earlier item being marked failed rather than checked off, like a repair
+-- step that is only needed when an inspection failed. `condition_value` is unused for them.
+ALTER TABLE action_items ADD COLUMN condition_failed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE action_item_executions ADD COLUMN condition_failed INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,42 @@
+-- Section headings become rows of their own among the items of a plan, ordered by the same
+-- `order_index`, instead of being stored with the item below them. Heading rows have no
+-- action. Existing order indexes are doubled to make room for a heading in front of each item,
+-- and the order indexes items refer to with their conditions and parents move along. Runs
+-- keep the heading of each item in `section`, as the plan may change while they go on.
+CREATE TABLE action_items_with_headings (
+    id BLOB PRIMARY KEY NOT NULL,
+    order_index INTEGER NOT NULL,
+    action_plan BLOB NOT NULL,
+    action BLOB,
+    heading TEXT,
+    estimated_minutes INTEGER,
+    sub_plan BLOB,
+    instructions TEXT,
+    item_type TEXT NOT NULL DEFAULT 'checkbox',
+    unit TEXT,
+    min_value REAL,
+    max_value REAL,
+    condition_index INTEGER,
+    condition_value TEXT,
+    condition_failed INTEGER NOT NULL DEFAULT 0,
+    parent_index INTEGER,
+    CHECK ((action IS NULL) <> (heading IS NULL)),
+    FOREIGN KEY (action_plan) REFERENCES action_plans(id),
+    FOREIGN KEY (action) REFERENCES actions(id)
+);
+
+INSERT INTO action_items_with_headings
+    (id, order_index, action_plan, action, estimated_minutes, sub_plan, instructions, item_type, unit, min_value, max_value, condition_index, condition_value, condition_failed, parent_index)
+SELECT
+    id, order_index * 2 + 1, action_plan, action, estimated_minutes, sub_plan, instructions, item_type, unit, min_value, max_value, condition_index * 2 + 1, condition_value, condition_failed, parent_index * 2 + 1
+FROM action_items;
+
+INSERT INTO action_items_with_headings (id, order_index, action_plan, heading)
+SELECT randomblob(16), order_index * 2, action_plan, section
+FROM action_items
+WHERE section IS NOT NULL;
+
+DROP TABLE action_items;
+ALTER TABLE action_items_with_headings RENAME TO action_items;
+CREATE INDEX action_plan_items_idx ON action_items(action_plan);
+CREATE INDEX action_plan_items_action_idx ON action_items(action);
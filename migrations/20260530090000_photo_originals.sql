@@ -0,0 +1,3 @@
+-- Photos are stored with their metadata stripped and their resolution capped. The upload as it
+-- was sent is kept next to them, and this is its size, or NULL when there is none.
+ALTER TABLE execution_item_attachments ADD COLUMN original_size_bytes INTEGER;
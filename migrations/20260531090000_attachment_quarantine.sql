@@ -0,0 +1,3 @@
+-- Why an attachment is held back until an admin reviews it: ClamAV flagged it or it could not
+-- be scanned. NULL for attachments that can be opened.
+ALTER TABLE execution_item_attachments ADD COLUMN quarantine_reason TEXT;
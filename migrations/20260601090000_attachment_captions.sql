@@ -0,0 +1,2 @@
+-- What a photo shows, e.g. "Filter after cleaning", for the galleries. NULL without one.
+ALTER TABLE execution_item_attachments ADD COLUMN caption TEXT;
@@ -0,0 +1,347 @@
+//! The action library: every checklist action with what belongs to the action itself rather
+//! than to a plan, its description and reference links like vendor documentation or wiki pages.
+//! Links are shown with the item whenever the action is part of an execution.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    response::{Html, Redirect},
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    authz::{Action, Resource, authorize},
+    markdown, templates, unix_now,
+};
+
+/// Longest link label accepted, in characters.
+const MAX_LABEL_CHARS: usize = 200;
+/// Longest link URL accepted, in characters.
+const MAX_URL_CHARS: usize = 2_000;
+
+/// A reference link of an action.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionLink {
+    id: Uuid,
+    /// The URL itself when no label was given.
+    label: String,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct ActionLibraryView {
+    actions: Vec<ActionLibraryEntry>,
+    is_admin: bool,
+}
+
+#[derive(Serialize)]
+struct ActionLibraryEntry {
+    id: Uuid,
+    name: String,
+    plan_count: i64,
+    link_count: i64,
+    has_description: bool,
+}
+
+#[derive(Serialize)]
+struct ActionShowView {
+    id: Uuid,
+    name: String,
+    description_html: Option<String>,
+    links: Vec<ActionLink>,
+    plans: Vec<ActionPlanUse>,
+    is_admin: bool,
+}
+
+#[derive(Serialize)]
+struct ActionPlanUse {
+    id: Uuid,
+    name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ActionLinkForm {
+    /// Shown instead of the URL. Optional.
+    label: Option<String>,
+    /// An `http` or `https` URL.
+    url: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/actions",
+    tag = "actions",
+    responses(
+        (status = 200, description = "All actions with how many plans use them", content_type = "text/html"),
+    )
+)]
+pub async fn index(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    let actions = sqlx::query!(
+        r#"
+        SELECT
+            actions.id as "id!: uuid::Uuid",
+            actions.name as "name!",
+            actions.description IS NOT NULL AND actions.description != '' as "has_description!: bool",
+            (
+                SELECT COUNT(DISTINCT action_items.action_plan)
+                FROM action_items
+                INNER JOIN action_plans ON action_plans.id = action_items.action_plan
+                WHERE action_items.action = actions.id
+                    AND (action_plans.deleted_at IS NULL OR action_plans.deleted_at <= 0)
+            ) as "plan_count!: i64",
+            (
+                SELECT COUNT(*)
+                FROM action_links
+                WHERE action_links.action = actions.id
+            ) as "link_count!: i64"
+        FROM actions
+        ORDER BY actions.name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|row| ActionLibraryEntry {
+        id: row.id,
+        name: row.name,
+        plan_count: row.plan_count,
+        link_count: row.link_count,
+        has_description: row.has_description,
+    })
+    .collect();
+
+    let rendered = templates::render(
+        &state.jinja,
+        "actions.html",
+        ActionLibraryView {
+            actions,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    get,
+    path = "/actions/{id}",
+    tag = "actions",
+    params(("id" = Uuid, Path, description = "Action id")),
+    responses(
+        (status = 200, description = "The action with its description, reference links and the plans using it", content_type = "text/html"),
+        (status = 404, description = "Action not found", content_type = "text/html"),
+    )
+)]
+pub async fn show_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Html<String>, AppError> {
+    let action = sqlx::query!("SELECT name, description FROM actions WHERE id = $1", id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| {
+            AppError::not_found_for("Action", format!("No action exists for id: {}", id))
+        })?;
+
+    let plans = sqlx::query_as!(
+        ActionPlanUse,
+        r#"
+        SELECT DISTINCT action_plans.id as "id!: uuid::Uuid", action_plans.name
+        FROM action_items
+        INNER JOIN action_plans ON action_plans.id = action_items.action_plan
+        WHERE action_items.action = $1
+            AND (action_plans.deleted_at IS NULL OR action_plans.deleted_at <= 0)
+        ORDER BY action_plans.name COLLATE NOCASE ASC
+        "#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let rendered = templates::render(
+        &state.jinja,
+        "action_show.html",
+        ActionShowView {
+            id,
+            name: action.name,
+            description_html: action
+                .description
+                .as_deref()
+                .filter(|description| !description.is_empty())
+                .map(markdown::to_html),
+            links: fetch_links(&state.db, id).await?,
+            plans,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/actions/{id}/links",
+    tag = "actions",
+    params(("id" = Uuid, Path, description = "Action id")),
+    request_body(content = ActionLinkForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Adds the link and redirects to the action"),
+        (status = 404, description = "Action not found", content_type = "text/html"),
+        (status = 409, description = "The URL is not an http or https URL, or the link is too long", content_type = "text/html"),
+    )
+)]
+pub async fn link_create_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<ActionLinkForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
+    let url = form.url.trim();
+    let lower = url.to_ascii_lowercase();
+    if !(lower.starts_with("https://") || lower.starts_with("http://"))
+        || url.chars().any(char::is_whitespace)
+    {
+        return Err(AppError::conflict(
+            "Links need to be http or https URLs.".to_string(),
+        ));
+    }
+    let label = form
+        .label
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty());
+    if url.chars().count() > MAX_URL_CHARS
+        || label
+            .as_ref()
+            .is_some_and(|label| label.chars().count() > MAX_LABEL_CHARS)
+    {
+        return Err(AppError::conflict(format!(
+            "Links can have at most {} characters and labels at most {}.",
+            MAX_URL_CHARS, MAX_LABEL_CHARS
+        )));
+    }
+
+    let exists = sqlx::query_scalar!("SELECT COUNT(*) FROM actions WHERE id = $1", id)
+        .fetch_one(&state.db)
+        .await?
+        > 0;
+    if !exists {
+        return Err(AppError::not_found_for(
+            "Action",
+            format!("No action exists for id: {}", id),
+        ));
+    }
+
+    let link_id = Uuid::new_v4();
+    let now = unix_now();
+    sqlx::query!(
+        r#"
+        INSERT INTO action_links (id, action, label, url, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        link_id,
+        id,
+        label,
+        url,
+        now
+    )
+    .execute(&state.db)
+    .await?;
+
+    Ok(Redirect::to(&format!("/actions/{}", id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/action-links/{id}/delete",
+    tag = "actions",
+    params(("id" = Uuid, Path, description = "Link id")),
+    responses(
+        (status = 303, description = "Removes the link and redirects to its action"),
+        (status = 404, description = "Link not found", content_type = "text/html"),
+    )
+)]
+pub async fn link_delete_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
+    let action_id = sqlx::query_scalar!(
+        r#"DELETE FROM action_links WHERE id = $1 RETURNING action as "action: uuid::Uuid""#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::not_found_for("Link", format!("No link exists for id: {}", id)))?;
+
+    Ok(Redirect::to(&format!("/actions/{}", action_id)))
+}
+
+/// Links of one action in the order they were added.
+pub async fn fetch_links(db: &SqlitePool, action_id: Uuid) -> Result<Vec<ActionLink>, AppError> {
+    Ok(sqlx::query!(
+        r#"
+        SELECT id as "id!: uuid::Uuid", label, url
+        FROM action_links
+        WHERE action = $1
+        ORDER BY created_at ASC, rowid ASC
+        "#,
+        action_id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| ActionLink {
+        id: row.id,
+        label: row.label.unwrap_or_else(|| row.url.clone()),
+        url: row.url,
+    })
+    .collect())
+}
+
+/// Links of the actions in an execution, by action id.
+pub async fn fetch_for_execution(
+    db: &SqlitePool,
+    execution_id: Uuid,
+) -> Result<HashMap<Uuid, Vec<ActionLink>>, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            action_links.id as "id!: uuid::Uuid",
+            action_links.action as "action_id!: uuid::Uuid",
+            action_links.label,
+            action_links.url
+        FROM action_links
+        WHERE action_links.action IN (
+            SELECT action
+            FROM action_item_executions
+            WHERE action_plan_execution = $1
+        )
+        ORDER BY action_links.created_at ASC, action_links.rowid ASC
+        "#,
+        execution_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut links: HashMap<Uuid, Vec<ActionLink>> = HashMap::new();
+    for row in rows {
+        links.entry(row.action_id).or_default().push(ActionLink {
+            id: row.id,
+            label: row.label.unwrap_or_else(|| row.url.clone()),
+            url: row.url,
+        });
+    }
+    Ok(links)
+}
@@ -1,18 +1,33 @@
 use axum::{
     Json,
-    extract::{Path, Query, State},
-    response::{Html, Redirect},
+    extract::{Path, State},
+    http::{HeaderValue, header},
+    response::{Html, IntoResponse, Redirect, Response},
 };
-use axum_extra::extract::Form;
+use axum_extra::extract::{Form, Query, cookie::CookieJar};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
-use sqlx::{Sqlite, Transaction};
+use sqlx::{Sqlite, SqliteConnection, SqlitePool, Transaction};
 use std::collections::{HashMap, HashSet};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 use crate::{
-    AppError, AppState, CurrentUser, format_unix_timestamp,
+    AppError, AppState, CurrentUser,
+    attachments::{self, PlanAttachment},
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, Role, authorize},
+    conditions,
+    flash::{self, Flash, UNDO_GRACE_PERIOD_SECONDS},
+    format_duration, format_unix_timestamp, headings,
+    item_types::{self, ItemType, ItemTypeOption},
+    markdown,
+    procedure_changes::{self, ProcedureAcknowledgment},
+    sync::{self, PlanSyncInfo},
     tags::{self, TagBadge},
+    tasks, templates, unix_now,
+    users::{self, HomePage},
+    webhooks,
 };
 
 #[derive(FromRow, Debug, Serialize)]
@@ -30,9 +45,17 @@ pub struct ActionPlanList {
     search_query: String,
     selected_tag: Option<TagBadge>,
     selected_tag_id: String,
+    undo_notice: Option<PlanUndoNotice>,
+    open_task_count: i64,
     is_admin: bool,
 }
 
+#[derive(Serialize)]
+struct PlanUndoNotice {
+    id: Uuid,
+    name: String,
+}
+
 #[derive(Serialize)]
 pub struct ActionPlanListItem {
     id: Uuid,
@@ -42,11 +65,50 @@ pub struct ActionPlanListItem {
     last_finished_display: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "action_plans",
+    params(ActionPlanListQuery),
+    responses(
+        (status = 200, description = "Action plan list, for users whose home page is the plan list", content_type = "text/html"),
+        (status = 303, description = "Redirects to the home page the user chose on the account page"),
+    )
+)]
+pub async fn home(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    jar: CookieJar,
+    query: Query<ActionPlanListQuery>,
+) -> Result<Response, AppError> {
+    match users::fetch_home_page(&state.db, current_user.id).await? {
+        HomePage::Plans => Ok(index(State(state), current_user, jar, query)
+            .await?
+            .into_response()),
+        home_page => Ok(Redirect::to(home_page.path()).into_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/plans",
+    tag = "action_plans",
+    params(ActionPlanListQuery),
+    responses(
+        (status = 200, description = "Action plan list", content_type = "text/html"),
+    )
+)]
 pub async fn index(
     State(state): State<AppState>,
     current_user: CurrentUser,
+    jar: CookieJar,
     Query(query): Query<ActionPlanListQuery>,
-) -> Result<Html<String>, AppError> {
+) -> Result<(CookieJar, Html<String>), AppError> {
+    let (jar, pending_flash) = flash::take(jar, &state.config);
+    let undo_notice = match pending_flash {
+        Some(Flash::PlanDeleted { plan_id }) => fetch_undo_notice(&state, plan_id).await?,
+        None => None,
+    };
     let sort = query.sort.unwrap_or_else(|| "name".to_string());
     let show_deleted = query.deleted.unwrap_or(false);
     let search_query = query.q.unwrap_or_default().trim().to_string();
@@ -114,13 +176,13 @@ pub async fn index(
 
     match sort.as_str() {
         "last_execution_desc" => {
-            action_plan_list.sort_by(|a, b| b.last_execution_unix.cmp(&a.last_execution_unix));
+            action_plan_list.sort_by_key(|item| std::cmp::Reverse(item.last_execution_unix));
         }
         "last_execution_asc" => {
-            action_plan_list.sort_by(|a, b| a.last_execution_unix.cmp(&b.last_execution_unix));
+            action_plan_list.sort_by_key(|item| item.last_execution_unix);
         }
         _ => {
-            action_plan_list.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+            action_plan_list.sort_by_key(|item| item.name.to_lowercase());
         }
     }
 
@@ -135,29 +197,66 @@ pub async fn index(
         })
         .collect();
 
-    let template = state
-        .jinja
-        .get_template("action_plan_list.html")
-        .expect("template is loaded");
-    let rendered = template.render(&ActionPlanList {
-        action_plans,
-        current_sort: sort,
-        show_deleted,
-        search_query,
-        selected_tag_id: selected_tag_id
-            .map(|value| value.to_string())
-            .unwrap_or_default(),
-        selected_tag,
-        is_admin: current_user.is_admin,
-    })?;
+    let rendered = templates::render(
+        &state.jinja,
+        "action_plan_list.html",
+        &ActionPlanList {
+            action_plans,
+            current_sort: sort,
+            show_deleted,
+            search_query,
+            selected_tag_id: selected_tag_id
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            selected_tag,
+            undo_notice,
+            open_task_count: tasks::count_open(&state.db).await?,
+            is_admin: current_user.is_admin,
+        },
+    )?;
 
-    Ok(Html(rendered))
+    Ok((jar, Html(rendered)))
+}
+
+async fn fetch_undo_notice(
+    state: &AppState,
+    plan_id: Uuid,
+) -> Result<Option<PlanUndoNotice>, AppError> {
+    let deleted_since = unix_now().saturating_sub(UNDO_GRACE_PERIOD_SECONDS);
+    let plan = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            name
+        FROM action_plans
+        WHERE id = $1
+            AND deleted_at >= $2
+        "#,
+        plan_id,
+        deleted_since
+    )
+    .fetch_optional(&state.db)
+    .await?;
+
+    Ok(plan.map(|plan| PlanUndoNotice {
+        id: plan.id,
+        name: plan.name,
+    }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/action_plan/new",
+    tag = "action_plans",
+    responses(
+        (status = 200, description = "Empty action plan editor", content_type = "text/html"),
+    )
+)]
 pub async fn new_get(
     State(state): State<AppState>,
     current_user: CurrentUser,
 ) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
     let plan = ActionPlanEdit {
         id: None,
         form_action: "/action_plan/new".to_string(),
@@ -165,23 +264,75 @@ pub async fn new_get(
         name: String::new(),
         items: Vec::new(),
         available_tags: action_plan_tag_options(tags::fetch_all_badges(&state.db).await?, None),
+        available_sub_plans: fetch_sub_plan_options(&state.db, None).await?,
+        item_types: ItemType::options(),
         is_admin: current_user.is_admin,
     };
 
     edit_action_plan(&state, &plan)
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct ActionPlanForm {
     name: String,
     items: Option<Vec<String>>,
+    /// Estimated minutes per item, in the same order as `items`. Empty for no estimate.
+    estimated_minutes: Option<Vec<String>>,
+    /// Plan id to run as a sub-procedure per item, in the same order as `items`. Empty for a
+    /// plain item.
+    sub_plans: Option<Vec<String>>,
+    /// Markdown description of each item's action, in the same order as `items`.
+    descriptions: Option<Vec<String>>,
+    /// Action name each description was loaded for. A description only replaces (or clears)
+    /// the stored one while the item still has that name; otherwise only a non-empty one does.
+    description_for: Option<Vec<String>>,
+    /// Instructions for each item that only apply in this plan, in the same order as `items`.
+    /// Empty for none.
+    instructions: Option<Vec<String>>,
+    /// `checkbox`, `text` or `number` per item, in the same order as `items`. Missing or
+    /// unknown types are plain checkboxes.
+    item_types: Option<Vec<String>>,
+    /// Unit of each numeric item, like `bar`, in the same order as `items`.
+    units: Option<Vec<String>>,
+    /// Lowest expected reading of each numeric item, in the same order as `items`. Empty for
+    /// no lower bound.
+    min_values: Option<Vec<String>>,
+    /// Highest expected reading of each numeric item, in the same order as `items`. Empty for
+    /// no upper bound.
+    max_values: Option<Vec<String>>,
+    /// Step number (counting from 1) of the earlier item each item depends on, in the same
+    /// order as `items`. Empty for items that always apply.
+    condition_steps: Option<Vec<String>>,
+    /// Answer the earlier item needs for each item to apply, in the same order as `items`.
+    /// Empty for any answer.
+    condition_values: Option<Vec<String>>,
+    /// `failed` for items that apply once the earlier item was marked failed rather than
+    /// checked off, in the same order as `items`.
+    condition_modes: Option<Vec<String>>,
+    /// Section heading to insert above each item, in the same order as `items`. Empty for
+    /// items that continue the current section.
+    sections: Option<Vec<String>>,
+    /// `sub` for items nested under the closest item above that is not nested itself, in the
+    /// same order as `items`. Anything else for top-level items.
+    nestings: Option<Vec<String>>,
     tag_ids: Option<Vec<Uuid>>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/action_plan/new",
+    tag = "action_plans",
+    request_body(content = ActionPlanForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Redirects to the created action plan"),
+    )
+)]
 pub async fn new_post(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Form(form): Form<ActionPlanForm>,
 ) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
     let mut tx = state.db.begin().await?;
 
     let plan_id = Uuid::new_v4();
@@ -193,16 +344,30 @@ pub async fn new_post(
     )
     .execute(&mut *tx)
     .await?;
+    assign_slug(&mut tx, plan_id, &form.name).await?;
 
-    update_plan_items(tx, plan_id, form, None).await
+    let redirect = update_plan_items(tx, &current_user, plan_id, form, None, None).await?;
+    webhooks::plan_published(&state, plan_id);
+    Ok(redirect)
 }
 
+#[utoipa::path(
+    get,
+    path = "/action_plan/{id}/edit",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan id"), EditContext),
+    responses(
+        (status = 200, description = "Action plan editor", content_type = "text/html"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+    )
+)]
 pub async fn edit_get(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Path(id): Path<Uuid>,
     Query(query): Query<EditContext>,
 ) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
     let execution_id = query.execution_id;
 
     let plan = sqlx::query_as!(
@@ -227,19 +392,7 @@ pub async fn edit_get(
         ));
     };
 
-    let items = sqlx::query_as!(
-        ActionPlanItem,
-        r#"
-        SELECT actions.name as "name!"
-        FROM action_items
-        INNER JOIN actions ON actions.id = action_items.action
-        WHERE action_items.action_plan = $1
-        ORDER BY action_items.order_index ASC
-        "#,
-        id
-    )
-    .fetch_all(&state.db)
-    .await?;
+    let items = fetch_plan_items(&state.db, id).await?;
     let selected_tag_ids = tags::fetch_selected_tag_ids(&state.db, id).await?;
 
     let plan = ActionPlanEdit {
@@ -263,58 +416,147 @@ pub async fn edit_get(
             tags::fetch_all_badges(&state.db).await?,
             Some(selected_tag_ids),
         ),
+        available_sub_plans: fetch_sub_plan_options(&state.db, Some(plan.id)).await?,
+        item_types: ItemType::options(),
         is_admin: current_user.is_admin,
     };
 
     edit_action_plan(&state, &plan)
 }
 
+#[utoipa::path(
+    post,
+    path = "/action_plan/{id}/edit",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan id"), EditContext),
+    request_body(content = ActionPlanForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Redirects to the action plan or the execution being edited"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+    )
+)]
 pub async fn edit_post(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
     Query(query): Query<EditContext>,
     Form(form): Form<ActionPlanForm>,
 ) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
     let execution_id = query.execution_id;
     let mut tx = state.db.begin().await?;
 
-    let update_result = sqlx::query!(
-        "UPDATE action_plans SET name = $1 WHERE id = $2 AND (deleted_at IS NULL OR deleted_at <= 0)",
-        form.name,
+    let previous_name = sqlx::query_scalar!(
+        "SELECT name FROM action_plans WHERE id = $1 AND (deleted_at IS NULL OR deleted_at <= 0)",
         id
     )
-    .execute(&mut *tx)
+    .fetch_optional(&mut *tx)
     .await?;
-    if update_result.rows_affected() == 0 {
+    let Some(previous_name) = previous_name else {
         return Err(AppError::not_found_for(
             "Action Plan",
             format!("No action plan exists for id: {}", id),
         ));
+    };
+    let before = plan_snapshot(&mut tx, id).await?;
+
+    if previous_name != form.name {
+        sqlx::query!(
+            "UPDATE action_plans SET name = $1 WHERE id = $2",
+            form.name,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let rename_id = Uuid::new_v4();
+        let now = unix_now();
+        sqlx::query!(
+            r#"
+            INSERT INTO action_plan_renames (id, action_plan, old_name, new_name, renamed_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            rename_id,
+            id,
+            previous_name,
+            form.name,
+            now
+        )
+        .execute(&mut *tx)
+        .await?;
+        assign_slug(&mut tx, id, &form.name).await?;
     }
 
-    update_plan_items(tx, id, form, execution_id).await
+    let redirect =
+        update_plan_items(tx, &current_user, id, form, execution_id, Some(before)).await?;
+    webhooks::plan_published(&state, id);
+    Ok(redirect)
 }
 
+/// Saves the items and tags of a new or edited plan. `before` is the snapshot of an edited plan
+/// for the audit log, `None` for a new one.
 async fn update_plan_items<'c>(
     mut tx: Transaction<'c, Sqlite>,
+    current_user: &CurrentUser,
     plan_id: Uuid,
     form: ActionPlanForm,
     execution_id: Option<Uuid>,
+    before: Option<String>,
 ) -> Result<Redirect, AppError> {
     let ActionPlanForm {
-        name: _,
+        name,
         items,
+        estimated_minutes,
+        sub_plans,
+        descriptions,
+        description_for,
+        instructions,
+        item_types,
+        units,
+        min_values,
+        max_values,
+        condition_steps,
+        condition_values,
+        condition_modes,
+        sections,
+        nestings,
         tag_ids,
     } = form;
     let selected_tag_ids = normalize_tag_ids(tag_ids);
-    let mut execution_state_by_name: HashMap<String, Option<i64>> = HashMap::new();
+    let mut execution_state_by_name: HashMap<String, ExecutionItemState> = HashMap::new();
 
     if let Some(execution_id) = execution_id {
         let execution_items = sqlx::query!(
             r#"
             SELECT
                 actions.name as "name!",
-                action_item_executions.finished as "finished?"
+                action_item_executions.finished as "finished?",
+                action_item_executions.actual_seconds,
+                action_item_executions.linked_execution as "linked_execution: uuid::Uuid",
+                action_item_executions.item_type,
+                action_item_executions.value,
+                action_item_executions.skip_reason,
+                action_item_executions.skipped_at,
+                action_item_executions.skipped_by as "skipped_by: uuid::Uuid",
+                action_item_executions.failure_note,
+                action_item_executions.failed_at,
+                action_item_executions.failed_by as "failed_by: uuid::Uuid",
+                action_item_executions.note,
+                action_item_executions.blocked_reason,
+                action_item_executions.blocked_at,
+                action_item_executions.blocked_by as "blocked_by: uuid::Uuid",
+                action_item_executions.block_resolved_at,
+                action_item_executions.block_resolved_by as "block_resolved_by: uuid::Uuid",
+                action_item_executions.block_resolution,
+                action_item_executions.finished_by as "finished_by: uuid::Uuid",
+                action_item_executions.scanned_by as "scanned_by: uuid::Uuid",
+                action_item_executions.assigned_to as "assigned_to: uuid::Uuid",
+                action_item_executions.id as "id!: uuid::Uuid",
+                (
+                    SELECT COUNT(*)
+                    FROM execution_item_attachments
+                    WHERE execution_item_attachments.action_item_execution = action_item_executions.id
+                ) as "attachment_count!: i64"
             FROM action_item_executions
             INNER JOIN actions ON actions.id = action_item_executions.action
             WHERE action_item_executions.action_plan_execution = $1
@@ -325,7 +567,34 @@ async fn update_plan_items<'c>(
         .await?;
 
         for item in execution_items {
-            execution_state_by_name.insert(item.name, item.finished);
+            execution_state_by_name.insert(
+                item.name,
+                ExecutionItemState {
+                    finished: item.finished,
+                    actual_seconds: item.actual_seconds,
+                    linked_execution: item.linked_execution,
+                    item_type: ItemType::from_db(&item.item_type),
+                    value: item.value,
+                    skip_reason: item.skip_reason,
+                    skipped_at: item.skipped_at,
+                    skipped_by: item.skipped_by,
+                    failure_note: item.failure_note,
+                    failed_at: item.failed_at,
+                    failed_by: item.failed_by,
+                    note: item.note,
+                    blocked_reason: item.blocked_reason,
+                    blocked_at: item.blocked_at,
+                    blocked_by: item.blocked_by,
+                    block_resolved_at: item.block_resolved_at,
+                    block_resolved_by: item.block_resolved_by,
+                    block_resolution: item.block_resolution,
+                    finished_by: item.finished_by,
+                    scanned_by: item.scanned_by,
+                    assigned_to: item.assigned_to,
+                    previous_id: Some(item.id),
+                    attachment_count: item.attachment_count,
+                },
+            );
         }
         sqlx::query!(
             r#"
@@ -358,10 +627,164 @@ async fn update_plan_items<'c>(
         .await?;
     }
 
-    let normalized_items = normalize_items(items);
+    let sub_plan_options = fetch_sub_plan_options(&mut *tx, Some(plan_id)).await?;
+    let normalized_items = normalize_plan_items(
+        items,
+        estimated_minutes,
+        sub_plans,
+        descriptions,
+        description_for,
+        instructions,
+        item_types,
+        units,
+        min_values,
+        max_values,
+        condition_steps,
+        condition_values,
+        condition_modes,
+        sections,
+        nestings,
+        &sub_plan_options
+            .iter()
+            .map(|option| option.id)
+            .collect::<HashSet<_>>(),
+    );
+    if let Some(item) = normalized_items.iter().find(|item| {
+        item.description.as_ref().is_some_and(|description| {
+            description
+                .as_ref()
+                .is_some_and(|description| description.chars().count() > markdown::MAX_SOURCE_CHARS)
+        })
+    }) {
+        return Err(AppError::conflict(format!(
+            "The description of \"{}\" is longer than {} characters.",
+            item.name,
+            markdown::MAX_SOURCE_CHARS
+        )));
+    }
+    if let Some(item) = normalized_items.iter().find(|item| {
+        item.instructions
+            .as_ref()
+            .is_some_and(|instructions| instructions.chars().count() > MAX_INSTRUCTIONS_CHARS)
+    }) {
+        return Err(AppError::conflict(format!(
+            "The instructions for \"{}\" are longer than {} characters.",
+            item.name, MAX_INSTRUCTIONS_CHARS
+        )));
+    }
+    if let Some(item) = normalized_items.iter().find(|item| {
+        item.unit
+            .as_ref()
+            .is_some_and(|unit| unit.chars().count() > item_types::MAX_UNIT_CHARS)
+    }) {
+        return Err(AppError::conflict(format!(
+            "The unit of \"{}\" is longer than {} characters.",
+            item.name,
+            item_types::MAX_UNIT_CHARS
+        )));
+    }
+    if let Some(item) = normalized_items.iter().find(|item| {
+        item.section
+            .as_ref()
+            .is_some_and(|section| section.chars().count() > headings::MAX_CHARS)
+    }) {
+        return Err(AppError::conflict(format!(
+            "The section heading above \"{}\" is longer than {} characters.",
+            item.name,
+            headings::MAX_CHARS
+        )));
+    }
+    if let Some(item) = normalized_items.iter().find(
+        |item| matches!((item.min_value, item.max_value), (Some(min), Some(max)) if min > max),
+    ) {
+        return Err(AppError::conflict(format!(
+            "The lowest expected reading of \"{}\" is above the highest.",
+            item.name
+        )));
+    }
+    for (position, item) in normalized_items.iter().enumerate() {
+        let Some(condition_step) = item.condition_step else {
+            continue;
+        };
+        if condition_step < 1 || condition_step > position as i64 {
+            return Err(AppError::conflict(format!(
+                "\"{}\" can only depend on a step before it.",
+                item.name
+            )));
+        }
+        let other = &normalized_items[condition_step as usize - 1];
+        if item.condition_value.is_some() && !other.item_type.captures_value() {
+            return Err(AppError::conflict(format!(
+                "\"{}\" depends on an answer, but \"{}\" is only checked off.",
+                item.name, other.name
+            )));
+        }
+        if item
+            .condition_value
+            .as_ref()
+            .is_some_and(|value| value.chars().count() > conditions::MAX_VALUE_CHARS)
+        {
+            return Err(AppError::conflict(format!(
+                "The answer \"{}\" depends on is longer than {} characters.",
+                item.name,
+                conditions::MAX_VALUE_CHARS
+            )));
+        }
+    }
+
+    // Headings take a place in the order of their own, right in front of their item.
+    let mut next_order = 0;
+    let order_indexes = normalized_items
+        .iter()
+        .map(|item| {
+            next_order += i64::from(item.section.is_some());
+            next_order += 1;
+            next_order - 1
+        })
+        .collect::<Vec<_>>();
+
+    // Sub-items hang under the closest top-level item above them.
+    let mut parent_indexes = Vec::with_capacity(normalized_items.len());
+    let mut last_top_level_index = None;
+    // Photos and files are evidence of the run, so an item holding some cannot just disappear
+    // with an edit.
+    let kept_names = normalized_items
+        .iter()
+        .map(|item| item.name.as_str())
+        .collect::<HashSet<_>>();
+    if let Some(name) = execution_state_by_name
+        .iter()
+        .filter(|(name, state)| state.attachment_count > 0 && !kept_names.contains(name.as_str()))
+        .map(|(name, _)| name)
+        .min()
+    {
+        return Err(AppError::conflict(format!(
+            "\"{}\" has attachments in this run. Keep the item or delete its attachments first.",
+            name
+        )));
+    }
+
+    for (item, order) in normalized_items.iter().zip(&order_indexes) {
+        if !item.is_sub_item {
+            last_top_level_index = Some(*order);
+            parent_indexes.push(None);
+            continue;
+        }
+        let Some(parent_index) = last_top_level_index else {
+            return Err(AppError::conflict(format!(
+                "\"{}\" cannot be a sub-item, there is no item above it.",
+                item.name
+            )));
+        };
+        parent_indexes.push(Some(parent_index));
+    }
 
-    for (order, item) in normalized_items.iter().enumerate() {
-        let action = sqlx::query!("SELECT id FROM actions WHERE name = $1", item)
+    for ((item, order), parent_index) in normalized_items
+        .iter()
+        .zip(order_indexes.iter().copied())
+        .zip(parent_indexes)
+    {
+        let action = sqlx::query!("SELECT id FROM actions WHERE name = $1", item.name)
             .fetch_optional(&mut *tx)
             .await?;
 
@@ -372,7 +795,7 @@ async fn update_plan_items<'c>(
                 sqlx::query!(
                     "INSERT INTO actions (id, name) VALUES ($1, $2)",
                     action_id,
-                    item
+                    item.name
                 )
                 .execute(&mut *tx)
                 .await?;
@@ -380,14 +803,54 @@ async fn update_plan_items<'c>(
                 action_id
             }
         };
-        let order = order as i64;
+        if let Some(description) = &item.description {
+            sqlx::query!(
+                "UPDATE actions SET description = $1 WHERE id = $2",
+                description,
+                action
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        if let Some(heading) = &item.section {
+            let heading_id = Uuid::new_v4();
+            let heading_order = order - 1;
+            sqlx::query!(
+                "INSERT INTO action_items (id, order_index, action_plan, heading) VALUES ($1, $2, $3, $4)",
+                heading_id,
+                heading_order,
+                plan_id,
+                heading
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
         let item_id = Uuid::new_v4();
+        let item_type = item.item_type.as_str();
+        // Validated above to point at an earlier item.
+        let condition_index = item
+            .condition_step
+            .map(|step| order_indexes[step as usize - 1]);
         sqlx::query!(
-            "INSERT INTO action_items (id, order_index, action_plan, action) VALUES ($1, $2, $3, $4)",
+            r#"
+            INSERT INTO action_items (id, order_index, action_plan, action, estimated_minutes, sub_plan, instructions, item_type, unit, min_value, max_value, condition_index, condition_value, condition_failed, parent_index)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            "#,
             item_id,
             order,
             plan_id,
-            action
+            action,
+            item.estimated_minutes,
+            item.sub_plan,
+            item.instructions,
+            item_type,
+            item.unit,
+            item.min_value,
+            item.max_value,
+            condition_index,
+            item.condition_value,
+            item.condition_failed,
+            parent_index
         )
         .execute(&mut *tx)
         .await?;
@@ -399,6 +862,17 @@ async fn update_plan_items<'c>(
             SELECT
                 action_items.action as "action_id: uuid::Uuid",
                 action_items.order_index,
+                action_items.estimated_minutes,
+                action_items.sub_plan as "sub_plan: uuid::Uuid",
+                action_items.instructions,
+                action_items.item_type,
+                action_items.unit,
+                action_items.min_value,
+                action_items.max_value,
+                action_items.condition_index,
+                action_items.condition_value,
+                action_items.condition_failed,
+                action_items.parent_index,
                 actions.name as "name!"
             FROM action_items
             INNER JOIN actions ON actions.id = action_items.action
@@ -409,27 +883,93 @@ async fn update_plan_items<'c>(
         )
         .fetch_all(&mut *tx)
         .await?;
+        let mut headings = headings::fetch_above_items(&mut *tx, plan_id).await?;
 
         for item in new_plan_items {
             let execution_item_id = Uuid::new_v4();
-            let finished = execution_state_by_name.get(&item.name).cloned().flatten();
+            let previous = execution_state_by_name
+                .get(&item.name)
+                .cloned()
+                .unwrap_or_default();
+            // A link only counts while the item still calls for the same plan.
+            let linked_execution = previous
+                .linked_execution
+                .filter(|_| item.sub_plan.is_some());
+            // And a value only while the item still asks for the same kind of value.
+            let value = previous
+                .value
+                .filter(|_| previous.item_type == ItemType::from_db(&item.item_type));
+            // Checked against the range as it is now.
+            let out_of_spec = value.as_deref().is_some_and(|value| {
+                item_types::is_out_of_spec(value, item.min_value, item.max_value)
+            });
+            let section = headings.remove(&item.order_index);
 
             sqlx::query!(
                 r#"
-                INSERT INTO action_item_executions (id, action, order_index, action_plan_execution, finished)
-                VALUES ($1, $2, $3, $4, $5)
+                INSERT INTO action_item_executions
+                    (id, action, order_index, action_plan_execution, finished, estimated_minutes, actual_seconds, sub_plan, linked_execution, instructions, item_type, unit, value, min_value, max_value, out_of_spec, condition_index, condition_value, condition_failed, skip_reason, skipped_at, skipped_by, failure_note, failed_at, failed_by, section, parent_index, note, blocked_reason, blocked_at, blocked_by, block_resolved_at, block_resolved_by, block_resolution, finished_by, scanned_by, assigned_to)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37)
                 "#,
                 execution_item_id,
                 item.action_id,
                 item.order_index,
                 execution_id,
-                finished
+                previous.finished,
+                item.estimated_minutes,
+                previous.actual_seconds,
+                item.sub_plan,
+                linked_execution,
+                item.instructions,
+                item.item_type,
+                item.unit,
+                value,
+                item.min_value,
+                item.max_value,
+                out_of_spec,
+                item.condition_index,
+                item.condition_value,
+                item.condition_failed,
+                previous.skip_reason,
+                previous.skipped_at,
+                previous.skipped_by,
+                previous.failure_note,
+                previous.failed_at,
+                previous.failed_by,
+                section,
+                item.parent_index,
+                previous.note,
+                previous.blocked_reason,
+                previous.blocked_at,
+                previous.blocked_by,
+                previous.block_resolved_at,
+                previous.block_resolved_by,
+                previous.block_resolution,
+                previous.finished_by,
+                previous.scanned_by,
+                previous.assigned_to
             )
             .execute(&mut *tx)
             .await?;
+            if let Some(previous_id) = previous.previous_id {
+                tasks::move_to_item(&mut tx, previous_id, execution_item_id).await?;
+                attachments::move_to_item(&mut tx, previous_id, execution_item_id).await?;
+            }
         }
     }
 
+    let after = plan_snapshot(&mut tx, plan_id).await?;
+    let (action, summary) = match before {
+        Some(_) => (AuditAction::Update, format!("Edited plan \"{}\"", name)),
+        None => (AuditAction::Create, format!("Created plan \"{}\"", name)),
+    };
+    audit::record(
+        &mut *tx,
+        current_user,
+        AuditEntry::new(AuditEntity::Plan, plan_id, action, summary).change(before, Some(after)),
+    )
+    .await?;
+
     tx.commit().await?;
 
     if let Some(execution_id) = execution_id {
@@ -439,6 +979,16 @@ async fn update_plan_items<'c>(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/action_plan/{id}",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan id")),
+    responses(
+        (status = 200, description = "Action plan details with its executions", content_type = "text/html"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+    )
+)]
 pub async fn show_action_plan(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -464,13 +1014,50 @@ pub async fn show_action_plan(
             format!("No action plan exists for id: {}", id),
         ));
     };
-
-    let items = sqlx::query_as!(
-        ActionPlanItem,
+    let slug = sqlx::query_scalar!("SELECT slug FROM action_plans WHERE id = $1", id)
+        .fetch_one(&state.db)
+        .await?;
+    let renames = sqlx::query!(
         r#"
-        SELECT actions.name as "name!"
+        SELECT old_name, renamed_at
+        FROM action_plan_renames
+        WHERE action_plan = $1
+        ORDER BY renamed_at DESC
+        "#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|rename| PlanRename {
+        old_name: rename.old_name,
+        renamed_at_display: format_unix_timestamp(rename.renamed_at),
+    })
+    .collect();
+
+    let item_rows = sqlx::query!(
+        r#"
+        SELECT
+            actions.name as "name!",
+            action_items.order_index,
+            action_items.instructions,
+            action_items.parent_index,
+            action_items.estimated_minutes,
+            (
+                SELECT AVG(action_item_executions.actual_seconds)
+                FROM action_item_executions
+                INNER JOIN action_plan_executions
+                    ON action_plan_executions.id = action_item_executions.action_plan_execution
+                WHERE action_plan_executions.action_plan = action_items.action_plan
+                    AND action_plan_executions.finished > 0
+                    AND action_item_executions.action = action_items.action
+                    AND action_item_executions.actual_seconds IS NOT NULL
+            ) as "average_actual_seconds: f64",
+            action_items.sub_plan as "sub_plan: uuid::Uuid",
+            sub_plans.name as "sub_plan_name?"
         FROM action_items
         INNER JOIN actions ON actions.id = action_items.action
+        LEFT JOIN action_plans AS sub_plans ON sub_plans.id = action_items.sub_plan
         WHERE action_items.action_plan = $1
         ORDER BY action_items.order_index ASC
         "#,
@@ -478,19 +1065,58 @@ pub async fn show_action_plan(
     )
     .fetch_all(&state.db)
     .await?;
+    let mut headings = headings::fetch_above_items(&state.db, id).await?;
+    let estimated_total_minutes = item_rows
+        .iter()
+        .filter_map(|item| item.estimated_minutes)
+        .sum::<i64>();
+    let items = item_rows
+        .into_iter()
+        .map(|item| ActionPlanShowItem {
+            name: item.name,
+            instructions: item.instructions,
+            section: headings.remove(&item.order_index),
+            is_sub_item: item.parent_index.is_some(),
+            estimate_display: item.estimated_minutes.map(format_estimate),
+            average_actual_display: item
+                .average_actual_seconds
+                .map(|seconds| format_duration(seconds.round() as i64)),
+            sub_plan: item.sub_plan,
+            sub_plan_name: item.sub_plan_name,
+        })
+        .collect();
     let tags = tags::fetch_badges_for_plan(&state.db, id).await?;
 
+    let durations = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) as "run_count!: i64",
+            AVG(finished - started) as "average_seconds: f64"
+        FROM action_plan_executions
+        WHERE action_plan = $1
+            AND finished > 0
+        "#,
+        id
+    )
+    .fetch_one(&state.db)
+    .await?;
+
     let active_execution_rows = sqlx::query_as!(
         PlanExecutionActiveRow,
         r#"
         SELECT
-            id as "id!: uuid::Uuid",
-            started as "started!",
-            note
+            action_plan_executions.id as "id!: uuid::Uuid",
+            action_plan_executions.started as "started!",
+            action_plan_executions.note,
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label,
+            merged_plans.name as "merged_from_name?"
         FROM action_plan_executions
-        WHERE action_plan = $1
-            AND (finished IS NULL OR finished <= 0)
-        ORDER BY started DESC
+        LEFT JOIN action_plans AS merged_plans
+            ON merged_plans.id = action_plan_executions.merged_from
+        WHERE action_plan_executions.action_plan = $1
+            AND (action_plan_executions.finished IS NULL OR action_plan_executions.finished <= 0)
+        ORDER BY action_plan_executions.started DESC
         "#,
         id
     )
@@ -501,14 +1127,19 @@ pub async fn show_action_plan(
         PlanExecutionFinishedRow,
         r#"
         SELECT
-            id as "id!: uuid::Uuid",
-            started as "started!",
-            finished as "finished!",
-            note
+            action_plan_executions.id as "id!: uuid::Uuid",
+            action_plan_executions.started as "started!",
+            action_plan_executions.finished as "finished!",
+            action_plan_executions.note,
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label,
+            merged_plans.name as "merged_from_name?"
         FROM action_plan_executions
-        WHERE action_plan = $1
-            AND finished > 0
-        ORDER BY finished DESC
+        LEFT JOIN action_plans AS merged_plans
+            ON merged_plans.id = action_plan_executions.merged_from
+        WHERE action_plan_executions.action_plan = $1
+            AND action_plan_executions.finished > 0
+        ORDER BY action_plan_executions.finished DESC
         "#,
         id
     )
@@ -521,6 +1152,9 @@ pub async fn show_action_plan(
             id: row.id,
             started_display: format_unix_timestamp(row.started),
             note: row.note,
+            run_number: row.run_number,
+            label: row.label,
+            merged_from_name: row.merged_from_name,
         })
         .collect();
 
@@ -531,6 +1165,9 @@ pub async fn show_action_plan(
             started_display: format_unix_timestamp(row.started),
             finished_display: format_unix_timestamp(row.finished),
             note: row.note,
+            run_number: row.run_number,
+            label: row.label,
+            merged_from_name: row.merged_from_name,
         })
         .collect();
 
@@ -539,6 +1176,8 @@ pub async fn show_action_plan(
     let plan = ActionPlanShow {
         id: plan.id,
         name: plan.name,
+        slug,
+        renames,
         tags,
         is_deleted: plan.deleted_at.map(|value| value > 0).unwrap_or(false),
         deleted_at_display: plan
@@ -546,26 +1185,198 @@ pub async fn show_action_plan(
             .filter(|value| *value > 0)
             .map(format_unix_timestamp),
         items,
+        estimated_total_display: (estimated_total_minutes > 0)
+            .then(|| format_estimate(estimated_total_minutes)),
+        average_duration_display: durations
+            .average_seconds
+            .map(|seconds| format_duration(seconds.round() as i64)),
+        finished_run_count: durations.run_count,
         active_executions,
         finished_executions,
         active_execution_link,
+        sync: sync::fetch_plan_info(&state.db, &state.config, id).await?,
+        attachments: attachments::fetch_for_plan(&state.db, id).await?,
+        attachment_accept: state.config.attachment_allowed_types.join(","),
+        acknowledgments: procedure_changes::fetch_for_plan(&state.db, id).await?,
+        can_run_executions: current_user.role >= Role::Operator,
         is_admin: current_user.is_admin,
     };
 
-    let template = state
-        .jinja
-        .get_template("action_plan_show.html")
-        .expect("template is loaded");
-    let rendered = template.render(&plan)?;
+    let rendered = templates::render(&state.jinja, "action_plan_show.html", &plan)?;
 
     Ok(Html(rendered))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/plans/{id}/markdown",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan id")),
+    responses(
+        (status = 200, description = "The action plan as a Markdown checklist", content_type = "text/markdown"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+    )
+)]
+pub async fn markdown_get(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let markdown = plan_markdown(&state.db, id).await?;
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/markdown; charset=utf-8"),
+        )],
+        markdown,
+    ))
+}
+
+/// The plan as a Markdown checklist, for the export and the publish webhook.
+pub async fn plan_markdown(db: &SqlitePool, id: Uuid) -> Result<String, AppError> {
+    let plan_name = sqlx::query_scalar!(
+        r#"
+        SELECT name
+        FROM action_plans
+        WHERE id = $1
+            AND (deleted_at IS NULL OR deleted_at <= 0)
+        "#,
+        id
+    )
+    .fetch_optional(db)
+    .await?;
+    let Some(plan_name) = plan_name else {
+        return Err(AppError::not_found_for(
+            "Action Plan",
+            format!("No action plan exists for id: {}", id),
+        ));
+    };
+
+    let items = fetch_plan_items(db, id).await?;
+    let tags = tags::fetch_badges_for_plan(db, id).await?;
+
+    let mut markdown = format!("# {}\n\n", escape_markdown(&plan_name));
+    if !tags.is_empty() {
+        let tag_names = tags
+            .iter()
+            .map(|tag| escape_markdown(&tag.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        markdown.push_str(&format!("Tags: {}\n\n", tag_names));
+    }
+    for item in &items {
+        if let Some(section) = &item.section {
+            if !markdown.ends_with("\n\n") {
+                markdown.push('\n');
+            }
+            markdown.push_str(&format!("## {}\n\n", escape_markdown(section)));
+        }
+        let indent = if item.parent_index.is_some() {
+            "  "
+        } else {
+            ""
+        };
+        let mut line = format!("{}- [ ] {}", indent, escape_markdown(&item.name));
+        match ItemType::from_db(&item.item_type) {
+            ItemType::Checkbox => {}
+            ItemType::Text => line.push_str(": ___"),
+            ItemType::Number => {
+                line.push_str(&format!(
+                    ": ___{}",
+                    item.unit
+                        .as_deref()
+                        .map(|unit| format!(" {}", escape_markdown(unit)))
+                        .unwrap_or_default()
+                ));
+                if let Some(range) =
+                    item_types::range_display(item.min_value, item.max_value, item.unit.as_deref())
+                {
+                    line.push_str(&format!(" (expected {})", escape_markdown(&range)));
+                }
+            }
+        }
+        if let Some(minutes) = item.estimated_minutes {
+            line.push_str(&format!(" (~{})", format_estimate(minutes)));
+        }
+        if let Some(sub_plan_name) = &item.sub_plan_name {
+            line.push_str(&format!(
+                " (sub-procedure: {})",
+                escape_markdown(sub_plan_name)
+            ));
+        }
+        if let Some(other) = item
+            .condition_index
+            .and_then(|index| items.iter().find(|other| other.order_index == index))
+        {
+            line.push_str(&format!(
+                " ({})",
+                escape_markdown(&conditions::describe(
+                    &other.name,
+                    item.condition_value.as_deref(),
+                    item.condition_failed
+                ))
+            ));
+        }
+        markdown.push_str(&line);
+        markdown.push('\n');
+        // Descriptions are Markdown already, so they go in as written, indented past the
+        // item's bullet to stay part of it.
+        if let Some(description) = item
+            .description
+            .as_deref()
+            .filter(|description| !description.trim().is_empty())
+        {
+            for description_line in description.trim().lines().map(str::trim_end) {
+                if description_line.is_empty() {
+                    markdown.push('\n');
+                } else {
+                    markdown.push_str(&format!("{}  {}\n", indent, description_line));
+                }
+            }
+        }
+        if let Some(instructions) = &item.instructions {
+            for instruction in instructions.lines() {
+                markdown.push_str(&format!("{}  > {}\n", indent, escape_markdown(instruction)));
+            }
+        }
+    }
+
+    Ok(markdown)
+}
+
+/// Escapes characters that would otherwise turn plain names into Markdown formatting.
+fn escape_markdown(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        if matches!(
+            character,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+#[utoipa::path(
+    post,
+    path = "/action_plan/{id}/delete",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan id")),
+    responses(
+        (status = 303, description = "Marks the action plan as deleted and redirects to the plan list with an undo notice"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+    )
+)]
 pub async fn delete_post(
     State(state): State<AppState>,
+    current_user: CurrentUser,
+    jar: CookieJar,
     Path(id): Path<Uuid>,
-) -> Result<Redirect, AppError> {
+) -> Result<(CookieJar, Redirect), AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
     let now = unix_now();
+    let mut tx = state.db.begin().await?;
     let result = sqlx::query!(
         r#"
         UPDATE action_plans
@@ -576,7 +1387,7 @@ pub async fn delete_post(
         now,
         id
     )
-    .execute(&state.db)
+    .execute(&mut *tx)
     .await?;
 
     if result.rows_affected() == 0 {
@@ -585,14 +1396,43 @@ pub async fn delete_post(
             format!("No active action plan exists for id: {}", id),
         ));
     }
+    let before = plan_snapshot(&mut tx, id).await?;
+    let name = before.lines().next().unwrap_or_default().to_string();
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Plan,
+            id,
+            AuditAction::Delete,
+            format!("Deleted plan \"{}\"", name),
+        )
+        .change(Some(before), None),
+    )
+    .await?;
+    tx.commit().await?;
 
-    Ok(Redirect::to("/"))
+    let jar = flash::set(jar, &state.config, Flash::PlanDeleted { plan_id: id });
+    Ok((jar, Redirect::to("/plans")))
 }
 
+#[utoipa::path(
+    post,
+    path = "/action_plan/{id}/undelete",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan id")),
+    responses(
+        (status = 303, description = "Restores the action plan and redirects to it"),
+        (status = 404, description = "Deleted action plan not found", content_type = "text/html"),
+    )
+)]
 pub async fn undelete_post(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
 ) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
+    let mut tx = state.db.begin().await?;
     let result = sqlx::query!(
         r#"
         UPDATE action_plans
@@ -602,7 +1442,7 @@ pub async fn undelete_post(
         "#,
         id
     )
-    .execute(&state.db)
+    .execute(&mut *tx)
     .await?;
 
     if result.rows_affected() == 0 {
@@ -611,97 +1451,969 @@ pub async fn undelete_post(
             format!("No deleted action plan exists for id: {}", id),
         ));
     }
+    let after = plan_snapshot(&mut tx, id).await?;
+    let name = after.lines().next().unwrap_or_default().to_string();
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Plan,
+            id,
+            AuditAction::Restore,
+            format!("Restored plan \"{}\"", name),
+        )
+        .change(None, Some(after)),
+    )
+    .await?;
+    tx.commit().await?;
 
+    webhooks::plan_published(&state, id);
     Ok(Redirect::to(&format!("/action_plan/{}", id)))
 }
 
 #[derive(Serialize)]
-pub struct ActionPlanEdit {
-    id: Option<Uuid>,
-    form_action: String,
-    cancel_url: String,
-    name: String,
-    items: Vec<ActionPlanItem>,
-    available_tags: Vec<ActionPlanTagOption>,
-    is_admin: bool,
-}
-
-#[derive(Serialize)]
-pub struct ActionPlanShow {
+struct MergePlanView {
     id: Uuid,
     name: String,
-    tags: Vec<TagBadge>,
-    is_deleted: bool,
-    deleted_at_display: Option<String>,
-    items: Vec<ActionPlanItem>,
-    active_executions: Vec<PlanExecutionActive>,
-    finished_executions: Vec<PlanExecutionFinished>,
-    active_execution_link: Option<Uuid>,
+    candidates: Vec<PlanSearchItem>,
     is_admin: bool,
 }
 
-#[derive(Serialize)]
-pub struct ActionPlanItem {
-    pub name: String,
+/// What to do when both plans contain the same action with different estimates.
+#[derive(Debug, Default, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeEstimateChoice {
+    #[default]
+    KeepTarget,
+    TakeSource,
 }
 
-#[derive(Serialize)]
-pub struct ActionPlanTagOption {
-    id: Uuid,
-    name: String,
-    color_style: String,
-    selected: bool,
+#[derive(Deserialize, ToSchema)]
+pub struct MergePlanForm {
+    /// The plan merged into this one and deleted afterwards.
+    source_id: Uuid,
+    #[serde(default)]
+    estimates: MergeEstimateChoice,
 }
 
-#[derive(FromRow, Serialize)]
-struct PlanExecutionActive {
-    id: Uuid,
-    started_display: String,
-    note: Option<String>,
-}
+#[utoipa::path(
+    get,
+    path = "/action_plan/{id}/merge",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan that receives the other plan")),
+    responses(
+        (status = 200, description = "Form for merging another plan into this one", content_type = "text/html"),
+        (status = 403, description = "Only admin users can merge plans", content_type = "text/html"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+    )
+)]
+pub async fn merge_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::MergePlans, Resource::Everything)?;
+    let name = fetch_active_plan_name(&state.db, id).await?;
 
-#[derive(FromRow, Serialize)]
-struct PlanExecutionFinished {
-    id: Uuid,
-    started_display: String,
-    finished_display: String,
-    note: Option<String>,
-}
+    let candidates = sqlx::query_as!(
+        PlanSearchItem,
+        r#"
+        SELECT id as "id!: uuid::Uuid", name
+        FROM action_plans
+        WHERE (deleted_at IS NULL OR deleted_at <= 0)
+            AND id != $1
+        ORDER BY name ASC
+        "#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await?;
 
-#[derive(FromRow)]
-struct PlanExecutionActiveRow {
-    id: Uuid,
-    started: i64,
-    note: Option<String>,
-}
+    let rendered = templates::render(
+        &state.jinja,
+        "action_plan_merge.html",
+        MergePlanView {
+            id,
+            name,
+            candidates,
+            is_admin: current_user.is_admin,
+        },
+    )?;
 
-#[derive(FromRow)]
-struct PlanExecutionFinishedRow {
-    id: Uuid,
-    started: i64,
-    finished: i64,
-    note: Option<String>,
+    Ok(Html(rendered))
 }
 
-fn edit_action_plan(state: &AppState, plan: &ActionPlanEdit) -> Result<Html<String>, AppError> {
-    let template = state
-        .jinja
-        .get_template("action_plan_edit.html")
-        .expect("template is loaded");
-    let rendered = template.render(plan)?;
+/// Merges the source plan into this one: the source's items missing here are appended, its
+/// tags are added, its executions move over (numbered after this plan's runs and marked with
+/// `merged_from`), and the source plan is deleted.
+#[utoipa::path(
+    post,
+    path = "/action_plan/{id}/merge",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan that receives the other plan")),
+    request_body(content = MergePlanForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Plans merged, redirects to the remaining plan"),
+        (status = 403, description = "Only admin users can merge plans", content_type = "text/html"),
+        (status = 404, description = "One of the plans was not found", content_type = "text/html"),
+        (status = 409, description = "A plan cannot be merged into itself", content_type = "text/html"),
+    )
+)]
+pub async fn merge_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<MergePlanForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::MergePlans, Resource::Everything)?;
+    let source_id = form.source_id;
+    if source_id == id {
+        return Err(AppError::conflict("A plan cannot be merged into itself."));
+    }
+    let target_name = fetch_active_plan_name(&state.db, id).await?;
+    let source_name = fetch_active_plan_name(&state.db, source_id).await?;
+
+    let mut tx = state.db.begin().await?;
+    let before = plan_snapshot(&mut tx, id).await?;
+
+    let target_items = sqlx::query!(
+        r#"
+        SELECT action as "action_id: uuid::Uuid", order_index
+        FROM action_items
+        WHERE action_plan = $1
+        "#,
+        id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let target_actions = target_items
+        .iter()
+        .filter_map(|item| item.action_id)
+        .collect::<HashSet<_>>();
+    let mut next_order = target_items
+        .iter()
+        .map(|item| item.order_index + 1)
+        .max()
+        .unwrap_or(0);
+    // Where each action ends up in the target, to point conditions of merged items at it.
+    let mut target_index_by_action = target_items
+        .iter()
+        .filter_map(|item| Some((item.action_id?, item.order_index)))
+        .collect::<HashMap<_, _>>();
+
+    let source_items = sqlx::query!(
+        r#"
+        SELECT
+            action as "action_id: uuid::Uuid",
+            order_index,
+            estimated_minutes,
+            sub_plan as "sub_plan: uuid::Uuid",
+            instructions,
+            item_type,
+            unit,
+            min_value,
+            max_value,
+            condition_index,
+            condition_value,
+            condition_failed as "condition_failed: bool",
+            heading,
+            parent_index
+        FROM action_items
+        WHERE action_plan = $1
+        ORDER BY order_index ASC
+        "#,
+        source_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let source_action_by_index = source_items
+        .iter()
+        .filter_map(|item| Some((item.order_index, item.action_id?)))
+        .collect::<HashMap<_, _>>();
+    let mut added_actions = HashSet::new();
+    // Where each added item ends up. Sub-items only stay nested under a parent added along with
+    // them, as an item already in the target may be a sub-item itself.
+    let mut added_index_by_action = HashMap::new();
+    // A heading goes in front of the next added item, as the items right after it may already
+    // be in the target.
+    let mut pending_heading = None;
+    for item in source_items {
+        let Some(action_id) = item.action_id else {
+            pending_heading = item.heading;
+            continue;
+        };
+        if target_actions.contains(&action_id) {
+            if matches!(form.estimates, MergeEstimateChoice::TakeSource)
+                && item.estimated_minutes.is_some()
+            {
+                sqlx::query!(
+                    "UPDATE action_items SET estimated_minutes = $1 WHERE action_plan = $2 AND action = $3",
+                    item.estimated_minutes,
+                    id,
+                    action_id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            continue;
+        }
+        if !added_actions.insert(action_id) {
+            continue;
+        }
+
+        if let Some(heading) = pending_heading.take() {
+            let heading_id = Uuid::new_v4();
+            sqlx::query!(
+                "INSERT INTO action_items (id, order_index, action_plan, heading) VALUES ($1, $2, $3, $4)",
+                heading_id,
+                next_order,
+                id,
+                heading
+            )
+            .execute(&mut *tx)
+            .await?;
+            next_order += 1;
+        }
+
+        let item_id = Uuid::new_v4();
+        // An item calling for the target itself would be unsatisfiable after the merge.
+        let sub_plan = item.sub_plan.filter(|sub_plan| *sub_plan != id);
+        let condition_index = item
+            .condition_index
+            .and_then(|index| source_action_by_index.get(&index))
+            .and_then(|action_id| target_index_by_action.get(action_id))
+            .copied();
+        let condition_value = item.condition_value.filter(|_| condition_index.is_some());
+        let condition_failed = item.condition_failed && condition_index.is_some();
+        let parent_index = item
+            .parent_index
+            .and_then(|index| source_action_by_index.get(&index))
+            .and_then(|action_id| added_index_by_action.get(action_id))
+            .copied();
+        target_index_by_action.insert(action_id, next_order);
+        added_index_by_action.insert(action_id, next_order);
+        sqlx::query!(
+            r#"
+            INSERT INTO action_items (id, order_index, action_plan, action, estimated_minutes, sub_plan, instructions, item_type, unit, min_value, max_value, condition_index, condition_value, condition_failed, parent_index)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            "#,
+            item_id,
+            next_order,
+            id,
+            action_id,
+            item.estimated_minutes,
+            sub_plan,
+            item.instructions,
+            item.item_type,
+            item.unit,
+            item.min_value,
+            item.max_value,
+            condition_index,
+            condition_value,
+            condition_failed,
+            parent_index
+        )
+        .execute(&mut *tx)
+        .await?;
+        next_order += 1;
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT OR IGNORE INTO action_plan_tags (action_plan, tag)
+        SELECT $1, tag FROM action_plan_tags WHERE action_plan = $2
+        "#,
+        id,
+        source_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let last_run_number = sqlx::query_scalar!(
+        r#"
+        SELECT MAX(
+            (SELECT COALESCE(MAX(run_number), 0) FROM action_plan_executions WHERE action_plan = $1),
+            (SELECT archived_run_number FROM action_plans WHERE id = $1)
+        ) as "run_number!: i64"
+        "#,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE action_plan_executions
+        SET
+            action_plan = $1,
+            merged_from = COALESCE(merged_from, $2),
+            run_number = $3 + COALESCE(run_number, 0)
+        WHERE action_plan = $2
+        "#,
+        id,
+        source_id,
+        last_run_number
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Links to the merged plan lead to the remaining one from now on.
+    sqlx::query!(
+        "UPDATE action_plan_slugs SET action_plan = $1 WHERE action_plan = $2",
+        id,
+        source_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE plan_attachments SET action_plan = $1 WHERE action_plan = $2",
+        id,
+        source_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE action_plans SET slug = NULL WHERE id = $1",
+        source_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let now = unix_now();
+    sqlx::query!(
+        "UPDATE action_plans SET deleted_at = $1 WHERE id = $2",
+        now,
+        source_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let after = plan_snapshot(&mut tx, id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Plan,
+            id,
+            AuditAction::Merge,
+            format!("Merged plan \"{}\" into \"{}\"", source_name, target_name),
+        )
+        .change(Some(before), Some(after)),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(
+        user = current_user.name,
+        target = %id,
+        source = %source_id,
+        "Merged action plans"
+    );
+    webhooks::plan_published(&state, id);
+    Ok(Redirect::to(&format!("/action_plan/{}", id)))
+}
+
+/// The name and items of a plan as text, for the audit log.
+pub(crate) async fn plan_snapshot(
+    conn: &mut SqliteConnection,
+    plan_id: Uuid,
+) -> Result<String, AppError> {
+    let name = sqlx::query_scalar!("SELECT name FROM action_plans WHERE id = $1", plan_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let items = sqlx::query_scalar!(
+        r#"
+        SELECT actions.name as "name!"
+        FROM action_items
+        INNER JOIN actions ON actions.id = action_items.action
+        WHERE action_items.action_plan = $1
+        ORDER BY action_items.order_index ASC
+        "#,
+        plan_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(format!("{}\nItems: {}", name, items.join(", ")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plans/{slug}",
+    tag = "action_plans",
+    params(("slug" = String, Path, description = "Current or earlier slug of the action plan")),
+    responses(
+        (status = 303, description = "Redirects to the action plan"),
+        (status = 404, description = "No plan ever had this slug", content_type = "text/html"),
+    )
+)]
+pub async fn slug_get(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Redirect, AppError> {
+    let plan_id = sqlx::query_scalar!(
+        r#"SELECT action_plan as "action_plan: uuid::Uuid" FROM action_plan_slugs WHERE slug = $1"#,
+        slug
+    )
+    .fetch_optional(&state.db)
+    .await?;
+    let Some(plan_id) = plan_id else {
+        return Err(AppError::not_found_for(
+            "Action Plan",
+            format!("No action plan exists for: {}", slug),
+        ));
+    };
+
+    Ok(Redirect::to(&format!("/action_plan/{}", plan_id)))
+}
+
+/// Makes the slug for `name` the plan's current one. Slugs held by other plans, now or in
+/// the past, are never reused; a number is appended instead.
+pub async fn assign_slug(
+    conn: &mut sqlx::SqliteConnection,
+    plan_id: Uuid,
+    name: &str,
+) -> Result<(), AppError> {
+    let base = slugify(name);
+    let mut suffix = 1;
+    let slug = loop {
+        let candidate = if suffix == 1 {
+            base.clone()
+        } else {
+            format!("{}-{}", base, suffix)
+        };
+        let owner = sqlx::query_scalar!(
+            r#"SELECT action_plan as "action_plan: uuid::Uuid" FROM action_plan_slugs WHERE slug = $1"#,
+            candidate
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        match owner {
+            Some(owner) if owner != plan_id => suffix += 1,
+            Some(_) => break candidate,
+            None => {
+                let now = unix_now();
+                sqlx::query!(
+                    "INSERT INTO action_plan_slugs (slug, action_plan, created_at) VALUES ($1, $2, $3)",
+                    candidate,
+                    plan_id,
+                    now
+                )
+                .execute(&mut *conn)
+                .await?;
+                break candidate;
+            }
+        }
+    };
+
+    sqlx::query!(
+        "UPDATE action_plans SET slug = $1 WHERE id = $2",
+        slug,
+        plan_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Gives plans created before slugs existed (or restored from old backups) their slug.
+pub async fn assign_missing_slugs(db: &sqlx::SqlitePool) -> Result<usize, AppError> {
+    let mut tx = db.begin().await?;
+    let plans = sqlx::query!(
+        r#"
+        SELECT id as "id: uuid::Uuid", name
+        FROM action_plans
+        WHERE slug IS NULL
+            AND (deleted_at IS NULL OR deleted_at <= 0)
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for plan in &plans {
+        assign_slug(&mut tx, plan.id, &plan.name).await?;
+    }
+    tx.commit().await?;
+    Ok(plans.len())
+}
+
+const MAX_SLUG_LENGTH: usize = 60;
+
+/// Lowercase ASCII letters and digits separated by single dashes, e.g. "Server Room Check"
+/// becomes "server-room-check". German umlauts are spelled out.
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for character in name.to_lowercase().chars() {
+        let replacement = match character {
+            'ä' => "ae",
+            'ö' => "oe",
+            'ü' => "ue",
+            'ß' => "ss",
+            character if character.is_ascii_alphanumeric() => {
+                slug.push(character);
+                continue;
+            }
+            _ => "-",
+        };
+        if replacement == "-" {
+            if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        } else {
+            slug.push_str(replacement);
+        }
+    }
+
+    let slug = slug.chars().take(MAX_SLUG_LENGTH).collect::<String>();
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "plan".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+async fn fetch_active_plan_name(db: &sqlx::SqlitePool, id: Uuid) -> Result<String, AppError> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT name
+        FROM action_plans
+        WHERE id = $1
+            AND (deleted_at IS NULL OR deleted_at <= 0)
+        "#,
+        id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Action Plan",
+            format!("No action plan exists for id: {}", id),
+        )
+    })
+}
+
+#[derive(Serialize)]
+pub struct ActionPlanEdit {
+    id: Option<Uuid>,
+    form_action: String,
+    cancel_url: String,
+    name: String,
+    items: Vec<ActionPlanItem>,
+    available_tags: Vec<ActionPlanTagOption>,
+    /// Plans an item can call for as a sub-procedure.
+    available_sub_plans: Vec<SubPlanOption>,
+    item_types: Vec<ItemTypeOption>,
+    is_admin: bool,
+}
+
+#[derive(Serialize)]
+pub struct ActionPlanShow {
+    id: Uuid,
+    name: String,
+    slug: Option<String>,
+    /// Earlier names, newest first.
+    renames: Vec<PlanRename>,
+    tags: Vec<TagBadge>,
+    is_deleted: bool,
+    deleted_at_display: Option<String>,
+    items: Vec<ActionPlanShowItem>,
+    estimated_total_display: Option<String>,
+    average_duration_display: Option<String>,
+    finished_run_count: i64,
+    active_executions: Vec<PlanExecutionActive>,
+    finished_executions: Vec<PlanExecutionFinished>,
+    active_execution_link: Option<Uuid>,
+    /// Set for plans pulled from a central instance.
+    sync: Option<PlanSyncInfo>,
+    attachments: Vec<PlanAttachment>,
+    attachment_accept: String,
+    acknowledgments: Vec<ProcedureAcknowledgment>,
+    can_run_executions: bool,
+    is_admin: bool,
+}
+
+#[derive(Serialize)]
+pub struct ActionPlanItem {
+    pub name: String,
+    pub description: Option<String>,
+    pub order_index: i64,
+    pub instructions: Option<String>,
+    pub item_type: String,
+    pub unit: Option<String>,
+    pub min_value: Option<f64>,
+    pub max_value: Option<f64>,
+    /// Order index of the earlier item this one depends on.
+    pub condition_index: Option<i64>,
+    /// Step number of that item in the editor, counting items from 1.
+    pub condition_step: Option<i64>,
+    pub condition_value: Option<String>,
+    /// Depends on the earlier item being marked failed instead of checked off.
+    pub condition_failed: bool,
+    /// Heading shown above this item.
+    pub section: Option<String>,
+    /// Order index of the item this one is nested under.
+    pub parent_index: Option<i64>,
+    pub estimated_minutes: Option<i64>,
+    pub sub_plan: Option<Uuid>,
+    pub sub_plan_name: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SubPlanOption {
+    id: Uuid,
+    name: String,
+}
+
+/// What an execution item keeps when the plan is edited during the run.
+#[derive(Clone, Default)]
+struct ExecutionItemState {
+    finished: Option<i64>,
+    actual_seconds: Option<i64>,
+    linked_execution: Option<Uuid>,
+    item_type: ItemType,
+    value: Option<String>,
+    skip_reason: Option<String>,
+    skipped_at: Option<i64>,
+    skipped_by: Option<Uuid>,
+    failure_note: Option<String>,
+    failed_at: Option<i64>,
+    failed_by: Option<Uuid>,
+    note: Option<String>,
+    blocked_reason: Option<String>,
+    blocked_at: Option<i64>,
+    blocked_by: Option<Uuid>,
+    block_resolved_at: Option<i64>,
+    block_resolved_by: Option<Uuid>,
+    block_resolution: Option<String>,
+    finished_by: Option<Uuid>,
+    scanned_by: Option<Uuid>,
+    assigned_to: Option<Uuid>,
+    /// Id of the item before the edit, to carry its follow-up tasks and attachments over.
+    previous_id: Option<Uuid>,
+    attachment_count: i64,
+}
+
+/// Longest plan-specific instructions accepted for an item, in characters.
+const MAX_INSTRUCTIONS_CHARS: usize = 1_000;
+
+/// A non-empty item from the plan form with its optional estimate and sub-procedure.
+struct PlanItemInput {
+    name: String,
+    estimated_minutes: Option<i64>,
+    sub_plan: Option<Uuid>,
+    /// New description for the action, `Some(None)` to clear it, `None` to keep it.
+    description: Option<Option<String>>,
+    /// What to mind about the item in this plan only.
+    instructions: Option<String>,
+    item_type: ItemType,
+    /// Only kept for numeric items, like the expected range.
+    unit: Option<String>,
+    min_value: Option<f64>,
+    max_value: Option<f64>,
+    /// Step number of the earlier item this one depends on, counting from 1.
+    condition_step: Option<i64>,
+    condition_value: Option<String>,
+    condition_failed: bool,
+    /// Heading to insert above this item.
+    section: Option<String>,
+    /// Nested under the closest top-level item above.
+    is_sub_item: bool,
+}
+
+#[derive(Serialize)]
+struct PlanRename {
+    old_name: String,
+    renamed_at_display: String,
+}
+
+#[derive(Serialize)]
+struct ActionPlanShowItem {
+    name: String,
+    instructions: Option<String>,
+    /// Heading of the section this item starts.
+    section: Option<String>,
+    is_sub_item: bool,
+    estimate_display: Option<String>,
+    /// Average time the item took in finished runs, from `actual_seconds`.
+    average_actual_display: Option<String>,
+    sub_plan: Option<Uuid>,
+    sub_plan_name: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ActionPlanTagOption {
+    id: Uuid,
+    name: String,
+    color_style: String,
+    selected: bool,
+}
+
+#[derive(FromRow, Serialize)]
+struct PlanExecutionActive {
+    id: Uuid,
+    started_display: String,
+    note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    merged_from_name: Option<String>,
+}
+
+#[derive(FromRow, Serialize)]
+struct PlanExecutionFinished {
+    id: Uuid,
+    started_display: String,
+    finished_display: String,
+    note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    merged_from_name: Option<String>,
+}
+
+#[derive(FromRow)]
+struct PlanExecutionActiveRow {
+    id: Uuid,
+    started: i64,
+    note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    merged_from_name: Option<String>,
+}
+
+#[derive(FromRow)]
+struct PlanExecutionFinishedRow {
+    id: Uuid,
+    started: i64,
+    finished: i64,
+    note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    merged_from_name: Option<String>,
+}
+
+fn edit_action_plan(state: &AppState, plan: &ActionPlanEdit) -> Result<Html<String>, AppError> {
+    let rendered = templates::render(&state.jinja, "action_plan_edit.html", plan)?;
 
     Ok(Html(rendered))
 }
 
 fn normalize_items(items: Option<Vec<String>>) -> Vec<String> {
     items
-        .unwrap_or_else(|| Vec::new())
+        .unwrap_or_default()
         .into_iter()
         .map(|item| item.trim().to_string())
         .filter(|item| !item.is_empty())
         .collect()
 }
 
+/// Pairs each non-empty item with its estimate and sub-procedure. Estimates that are empty, not
+/// a number or not positive, range bounds that are not numbers and sub-procedures outside
+/// `allowed_sub_plans` are dropped rather than rejected, like blank item rows.
+#[allow(clippy::too_many_arguments)]
+fn normalize_plan_items(
+    items: Option<Vec<String>>,
+    estimated_minutes: Option<Vec<String>>,
+    sub_plans: Option<Vec<String>>,
+    descriptions: Option<Vec<String>>,
+    description_for: Option<Vec<String>>,
+    instructions: Option<Vec<String>>,
+    item_types: Option<Vec<String>>,
+    units: Option<Vec<String>>,
+    min_values: Option<Vec<String>>,
+    max_values: Option<Vec<String>>,
+    condition_steps: Option<Vec<String>>,
+    condition_values: Option<Vec<String>>,
+    condition_modes: Option<Vec<String>>,
+    sections: Option<Vec<String>>,
+    nestings: Option<Vec<String>>,
+    allowed_sub_plans: &HashSet<Uuid>,
+) -> Vec<PlanItemInput> {
+    let mut estimated_minutes = estimated_minutes.unwrap_or_default().into_iter();
+    let mut sub_plans = sub_plans.unwrap_or_default().into_iter();
+    let mut descriptions = descriptions.unwrap_or_default().into_iter();
+    let mut description_for = description_for.unwrap_or_default().into_iter();
+    let mut instructions = instructions.unwrap_or_default().into_iter();
+    let mut item_types = item_types.unwrap_or_default().into_iter();
+    let mut units = units.unwrap_or_default().into_iter();
+    let mut min_values = min_values.unwrap_or_default().into_iter();
+    let mut max_values = max_values.unwrap_or_default().into_iter();
+    let mut condition_steps = condition_steps.unwrap_or_default().into_iter();
+    let mut condition_values = condition_values.unwrap_or_default().into_iter();
+    let mut condition_modes = condition_modes.unwrap_or_default().into_iter();
+    let mut sections = sections.unwrap_or_default().into_iter();
+    let mut nestings = nestings.unwrap_or_default().into_iter();
+    items
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| {
+            let estimate = estimated_minutes
+                .next()
+                .and_then(|value| value.trim().parse::<i64>().ok())
+                .filter(|minutes| *minutes > 0);
+            let sub_plan = sub_plans
+                .next()
+                .and_then(|value| Uuid::parse_str(value.trim()).ok())
+                .filter(|id| allowed_sub_plans.contains(id));
+            let name = item.trim().to_string();
+            let description = descriptions
+                .next()
+                .map(|value| value.trim().replace("\r\n", "\n"))
+                .unwrap_or_default();
+            let is_loaded = description_for
+                .next()
+                .is_some_and(|loaded_for| loaded_for.trim() == name);
+            let description = match (is_loaded, description.is_empty()) {
+                (true, true) => Some(None),
+                (_, false) => Some(Some(description)),
+                (false, true) => None,
+            };
+            let instructions = instructions
+                .next()
+                .map(|value| value.trim().replace("\r\n", "\n"))
+                .filter(|value| !value.is_empty());
+            let item_type = item_types
+                .next()
+                .map(|value| ItemType::from_db(value.trim()))
+                .unwrap_or_default();
+            let unit = units
+                .next()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty() && item_type == ItemType::Number);
+            let min_value = min_values
+                .next()
+                .and_then(|value| item_types::parse_bound(&value))
+                .filter(|_| item_type == ItemType::Number);
+            let max_value = max_values
+                .next()
+                .and_then(|value| item_types::parse_bound(&value))
+                .filter(|_| item_type == ItemType::Number);
+            let condition_step = condition_steps
+                .next()
+                .and_then(|value| value.trim().parse::<i64>().ok());
+            let condition_failed = condition_modes
+                .next()
+                .is_some_and(|value| value.trim() == "failed")
+                && condition_step.is_some();
+            // An answer means nothing for failed items.
+            let condition_value = condition_values
+                .next()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty() && condition_step.is_some() && !condition_failed);
+            let section = sections
+                .next()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty());
+            let is_sub_item = nestings.next().is_some_and(|value| value.trim() == "sub");
+            PlanItemInput {
+                name,
+                estimated_minutes: estimate,
+                sub_plan,
+                description,
+                instructions,
+                item_type,
+                unit,
+                min_value,
+                max_value,
+                condition_step,
+                condition_value,
+                condition_failed,
+                section,
+                is_sub_item,
+            }
+        })
+        .filter(|item| !item.name.is_empty())
+        .collect()
+}
+
+/// Items of a plan in order, each with the heading above it.
+async fn fetch_plan_items(db: &SqlitePool, plan_id: Uuid) -> Result<Vec<ActionPlanItem>, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            actions.name as "name!",
+            actions.description,
+            action_items.order_index,
+            action_items.instructions,
+            action_items.item_type,
+            action_items.unit,
+            action_items.min_value,
+            action_items.max_value,
+            action_items.condition_index,
+            action_items.condition_value,
+            action_items.condition_failed as "condition_failed: bool",
+            action_items.parent_index,
+            action_items.estimated_minutes,
+            action_items.sub_plan as "sub_plan: uuid::Uuid",
+            sub_plans.name as "sub_plan_name?"
+        FROM action_items
+        INNER JOIN actions ON actions.id = action_items.action
+        LEFT JOIN action_plans AS sub_plans ON sub_plans.id = action_items.sub_plan
+        WHERE action_items.action_plan = $1
+        ORDER BY action_items.order_index ASC
+        "#,
+        plan_id
+    )
+    .fetch_all(db)
+    .await?;
+    let mut headings = headings::fetch_above_items(db, plan_id).await?;
+    // Steps count items only, so headings in between do not shift them.
+    let step_by_index = rows
+        .iter()
+        .enumerate()
+        .map(|(position, row)| (row.order_index, position as i64 + 1))
+        .collect::<HashMap<_, _>>();
+    Ok(rows
+        .into_iter()
+        .map(|row| ActionPlanItem {
+            name: row.name,
+            description: row.description,
+            order_index: row.order_index,
+            instructions: row.instructions,
+            item_type: row.item_type,
+            unit: row.unit,
+            min_value: row.min_value,
+            max_value: row.max_value,
+            condition_step: row
+                .condition_index
+                .and_then(|index| step_by_index.get(&index))
+                .copied(),
+            condition_index: row.condition_index,
+            condition_value: row.condition_value,
+            condition_failed: row.condition_failed,
+            section: headings.remove(&row.order_index),
+            parent_index: row.parent_index,
+            estimated_minutes: row.estimated_minutes,
+            sub_plan: row.sub_plan,
+            sub_plan_name: row.sub_plan_name,
+        })
+        .collect())
+}
+
+/// Active plans other than `plan_id`, by name, for the sub-procedure choice in the editor.
+async fn fetch_sub_plan_options<'e, E>(
+    executor: E,
+    plan_id: Option<Uuid>,
+) -> Result<Vec<SubPlanOption>, AppError>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    Ok(sqlx::query_as!(
+        SubPlanOption,
+        r#"
+        SELECT id as "id: uuid::Uuid", name
+        FROM action_plans
+        WHERE (deleted_at IS NULL OR deleted_at <= 0)
+            AND ($1 IS NULL OR id != $1)
+        ORDER BY name COLLATE NOCASE ASC
+        "#,
+        plan_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+pub fn format_estimate(minutes: i64) -> String {
+    format_duration(minutes.saturating_mul(60))
+}
+
 fn normalize_tag_ids(tag_ids: Option<Vec<Uuid>>) -> Vec<Uuid> {
     let mut unique = HashSet::new();
 
@@ -728,12 +2440,14 @@ fn action_plan_tag_options(
         .collect()
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct EditContext {
     execution_id: Option<Uuid>,
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ActionPlanListQuery {
     sort: Option<String>,
     deleted: Option<bool>,
@@ -751,73 +2465,226 @@ struct ActionPlanListSortItem {
     last_execution_unix: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ActionSearchQuery {
     q: Option<String>,
+    /// Maximum number of results, defaults to 10 and is capped at 50.
+    limit: Option<i64>,
+    offset: Option<i64>,
+    /// Action names to leave out, e.g. the items already present in the plan being edited.
+    #[serde(default)]
+    exclude: Vec<String>,
+    /// Only return actions used by plans carrying this tag.
+    #[serde(default, deserialize_with = "deserialize_optional_uuid")]
+    tag_id: Option<Uuid>,
 }
 
-#[derive(Debug, Serialize)]
+const ACTION_SEARCH_DEFAULT_LIMIT: i64 = 10;
+const ACTION_SEARCH_MAX_LIMIT: i64 = 50;
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ActionSearchItem {
     name: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/actions/search",
+    tag = "action_plans",
+    params(ActionSearchQuery),
+    responses(
+        (status = 200, description = "Matching actions, most used first", body = Vec<ActionSearchItem>),
+    )
+)]
 pub async fn search_actions(
     State(state): State<AppState>,
     Query(query): Query<ActionSearchQuery>,
 ) -> Result<Json<Vec<ActionSearchItem>>, AppError> {
     let q = query.q.unwrap_or_default().trim().to_string();
-    let actions = if q.is_empty() {
-        sqlx::query!(
-            r#"
-            SELECT
-                actions.name as "name!",
-                COUNT(DISTINCT action_items.action_plan) as "usage_count!: i64"
-            FROM actions
-            LEFT JOIN action_items ON action_items.action = actions.id
-            GROUP BY actions.id, actions.name
-            ORDER BY COUNT(DISTINCT action_items.action_plan) DESC, actions.name ASC
-            LIMIT 10
-            "#
-        )
-        .fetch_all(&state.db)
-        .await?
-        .into_iter()
-        .map(|row| ActionSearchItem { name: row.name })
-        .collect()
-    } else {
-        let pattern = format!("%{}%", q);
-        sqlx::query!(
+    let pattern = format!("%{}%", q);
+    let limit = query
+        .limit
+        .unwrap_or(ACTION_SEARCH_DEFAULT_LIMIT)
+        .clamp(1, ACTION_SEARCH_MAX_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let excluded_names = serde_json::to_string(&normalize_items(Some(query.exclude)))?;
+    let tag_id = query.tag_id;
+
+    let actions = sqlx::query!(
+        r#"
+        SELECT
+            actions.name as "name!",
+            COUNT(DISTINCT action_items.action_plan) as "usage_count!: i64"
+        FROM actions
+        LEFT JOIN action_items ON action_items.action = actions.id
+        WHERE LOWER(actions.name) LIKE LOWER($1)
+            AND actions.name NOT IN (SELECT value FROM json_each($2))
+            AND (
+                $3 IS NULL
+                OR EXISTS (
+                    SELECT 1
+                    FROM action_items AS tagged_items
+                    INNER JOIN action_plan_tags
+                        ON action_plan_tags.action_plan = tagged_items.action_plan
+                    WHERE tagged_items.action = actions.id
+                        AND action_plan_tags.tag = $3
+                )
+            )
+        GROUP BY actions.id, actions.name
+        ORDER BY COUNT(DISTINCT action_items.action_plan) DESC, actions.name ASC
+        LIMIT $4 OFFSET $5
+        "#,
+        pattern,
+        excluded_names,
+        tag_id,
+        limit,
+        offset
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|row| ActionSearchItem { name: row.name })
+    .collect();
+
+    Ok(Json(actions))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PlanSearchQuery {
+    q: Option<String>,
+    /// Plan to leave out, e.g. the one being edited.
+    #[serde(default, deserialize_with = "deserialize_optional_uuid")]
+    exclude_id: Option<Uuid>,
+}
+
+const PLAN_SEARCH_LIMIT: i64 = 10;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlanSearchItem {
+    id: Uuid,
+    name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlanItemsSearchResult {
+    id: Uuid,
+    name: String,
+    /// All items of the plan in order, so a block of them can be copied.
+    items: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/action_plan/search",
+    tag = "action_plans",
+    params(PlanSearchQuery),
+    responses(
+        (status = 200, description = "Plans with a similar name, to avoid creating duplicates", body = Vec<PlanSearchItem>),
+    )
+)]
+pub async fn search_plans(
+    State(state): State<AppState>,
+    Query(query): Query<PlanSearchQuery>,
+) -> Result<Json<Vec<PlanSearchItem>>, AppError> {
+    let q = query.q.unwrap_or_default().trim().to_string();
+    if q.is_empty() {
+        return Ok(Json(Vec::new()));
+    }
+    let pattern = format!("%{}%", q);
+
+    let plans = sqlx::query_as!(
+        PlanSearchItem,
+        r#"
+        SELECT id as "id!: uuid::Uuid", name
+        FROM action_plans
+        WHERE LOWER(name) LIKE LOWER($1)
+            AND (deleted_at IS NULL OR deleted_at <= 0)
+            AND ($2 IS NULL OR id != $2)
+        ORDER BY name ASC
+        LIMIT $3
+        "#,
+        pattern,
+        query.exclude_id,
+        PLAN_SEARCH_LIMIT
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(plans))
+}
+
+#[utoipa::path(
+    get,
+    path = "/action_plan/items/search",
+    tag = "action_plans",
+    params(PlanSearchQuery),
+    responses(
+        (status = 200, description = "Plans whose name or items match, with all of their items", body = Vec<PlanItemsSearchResult>),
+    )
+)]
+pub async fn search_plan_items(
+    State(state): State<AppState>,
+    Query(query): Query<PlanSearchQuery>,
+) -> Result<Json<Vec<PlanItemsSearchResult>>, AppError> {
+    let q = query.q.unwrap_or_default().trim().to_string();
+    if q.is_empty() {
+        return Ok(Json(Vec::new()));
+    }
+    let pattern = format!("%{}%", q);
+
+    let plans = sqlx::query!(
+        r#"
+        SELECT action_plans.id as "id!: uuid::Uuid", action_plans.name
+        FROM action_plans
+        WHERE (action_plans.deleted_at IS NULL OR action_plans.deleted_at <= 0)
+            AND ($2 IS NULL OR action_plans.id != $2)
+            AND (
+                LOWER(action_plans.name) LIKE LOWER($1)
+                OR EXISTS (
+                    SELECT 1
+                    FROM action_items
+                    INNER JOIN actions ON actions.id = action_items.action
+                    WHERE action_items.action_plan = action_plans.id
+                        AND LOWER(actions.name) LIKE LOWER($1)
+                )
+            )
+        ORDER BY action_plans.name ASC
+        LIMIT $3
+        "#,
+        pattern,
+        query.exclude_id,
+        PLAN_SEARCH_LIMIT
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut results = Vec::with_capacity(plans.len());
+    for plan in plans {
+        let items = sqlx::query_scalar!(
             r#"
-            SELECT
-                actions.name as "name!",
-                COUNT(DISTINCT action_items.action_plan) as "usage_count!: i64"
-            FROM actions
-            LEFT JOIN action_items ON action_items.action = actions.id
-            WHERE LOWER(actions.name) LIKE LOWER($1)
-            GROUP BY actions.id, actions.name
-            ORDER BY COUNT(DISTINCT action_items.action_plan) DESC, actions.name ASC
-            LIMIT 10
+            SELECT actions.name
+            FROM action_items
+            INNER JOIN actions ON actions.id = action_items.action
+            WHERE action_items.action_plan = $1
+            ORDER BY action_items.order_index ASC
             "#,
-            pattern
+            plan.id
         )
         .fetch_all(&state.db)
-        .await?
-        .into_iter()
-        .map(|row| ActionSearchItem { name: row.name })
-        .collect()
-    };
-
-    Ok(Json(actions))
-}
+        .await?;
+        results.push(PlanItemsSearchResult {
+            id: plan.id,
+            name: plan.name,
+            items,
+        });
+    }
 
-fn unix_now() -> i64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|duration| duration.as_secs() as i64)
-        .unwrap_or(0)
+    Ok(Json(results))
 }
 
-fn deserialize_optional_uuid<'de, D>(deserializer: D) -> Result<Option<Uuid>, D::Error>
+pub(crate) fn deserialize_optional_uuid<'de, D>(deserializer: D) -> Result<Option<Uuid>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
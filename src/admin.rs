@@ -0,0 +1,171 @@
+use axum::{
+    extract::{Path, State},
+    response::Html,
+};
+use serde::Serialize;
+
+use crate::{
+    AppError, AppState, CurrentUser, attachments,
+    authz::{Action, Resource, authorize},
+    format_duration,
+    jobs::{self, Job, JobReport},
+    logging::EndpointSummary,
+    self_test::StartupWarning,
+    sync::{self, SyncStatus},
+    templates,
+};
+
+const SLOWEST_ENDPOINT_LIMIT: usize = 20;
+
+#[derive(Serialize)]
+struct AdminPageView {
+    startup_warnings: Vec<StartupWarning>,
+    slowest_endpoints: Vec<EndpointSummary>,
+    slow_query_threshold_ms: u64,
+    jobs: Vec<JobSummary>,
+    sync: SyncStatus,
+    /// Attachments waiting in the quarantine.
+    quarantined_count: i64,
+    is_admin: bool,
+}
+
+#[derive(Serialize)]
+struct JobSummary {
+    slug: &'static str,
+    label: &'static str,
+    description: &'static str,
+    /// `None` when the job is turned off.
+    interval_display: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JobReportView {
+    slug: &'static str,
+    label: &'static str,
+    description: &'static str,
+    report: JobReport,
+    total: u64,
+    is_admin: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Diagnostics page with startup check results and the slowest routes since startup", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
+pub async fn index(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+
+    let rendered = templates::render(
+        &state.jinja,
+        "admin.html",
+        AdminPageView {
+            startup_warnings: state.startup_warnings.to_vec(),
+            slowest_endpoints: state.endpoint_timings.slowest(SLOWEST_ENDPOINT_LIMIT),
+            slow_query_threshold_ms: state.config.slow_query_threshold_ms,
+            jobs: Job::ALL
+                .into_iter()
+                .map(|job| JobSummary {
+                    slug: job.as_str(),
+                    label: job.label(),
+                    description: job.description(),
+                    interval_display: job
+                        .interval_seconds(&state.config)
+                        .map(|seconds| format_duration(seconds as i64)),
+                })
+                .collect(),
+            sync: sync::fetch_status(&state.db, &state.config).await?,
+            quarantined_count: attachments::quarantined_count(&state.db).await?,
+            is_admin: true,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/jobs/{job}",
+    tag = "admin",
+    params(("job" = String, Path, description = "Job name: action_gc, session_gc or archive")),
+    responses(
+        (status = 200, description = "Dry run of the job, listing counts and examples of what a run would remove, with a button to run it", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 409, description = "The job is turned off in the configuration", content_type = "text/html"),
+    )
+)]
+pub async fn job_preview_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(job): Path<Job>,
+) -> Result<Html<String>, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+
+    let report = jobs::run(&state.db, &state.config, job, true).await?;
+    render_job_report(&state, report)
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/{job}",
+    tag = "admin",
+    params(("job" = String, Path, description = "Job name: action_gc, session_gc or archive")),
+    responses(
+        (status = 200, description = "Runs the job now and lists what it removed", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 409, description = "The job is turned off in the configuration", content_type = "text/html"),
+    )
+)]
+pub async fn job_run_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(job): Path<Job>,
+) -> Result<Html<String>, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+
+    let report = jobs::run(&state.db, &state.config, job, false).await?;
+    tracing::info!(
+        user = current_user.name,
+        "{} run from the admin page: {}.",
+        job.label(),
+        report
+    );
+    render_job_report(&state, report)
+}
+
+fn render_job_report(state: &AppState, report: JobReport) -> Result<Html<String>, AppError> {
+    let job = report.job;
+    let rendered = templates::render(
+        &state.jinja,
+        "job_report.html",
+        JobReportView {
+            slug: job.as_str(),
+            label: job.label(),
+            description: job.description(),
+            total: report.total(),
+            report,
+            is_admin: true,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
@@ -0,0 +1,429 @@
+//! Cold storage for old executions. Finished executions older than `archive_after_years` are
+//! moved into a separate SQLite file that every pool connection attaches as `archive`, which
+//! keeps the main database small. The archive is not known to the compile-time checked
+//! queries, so everything here uses runtime queries.
+
+use std::{path::Path, sync::Arc};
+
+use axum::{
+    extract::{Path as UrlPath, State},
+    response::Html,
+};
+use serde::Serialize;
+use sqlx::{SqliteConnection, SqlitePool, prelude::FromRow};
+use tokio::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    attachments::{self, ItemAttachment},
+    config::Config,
+    format_unix_timestamp, item_types,
+    jobs::{self, Job},
+    templates, unix_now,
+};
+
+const SECONDS_PER_YEAR: i64 = 365 * 24 * 60 * 60;
+
+/// Whether the archive database is attached: archiving is on, or an earlier archive exists
+/// and should stay readable.
+pub fn is_enabled(config: &Config) -> bool {
+    config.archive_after_years > 0 || Path::new(&config.archive_db_path).exists()
+}
+
+/// Attaches the archive file to a freshly opened connection.
+pub async fn attach(conn: &mut SqliteConnection, path: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("ATTACH DATABASE $1 AS archive")
+        .bind(path)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Creates the archive tables. Items keep the action name rather than the action id, as the
+/// action GC only sees the main database and may delete actions only archived runs use.
+/// Columns added later are added to archives created before them.
+pub async fn prepare(db: &SqlitePool) -> Result<(), sqlx::Error> {
+    let statements = [
+        "PRAGMA archive.journal_mode = WAL",
+        r#"
+        CREATE TABLE IF NOT EXISTS archive.action_plan_executions (
+            id BLOB PRIMARY KEY NOT NULL,
+            action_plan BLOB NOT NULL,
+            action_plan_name TEXT NOT NULL,
+            started INTEGER NOT NULL,
+            finished INTEGER NOT NULL,
+            note TEXT,
+            label TEXT,
+            run_number INTEGER NOT NULL,
+            merged_from BLOB,
+            archived_at INTEGER NOT NULL
+        )
+        "#,
+        "CREATE INDEX IF NOT EXISTS archive.archived_executions_finished_idx ON action_plan_executions(finished)",
+        r#"
+        CREATE TABLE IF NOT EXISTS archive.action_item_executions (
+            id BLOB PRIMARY KEY NOT NULL,
+            action_plan_execution BLOB NOT NULL,
+            action_name TEXT NOT NULL,
+            order_index INTEGER NOT NULL,
+            finished INTEGER,
+            estimated_minutes INTEGER,
+            actual_seconds INTEGER,
+            value TEXT,
+            unit TEXT,
+            out_of_spec INTEGER NOT NULL DEFAULT 0,
+            skip_reason TEXT,
+            failure_note TEXT
+        )
+        "#,
+        "CREATE INDEX IF NOT EXISTS archive.archived_items_execution_idx ON action_item_executions(action_plan_execution)",
+    ];
+    for statement in statements {
+        sqlx::query(statement).execute(db).await?;
+    }
+
+    for (column, definition) in [
+        ("value", "value TEXT"),
+        ("unit", "unit TEXT"),
+        ("out_of_spec", "out_of_spec INTEGER NOT NULL DEFAULT 0"),
+        ("skip_reason", "skip_reason TEXT"),
+        ("failure_note", "failure_note TEXT"),
+    ] {
+        let exists = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM pragma_table_info('action_item_executions', 'archive') WHERE name = $1",
+        )
+        .bind(column)
+        .fetch_one(db)
+        .await?
+            > 0;
+        if !exists {
+            sqlx::query(&format!(
+                "ALTER TABLE archive.action_item_executions ADD COLUMN {}",
+                definition
+            ))
+            .execute(db)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn run_scheduler(db: SqlitePool, config: Arc<Config>) {
+    if config.archive_after_years == 0 {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(config.archive_interval_seconds));
+    loop {
+        interval.tick().await;
+        jobs::run_scheduled(&db, &config, Job::Archive).await;
+    }
+}
+
+/// Executions finished before this are due for the archive.
+pub fn cutoff(config: &Config) -> i64 {
+    unix_now() - i64::from(config.archive_after_years) * SECONDS_PER_YEAR
+}
+
+/// Moves executions finished before `cutoff` with their items into the archive and returns
+/// how many were moved. Copies are written first, so if `conn` is not in a transaction, an
+/// interrupted run only leaves rows in both databases, which the next run cleans up.
+pub async fn archive_finished_before(
+    conn: &mut SqliteConnection,
+    cutoff: i64,
+) -> Result<u64, sqlx::Error> {
+    let now = unix_now();
+
+    sqlx::query(
+        r#"
+        UPDATE main.action_plans
+        SET archived_run_number = MAX(
+            archived_run_number,
+            (
+                SELECT MAX(run_number)
+                FROM main.action_plan_executions
+                WHERE action_plan = action_plans.id AND finished > 0 AND finished < $1
+            )
+        )
+        WHERE id IN (
+            SELECT action_plan
+            FROM main.action_plan_executions
+            WHERE finished > 0 AND finished < $1
+        )
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO archive.action_plan_executions
+            (id, action_plan, action_plan_name, started, finished, note, label, run_number, merged_from, archived_at)
+        SELECT
+            executions.id,
+            executions.action_plan,
+            action_plans.name,
+            executions.started,
+            executions.finished,
+            executions.note,
+            executions.label,
+            executions.run_number,
+            executions.merged_from,
+            $2
+        FROM main.action_plan_executions AS executions
+        INNER JOIN main.action_plans ON action_plans.id = executions.action_plan
+        WHERE executions.finished > 0 AND executions.finished < $1
+        "#,
+    )
+    .bind(cutoff)
+    .bind(now)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO archive.action_item_executions
+            (id, action_plan_execution, action_name, order_index, finished, estimated_minutes, actual_seconds, value, unit, out_of_spec, skip_reason, failure_note)
+        SELECT
+            items.id,
+            items.action_plan_execution,
+            actions.name,
+            items.order_index,
+            items.finished,
+            items.estimated_minutes,
+            items.actual_seconds,
+            items.value,
+            items.unit,
+            items.out_of_spec,
+            items.skip_reason,
+            items.failure_note
+        FROM main.action_item_executions AS items
+        INNER JOIN main.actions ON actions.id = items.action
+        WHERE items.action_plan_execution IN (
+            SELECT id FROM main.action_plan_executions WHERE finished > 0 AND finished < $1
+        )
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM main.action_item_executions
+        WHERE action_plan_execution IN (
+            SELECT id FROM main.action_plan_executions WHERE finished > 0 AND finished < $1
+        )
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *conn)
+    .await?;
+
+    // The archive keeps only the runs and their items, not who they were handed over to or
+    // what was discussed on them.
+    sqlx::query(
+        r#"
+        DELETE FROM main.execution_transfers
+        WHERE action_plan_execution IN (
+            SELECT id FROM main.action_plan_executions WHERE finished > 0 AND finished < $1
+        )
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM main.execution_comments
+        WHERE action_plan_execution IN (
+            SELECT id FROM main.action_plan_executions WHERE finished > 0 AND finished < $1
+        )
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *conn)
+    .await?;
+
+    let moved =
+        sqlx::query("DELETE FROM main.action_plan_executions WHERE finished > 0 AND finished < $1")
+            .bind(cutoff)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+
+    Ok(moved)
+}
+
+#[derive(FromRow)]
+struct ArchivedExecutionRow {
+    id: Uuid,
+    action_plan_name: String,
+    started: i64,
+    finished: i64,
+    note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ArchivedExecutionListItem {
+    id: Uuid,
+    action_plan_name: String,
+    started_display: String,
+    finished_display: String,
+    note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+}
+
+impl From<ArchivedExecutionRow> for ArchivedExecutionListItem {
+    fn from(row: ArchivedExecutionRow) -> Self {
+        Self {
+            id: row.id,
+            action_plan_name: row.action_plan_name,
+            started_display: format_unix_timestamp(row.started),
+            finished_display: format_unix_timestamp(row.finished),
+            note: row.note,
+            run_number: row.run_number,
+            label: row.label,
+        }
+    }
+}
+
+/// Archived executions, newest first, optionally filtered like the live list by label or note.
+pub async fn list_executions(
+    db: &SqlitePool,
+    search_query: &str,
+) -> Result<Vec<ArchivedExecutionListItem>, AppError> {
+    let search_pattern = format!("%{}%", search_query);
+    let rows = sqlx::query_as::<_, ArchivedExecutionRow>(
+        r#"
+        SELECT id, action_plan_name, started, finished, note, run_number, label
+        FROM archive.action_plan_executions
+        WHERE $1 = ''
+            OR LOWER(IFNULL(note, '')) LIKE LOWER($2)
+            OR LOWER(IFNULL(label, '')) LIKE LOWER($2)
+        ORDER BY finished DESC
+        "#,
+    )
+    .bind(search_query)
+    .bind(search_pattern)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+#[derive(FromRow)]
+struct ArchivedItemRow {
+    id: Uuid,
+    action_name: String,
+    finished: Option<i64>,
+    value: Option<String>,
+    unit: Option<String>,
+    out_of_spec: bool,
+    skip_reason: Option<String>,
+    failure_note: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ArchivedItem {
+    action_name: String,
+    finished_display: Option<String>,
+    value_display: Option<String>,
+    out_of_spec: bool,
+    skip_reason: Option<String>,
+    failure_note: Option<String>,
+    attachments: Vec<ItemAttachment>,
+}
+
+#[derive(Serialize)]
+struct ArchivedExecutionView {
+    execution: ArchivedExecutionListItem,
+    items: Vec<ArchivedItem>,
+    is_admin: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/executions/archive/{id}",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Archived execution id")),
+    responses(
+        (status = 200, description = "Read-only view of an archived execution", content_type = "text/html"),
+        (status = 404, description = "No archived execution with this id", content_type = "text/html"),
+    )
+)]
+pub async fn show(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    UrlPath(id): UrlPath<Uuid>,
+) -> Result<Html<String>, AppError> {
+    let execution = if state.archive_enabled {
+        sqlx::query_as::<_, ArchivedExecutionRow>(
+            r#"
+            SELECT id, action_plan_name, started, finished, note, run_number, label
+            FROM archive.action_plan_executions
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?
+    } else {
+        None
+    };
+    let Some(execution) = execution else {
+        return Err(AppError::not_found_for(
+            "Execution",
+            format!("No archived execution exists for id: {}", id),
+        ));
+    };
+
+    // Attachments stay in the main database when their items are archived.
+    let mut item_attachments =
+        attachments::fetch_for_execution(&state.db, id, &current_user, false).await?;
+    let items = sqlx::query_as::<_, ArchivedItemRow>(
+        r#"
+        SELECT id, action_name, finished, value, unit, out_of_spec, skip_reason, failure_note
+        FROM archive.action_item_executions
+        WHERE action_plan_execution = $1
+        ORDER BY order_index ASC
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|item| ArchivedItem {
+        action_name: item.action_name,
+        finished_display: item
+            .finished
+            .filter(|finished| *finished > 0)
+            .map(format_unix_timestamp),
+        value_display: item
+            .value
+            .map(|value| item_types::value_display(&value, item.unit.as_deref())),
+        out_of_spec: item.out_of_spec,
+        skip_reason: item.skip_reason,
+        failure_note: item.failure_note,
+        attachments: item_attachments.remove(&item.id).unwrap_or_default(),
+    })
+    .collect();
+
+    let rendered = templates::render(
+        &state.jinja,
+        "execution_archived_show.html",
+        ArchivedExecutionView {
+            execution: execution.into(),
+            items,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
@@ -0,0 +1,192 @@
+use std::{io::Write, sync::LazyLock};
+
+use axum::{
+    body::Body,
+    extract::Path,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use flate2::{Compression, write::GzEncoder};
+use sha2::{Digest, Sha256};
+
+/// Fingerprinted URLs never change content, so browsers may keep them for a year.
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Unfingerprinted URLs are still served for old pages and bookmarks, but must be revalidated.
+const REVALIDATE_CACHE_CONTROL: &str = "no-cache";
+
+struct EmbeddedAsset {
+    file_name: &'static str,
+    content_type: &'static str,
+    body: &'static [u8],
+}
+
+const EMBEDDED_ASSETS: &[EmbeddedAsset] = &[
+    EmbeddedAsset {
+        file_name: "style.css",
+        content_type: "text/css; charset=utf-8",
+        body: include_bytes!("../assets/static/style.css"),
+    },
+    EmbeddedAsset {
+        file_name: "script.js",
+        content_type: "application/javascript; charset=utf-8",
+        body: include_bytes!("../assets/static/script.js"),
+    },
+    EmbeddedAsset {
+        file_name: "action_item_search.js",
+        content_type: "application/javascript; charset=utf-8",
+        body: include_bytes!("../assets/static/action_item_search.js"),
+    },
+    EmbeddedAsset {
+        file_name: "action_plan_search.js",
+        content_type: "application/javascript; charset=utf-8",
+        body: include_bytes!("../assets/static/action_plan_search.js"),
+    },
+    EmbeddedAsset {
+        file_name: "action_plan_reorder.js",
+        content_type: "application/javascript; charset=utf-8",
+        body: include_bytes!("../assets/static/action_plan_reorder.js"),
+    },
+    EmbeddedAsset {
+        file_name: "tag_filter.js",
+        content_type: "application/javascript; charset=utf-8",
+        body: include_bytes!("../assets/static/tag_filter.js"),
+    },
+    EmbeddedAsset {
+        file_name: "tag_picker.js",
+        content_type: "application/javascript; charset=utf-8",
+        body: include_bytes!("../assets/static/tag_picker.js"),
+    },
+];
+
+struct PreparedAsset {
+    file_name: &'static str,
+    content_type: &'static str,
+    body: &'static [u8],
+    gzip_body: Vec<u8>,
+    etag: HeaderValue,
+    fingerprinted_name: String,
+}
+
+/// Hashed and compressed once, on first use.
+static ASSETS: LazyLock<Vec<PreparedAsset>> =
+    LazyLock::new(|| EMBEDDED_ASSETS.iter().map(prepare).collect());
+
+/// Looks an asset up by its plain or its fingerprinted file name.
+fn find(file_name: &str) -> Option<&'static PreparedAsset> {
+    ASSETS
+        .iter()
+        .find(|asset| asset.file_name == file_name || asset.fingerprinted_name == file_name)
+}
+
+fn prepare(embedded: &EmbeddedAsset) -> PreparedAsset {
+    let hash = Sha256::digest(embedded.body)
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    let fingerprinted_name = match embedded.file_name.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}.{}.{}", stem, hash, extension),
+        None => format!("{}.{}", embedded.file_name, hash),
+    };
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(embedded.body)
+        .expect("compressing into memory cannot fail");
+    let gzip_body = encoder
+        .finish()
+        .expect("compressing into memory cannot fail");
+
+    PreparedAsset {
+        file_name: embedded.file_name,
+        content_type: embedded.content_type,
+        body: embedded.body,
+        gzip_body,
+        etag: HeaderValue::from_str(&format!("\"{}\"", hash)).expect("hex is a valid header"),
+        fingerprinted_name,
+    }
+}
+
+/// URL path of the fingerprinted asset, for templates: `{{ base_path }}{{ asset_url("style.css") }}`.
+pub fn asset_url(file_name: &str) -> Result<minijinja::Value, minijinja::Error> {
+    // Built only from embedded file names and hex digits, so it needs no escaping.
+    find(file_name)
+        .map(|asset| {
+            minijinja::Value::from_safe_string(format!("/static/{}", asset.fingerprinted_name))
+        })
+        .ok_or_else(|| {
+            minijinja::Error::new(
+                minijinja::ErrorKind::InvalidOperation,
+                format!("unknown static asset {}", file_name),
+            )
+        })
+}
+
+/// Serves embedded assets with an ETag, long-lived caching for fingerprinted URLs and gzip
+/// for clients that accept it.
+pub async fn serve(Path(file_name): Path<String>, headers: HeaderMap) -> Response {
+    let Some(asset) = find(&file_name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let cache_control = if file_name == asset.fingerprinted_name {
+        IMMUTABLE_CACHE_CONTROL
+    } else {
+        REVALIDATE_CACHE_CONTROL
+    };
+    let builder = Response::builder()
+        .header(header::ETAG, asset.etag.clone())
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::VARY, "Accept-Encoding");
+
+    if etag_matches(&headers, &asset.etag) {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response());
+    }
+
+    let builder = builder.header(header::CONTENT_TYPE, asset.content_type);
+    let response = if accepts_gzip(&headers) {
+        builder
+            .header(header::CONTENT_ENCODING, "gzip")
+            .body(Body::from(asset.gzip_body.clone()))
+    } else {
+        builder.body(Body::from(asset.body))
+    };
+    response.unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+fn etag_matches(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default();
+    if_none_match
+        .split(',')
+        .map(|candidate| candidate.trim().trim_start_matches("W/"))
+        .any(|candidate| candidate == "*" || candidate == etag)
+}
+
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    let Some(accept_encoding) = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    accept_encoding.split(',').any(|entry| {
+        let mut parts = entry.split(';').map(str::trim);
+        let coding = parts.next().unwrap_or_default();
+        let rejected = parts.any(|parameter| {
+            parameter
+                .strip_prefix("q=")
+                .and_then(|quality| quality.parse::<f32>().ok())
+                .is_some_and(|quality| quality == 0.0)
+        });
+        (coding.eq_ignore_ascii_case("gzip") || coding == "*") && !rejected
+    })
+}
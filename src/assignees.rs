@@ -0,0 +1,268 @@
+//! Assigning single items of an execution to people, so a team can split up a large plan, and
+//! the "My items" page listing what is assigned to the current user in open executions.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    response::{Html, Redirect},
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, Role, authorize},
+    executions::{self, ItemGrouping},
+    format_unix_timestamp, templates,
+};
+
+/// A user items can be assigned to.
+#[derive(Debug, Serialize)]
+pub struct AssigneeOption {
+    id: Uuid,
+    name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ItemAssigneeForm {
+    /// User id, empty to unassign.
+    assignee: String,
+    /// How the execution page was grouped, to return to the same view.
+    #[serde(default)]
+    group: ItemGrouping,
+}
+
+#[derive(Debug, Serialize)]
+struct MyItemsView {
+    executions: Vec<MyItemsExecution>,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct MyItemsExecution {
+    id: Uuid,
+    action_plan_name: String,
+    run_number: i64,
+    label: Option<String>,
+    started_display: String,
+    done_count: usize,
+    items: Vec<MyItem>,
+}
+
+#[derive(Debug, Serialize)]
+struct MyItem {
+    id: Uuid,
+    name: String,
+    is_finished: bool,
+    finished_display: Option<String>,
+}
+
+/// Active users who can check off items, alphabetically.
+pub async fn fetch_options(db: &SqlitePool) -> Result<Vec<AssigneeOption>, AppError> {
+    Ok(sqlx::query_as!(
+        AssigneeOption,
+        r#"
+        SELECT id as "id: uuid::Uuid", name
+        FROM users
+        WHERE is_disabled = 0 AND role IN ('operator', 'admin')
+        ORDER BY name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/assignee",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body(content = ItemAssigneeForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Assigns or unassigns the item and redirects to its execution"),
+        (status = 404, description = "Item or user not found", content_type = "text/html"),
+        (status = 409, description = "The execution is completed or the user cannot run executions", content_type = "text/html"),
+    )
+)]
+pub async fn assign_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<ItemAssigneeForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let assignee = match form.assignee.trim() {
+        "" => None,
+        value => Some(Uuid::parse_str(value).map_err(|_| {
+            AppError::not_found_for("User", format!("No user exists for id: {}", value))
+        })?),
+    };
+
+    let mut tx = state.db.begin().await?;
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id: uuid::Uuid",
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?",
+            assignees.name as "assignee_name?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        LEFT JOIN users AS assignees ON assignees.id = action_item_executions.assigned_to
+        WHERE action_item_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Execution",
+            format!("No execution item exists for id: {}", id),
+        )
+    })?;
+    if item.execution_finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "Items of a completed execution cannot be reassigned.",
+        ));
+    }
+
+    let assignee_name = match assignee {
+        Some(user_id) => {
+            let user = sqlx::query!(
+                "SELECT name, role, is_disabled FROM users WHERE id = $1",
+                user_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                AppError::not_found_for("User", format!("No user exists for id: {}", user_id))
+            })?;
+            if user.is_disabled != 0 || Role::from_db(&user.role) < Role::Operator {
+                return Err(AppError::conflict(format!(
+                    "{} cannot run executions, so items cannot be assigned to them.",
+                    user.name
+                )));
+            }
+            Some(user.name)
+        }
+        None => None,
+    };
+
+    sqlx::query!(
+        "UPDATE action_item_executions SET assigned_to = $1 WHERE id = $2",
+        assignee,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if item.assignee_name != assignee_name {
+        let description = executions::execution_description(&mut tx, item.execution_id).await?;
+        let summary = match &assignee_name {
+            Some(name) => format!(
+                "Assigned \"{}\" in {} to {}",
+                item.item_name, description, name
+            ),
+            None => format!("Unassigned \"{}\" in {}", item.item_name, description),
+        };
+        audit::record(
+            &mut *tx,
+            &current_user,
+            AuditEntry::new(AuditEntity::ExecutionItem, id, AuditAction::Update, summary)
+                .change(item.assignee_name, assignee_name),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}{}",
+        item.execution_id,
+        form.group.query()
+    )))
+}
+
+#[utoipa::path(
+    get,
+    path = "/executions/my-items",
+    tag = "executions",
+    responses(
+        (status = 200, description = "Items assigned to the current user in open executions", content_type = "text/html"),
+    )
+)]
+pub async fn my_items_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.id as "id!: uuid::Uuid",
+            actions.name as "name!",
+            action_item_executions.finished as "finished?",
+            action_plan_executions.id as "execution_id!: uuid::Uuid",
+            action_plans.name as "action_plan_name!",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label,
+            action_plan_executions.started as "started!"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_item_executions.assigned_to = $1
+            AND (action_plan_executions.finished IS NULL OR action_plan_executions.finished <= 0)
+        ORDER BY action_plan_executions.started ASC, action_item_executions.order_index ASC
+        "#,
+        current_user.id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut executions: Vec<MyItemsExecution> = Vec::new();
+    let mut positions: HashMap<Uuid, usize> = HashMap::new();
+    for row in rows {
+        let position = *positions.entry(row.execution_id).or_insert_with(|| {
+            executions.push(MyItemsExecution {
+                id: row.execution_id,
+                action_plan_name: row.action_plan_name,
+                run_number: row.run_number,
+                label: row.label,
+                started_display: format_unix_timestamp(row.started),
+                done_count: 0,
+                items: Vec::new(),
+            });
+            executions.len() - 1
+        });
+        let finished = row.finished.filter(|value| *value > 0);
+        let execution = &mut executions[position];
+        if finished.is_some() {
+            execution.done_count += 1;
+        }
+        execution.items.push(MyItem {
+            id: row.id,
+            name: row.name,
+            is_finished: finished.is_some(),
+            finished_display: finished.map(format_unix_timestamp),
+        });
+    }
+
+    let rendered = templates::render(
+        &state.jinja,
+        "my_items.html",
+        MyItemsView {
+            executions,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
@@ -0,0 +1,1377 @@
+//! Photos and files attached to execution items, e.g. evidence photos some audits require, and
+//! reference documents attached to action plans, like manuals or wiring diagrams. Files are
+//! kept in the storage backend rather than the database. Photos on items are stored without
+//! their metadata and at a capped resolution, with a small JPEG thumbnail for the execution
+//! page and, if configured, the upload as it was sent.
+
+use std::{collections::HashMap, io::Cursor};
+
+use anyhow::Context;
+use axum::{
+    extract::{Multipart, Path, State},
+    http::header,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use image::{
+    DynamicImage, ImageDecoder, ImageFormat, ImageReader, codecs::jpeg::JpegEncoder,
+    imageops::FilterType,
+};
+use serde::Serialize;
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, authorize},
+    config::Config,
+    executions::{self, ItemGrouping},
+    format_unix_timestamp,
+    storage::Storage,
+    templates, unix_now,
+    usage::format_bytes,
+    virus_scan,
+};
+
+/// Longest side of a thumbnail in pixels.
+const THUMBNAIL_SIZE: u32 = 320;
+/// Quality of photos stored as JPEG, high enough that details like gauge readings stay legible.
+const PHOTO_JPEG_QUALITY: u8 = 90;
+/// Files accepted in one upload, so it is held in memory at most this many times
+/// `attachment_max_bytes`.
+const MAX_FILES_PER_UPLOAD: usize = 10;
+/// Longest caption, in characters.
+const MAX_CAPTION_CHARS: usize = 200;
+
+/// An attachment as listed under its item.
+#[derive(Debug, Serialize)]
+pub struct ItemAttachment {
+    id: Uuid,
+    file_name: String,
+    size_display: String,
+    has_thumbnail: bool,
+    /// Whether the upload as it was sent can be downloaded, which only admins can.
+    has_original: bool,
+    /// Set while the file is held back for an admin to review.
+    quarantine_reason: Option<String>,
+    caption: Option<String>,
+    uploaded_display: String,
+    uploaded_by_name: String,
+    can_delete: bool,
+}
+
+/// A reference document as listed on its plan and the plan's executions.
+#[derive(Debug, Serialize)]
+pub struct PlanAttachment {
+    id: Uuid,
+    file_name: String,
+    size_display: String,
+    uploaded_display: String,
+    uploaded_by_name: String,
+}
+
+/// An attachment waiting for an admin to release or delete it.
+#[derive(Debug, Serialize)]
+struct QuarantinedAttachment {
+    id: Uuid,
+    execution_id: Uuid,
+    file_name: String,
+    size_display: String,
+    reason: String,
+    /// `None` once the execution has been moved to the archive.
+    item_name: Option<String>,
+    plan_name: Option<String>,
+    uploaded_display: String,
+    uploaded_by_name: String,
+}
+
+#[derive(Serialize)]
+struct QuarantineView {
+    attachments: Vec<QuarantinedAttachment>,
+    is_scanning: bool,
+    is_admin: bool,
+}
+
+/// The files of an upload form and, for items, how the execution page was grouped and the
+/// caption for the files.
+struct UploadForm {
+    files: Vec<Upload>,
+    group: ItemGrouping,
+    caption: Option<String>,
+}
+
+/// A file read from the upload form, checked against the configured limits.
+struct Upload {
+    file_name: String,
+    content_type: String,
+    bytes: Vec<u8>,
+}
+
+/// What [`store`] wrote for an upload.
+struct Stored {
+    size_bytes: i64,
+    original_size_bytes: Option<i64>,
+    has_thumbnail: bool,
+}
+
+/// A photo as it is stored, and its thumbnail.
+struct ProcessedPhoto {
+    image: Vec<u8>,
+    thumbnail: Vec<u8>,
+}
+
+/// Attachments of an execution by item, oldest first. `can_change` is false once the
+/// execution is completed or archived.
+pub async fn fetch_for_execution(
+    db: &SqlitePool,
+    execution_id: Uuid,
+    current_user: &CurrentUser,
+    can_change: bool,
+) -> Result<HashMap<Uuid, Vec<ItemAttachment>>, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            action_item_execution as "item_id: uuid::Uuid",
+            file_name,
+            size_bytes,
+            has_thumbnail,
+            original_size_bytes,
+            quarantine_reason,
+            caption,
+            uploaded_at,
+            uploaded_by as "uploaded_by: uuid::Uuid",
+            uploaded_by_name
+        FROM execution_item_attachments
+        WHERE action_plan_execution = $1
+        ORDER BY uploaded_at ASC, rowid ASC
+        "#,
+        execution_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut attachments: HashMap<Uuid, Vec<ItemAttachment>> = HashMap::new();
+    for row in rows {
+        attachments
+            .entry(row.item_id)
+            .or_default()
+            .push(ItemAttachment {
+                id: row.id,
+                file_name: row.file_name,
+                size_display: format_bytes(row.size_bytes as u64),
+                has_thumbnail: row.has_thumbnail != 0,
+                has_original: row.original_size_bytes.is_some() && current_user.is_admin,
+                quarantine_reason: row.quarantine_reason,
+                caption: row.caption,
+                uploaded_display: format_unix_timestamp(row.uploaded_at),
+                uploaded_by_name: row.uploaded_by_name,
+                can_delete: can_change
+                    && (row.uploaded_by == current_user.id || current_user.is_admin),
+            });
+    }
+    Ok(attachments)
+}
+
+/// Reference documents of a plan, oldest first.
+pub async fn fetch_for_plan(
+    db: &SqlitePool,
+    plan_id: Uuid,
+) -> Result<Vec<PlanAttachment>, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id as "id: uuid::Uuid", file_name, size_bytes, uploaded_at, uploaded_by_name
+        FROM plan_attachments
+        WHERE action_plan = $1
+        ORDER BY uploaded_at ASC, rowid ASC
+        "#,
+        plan_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| PlanAttachment {
+            id: row.id,
+            file_name: row.file_name,
+            size_display: format_bytes(row.size_bytes as u64),
+            uploaded_display: format_unix_timestamp(row.uploaded_at),
+            uploaded_by_name: row.uploaded_by_name,
+        })
+        .collect())
+}
+
+/// Total size of all attachments, for the usage report.
+pub async fn total_bytes(db: &SqlitePool) -> Result<u64, AppError> {
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT (
+            (SELECT COALESCE(SUM(size_bytes + COALESCE(original_size_bytes, 0)), 0)
+                FROM execution_item_attachments)
+            + (SELECT COALESCE(SUM(size_bytes), 0) FROM plan_attachments)
+        ) as "total!: i64"
+        "#
+    )
+    .fetch_one(db)
+    .await?;
+    Ok(total as u64)
+}
+
+/// Deletes the attachment rows of an execution. Pass the returned ids to [`remove_files`] once
+/// the transaction is committed.
+pub async fn delete_for_execution(
+    conn: &mut SqliteConnection,
+    execution_id: Uuid,
+) -> Result<Vec<Uuid>, AppError> {
+    Ok(sqlx::query_scalar!(
+        r#"
+        DELETE FROM execution_item_attachments
+        WHERE action_plan_execution = $1
+        RETURNING id as "id: uuid::Uuid"
+        "#,
+        execution_id
+    )
+    .fetch_all(&mut *conn)
+    .await?)
+}
+
+/// Points attachments at an item's new id after the items of a run were rebuilt from the edited
+/// plan.
+pub async fn move_to_item(
+    conn: &mut SqliteConnection,
+    previous_item_id: Uuid,
+    item_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE execution_item_attachments
+        SET action_item_execution = $1
+        WHERE action_item_execution = $2
+        "#,
+        item_id,
+        previous_item_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Like [`delete_for_execution`] for every execution in the main database, when a backup
+/// replaces them all. Attachments of archived runs stay.
+pub async fn delete_for_live_executions(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<Uuid>, AppError> {
+    Ok(sqlx::query_scalar!(
+        r#"
+        DELETE FROM execution_item_attachments
+        WHERE action_plan_execution IN (SELECT id FROM action_plan_executions)
+        RETURNING id as "id: uuid::Uuid"
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await?)
+}
+
+/// Removes stored files and thumbnails. Failures are only logged, as the rows are gone already.
+pub async fn remove_files(storage: &dyn Storage, ids: Vec<Uuid>) {
+    for id in ids {
+        for key in [file_key(id), thumbnail_key(id), original_key(id)] {
+            if let Err(err) = storage.delete(&key).await {
+                tracing::warn!("Could not remove {}: {:#}", key, err);
+            }
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/attachments",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body(content_type = "multipart/form-data", description = "One or more files in `file` fields, optionally a `caption` for them and `group` to return to the same view"),
+    responses(
+        (status = 303, description = "Stores the files and redirects to the item's execution. Files ClamAV flags or cannot scan are quarantined"),
+        (status = 404, description = "Execution item not found", content_type = "text/html"),
+        (status = 409, description = "No file, a type that is not allowed or the execution is completed", content_type = "text/html"),
+        (status = 413, description = "A file is larger than attachment_max_bytes", content_type = "text/html"),
+    )
+)]
+pub async fn upload_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    multipart: Multipart,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id: uuid::Uuid",
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        WHERE action_item_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| item_not_found(id))?;
+    if item.execution_finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "Files cannot be attached to items of a completed execution.",
+        ));
+    }
+
+    let form = read_upload_form(multipart, &state.config).await?;
+    for upload in form.files {
+        let attachment_id = Uuid::new_v4();
+        // Quarantined files are kept as sent, so nothing decodes them before they are reviewed.
+        let quarantine_reason =
+            virus_scan::scan(&state.config, &upload.file_name, &upload.bytes).await;
+        let stored = match store(
+            state.storage.as_ref(),
+            &state.config,
+            attachment_id,
+            &upload,
+            quarantine_reason.is_none(),
+        )
+        .await
+        {
+            Ok(stored) => stored,
+            Err(err) => {
+                remove_files(state.storage.as_ref(), vec![attachment_id]).await;
+                return Err(err);
+            }
+        };
+        if let Err(err) = record_upload(
+            &state.db,
+            &current_user,
+            attachment_id,
+            id,
+            item.execution_id,
+            &item.item_name,
+            &upload,
+            form.caption.as_deref(),
+            &stored,
+            quarantine_reason.as_deref(),
+        )
+        .await
+        {
+            remove_files(state.storage.as_ref(), vec![attachment_id]).await;
+            return Err(err);
+        }
+    }
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}{}",
+        item.execution_id,
+        form.group.query()
+    )))
+}
+
+#[utoipa::path(
+    get,
+    path = "/attachments/{id}",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Attachment id")),
+    responses(
+        (status = 200, description = "The attached file. Images are shown inline, other files are downloaded", content_type = "application/octet-stream"),
+        (status = 404, description = "Attachment not found", content_type = "text/html"),
+        (status = 409, description = "The file is quarantined", content_type = "text/html"),
+    )
+)]
+pub async fn file_get(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let attachment = sqlx::query!(
+        r#"
+        SELECT file_name, content_type, quarantine_reason
+        FROM execution_item_attachments
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| attachment_not_found(id))?;
+    if attachment.quarantine_reason.is_some() {
+        return Err(AppError::conflict(format!(
+            "\"{}\" is quarantined until an admin has reviewed it.",
+            attachment.file_name
+        )));
+    }
+
+    serve_file(
+        state.storage.as_ref(),
+        &file_key(id),
+        id,
+        attachment.file_name,
+        attachment.content_type,
+    )
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/attachments/{id}/thumbnail",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Attachment id")),
+    responses(
+        (status = 200, description = "Small JPEG preview of an attached image", content_type = "image/jpeg"),
+        (status = 404, description = "Attachment not found or not an image", content_type = "text/html"),
+    )
+)]
+pub async fn thumbnail_get(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let has_thumbnail = sqlx::query_scalar!(
+        "SELECT has_thumbnail FROM execution_item_attachments WHERE id = $1",
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .is_some_and(|value| value != 0);
+    if !has_thumbnail {
+        return Err(attachment_not_found(id));
+    }
+    let bytes = read_stored(state.storage.as_ref(), &thumbnail_key(id), id).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/jpeg"),
+            (header::CACHE_CONTROL, "private, max-age=86400"),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/attachments/{id}/original",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Attachment id")),
+    responses(
+        (status = 200, description = "A photo as it was uploaded, with its metadata", content_type = "application/octet-stream"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "Attachment not found or no original kept", content_type = "text/html"),
+    )
+)]
+pub async fn original_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+    let attachment = sqlx::query!(
+        r#"
+        SELECT file_name, content_type, original_size_bytes
+        FROM execution_item_attachments
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .filter(|attachment| attachment.original_size_bytes.is_some())
+    .ok_or_else(|| attachment_not_found(id))?;
+
+    serve_file(
+        state.storage.as_ref(),
+        &original_key(id),
+        id,
+        attachment.file_name,
+        attachment.content_type,
+    )
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/attachments/{id}/delete",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Attachment id")),
+    responses(
+        (status = 303, description = "Deletes the attachment and redirects to its execution"),
+        (status = 403, description = "Only whoever uploaded it or an admin can delete an attachment", content_type = "text/html"),
+        (status = 404, description = "Attachment not found", content_type = "text/html"),
+        (status = 409, description = "The execution is completed", content_type = "text/html"),
+    )
+)]
+pub async fn delete_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let mut tx = state.db.begin().await?;
+    let attachment = sqlx::query!(
+        r#"
+        SELECT
+            execution_item_attachments.action_item_execution as "item_id: uuid::Uuid",
+            execution_item_attachments.action_plan_execution as "execution_id: uuid::Uuid",
+            execution_item_attachments.file_name,
+            execution_item_attachments.uploaded_by as "uploaded_by: uuid::Uuid",
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM execution_item_attachments
+        INNER JOIN action_item_executions
+            ON action_item_executions.id = execution_item_attachments.action_item_execution
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = execution_item_attachments.action_plan_execution
+        WHERE execution_item_attachments.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| attachment_not_found(id))?;
+    if attachment.uploaded_by != current_user.id && !current_user.is_admin {
+        return Err(AppError::forbidden(
+            "Only whoever uploaded it or an admin can delete an attachment.",
+        ));
+    }
+    if attachment
+        .execution_finished
+        .is_some_and(|finished| finished > 0)
+    {
+        return Err(AppError::conflict(
+            "Attachments of a completed execution are kept as part of its record.",
+        ));
+    }
+
+    sqlx::query!("DELETE FROM execution_item_attachments WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    let description = executions::execution_description(&mut tx, attachment.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            attachment.item_id,
+            AuditAction::Update,
+            format!(
+                "Removed \"{}\" from \"{}\" in {}",
+                attachment.file_name, attachment.item_name, description
+            ),
+        )
+        .change(Some(attachment.file_name), None),
+    )
+    .await?;
+    tx.commit().await?;
+    remove_files(state.storage.as_ref(), vec![id]).await;
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}",
+        attachment.execution_id
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/action_plan/{id}/attachments",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan id")),
+    request_body(content_type = "multipart/form-data", description = "One or more files in `file` fields"),
+    responses(
+        (status = 303, description = "Stores the files and redirects to the plan"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+        (status = 409, description = "No file, a type that is not allowed or a file ClamAV flagged", content_type = "text/html"),
+        (status = 413, description = "A file is larger than attachment_max_bytes", content_type = "text/html"),
+    )
+)]
+pub async fn plan_upload_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    multipart: Multipart,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
+    let plan_name = sqlx::query_scalar!(
+        "SELECT name FROM action_plans WHERE id = $1 AND (deleted_at IS NULL OR deleted_at <= 0)",
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Action Plan",
+            format!("No action plan exists for id: {}", id),
+        )
+    })?;
+
+    let form = read_upload_form(multipart, &state.config).await?;
+    for upload in &form.files {
+        if let Some(reason) =
+            virus_scan::scan(&state.config, &upload.file_name, &upload.bytes).await
+        {
+            return Err(AppError::conflict(format!(
+                "\"{}\" was not attached. {}.",
+                upload.file_name, reason
+            )));
+        }
+    }
+    for upload in form.files {
+        let attachment_id = Uuid::new_v4();
+        let result = match store(
+            state.storage.as_ref(),
+            &state.config,
+            attachment_id,
+            &upload,
+            false,
+        )
+        .await
+        {
+            Ok(_) => {
+                record_plan_upload(
+                    &state.db,
+                    &current_user,
+                    attachment_id,
+                    id,
+                    &plan_name,
+                    &upload,
+                )
+                .await
+            }
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            remove_files(state.storage.as_ref(), vec![attachment_id]).await;
+            return Err(err);
+        }
+    }
+
+    Ok(Redirect::to(&format!("/action_plan/{}#attachments", id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/plan-attachments/{id}",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Plan attachment id")),
+    responses(
+        (status = 200, description = "The reference document. Images are shown inline, other files are downloaded", content_type = "application/octet-stream"),
+        (status = 404, description = "Attachment not found", content_type = "text/html"),
+    )
+)]
+pub async fn plan_file_get(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let attachment = sqlx::query!(
+        "SELECT file_name, content_type FROM plan_attachments WHERE id = $1",
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| attachment_not_found(id))?;
+
+    serve_file(
+        state.storage.as_ref(),
+        &file_key(id),
+        id,
+        attachment.file_name,
+        attachment.content_type,
+    )
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/plan-attachments/{id}/delete",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Plan attachment id")),
+    responses(
+        (status = 303, description = "Deletes the reference document and redirects to its plan"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "Attachment not found", content_type = "text/html"),
+    )
+)]
+pub async fn plan_delete_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
+    let mut tx = state.db.begin().await?;
+    let attachment = sqlx::query!(
+        r#"
+        SELECT
+            plan_attachments.action_plan as "plan_id: uuid::Uuid",
+            plan_attachments.file_name,
+            action_plans.name as "plan_name!"
+        FROM plan_attachments
+        INNER JOIN action_plans ON action_plans.id = plan_attachments.action_plan
+        WHERE plan_attachments.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| attachment_not_found(id))?;
+
+    sqlx::query!("DELETE FROM plan_attachments WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Plan,
+            attachment.plan_id,
+            AuditAction::Update,
+            format!(
+                "Removed \"{}\" from plan \"{}\"",
+                attachment.file_name, attachment.plan_name
+            ),
+        )
+        .change(Some(attachment.file_name), None),
+    )
+    .await?;
+    tx.commit().await?;
+    remove_files(state.storage.as_ref(), vec![id]).await;
+
+    Ok(Redirect::to(&format!(
+        "/action_plan/{}#attachments",
+        attachment.plan_id
+    )))
+}
+
+/// Number of attachments waiting in the quarantine, for the admin page.
+pub async fn quarantined_count(db: &SqlitePool) -> Result<i64, AppError> {
+    Ok(sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!: i64"
+        FROM execution_item_attachments
+        WHERE quarantine_reason IS NOT NULL
+        "#
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/quarantine",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Attachments held back because ClamAV flagged them or could not scan them, to release or delete", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
+pub async fn quarantine_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+    // Left joins, as executions moved to the archive leave their attachments here.
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            execution_item_attachments.id as "id: uuid::Uuid",
+            execution_item_attachments.action_plan_execution as "execution_id: uuid::Uuid",
+            execution_item_attachments.file_name,
+            execution_item_attachments.size_bytes,
+            execution_item_attachments.quarantine_reason as "quarantine_reason!",
+            execution_item_attachments.uploaded_at,
+            execution_item_attachments.uploaded_by_name,
+            actions.name as "item_name?",
+            action_plans.name as "plan_name?"
+        FROM execution_item_attachments
+        LEFT JOIN action_item_executions
+            ON action_item_executions.id = execution_item_attachments.action_item_execution
+        LEFT JOIN actions ON actions.id = action_item_executions.action
+        LEFT JOIN action_plan_executions
+            ON action_plan_executions.id = execution_item_attachments.action_plan_execution
+        LEFT JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE execution_item_attachments.quarantine_reason IS NOT NULL
+        ORDER BY execution_item_attachments.uploaded_at ASC, execution_item_attachments.rowid ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let rendered = templates::render(
+        &state.jinja,
+        "quarantine.html",
+        QuarantineView {
+            attachments: rows
+                .into_iter()
+                .map(|row| QuarantinedAttachment {
+                    id: row.id,
+                    execution_id: row.execution_id,
+                    file_name: row.file_name,
+                    size_display: format_bytes(row.size_bytes as u64),
+                    reason: row.quarantine_reason,
+                    item_name: row.item_name,
+                    plan_name: row.plan_name,
+                    uploaded_display: format_unix_timestamp(row.uploaded_at),
+                    uploaded_by_name: row.uploaded_by_name,
+                })
+                .collect(),
+            is_scanning: state.config.clamd_address.is_some(),
+            is_admin: true,
+        },
+    )?;
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/quarantine/{id}/release",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Attachment id")),
+    responses(
+        (status = 303, description = "Makes the file available on its item, processing photos as on upload, and redirects to the quarantine"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "No quarantined attachment with this id", content_type = "text/html"),
+    )
+)]
+pub async fn quarantine_release_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+    let attachment = sqlx::query!(
+        r#"
+        SELECT
+            action_item_execution as "item_id: uuid::Uuid",
+            action_plan_execution as "execution_id: uuid::Uuid",
+            file_name,
+            content_type
+        FROM execution_item_attachments
+        WHERE id = $1 AND quarantine_reason IS NOT NULL
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| attachment_not_found(id))?;
+
+    let upload = Upload {
+        bytes: read_stored(state.storage.as_ref(), &file_key(id), id).await?,
+        file_name: attachment.file_name,
+        content_type: attachment.content_type,
+    };
+    let stored = store(state.storage.as_ref(), &state.config, id, &upload, true).await?;
+
+    let mut tx = state.db.begin().await?;
+    sqlx::query!(
+        r#"
+        UPDATE execution_item_attachments
+        SET quarantine_reason = NULL, size_bytes = $2, has_thumbnail = $3, original_size_bytes = $4
+        WHERE id = $1
+        "#,
+        id,
+        stored.size_bytes,
+        stored.has_thumbnail,
+        stored.original_size_bytes
+    )
+    .execute(&mut *tx)
+    .await?;
+    let description = executions::execution_description(&mut tx, attachment.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            attachment.item_id,
+            AuditAction::Update,
+            format!(
+                "Released \"{}\" in {} from the quarantine",
+                upload.file_name, description
+            ),
+        ),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Redirect::to("/admin/quarantine"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/quarantine/{id}/delete",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Attachment id")),
+    responses(
+        (status = 303, description = "Deletes the quarantined file, also from completed executions, and redirects to the quarantine"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "No quarantined attachment with this id", content_type = "text/html"),
+    )
+)]
+pub async fn quarantine_delete_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+    let mut tx = state.db.begin().await?;
+    let attachment = sqlx::query!(
+        r#"
+        DELETE FROM execution_item_attachments
+        WHERE id = $1 AND quarantine_reason IS NOT NULL
+        RETURNING
+            action_item_execution as "item_id: uuid::Uuid",
+            action_plan_execution as "execution_id: uuid::Uuid",
+            file_name
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| attachment_not_found(id))?;
+    let description = executions::execution_description(&mut tx, attachment.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            attachment.item_id,
+            AuditAction::Update,
+            format!(
+                "Deleted quarantined \"{}\" from {}",
+                attachment.file_name, description
+            ),
+        )
+        .change(Some(attachment.file_name.clone()), None),
+    )
+    .await?;
+    tx.commit().await?;
+    remove_files(state.storage.as_ref(), vec![id]).await;
+
+    Ok(Redirect::to("/admin/quarantine"))
+}
+
+/// Reads the files of an upload form, rejecting any that exceed the configured limits.
+async fn read_upload_form(
+    mut multipart: Multipart,
+    config: &Config,
+) -> Result<UploadForm, AppError> {
+    let mut uploads = Vec::new();
+    let mut group = ItemGrouping::Plan;
+    let mut caption = None;
+    while let Some(mut field) = multipart.next_field().await.map_err(upload_failed)? {
+        match field.name() {
+            Some("caption") => {
+                let value = field.text().await.map_err(upload_failed)?;
+                let value = value.trim();
+                if value.chars().count() > MAX_CAPTION_CHARS {
+                    return Err(AppError::conflict(format!(
+                        "Captions can be at most {} characters long.",
+                        MAX_CAPTION_CHARS
+                    )));
+                }
+                caption = Some(value.to_string()).filter(|value| !value.is_empty());
+            }
+            Some("group") => {
+                let value = field.text().await.map_err(upload_failed)?;
+                group = match value.as_str() {
+                    "assignee" => ItemGrouping::Assignee,
+                    _ => ItemGrouping::Plan,
+                };
+            }
+            Some("file") => {
+                if uploads.len() == MAX_FILES_PER_UPLOAD {
+                    return Err(AppError::payload_too_large(format!(
+                        "Attach at most {} files at once.",
+                        MAX_FILES_PER_UPLOAD
+                    )));
+                }
+                let file_name = clean_file_name(field.file_name().unwrap_or_default());
+                let declared_type = field
+                    .content_type()
+                    .and_then(|value| value.parse::<mime::Mime>().ok())
+                    .map(|value| value.essence_str().to_ascii_lowercase());
+                let mut bytes = Vec::new();
+                while let Some(chunk) = field.chunk().await.map_err(upload_failed)? {
+                    if (bytes.len() + chunk.len()) as u64 > config.attachment_max_bytes {
+                        return Err(AppError::payload_too_large(format!(
+                            "\"{}\" is larger than the {} allowed per file.",
+                            file_name,
+                            format_bytes(config.attachment_max_bytes)
+                        )));
+                    }
+                    bytes.extend_from_slice(&chunk);
+                }
+                // Browsers send an empty part when no file was chosen.
+                if bytes.is_empty() {
+                    continue;
+                }
+
+                let content_type = content_type(&bytes, declared_type);
+                if !config
+                    .attachment_allowed_types
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(&content_type))
+                {
+                    return Err(AppError::conflict(format!(
+                        "\"{}\" is {}, which cannot be attached. Allowed are: {}.",
+                        file_name,
+                        content_type,
+                        config.attachment_allowed_types.join(", ")
+                    )));
+                }
+                uploads.push(Upload {
+                    file_name,
+                    content_type,
+                    bytes,
+                });
+            }
+            _ => {}
+        }
+    }
+    if uploads.is_empty() {
+        return Err(AppError::conflict("Choose a file to attach."));
+    }
+
+    Ok(UploadForm {
+        files: uploads,
+        group,
+        caption,
+    })
+}
+
+/// Writes the file. With `as_photo`, images that can be decoded are stored as processed by
+/// [`process_photo`] with a thumbnail and, if configured, the upload as it was sent.
+async fn store(
+    storage: &dyn Storage,
+    config: &Config,
+    id: Uuid,
+    upload: &Upload,
+    as_photo: bool,
+) -> Result<Stored, AppError> {
+    let photo = if as_photo && upload.content_type.starts_with("image/") {
+        let bytes = upload.bytes.clone();
+        let max_pixels = config.photo_max_pixels;
+        match tokio::task::spawn_blocking(move || process_photo(&bytes, max_pixels)).await? {
+            Ok(photo) => Some(photo),
+            Err(err) => {
+                tracing::debug!("Storing \"{}\" as uploaded: {:#}", upload.file_name, err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let Some(photo) = photo else {
+        storage.put(&file_key(id), upload.bytes.clone()).await?;
+        return Ok(Stored {
+            size_bytes: upload.bytes.len() as i64,
+            original_size_bytes: None,
+            has_thumbnail: false,
+        });
+    };
+
+    let size_bytes = photo.image.len() as i64;
+    storage.put(&file_key(id), photo.image).await?;
+    storage.put(&thumbnail_key(id), photo.thumbnail).await?;
+    let original_size_bytes = if config.photo_keep_originals {
+        storage.put(&original_key(id), upload.bytes.clone()).await?;
+        Some(upload.bytes.len() as i64)
+    } else {
+        None
+    };
+    Ok(Stored {
+        size_bytes,
+        original_size_bytes,
+        has_thumbnail: true,
+    })
+}
+
+/// Re-encodes a photo in its own format, which leaves out its metadata. It is turned upright
+/// first, as its EXIF orientation says, and scaled down to `max_pixels` unless that is 0.
+fn process_photo(bytes: &[u8], max_pixels: u32) -> anyhow::Result<ProcessedPhoto> {
+    let reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    let format = reader.format().context("unknown image format")?;
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    if max_pixels > 0 && (image.width() > max_pixels || image.height() > max_pixels) {
+        image = image.resize(max_pixels, max_pixels, FilterType::Lanczos3);
+    }
+
+    let mut encoded = Vec::new();
+    if format == ImageFormat::Jpeg {
+        JpegEncoder::new_with_quality(&mut encoded, PHOTO_JPEG_QUALITY)
+            .encode_image(&image.to_rgb8())?;
+    } else {
+        image.write_to(&mut Cursor::new(&mut encoded), format)?;
+    }
+    let mut thumbnail = Vec::new();
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .into_rgb8()
+        .write_to(&mut Cursor::new(&mut thumbnail), ImageFormat::Jpeg)?;
+    Ok(ProcessedPhoto {
+        image: encoded,
+        thumbnail,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn record_upload(
+    db: &SqlitePool,
+    current_user: &CurrentUser,
+    attachment_id: Uuid,
+    item_id: Uuid,
+    execution_id: Uuid,
+    item_name: &str,
+    upload: &Upload,
+    caption: Option<&str>,
+    stored: &Stored,
+    quarantine_reason: Option<&str>,
+) -> Result<(), AppError> {
+    let now = unix_now();
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO execution_item_attachments (
+            id, action_item_execution, action_plan_execution, file_name, content_type,
+            size_bytes, has_thumbnail, original_size_bytes, uploaded_at, uploaded_by,
+            uploaded_by_name, quarantine_reason, caption
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        "#,
+        attachment_id,
+        item_id,
+        execution_id,
+        upload.file_name,
+        upload.content_type,
+        stored.size_bytes,
+        stored.has_thumbnail,
+        stored.original_size_bytes,
+        now,
+        current_user.id,
+        current_user.name,
+        quarantine_reason,
+        caption
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let description = executions::execution_description(&mut tx, execution_id).await?;
+    audit::record(
+        &mut *tx,
+        current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            item_id,
+            AuditAction::Update,
+            match quarantine_reason {
+                Some(reason) => format!(
+                    "Attached \"{}\" to \"{}\" in {}, quarantined: {}",
+                    upload.file_name, item_name, description, reason
+                ),
+                None => format!(
+                    "Attached \"{}\" to \"{}\" in {}",
+                    upload.file_name, item_name, description
+                ),
+            },
+        )
+        .change(None, Some(upload.file_name.clone())),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn record_plan_upload(
+    db: &SqlitePool,
+    current_user: &CurrentUser,
+    attachment_id: Uuid,
+    plan_id: Uuid,
+    plan_name: &str,
+    upload: &Upload,
+) -> Result<(), AppError> {
+    let size_bytes = upload.bytes.len() as i64;
+    let now = unix_now();
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO plan_attachments (
+            id, action_plan, file_name, content_type, size_bytes, uploaded_at, uploaded_by_name
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        attachment_id,
+        plan_id,
+        upload.file_name,
+        upload.content_type,
+        size_bytes,
+        now,
+        current_user.name
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    audit::record(
+        &mut *tx,
+        current_user,
+        AuditEntry::new(
+            AuditEntity::Plan,
+            plan_id,
+            AuditAction::Update,
+            format!(
+                "Attached \"{}\" to plan \"{}\"",
+                upload.file_name, plan_name
+            ),
+        )
+        .change(None, Some(upload.file_name.clone())),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Streams a stored file to the client. Only types recognized from the content are images, so
+/// nothing else is rendered inline.
+async fn serve_file(
+    storage: &dyn Storage,
+    key: &str,
+    id: Uuid,
+    file_name: String,
+    content_type: String,
+) -> Result<Response, AppError> {
+    let Some(body) = storage.open(key).await? else {
+        tracing::warn!("Attachment {} is missing from the storage as {}", id, key);
+        return Err(attachment_not_found(id));
+    };
+    let disposition = if content_type.starts_with("image/") {
+        "inline"
+    } else {
+        "attachment"
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(disposition, &file_name),
+            ),
+            (header::CACHE_CONTROL, "private, max-age=86400".to_string()),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// The type recognized from the content for images and PDFs, otherwise the declared one.
+/// Files only claiming to be images are treated as unknown, so they are never shown inline.
+fn content_type(bytes: &[u8], declared: Option<String>) -> String {
+    if let Ok(format) = image::guess_format(bytes) {
+        return format.to_mime_type().to_string();
+    }
+    if bytes.starts_with(b"%PDF-") {
+        return "application/pdf".to_string();
+    }
+    declared
+        .filter(|declared| !declared.starts_with("image/") && declared != "application/pdf")
+        .unwrap_or_else(|| "application/octet-stream".to_string())
+}
+
+/// The last path segment of the name the browser sent, without control characters.
+fn clean_file_name(name: &str) -> String {
+    let name: String = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|character| !character.is_control())
+        .collect();
+    let name = name.trim();
+    if name.is_empty() {
+        "attachment".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// `filename` with an ASCII fallback and `filename*` with the percent-encoded UTF-8 name.
+fn content_disposition(disposition: &str, file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|character| match character {
+            ' '..='~' if character != '"' && character != '\\' => character,
+            _ => '_',
+        })
+        .collect();
+    let encoded: String = file_name
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect();
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        disposition, fallback, encoded
+    )
+}
+
+async fn read_stored(storage: &dyn Storage, key: &str, id: Uuid) -> Result<Vec<u8>, AppError> {
+    match storage.get(key).await? {
+        Some(bytes) => Ok(bytes),
+        None => {
+            tracing::warn!("Attachment {} is missing from the storage as {}", id, key);
+            Err(attachment_not_found(id))
+        }
+    }
+}
+
+fn file_key(id: Uuid) -> String {
+    format!("attachments/{}", id)
+}
+
+fn thumbnail_key(id: Uuid) -> String {
+    format!("attachments/{}.thumb.jpg", id)
+}
+
+fn original_key(id: Uuid) -> String {
+    format!("attachments/{}.original", id)
+}
+
+fn upload_failed(err: axum::extract::multipart::MultipartError) -> AppError {
+    AppError::conflict(format!("The upload could not be read: {}", err.body_text()))
+}
+
+fn item_not_found(id: Uuid) -> AppError {
+    AppError::not_found_for(
+        "Execution",
+        format!("No execution item exists for id: {}", id),
+    )
+}
+
+fn attachment_not_found(id: Uuid) -> AppError {
+    AppError::not_found_for("Attachment", format!("No attachment exists for id: {}", id))
+}
@@ -0,0 +1,418 @@
+//! Append-only log of changes to plans, executions, checklist items, users and backups, kept as
+//! evidence of who did which maintenance step. Handlers record an entry in the same
+//! transaction as the change, so one never exists without the other. Admins read it as the
+//! activity feed under `/admin/activity`.
+
+use std::net::IpAddr;
+
+use axum::{
+    extract::{Query, State},
+    response::Html,
+};
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    authz::{Action, Resource, authorize},
+    format_unix_timestamp, templates, unix_now,
+};
+
+/// Entries shown per page of the activity feed, newest first.
+const PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    Plan,
+    Execution,
+    ExecutionItem,
+    User,
+    Invite,
+    Backup,
+    Task,
+}
+
+impl AuditEntity {
+    pub const ALL: [AuditEntity; 7] = [
+        AuditEntity::Plan,
+        AuditEntity::Execution,
+        AuditEntity::ExecutionItem,
+        AuditEntity::User,
+        AuditEntity::Invite,
+        AuditEntity::Backup,
+        AuditEntity::Task,
+    ];
+
+    /// The value stored in `audit_log.entity_type`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditEntity::Plan => "plan",
+            AuditEntity::Execution => "execution",
+            AuditEntity::ExecutionItem => "execution_item",
+            AuditEntity::User => "user",
+            AuditEntity::Invite => "invite",
+            AuditEntity::Backup => "backup",
+            AuditEntity::Task => "task",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|entity| entity.as_str() == value)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AuditEntity::Plan => "Plan",
+            AuditEntity::Execution => "Execution",
+            AuditEntity::ExecutionItem => "Checklist Item",
+            AuditEntity::User => "User",
+            AuditEntity::Invite => "Invite",
+            AuditEntity::Backup => "Backup",
+            AuditEntity::Task => "Follow-up Task",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Merge,
+    Complete,
+    Reopen,
+    Import,
+    Transfer,
+}
+
+impl AuditAction {
+    /// The value stored in `audit_log.action`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Merge => "merge",
+            AuditAction::Complete => "complete",
+            AuditAction::Reopen => "reopen",
+            AuditAction::Import => "import",
+            AuditAction::Transfer => "transfer",
+        }
+    }
+
+    fn label_for(value: &str) -> &str {
+        match value {
+            "create" => "Created",
+            "update" => "Changed",
+            "delete" => "Deleted",
+            "restore" => "Restored",
+            "merge" => "Merged",
+            "complete" => "Completed",
+            "reopen" => "Reopened",
+            "import" => "Imported",
+            "transfer" => "Handed over",
+            other => other,
+        }
+    }
+}
+
+/// One change to record. `before` and `after` are short readable summaries of the affected
+/// record, left out where the summary says everything.
+pub struct AuditEntry {
+    entity: AuditEntity,
+    entity_id: Option<Uuid>,
+    action: AuditAction,
+    summary: String,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        entity: AuditEntity,
+        entity_id: impl Into<Option<Uuid>>,
+        action: AuditAction,
+        summary: impl Into<String>,
+    ) -> Self {
+        Self {
+            entity,
+            entity_id: entity_id.into(),
+            action,
+            summary: summary.into(),
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn change(mut self, before: Option<String>, after: Option<String>) -> Self {
+        self.before = before;
+        self.after = after;
+        self
+    }
+}
+
+/// Records a change made by the logged-in user.
+pub async fn record<'c, E>(
+    executor: E,
+    actor: &CurrentUser,
+    entry: AuditEntry,
+) -> Result<(), AppError>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    record_as(executor, actor.id, &actor.name, actor.ip_address, entry).await
+}
+
+/// Records a change made by someone who is not logged in yet, e.g. a user accepting an invite.
+pub async fn record_as<'c, E>(
+    executor: E,
+    user_id: Uuid,
+    user_name: &str,
+    ip_address: Option<IpAddr>,
+    entry: AuditEntry,
+) -> Result<(), AppError>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let id = Uuid::new_v4();
+    let now = unix_now();
+    let ip_address = ip_address.map(|address| address.to_string());
+    let entity_type = entry.entity.as_str();
+    let action = entry.action.as_str();
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_log
+            (id, created_at, user_id, user_name, ip_address, entity_type, entity_id, action, summary, before_state, after_state)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+        id,
+        now,
+        user_id,
+        user_name,
+        ip_address,
+        entity_type,
+        entry.entity_id,
+        action,
+        entry.summary,
+        entry.before,
+        entry.after
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ActivityQuery {
+    /// Only changes made by this user.
+    user: Option<String>,
+    /// Only changes to this kind of record, e.g. `plan` or `user`.
+    entity: Option<String>,
+    /// First day to show, as `YYYY-MM-DD` in server time.
+    from: Option<String>,
+    /// Last day to show, as `YYYY-MM-DD` in server time.
+    to: Option<String>,
+    /// Text the summary has to contain, e.g. a plan name.
+    q: Option<String>,
+    /// Only entries older than this one, for paging.
+    before: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ActivityView {
+    entries: Vec<ActivityItem>,
+    users: Vec<UserOption>,
+    entities: Vec<EntityOption>,
+    selected_user: Option<Uuid>,
+    selected_entity: Option<&'static str>,
+    from: String,
+    to: String,
+    search_query: String,
+    /// Cursor for the next page, set when there may be older entries than the ones shown.
+    older_before: Option<i64>,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ActivityItem {
+    created_display: String,
+    user_name: String,
+    ip_address: Option<String>,
+    entity_label: &'static str,
+    action_label: String,
+    summary: String,
+    before_state: Option<String>,
+    after_state: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct UserOption {
+    id: Uuid,
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EntityOption {
+    value: &'static str,
+    label: &'static str,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/activity",
+    tag = "admin",
+    params(ActivityQuery),
+    responses(
+        (status = 200, description = "Timeline of changes from the audit log, newest first", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
+pub async fn activity_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Html<String>, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+
+    let selected_user = non_empty(&query.user).and_then(|user| user.parse::<Uuid>().ok());
+    let entity_type = non_empty(&query.entity)
+        .and_then(AuditEntity::from_db)
+        .map(AuditEntity::as_str);
+    let from = non_empty(&query.from).and_then(parse_date);
+    let to = non_empty(&query.to).and_then(parse_date);
+    let search_query = non_empty(&query.q).unwrap_or_default().to_string();
+
+    // Whole days in server time; the end is the midnight after the last day.
+    let since = from.and_then(local_midnight).unwrap_or(i64::MIN);
+    let until = to
+        .and_then(|date| date.succ_opt())
+        .and_then(local_midnight)
+        .unwrap_or(i64::MAX);
+    let before = query.before.unwrap_or(i64::MAX);
+    let search_pattern = format!("%{}%", search_query);
+    // Entries are never deleted, so the rowid orders them and pages without gaps.
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            rowid as "rowid!: i64",
+            created_at,
+            user_name,
+            ip_address,
+            entity_type,
+            action,
+            summary,
+            before_state,
+            after_state
+        FROM audit_log
+        WHERE ($1 IS NULL OR user_id = $1)
+            AND ($2 IS NULL OR entity_type = $2)
+            AND created_at >= $3
+            AND created_at < $4
+            AND summary LIKE $5
+            AND rowid < $6
+        ORDER BY rowid DESC
+        LIMIT $7
+        "#,
+        selected_user,
+        entity_type,
+        since,
+        until,
+        search_pattern,
+        before,
+        PAGE_SIZE
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let older_before = (rows.len() as i64 == PAGE_SIZE)
+        .then(|| rows.last().map(|row| row.rowid))
+        .flatten();
+    let entries = rows
+        .into_iter()
+        .map(|row| ActivityItem {
+            created_display: format_unix_timestamp(row.created_at),
+            user_name: row.user_name,
+            ip_address: row.ip_address,
+            entity_label: AuditEntity::from_db(&row.entity_type)
+                .map(AuditEntity::label)
+                .unwrap_or("Other"),
+            action_label: AuditAction::label_for(&row.action).to_string(),
+            summary: row.summary,
+            before_state: row.before_state,
+            after_state: row.after_state,
+        })
+        .collect();
+
+    // Everyone who ever changed something, under the name they had last, so deleted users can
+    // still be picked.
+    let users = sqlx::query_as!(
+        UserOption,
+        r#"
+        SELECT user_id as "id!: uuid::Uuid", user_name as name
+        FROM audit_log
+        WHERE rowid IN (
+            SELECT MAX(rowid) FROM audit_log WHERE user_id IS NOT NULL GROUP BY user_id
+        )
+        ORDER BY user_name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let rendered = templates::render(
+        &state.jinja,
+        "activity.html",
+        ActivityView {
+            entries,
+            users,
+            entities: AuditEntity::ALL
+                .into_iter()
+                .map(|entity| EntityOption {
+                    value: entity.as_str(),
+                    label: entity.label(),
+                })
+                .collect(),
+            selected_user,
+            selected_entity: entity_type,
+            from: from.map(|date| date.to_string()).unwrap_or_default(),
+            to: to.map(|date| date.to_string()).unwrap_or_default(),
+            search_query,
+            older_before,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+fn local_midnight(date: NaiveDate) -> Option<i64> {
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|datetime| datetime.timestamp())
+}
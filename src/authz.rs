@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{AppError, CurrentUser};
+
+/// What a user may do, each role including everything the ones before it may.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Reads plans, executions, reports and stats.
+    Viewer,
+    /// Also starts, checks off, completes and reopens executions.
+    Operator,
+    /// Also edits plans and tags and manages users and backups.
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Viewer, Role::Operator, Role::Admin];
+
+    /// The value stored in `users.role`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Unknown values fall back to the least privileged role.
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "admin" => Role::Admin,
+            "operator" => Role::Operator,
+            _ => Role::Viewer,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Role::Viewer => "Viewer",
+            Role::Operator => "Operator",
+            Role::Admin => "Admin",
+        }
+    }
+}
+
+/// Something a handler does that not every logged-in user may do. Everything else (viewing
+/// plans, executions, tags, reports and the calendar) is open to all logged-in users.
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    /// Any page behind the admin-only routes.
+    AccessAdminPages,
+    /// Starting, checking off, annotating, completing, reopening and deleting executions.
+    RunExecutions,
+    /// Creating, editing and deleting plans and tags.
+    EditPlans,
+    ManageUsers,
+    ManageBackups,
+    MergePlans,
+    RevokeSession,
+}
+
+/// What an action applies to, for rules that depend on ownership.
+#[derive(Debug, Clone, Copy)]
+pub enum Resource {
+    Everything,
+    Session { owner: Uuid },
+}
+
+/// The single place that decides who may do what. Handlers call it before acting, and the
+/// role layers of the router call it as a second line of defence.
+pub fn authorize(user: &CurrentUser, action: Action, resource: Resource) -> Result<(), AppError> {
+    let allowed = match (action, resource) {
+        (
+            Action::AccessAdminPages
+            | Action::EditPlans
+            | Action::ManageUsers
+            | Action::ManageBackups
+            | Action::MergePlans,
+            _,
+        ) => user.role == Role::Admin,
+        (Action::RunExecutions, _) => user.role >= Role::Operator,
+        (Action::RevokeSession, Resource::Session { owner }) => owner == user.id,
+        (Action::RevokeSession, Resource::Everything) => false,
+    };
+    if allowed {
+        return Ok(());
+    }
+
+    Err(AppError::forbidden(match action {
+        Action::AccessAdminPages => "Only admin users can access this page.",
+        Action::RunExecutions => "Viewers cannot run or change executions.",
+        Action::EditPlans => "Only admin users can edit action plans and tags.",
+        Action::ManageUsers => "Only admin users can manage users.",
+        Action::ManageBackups => "Only admin users can access backup and restore.",
+        Action::MergePlans => "Only admin users can merge action plans.",
+        Action::RevokeSession => "You can only end your own sessions.",
+    }))
+}
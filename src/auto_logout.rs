@@ -0,0 +1,135 @@
+//! Automatic logout for shared terminals. Sessions of users with an auto-logout time end after
+//! that long without activity, which is enforced when the session is next used. Pages show a
+//! countdown banner for the last minute, fed by a server-sent event stream that does not count
+//! as activity itself.
+
+use std::{convert::Infallible, future::Future, time::Duration};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use serde::Serialize;
+use tokio_stream::{Stream, StreamExt, wrappers::IntervalStream};
+use utoipa::ToSchema;
+
+use crate::{AppError, AppState, CurrentUser, config::Config, unix_now};
+
+/// Path of the countdown stream, which must not keep the session alive.
+pub const EVENTS_PATH: &str = "/session/idle-events";
+/// How often the countdown stream reports the remaining time.
+const EVENT_INTERVAL_SECONDS: u64 = 5;
+
+tokio::task_local! {
+    /// Auto-logout time of the user the current request is served for, for the layout.
+    static CURRENT_LIMIT: Option<i64>;
+}
+
+/// Remaining time before the session ends, as sent on the countdown stream.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IdleEvent {
+    logged_out: bool,
+    /// `None` when no auto-logout applies (anymore).
+    remaining_seconds: Option<i64>,
+}
+
+/// Seconds without activity after which a session ends, the shorter of the instance setting
+/// and the shortest setting of the user's groups. `None` when neither applies.
+pub fn limit_seconds(config: &Config, group_minutes: Option<i64>) -> Option<i64> {
+    [Some(config.auto_logout_minutes), group_minutes]
+        .into_iter()
+        .flatten()
+        .filter(|minutes| *minutes > 0)
+        .min()
+        .map(|minutes| minutes.saturating_mul(60))
+}
+
+/// Runs `future` with `limit` available to [`layout_limit`].
+pub async fn scope<F: Future>(limit: Option<i64>, future: F) -> F::Output {
+    CURRENT_LIMIT.scope(limit, future).await
+}
+
+/// Auto-logout time for the layout, so it only loads the countdown for users it applies to.
+pub fn layout_limit() -> Option<i64> {
+    CURRENT_LIMIT.try_with(|limit| *limit).ok().flatten()
+}
+
+#[utoipa::path(
+    get,
+    path = "/session/idle-events",
+    tag = "users",
+    responses(
+        (status = 200, description = "Server-sent events with the time left before the session is logged out for inactivity", content_type = "text/event-stream", body = IdleEvent),
+    )
+)]
+pub async fn events_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let interval = tokio::time::interval(Duration::from_secs(EVENT_INTERVAL_SECONDS));
+    let stream = IntervalStream::new(interval)
+        .then(move |_| {
+            let state = state.clone();
+            async move {
+                let event = match idle_event(&state, current_user.session_id).await {
+                    Ok(event) => event,
+                    Err(err) => {
+                        tracing::warn!("Reading the session's idle time failed: {:?}", err);
+                        return None;
+                    }
+                };
+                Event::default().json_data(event).ok()
+            }
+        })
+        .filter_map(|event| event.map(Ok));
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[utoipa::path(
+    post,
+    path = "/session/keep-alive",
+    tag = "users",
+    responses(
+        (status = 204, description = "Records activity, restarting the countdown to the automatic logout"),
+    )
+)]
+pub async fn keep_alive_post(_current_user: CurrentUser) -> StatusCode {
+    // Loading the session already recorded the activity.
+    StatusCode::NO_CONTENT
+}
+
+async fn idle_event(state: &AppState, session_id: uuid::Uuid) -> Result<IdleEvent, AppError> {
+    let session = sqlx::query!(
+        r#"
+        SELECT
+            user_sessions.last_seen,
+            (
+                SELECT MIN(user_groups.auto_logout_minutes)
+                FROM user_group_members
+                INNER JOIN user_groups ON user_groups.id = user_group_members.user_group
+                WHERE user_group_members.user_id = user_sessions.user_id
+                    AND user_groups.auto_logout_minutes > 0
+            ) as "group_minutes?: i64"
+        FROM user_sessions
+        WHERE user_sessions.id = $1
+        "#,
+        session_id
+    )
+    .fetch_optional(&state.db)
+    .await?;
+    let Some(session) = session else {
+        return Ok(IdleEvent {
+            logged_out: true,
+            remaining_seconds: None,
+        });
+    };
+
+    let remaining_seconds = limit_seconds(&state.config, session.group_minutes)
+        .map(|limit| session.last_seen.saturating_add(limit) - unix_now());
+    Ok(IdleEvent {
+        logged_out: remaining_seconds.is_some_and(|remaining| remaining <= 0),
+        remaining_seconds: remaining_seconds.map(|remaining| remaining.max(0)),
+    })
+}
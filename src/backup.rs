@@ -1,21 +1,47 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use axum::{
     Json,
-    extract::{Multipart, State},
+    extract::{Multipart, Query, State},
     http::{HeaderValue, header},
     response::{Html, IntoResponse},
 };
 use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-use crate::{AppError, AppState, CurrentUser};
+use crate::{
+    AppError, AppState, CurrentUser, action_plan, attachments,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, authorize},
+    config::Config,
+    item_types::ItemType,
+    storage::Storage,
+    templates, unix_now,
+};
+
+/// Key prefix of the backups written by [`run_scheduler`].
+const SCHEDULED_BACKUP_PREFIX: &str = "backups/maintenance-planner-backup-";
 
+#[utoipa::path(
+    get,
+    path = "/backup",
+    tag = "backup",
+    responses(
+        (status = 200, description = "Backup and restore page", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
 pub async fn index(
     State(state): State<AppState>,
     current_user: CurrentUser,
 ) -> Result<Html<String>, AppError> {
-    require_admin(&current_user)?;
+    authorize(&current_user, Action::ManageBackups, Resource::Everything)?;
     render_backup_page(&state, None, current_user.is_admin)
 }
 
@@ -24,33 +50,131 @@ fn render_backup_page(
     notice: Option<BackupNotice>,
     is_admin: bool,
 ) -> Result<Html<String>, AppError> {
-    let template = state
-        .jinja
-        .get_template("backup.html")
-        .expect("template is loaded");
-    let rendered = template.render(BackupPageView { notice, is_admin })?;
+    let rendered = templates::render(
+        &state.jinja,
+        "backup.html",
+        BackupPageView { notice, is_admin },
+    )?;
     Ok(Html(rendered))
 }
 
+#[utoipa::path(
+    get,
+    path = "/backup/export.json",
+    tag = "backup",
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "Backup of all plans, tags and executions", body = BackupFile),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
 pub async fn export_json(
     State(state): State<AppState>,
     current_user: CurrentUser,
+    Query(query): Query<ExportQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    require_admin(&current_user)?;
+    authorize(&current_user, Action::ManageBackups, Resource::Everything)?;
+    let mut backup = build(&state.db).await?;
+
+    let content_disposition = if query.anonymize.unwrap_or(false) {
+        anonymize(&mut backup);
+        "attachment; filename=\"maintenance-planner-backup-anonymized.json\""
+    } else {
+        "attachment; filename=\"maintenance-planner-backup.json\""
+    };
+
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static(content_disposition),
+        )],
+        Json(backup),
+    ))
+}
+
+/// Writes a backup to the storage every `backup_interval_seconds`, starting one interval after
+/// startup, and keeps the newest `backups_kept`.
+pub async fn run_scheduler(db: SqlitePool, config: Arc<Config>, storage: Arc<dyn Storage>) {
+    if config.backup_interval_seconds == 0 {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(config.backup_interval_seconds));
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if let Err(err) = write_scheduled(&db, &config, storage.as_ref()).await {
+            tracing::error!("Scheduled backup failed: {:#}", err);
+        }
+    }
+}
+
+async fn write_scheduled(
+    db: &SqlitePool,
+    config: &Config,
+    storage: &dyn Storage,
+) -> anyhow::Result<()> {
+    let backup = build(db).await.map_err(|err| anyhow::anyhow!("{}", err))?;
+    // The UTC timestamp sorts like the time, so the oldest backups come first when pruning.
+    let key = format!(
+        "{}{}.json",
+        SCHEDULED_BACKUP_PREFIX,
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+    );
+    storage.put(&key, serde_json::to_vec(&backup)?).await?;
+    tracing::info!("Wrote scheduled backup {}", key);
+
+    let mut keys = storage.list(SCHEDULED_BACKUP_PREFIX).await?;
+    keys.sort();
+    let excess = keys.len().saturating_sub(config.backups_kept);
+    for key in &keys[..excess] {
+        storage.delete(key).await?;
+    }
+    Ok(())
+}
 
+/// Everything a backup holds, as exported and written by the scheduled backups.
+pub async fn build(db: &SqlitePool) -> Result<BackupFile, AppError> {
     let plans = sqlx::query!(
         r#"
         SELECT
             id as "id: uuid::Uuid",
             name,
-            deleted_at as "deleted_at?"
+            deleted_at as "deleted_at?",
+            slug
         FROM action_plans
         ORDER BY name ASC
         "#
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await?;
 
+    let mut links_by_action: HashMap<String, Vec<BackupActionLink>> = HashMap::new();
+    let links = sqlx::query!(
+        r#"
+        SELECT
+            actions.name as "action_name!",
+            action_links.label,
+            action_links.url,
+            action_links.created_at
+        FROM action_links
+        INNER JOIN actions ON actions.id = action_links.action
+        ORDER BY action_links.created_at ASC, action_links.rowid ASC
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+    for link in links {
+        links_by_action
+            .entry(link.action_name)
+            .or_default()
+            .push(BackupActionLink {
+                label: link.label,
+                url: link.url,
+                created_at: link.created_at,
+            });
+    }
+
     let mut action_plans = Vec::with_capacity(plans.len());
     for plan in plans {
         let tags = sqlx::query!(
@@ -62,14 +186,26 @@ pub async fn export_json(
             "#,
             plan.id
         )
-        .fetch_all(&state.db)
+        .fetch_all(db)
         .await?;
 
         let items = sqlx::query!(
             r#"
             SELECT
                 action_items.order_index as "order_index!",
-                actions.name as "action_name!"
+                actions.name as "action_name!",
+                actions.description as "action_description",
+                action_items.instructions,
+                action_items.item_type,
+                action_items.unit,
+                action_items.min_value,
+                action_items.max_value,
+                action_items.condition_index,
+                action_items.condition_value,
+                action_items.condition_failed as "condition_failed: bool",
+                action_items.parent_index,
+                action_items.estimated_minutes,
+                action_items.sub_plan as "sub_plan: uuid::Uuid"
             FROM action_items
             INNER JOIN actions ON actions.id = action_items.action
             WHERE action_items.action_plan = $1
@@ -77,19 +213,99 @@ pub async fn export_json(
             "#,
             plan.id
         )
-        .fetch_all(&state.db)
+        .fetch_all(db)
+        .await?;
+
+        let headings = sqlx::query_as!(
+            BackupPlanHeading,
+            r#"
+            SELECT order_index, heading as "heading!"
+            FROM action_items
+            WHERE action_plan = $1 AND heading IS NOT NULL
+            ORDER BY order_index ASC
+            "#,
+            plan.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let previous_slugs = sqlx::query_scalar!(
+            r#"
+            SELECT slug
+            FROM action_plan_slugs
+            WHERE action_plan = $1 AND slug IS NOT $2
+            ORDER BY created_at ASC, slug ASC
+            "#,
+            plan.id,
+            plan.slug
+        )
+        .fetch_all(db)
+        .await?;
+
+        let renames = sqlx::query_as!(
+            BackupPlanRename,
+            r#"
+            SELECT old_name, new_name, renamed_at
+            FROM action_plan_renames
+            WHERE action_plan = $1
+            ORDER BY renamed_at ASC
+            "#,
+            plan.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let attachments = sqlx::query_as!(
+            BackupPlanAttachment,
+            r#"
+            SELECT
+                id as "id: uuid::Uuid",
+                file_name,
+                content_type,
+                size_bytes,
+                uploaded_at,
+                uploaded_by_name
+            FROM plan_attachments
+            WHERE action_plan = $1
+            ORDER BY uploaded_at ASC, rowid ASC
+            "#,
+            plan.id
+        )
+        .fetch_all(db)
         .await?;
 
         action_plans.push(BackupActionPlan {
             id: plan.id,
             name: plan.name,
             deleted_at: plan.deleted_at,
+            slug: plan.slug,
+            previous_slugs,
+            renames,
             tag_ids: tags.into_iter().map(|tag| tag.tag).collect(),
+            attachments,
+            headings,
             items: items
                 .into_iter()
                 .map(|item| BackupPlanItem {
                     order_index: item.order_index,
+                    action_links: links_by_action
+                        .get(&item.action_name)
+                        .cloned()
+                        .unwrap_or_default(),
                     action_name: item.action_name,
+                    action_description: item.action_description,
+                    instructions: item.instructions,
+                    item_type: ItemType::from_db(&item.item_type),
+                    unit: item.unit,
+                    min_value: item.min_value,
+                    max_value: item.max_value,
+                    condition_index: item.condition_index,
+                    condition_value: item.condition_value,
+                    condition_failed: item.condition_failed,
+                    section: None,
+                    parent_index: item.parent_index,
+                    estimated_minutes: item.estimated_minutes,
+                    sub_plan: item.sub_plan,
                 })
                 .collect(),
         });
@@ -102,12 +318,15 @@ pub async fn export_json(
             action_plan as "action_plan: uuid::Uuid",
             started as "started!",
             finished as "finished?",
-            note
+            note,
+            label,
+            run_number,
+            merged_from as "merged_from: uuid::Uuid"
         FROM action_plan_executions
         ORDER BY started DESC
         "#
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await?;
 
     let mut action_plan_executions = Vec::with_capacity(executions.len());
@@ -117,7 +336,32 @@ pub async fn export_json(
             SELECT
                 action_item_executions.order_index as "order_index!",
                 actions.name as "action_name!",
-                action_item_executions.finished as "finished?"
+                action_item_executions.finished as "finished?",
+                action_item_executions.estimated_minutes,
+                action_item_executions.actual_seconds,
+                action_item_executions.sub_plan as "sub_plan: uuid::Uuid",
+                action_item_executions.instructions,
+                action_item_executions.item_type,
+                action_item_executions.unit,
+                action_item_executions.value,
+                action_item_executions.min_value,
+                action_item_executions.max_value,
+                action_item_executions.out_of_spec as "out_of_spec!: bool",
+                action_item_executions.condition_index,
+                action_item_executions.condition_value,
+                action_item_executions.condition_failed as "condition_failed: bool",
+                action_item_executions.section,
+                action_item_executions.parent_index,
+                action_item_executions.linked_execution as "linked_execution: uuid::Uuid",
+                action_item_executions.note,
+                action_item_executions.blocked_reason,
+                action_item_executions.blocked_at,
+                action_item_executions.block_resolved_at,
+                action_item_executions.block_resolution,
+                action_item_executions.skip_reason,
+                action_item_executions.skipped_at,
+                action_item_executions.failure_note,
+                action_item_executions.failed_at
             FROM action_item_executions
             INNER JOIN actions ON actions.id = action_item_executions.action
             WHERE action_item_executions.action_plan_execution = $1
@@ -125,7 +369,122 @@ pub async fn export_json(
             "#,
             execution.id
         )
-        .fetch_all(&state.db)
+        .fetch_all(db)
+        .await?;
+
+        let mut attachments_by_item: HashMap<i64, Vec<BackupItemAttachment>> = HashMap::new();
+        let attachments = sqlx::query!(
+            r#"
+            SELECT
+                action_item_executions.order_index as "order_index!",
+                execution_item_attachments.id as "id: uuid::Uuid",
+                execution_item_attachments.file_name,
+                execution_item_attachments.content_type,
+                execution_item_attachments.size_bytes,
+                execution_item_attachments.original_size_bytes,
+                execution_item_attachments.has_thumbnail as "has_thumbnail: bool",
+                execution_item_attachments.uploaded_at,
+                execution_item_attachments.uploaded_by as "uploaded_by: uuid::Uuid",
+                execution_item_attachments.uploaded_by_name,
+                execution_item_attachments.quarantine_reason,
+                execution_item_attachments.caption
+            FROM execution_item_attachments
+            INNER JOIN action_item_executions
+                ON action_item_executions.id = execution_item_attachments.action_item_execution
+            WHERE execution_item_attachments.action_plan_execution = $1
+            ORDER BY execution_item_attachments.uploaded_at ASC, execution_item_attachments.rowid ASC
+            "#,
+            execution.id
+        )
+        .fetch_all(db)
+        .await?;
+        for attachment in attachments {
+            attachments_by_item
+                .entry(attachment.order_index)
+                .or_default()
+                .push(BackupItemAttachment {
+                    id: attachment.id,
+                    file_name: attachment.file_name,
+                    content_type: attachment.content_type,
+                    size_bytes: attachment.size_bytes,
+                    original_size_bytes: attachment.original_size_bytes,
+                    has_thumbnail: attachment.has_thumbnail,
+                    uploaded_at: attachment.uploaded_at,
+                    uploaded_by: attachment.uploaded_by,
+                    uploaded_by_name: attachment.uploaded_by_name,
+                    quarantine_reason: attachment.quarantine_reason,
+                    caption: attachment.caption,
+                });
+        }
+
+        let comments = sqlx::query_as!(
+            BackupComment,
+            r#"
+            SELECT
+                id as "id: uuid::Uuid",
+                parent as "parent: uuid::Uuid",
+                author as "author: uuid::Uuid",
+                author_name,
+                body,
+                created_at,
+                deleted_at
+            FROM execution_comments
+            WHERE action_plan_execution = $1
+            ORDER BY created_at ASC, rowid ASC
+            "#,
+            execution.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let transfers = sqlx::query_as!(
+            BackupTransfer,
+            r#"
+            SELECT
+                id as "id: uuid::Uuid",
+                transferred_at,
+                transferred_by as "transferred_by: uuid::Uuid",
+                transferred_by_name,
+                from_name,
+                to_user as "to_user: uuid::Uuid",
+                to_group as "to_group: uuid::Uuid",
+                to_name,
+                note
+            FROM execution_transfers
+            WHERE action_plan_execution = $1
+            ORDER BY transferred_at ASC, rowid ASC
+            "#,
+            execution.id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let tasks = sqlx::query_as!(
+            BackupTask,
+            r#"
+            SELECT
+                tasks.id as "id: uuid::Uuid",
+                action_item_executions.order_index as "item_order_index?",
+                tasks.title,
+                tasks.note,
+                tasks.action_plan_name,
+                tasks.run_number,
+                tasks.item_name,
+                tasks.created_at,
+                tasks.created_by as "created_by: uuid::Uuid",
+                tasks.assigned_to as "assigned_to: uuid::Uuid",
+                tasks.closed_at,
+                tasks.closed_by as "closed_by: uuid::Uuid",
+                tasks.resolution
+            FROM tasks
+            LEFT JOIN action_item_executions
+                ON action_item_executions.id = tasks.action_item_execution
+            WHERE tasks.action_plan_execution = $1
+            ORDER BY tasks.created_at ASC, tasks.rowid ASC
+            "#,
+            execution.id
+        )
+        .fetch_all(db)
         .await?;
 
         action_plan_executions.push(BackupExecution {
@@ -134,12 +493,46 @@ pub async fn export_json(
             started: execution.started,
             finished: execution.finished,
             note: execution.note,
+            label: execution.label,
+            run_number: execution.run_number,
+            merged_from: execution.merged_from,
+            comments,
+            transfers,
+            tasks,
             items: items
                 .into_iter()
                 .map(|item| BackupExecutionItem {
+                    attachments: attachments_by_item
+                        .remove(&item.order_index)
+                        .unwrap_or_default(),
                     order_index: item.order_index,
                     action_name: item.action_name,
                     finished: item.finished,
+                    estimated_minutes: item.estimated_minutes,
+                    actual_seconds: item.actual_seconds,
+                    sub_plan: item.sub_plan,
+                    instructions: item.instructions,
+                    item_type: ItemType::from_db(&item.item_type),
+                    unit: item.unit,
+                    value: item.value,
+                    min_value: item.min_value,
+                    max_value: item.max_value,
+                    out_of_spec: item.out_of_spec,
+                    condition_index: item.condition_index,
+                    condition_value: item.condition_value,
+                    condition_failed: item.condition_failed,
+                    section: item.section,
+                    parent_index: item.parent_index,
+                    linked_execution: item.linked_execution,
+                    note: item.note,
+                    blocked_reason: item.blocked_reason,
+                    blocked_at: item.blocked_at,
+                    block_resolved_at: item.block_resolved_at,
+                    block_resolution: item.block_resolution,
+                    skip_reason: item.skip_reason,
+                    skipped_at: item.skipped_at,
+                    failure_note: item.failure_note,
+                    failed_at: item.failed_at,
                 })
                 .collect(),
         });
@@ -154,10 +547,10 @@ pub async fn export_json(
         ORDER BY name COLLATE NOCASE ASC
         "#
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await?;
 
-    let backup = BackupFile {
+    Ok(BackupFile {
         version: 2,
         exported_at_unix: unix_now(),
         tags: tags
@@ -169,23 +562,144 @@ pub async fn export_json(
             .collect(),
         action_plans,
         action_plan_executions,
+    })
+}
+
+/// Replaces every free-text value with a neutral placeholder while keeping ids, ordering and
+/// timestamps, so the export can be loaded into test instances without revealing procedures.
+/// The same action name always maps to the same placeholder, so shared actions stay shared.
+fn anonymize(backup: &mut BackupFile) {
+    for (index, tag) in backup.tags.iter_mut().enumerate() {
+        tag.name = format!("Tag {}", index + 1);
+    }
+
+    let mut action_names: HashMap<String, String> = HashMap::new();
+    let mut pseudonymize_action = |name: &mut String| {
+        let next_number = action_names.len() + 1;
+        let pseudonym = action_names
+            .entry(std::mem::take(name))
+            .or_insert_with(|| format!("Action {}", next_number));
+        *name = pseudonym.clone();
     };
 
-    Ok((
-        [(
-            header::CONTENT_DISPOSITION,
-            HeaderValue::from_static("attachment; filename=\"maintenance-planner-backup.json\""),
-        )],
-        Json(backup),
-    ))
+    for (index, plan) in backup.action_plans.iter_mut().enumerate() {
+        plan.name = format!("Action Plan {}", index + 1);
+        // Slugs and earlier names are derived from the real names; new ones are assigned on import.
+        plan.slug = None;
+        plan.previous_slugs.clear();
+        plan.renames.clear();
+        // Their files stay behind on this instance anyway.
+        plan.attachments.clear();
+        for heading in &mut plan.headings {
+            heading.heading = "Section".to_string();
+        }
+        let text_items = text_item_indexes(
+            plan.items
+                .iter()
+                .map(|item| (item.order_index, item.item_type)),
+        );
+        for item in &mut plan.items {
+            anonymize_condition(&text_items, item.condition_index, &mut item.condition_value);
+            pseudonymize_action(&mut item.action_name);
+            item.action_description = None;
+            item.action_links.clear();
+            item.instructions = None;
+        }
+    }
+
+    for execution in &mut backup.action_plan_executions {
+        execution.note = None;
+        execution.label = None;
+        for comment in &mut execution.comments {
+            comment.author_name = "User".to_string();
+            comment.body = "Comment".to_string();
+        }
+        for transfer in &mut execution.transfers {
+            transfer.transferred_by_name = "User".to_string();
+            transfer.from_name = "User".to_string();
+            transfer.to_name = "User".to_string();
+            transfer.note.clear();
+        }
+        for task in &mut execution.tasks {
+            task.title = "Task".to_string();
+            task.note = "Failed".to_string();
+            task.action_plan_name = "Action Plan".to_string();
+            pseudonymize_action(&mut task.item_name);
+            if task.resolution.is_some() {
+                task.resolution = Some("Resolved".to_string());
+            }
+        }
+        let text_items = text_item_indexes(
+            execution
+                .items
+                .iter()
+                .map(|item| (item.order_index, item.item_type)),
+        );
+        for item in &mut execution.items {
+            anonymize_condition(&text_items, item.condition_index, &mut item.condition_value);
+            pseudonymize_action(&mut item.action_name);
+            item.note = None;
+            item.instructions = None;
+            // Like plan attachments, their files stay behind on this instance.
+            item.attachments.clear();
+            if item.section.is_some() {
+                item.section = Some("Section".to_string());
+            }
+            // Readings are kept, they are measurements rather than free text.
+            if item.item_type == ItemType::Text && item.value.is_some() {
+                item.value = Some("Value".to_string());
+            }
+            if item.blocked_reason.is_some() {
+                item.blocked_reason = Some("Blocked".to_string());
+            }
+            item.block_resolution = None;
+            if item.skip_reason.is_some() {
+                item.skip_reason = Some("Skipped".to_string());
+            }
+            if item.failure_note.is_some() {
+                item.failure_note = Some("Failed".to_string());
+            }
+        }
+    }
+}
+
+/// Order indexes of the text items among `items`.
+fn text_item_indexes(items: impl Iterator<Item = (i64, ItemType)>) -> HashSet<i64> {
+    items
+        .filter(|(_, item_type)| *item_type == ItemType::Text)
+        .map(|(order_index, _)| order_index)
+        .collect()
+}
+
+/// Replaces the answer a condition waits for when it is free text, the same way the answers
+/// themselves are replaced, so the condition still matches them.
+fn anonymize_condition(
+    text_items: &HashSet<i64>,
+    condition_index: Option<i64>,
+    condition_value: &mut Option<String>,
+) {
+    if condition_value.is_some() && condition_index.is_some_and(|index| text_items.contains(&index))
+    {
+        *condition_value = Some("Value".to_string());
+    }
 }
 
+#[utoipa::path(
+    post,
+    path = "/backup/import",
+    tag = "backup",
+    request_body(content_type = "multipart/form-data", description = "Backup JSON file in the `backup_file` field"),
+    responses(
+        (status = 200, description = "Backup page with the import result", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
 pub async fn import_json(
     State(state): State<AppState>,
     current_user: CurrentUser,
     mut multipart: Multipart,
 ) -> Result<Html<String>, AppError> {
-    require_admin(&current_user)?;
+    authorize(&current_user, Action::ManageBackups, Resource::Everything)?;
 
     let mut backup_bytes = None;
 
@@ -278,6 +792,18 @@ pub async fn import_json(
     }
 
     for plan in &backup.action_plans {
+        for sub_plan in plan.items.iter().filter_map(|item| item.sub_plan) {
+            if !plan_ids.contains(&sub_plan) {
+                return render_backup_page(
+                    &state,
+                    Some(BackupNotice::error(format!(
+                        "Action plan {} references unknown sub-procedure plan {}",
+                        plan.id, sub_plan
+                    ))),
+                    current_user.is_admin,
+                );
+            }
+        }
         for tag_id in &plan.tag_ids {
             if !tag_ids.contains(tag_id) {
                 return render_backup_page(
@@ -310,15 +836,38 @@ pub async fn import_json(
     sqlx::query!("DELETE FROM action_item_executions")
         .execute(&mut *tx)
         .await?;
+    sqlx::query!("DELETE FROM execution_transfers")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM execution_comments")
+        .execute(&mut *tx)
+        .await?;
+    // Follow-up tasks and attachments of archived runs stay, the archive is not part of a backup.
+    sqlx::query!(
+        "DELETE FROM tasks WHERE action_plan_execution IN (SELECT id FROM action_plan_executions)"
+    )
+    .execute(&mut *tx)
+    .await?;
+    let previous_item_attachments = attachments::delete_for_live_executions(&mut tx).await?;
     sqlx::query!("DELETE FROM action_plan_executions")
         .execute(&mut *tx)
         .await?;
+    let previous_plan_attachments =
+        sqlx::query_scalar!(r#"DELETE FROM plan_attachments RETURNING id as "id: uuid::Uuid""#)
+            .fetch_all(&mut *tx)
+            .await?;
     sqlx::query!("DELETE FROM action_plan_tags")
         .execute(&mut *tx)
         .await?;
     sqlx::query!("DELETE FROM action_items")
         .execute(&mut *tx)
         .await?;
+    sqlx::query!("DELETE FROM action_plan_slugs")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM action_plan_renames")
+        .execute(&mut *tx)
+        .await?;
     sqlx::query!("DELETE FROM action_plans")
         .execute(&mut *tx)
         .await?;
@@ -328,6 +877,8 @@ pub async fn import_json(
         .await?;
 
     let mut action_by_name: HashMap<String, Uuid> = HashMap::new();
+    // Every item repeats its action's links, so they are restored from the first one.
+    let mut linked_actions = std::collections::HashSet::new();
 
     for tag in &backup.tags {
         sqlx::query!(
@@ -349,6 +900,39 @@ pub async fn import_json(
         .execute(&mut *tx)
         .await?;
 
+        // A slug listed for two plans in a hand-edited file stays with the first one.
+        for slug in plan.previous_slugs.iter().chain(&plan.slug) {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO action_plan_slugs (slug, action_plan, created_at) VALUES ($1, $2, 0)",
+                slug,
+                plan.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        if let Some(slug) = &plan.slug {
+            sqlx::query!(
+                "UPDATE action_plans SET slug = $1 WHERE id = $2 AND EXISTS (SELECT 1 FROM action_plan_slugs WHERE slug = $1 AND action_plan = $2)",
+                slug,
+                plan.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        for rename in &plan.renames {
+            let rename_id = Uuid::new_v4();
+            sqlx::query!(
+                "INSERT INTO action_plan_renames (id, action_plan, old_name, new_name, renamed_at) VALUES ($1, $2, $3, $4, $5)",
+                rename_id,
+                plan.id,
+                rename.old_name,
+                rename.new_name,
+                rename.renamed_at
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
         for tag_id in &plan.tag_ids {
             sqlx::query!(
                 "INSERT INTO action_plan_tags (action_plan, tag) VALUES ($1, $2)",
@@ -359,74 +943,388 @@ pub async fn import_json(
             .await?;
         }
 
+        // Only the metadata is part of a backup, the files are expected in the storage.
+        for attachment in &plan.attachments {
+            sqlx::query!(
+                r#"
+                INSERT OR IGNORE INTO plan_attachments (
+                    id, action_plan, file_name, content_type, size_bytes, uploaded_at,
+                    uploaded_by_name
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+                attachment.id,
+                plan.id,
+                attachment.file_name,
+                attachment.content_type,
+                attachment.size_bytes,
+                attachment.uploaded_at,
+                attachment.uploaded_by_name
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Backups from before headings were rows of their own carry them with the item below.
+        // Their order indexes are spread out like the migration did, to make room for them.
+        let has_legacy_sections = plan.items.iter().any(|item| item.section.is_some());
+        let spread = |index: i64| {
+            if has_legacy_sections {
+                index * 2 + 1
+            } else {
+                index
+            }
+        };
+        let legacy_headings = plan.items.iter().filter_map(|item| {
+            Some(BackupPlanHeading {
+                order_index: item.order_index * 2,
+                heading: item.section.clone()?,
+            })
+        });
+        let headings = plan
+            .headings
+            .iter()
+            .map(|heading| BackupPlanHeading {
+                order_index: spread(heading.order_index),
+                heading: heading.heading.clone(),
+            })
+            .chain(legacy_headings);
+        for heading in headings {
+            let heading_id = Uuid::new_v4();
+            sqlx::query!(
+                "INSERT INTO action_items (id, order_index, action_plan, heading) VALUES ($1, $2, $3, $4)",
+                heading_id,
+                heading.order_index,
+                plan.id,
+                heading.heading
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
         for item in &plan.items {
             let action_id =
                 ensure_action_id(&mut tx, &mut action_by_name, item.action_name.as_str()).await?;
+            if let Some(description) = &item.action_description {
+                sqlx::query!(
+                    "UPDATE actions SET description = $1 WHERE id = $2",
+                    description,
+                    action_id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            if linked_actions.insert(action_id) {
+                for link in &item.action_links {
+                    let link_id = Uuid::new_v4();
+                    sqlx::query!(
+                        "INSERT INTO action_links (id, action, label, url, created_at) VALUES ($1, $2, $3, $4, $5)",
+                        link_id,
+                        action_id,
+                        link.label,
+                        link.url,
+                        link.created_at
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
 
             let item_id = Uuid::new_v4();
+            let item_type = item.item_type.as_str();
+            let order_index = spread(item.order_index);
+            let condition_index = item.condition_index.map(spread);
+            let parent_index = item.parent_index.map(spread);
             sqlx::query!(
-                "INSERT INTO action_items (id, order_index, action_plan, action) VALUES ($1, $2, $3, $4)",
+                "INSERT INTO action_items (id, order_index, action_plan, action, estimated_minutes, sub_plan, instructions, item_type, unit, min_value, max_value, condition_index, condition_value, condition_failed, parent_index) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)",
                 item_id,
-                item.order_index,
+                order_index,
                 plan.id,
-                action_id
+                action_id,
+                item.estimated_minutes,
+                item.sub_plan,
+                item.instructions,
+                item_type,
+                item.unit,
+                item.min_value,
+                item.max_value,
+                condition_index,
+                item.condition_value,
+                item.condition_failed,
+                parent_index
             )
             .execute(&mut *tx)
             .await?;
         }
     }
 
+    let run_numbers = assign_run_numbers(&backup.action_plan_executions);
+    let execution_ids = backup
+        .action_plan_executions
+        .iter()
+        .map(|execution| execution.id)
+        .collect::<std::collections::HashSet<_>>();
     for execution in &backup.action_plan_executions {
+        let run_number = run_numbers.get(&execution.id).copied();
         sqlx::query!(
-            "INSERT INTO action_plan_executions (id, action_plan, started, finished, note) VALUES ($1, $2, $3, $4, $5)",
+            "INSERT INTO action_plan_executions (id, action_plan, started, finished, note, label, run_number, merged_from) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
             execution.id,
             execution.action_plan,
             execution.started,
             execution.finished,
-            execution.note
+            execution.note,
+            execution.label,
+            run_number,
+            execution.merged_from
         )
         .execute(&mut *tx)
         .await?;
 
+        let mut item_ids: HashMap<i64, Uuid> = HashMap::with_capacity(execution.items.len());
         for item in &execution.items {
             let action_id =
                 ensure_action_id(&mut tx, &mut action_by_name, item.action_name.as_str()).await?;
 
             let item_id = Uuid::new_v4();
+            item_ids.insert(item.order_index, item_id);
+            let item_type = item.item_type.as_str();
+            // Links to runs deleted before the export are dropped.
+            let linked_execution = item
+                .linked_execution
+                .filter(|linked| execution_ids.contains(linked));
             sqlx::query!(
-                "INSERT INTO action_item_executions (id, action, order_index, action_plan_execution, finished) VALUES ($1, $2, $3, $4, $5)",
+                "INSERT INTO action_item_executions (id, action, order_index, action_plan_execution, finished, estimated_minutes, actual_seconds, sub_plan, linked_execution, note, blocked_reason, blocked_at, block_resolved_at, block_resolution, instructions, item_type, unit, value, min_value, max_value, out_of_spec, condition_index, condition_value, condition_failed, skip_reason, skipped_at, failure_note, failed_at, section, parent_index) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30)",
                 item_id,
                 action_id,
                 item.order_index,
                 execution.id,
-                item.finished
+                item.finished,
+                item.estimated_minutes,
+                item.actual_seconds,
+                item.sub_plan,
+                linked_execution,
+                item.note,
+                item.blocked_reason,
+                item.blocked_at,
+                item.block_resolved_at,
+                item.block_resolution,
+                item.instructions,
+                item_type,
+                item.unit,
+                item.value,
+                item.min_value,
+                item.max_value,
+                item.out_of_spec,
+                item.condition_index,
+                item.condition_value,
+                item.condition_failed,
+                item.skip_reason,
+                item.skipped_at,
+                item.failure_note,
+                item.failed_at,
+                item.section,
+                item.parent_index
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            // Only the metadata is part of a backup, the files are expected in the storage.
+            for attachment in &item.attachments {
+                sqlx::query!(
+                    r#"
+                    INSERT OR IGNORE INTO execution_item_attachments (
+                        id, action_item_execution, action_plan_execution, file_name, content_type,
+                        size_bytes, original_size_bytes, has_thumbnail, uploaded_at, uploaded_by,
+                        uploaded_by_name, quarantine_reason, caption
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                    "#,
+                    attachment.id,
+                    item_id,
+                    execution.id,
+                    attachment.file_name,
+                    attachment.content_type,
+                    attachment.size_bytes,
+                    attachment.original_size_bytes,
+                    attachment.has_thumbnail,
+                    attachment.uploaded_at,
+                    attachment.uploaded_by,
+                    attachment.uploaded_by_name,
+                    attachment.quarantine_reason,
+                    attachment.caption
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        for comment in &execution.comments {
+            sqlx::query!(
+                r#"
+                INSERT OR IGNORE INTO execution_comments (
+                    id, action_plan_execution, parent, author, author_name, body, created_at,
+                    deleted_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+                comment.id,
+                execution.id,
+                comment.parent,
+                comment.author,
+                comment.author_name,
+                comment.body,
+                comment.created_at,
+                comment.deleted_at
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Users and groups are not part of a backup. Names are kept with the rows, references
+        // to users or groups this instance does not know are dropped.
+        for transfer in &execution.transfers {
+            sqlx::query!(
+                r#"
+                INSERT OR IGNORE INTO execution_transfers (
+                    id, action_plan_execution, transferred_at, transferred_by, transferred_by_name,
+                    from_name, to_user, to_group, to_name, note
+                )
+                VALUES (
+                    $1, $2, $3, $4, $5, $6,
+                    (SELECT id FROM users WHERE id = $7),
+                    (SELECT id FROM user_groups WHERE id = $8),
+                    $9, $10
+                )
+                "#,
+                transfer.id,
+                execution.id,
+                transfer.transferred_at,
+                transfer.transferred_by,
+                transfer.transferred_by_name,
+                transfer.from_name,
+                transfer.to_user,
+                transfer.to_group,
+                transfer.to_name,
+                transfer.note
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for task in &execution.tasks {
+            let item_id = task
+                .item_order_index
+                .and_then(|order_index| item_ids.get(&order_index));
+            sqlx::query!(
+                r#"
+                INSERT OR IGNORE INTO tasks (
+                    id, title, note, action_plan_execution, action_item_execution,
+                    action_plan_name, run_number, item_name, created_at, created_by, assigned_to,
+                    closed_at, closed_by, resolution
+                )
+                VALUES (
+                    $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                    (SELECT id FROM users WHERE id = $11),
+                    $12, $13, $14
+                )
+                "#,
+                task.id,
+                task.title,
+                task.note,
+                execution.id,
+                item_id,
+                task.action_plan_name,
+                task.run_number,
+                task.item_name,
+                task.created_at,
+                task.created_by,
+                task.assigned_to,
+                task.closed_at,
+                task.closed_by,
+                task.resolution
             )
             .execute(&mut *tx)
             .await?;
         }
     }
 
+    let imported_item_attachments = backup
+        .action_plan_executions
+        .iter()
+        .flat_map(|execution| &execution.items)
+        .flat_map(|item| &item.attachments)
+        .map(|attachment| attachment.id)
+        .collect::<HashSet<_>>();
+    let mut attachment_ids = previous_item_attachments
+        .into_iter()
+        .filter(|id| !imported_item_attachments.contains(id))
+        .collect::<Vec<_>>();
+
+    let mut summary = format!(
+        "Backup imported. Restored {} action plan(s) and {} execution(s).",
+        backup.action_plans.len(),
+        backup.action_plan_executions.len()
+    );
+    if !attachment_ids.is_empty() {
+        summary.push_str(&format!(
+            " Removed {} item attachment(s) that are not in the backup.",
+            attachment_ids.len()
+        ));
+    }
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(AuditEntity::Backup, None, AuditAction::Import, &summary),
+    )
+    .await?;
+
     tx.commit().await?;
+    let imported_plan_attachments = backup
+        .action_plans
+        .iter()
+        .flat_map(|plan| &plan.attachments)
+        .map(|attachment| attachment.id)
+        .collect::<std::collections::HashSet<_>>();
+    attachment_ids.extend(
+        previous_plan_attachments
+            .into_iter()
+            .filter(|id| !imported_plan_attachments.contains(id)),
+    );
+    attachments::remove_files(state.storage.as_ref(), attachment_ids).await;
+    action_plan::assign_missing_slugs(&state.db).await?;
 
     render_backup_page(
         &state,
-        Some(BackupNotice::success(format!(
-            "Backup imported. Restored {} action plan(s) and {} execution(s).",
-            backup.action_plans.len(),
-            backup.action_plan_executions.len()
-        ))),
+        Some(BackupNotice::success(summary)),
         current_user.is_admin,
     )
 }
 
-fn require_admin(user: &CurrentUser) -> Result<(), AppError> {
-    if user.is_admin {
-        Ok(())
-    } else {
-        Err(AppError::forbidden(
-            "Only admin users can access backup and restore.",
-        ))
+/// Keeps the run numbers stored in the backup and numbers executions from older backups
+/// after them, per plan and in order of their start time.
+fn assign_run_numbers(executions: &[BackupExecution]) -> HashMap<Uuid, i64> {
+    let mut run_numbers = HashMap::new();
+    let mut next_by_plan: HashMap<Uuid, i64> = HashMap::new();
+    for execution in executions {
+        if let Some(run_number) = execution.run_number {
+            run_numbers.insert(execution.id, run_number);
+            let next = next_by_plan.entry(execution.action_plan).or_insert(1);
+            *next = (*next).max(run_number + 1);
+        }
     }
+
+    let mut unnumbered = executions
+        .iter()
+        .filter(|execution| execution.run_number.is_none())
+        .collect::<Vec<_>>();
+    unnumbered.sort_by_key(|execution| (execution.started, execution.id));
+    for execution in unnumbered {
+        let next = next_by_plan.entry(execution.action_plan).or_insert(1);
+        run_numbers.insert(execution.id, *next);
+        *next += 1;
+    }
+
+    run_numbers
 }
 
 async fn ensure_action_id(
@@ -451,14 +1349,7 @@ async fn ensure_action_id(
     Ok(action_id)
 }
 
-fn unix_now() -> i64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|duration| duration.as_secs() as i64)
-        .unwrap_or(0)
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BackupFile {
     version: i64,
     exported_at_unix: i64,
@@ -468,43 +1359,274 @@ pub struct BackupFile {
     action_plan_executions: Vec<BackupExecution>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BackupTag {
     id: Uuid,
     name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BackupActionPlan {
     id: Uuid,
     name: String,
     deleted_at: Option<i64>,
     #[serde(default)]
     tag_ids: Vec<Uuid>,
+    /// Missing in older backups; assigned from the name on import.
+    #[serde(default)]
+    slug: Option<String>,
+    /// Earlier slugs that still lead to this plan.
+    #[serde(default)]
+    previous_slugs: Vec<String>,
+    #[serde(default)]
+    renames: Vec<BackupPlanRename>,
+    /// Reference documents, without their content.
+    #[serde(default)]
+    attachments: Vec<BackupPlanAttachment>,
+    /// Section headings, ordered among the items by their order index.
+    #[serde(default)]
+    headings: Vec<BackupPlanHeading>,
     items: Vec<BackupPlanItem>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackupPlanHeading {
+    order_index: i64,
+    heading: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackupPlanAttachment {
+    id: Uuid,
+    file_name: String,
+    content_type: String,
+    size_bytes: i64,
+    uploaded_at: i64,
+    uploaded_by_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackupPlanRename {
+    old_name: String,
+    new_name: String,
+    renamed_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BackupPlanItem {
     order_index: i64,
     action_name: String,
+    /// Markdown description of the action, repeated on every item using it.
+    #[serde(default)]
+    action_description: Option<String>,
+    /// Reference links of the action, repeated like the description.
+    #[serde(default)]
+    action_links: Vec<BackupActionLink>,
+    #[serde(default)]
+    estimated_minutes: Option<i64>,
+    /// Plan this item calls for as a sub-procedure.
+    #[serde(default)]
+    sub_plan: Option<Uuid>,
+    /// Instructions for the item that only apply in this plan.
+    #[serde(default)]
+    instructions: Option<String>,
+    #[serde(default)]
+    item_type: ItemType,
+    /// Unit of a numeric item's value.
+    #[serde(default)]
+    unit: Option<String>,
+    /// Expected range of a numeric item's value.
+    #[serde(default)]
+    min_value: Option<f64>,
+    #[serde(default)]
+    max_value: Option<f64>,
+    /// Order index of the earlier item this one depends on.
+    #[serde(default)]
+    condition_index: Option<i64>,
+    /// Answer the earlier item needs for this one to apply.
+    #[serde(default)]
+    condition_value: Option<String>,
+    /// The earlier item needs to have been marked failed instead.
+    #[serde(default)]
+    condition_failed: bool,
+    /// Heading above this item, only in backups from before headings were stored in
+    /// `headings`.
+    #[serde(default, skip_serializing)]
+    section: Option<String>,
+    /// Order index of the item this one is nested under.
+    #[serde(default)]
+    parent_index: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BackupActionLink {
+    label: Option<String>,
+    url: String,
+    created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BackupExecution {
     id: Uuid,
     action_plan: Uuid,
     started: i64,
     finished: Option<i64>,
     note: Option<String>,
+    #[serde(default)]
+    label: Option<String>,
+    /// Missing in older backups; numbered by start time on import.
+    #[serde(default)]
+    run_number: Option<i64>,
+    /// The plan this execution belonged to before it was merged into `action_plan`.
+    #[serde(default)]
+    merged_from: Option<Uuid>,
+    #[serde(default)]
+    comments: Vec<BackupComment>,
+    /// Hand-overs of the run between users and groups.
+    #[serde(default)]
+    transfers: Vec<BackupTransfer>,
+    /// Follow-up tasks opened for failed items.
+    #[serde(default)]
+    tasks: Vec<BackupTask>,
     items: Vec<BackupExecutionItem>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BackupExecutionItem {
     order_index: i64,
     action_name: String,
     finished: Option<i64>,
+    #[serde(default)]
+    estimated_minutes: Option<i64>,
+    #[serde(default)]
+    actual_seconds: Option<i64>,
+    #[serde(default)]
+    sub_plan: Option<Uuid>,
+    #[serde(default)]
+    instructions: Option<String>,
+    #[serde(default)]
+    item_type: ItemType,
+    #[serde(default)]
+    unit: Option<String>,
+    /// What was entered when checking off a text or numeric item.
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default)]
+    min_value: Option<f64>,
+    #[serde(default)]
+    max_value: Option<f64>,
+    /// Whether the value was outside the expected range when it was entered.
+    #[serde(default)]
+    out_of_spec: bool,
+    #[serde(default)]
+    condition_index: Option<i64>,
+    #[serde(default)]
+    condition_value: Option<String>,
+    #[serde(default)]
+    condition_failed: bool,
+    #[serde(default)]
+    section: Option<String>,
+    #[serde(default)]
+    parent_index: Option<i64>,
+    /// The sub-procedure run the item was checked off with.
+    #[serde(default)]
+    linked_execution: Option<Uuid>,
+    #[serde(default)]
+    note: Option<String>,
+    /// Why the item could not be done, if it was marked as blocked.
+    #[serde(default)]
+    blocked_reason: Option<String>,
+    #[serde(default)]
+    blocked_at: Option<i64>,
+    #[serde(default)]
+    block_resolved_at: Option<i64>,
+    #[serde(default)]
+    block_resolution: Option<String>,
+    /// Why the item was left out of the run, if it was skipped.
+    #[serde(default)]
+    skip_reason: Option<String>,
+    #[serde(default)]
+    skipped_at: Option<i64>,
+    /// What was wrong with the item, if it failed. Its follow-up task is kept with the execution.
+    #[serde(default)]
+    failure_note: Option<String>,
+    #[serde(default)]
+    failed_at: Option<i64>,
+    /// Photos and files attached to the item, without their content. The files stay in the
+    /// storage under the attachment id and are linked to the item again on import.
+    #[serde(default)]
+    attachments: Vec<BackupItemAttachment>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackupItemAttachment {
+    id: Uuid,
+    file_name: String,
+    content_type: String,
+    size_bytes: i64,
+    /// Size of the photo as uploaded, if it was kept next to the resized one.
+    #[serde(default)]
+    original_size_bytes: Option<i64>,
+    #[serde(default)]
+    has_thumbnail: bool,
+    uploaded_at: i64,
+    uploaded_by: Uuid,
+    uploaded_by_name: String,
+    /// Why the file is held back, if it was quarantined.
+    #[serde(default)]
+    quarantine_reason: Option<String>,
+    #[serde(default)]
+    caption: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackupComment {
+    id: Uuid,
+    /// The comment this one replies to.
+    parent: Option<Uuid>,
+    author: Uuid,
+    author_name: String,
+    body: String,
+    created_at: i64,
+    deleted_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackupTransfer {
+    id: Uuid,
+    transferred_at: i64,
+    transferred_by: Uuid,
+    transferred_by_name: String,
+    from_name: String,
+    to_user: Option<Uuid>,
+    to_group: Option<Uuid>,
+    to_name: String,
+    note: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackupTask {
+    id: Uuid,
+    /// Order index of the failed item the task was opened for.
+    item_order_index: Option<i64>,
+    title: String,
+    note: String,
+    action_plan_name: String,
+    run_number: i64,
+    item_name: String,
+    created_at: i64,
+    created_by: Uuid,
+    assigned_to: Option<Uuid>,
+    closed_at: Option<i64>,
+    closed_by: Option<Uuid>,
+    resolution: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// Replace names and notes with placeholders, keeping structure and timestamps.
+    anonymize: Option<bool>,
 }
 
 #[derive(Debug, Serialize)]
@@ -0,0 +1,359 @@
+//! Marking items as blocked when they cannot be done in a run, e.g. because a part is missing.
+//! Unlike an unchecked item, a blocked one does not hold up completing the execution; the
+//! reason is kept as an exception record, and the item stays listed on the executions page
+//! until someone resolves it.
+
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, authorize},
+    events,
+    executions::{self, ItemGrouping},
+    format_unix_timestamp, unix_now,
+};
+
+/// Why an item was blocked and, once resolved, how.
+#[derive(Debug, Serialize)]
+pub struct ItemBlock {
+    reason: String,
+    blocked_display: String,
+    blocked_by_name: Option<String>,
+    resolved_display: Option<String>,
+    resolved_by_name: Option<String>,
+    resolution: Option<String>,
+}
+
+impl ItemBlock {
+    pub fn new(
+        reason: Option<String>,
+        blocked_at: Option<i64>,
+        blocked_by_name: Option<String>,
+        resolved_at: Option<i64>,
+        resolved_by_name: Option<String>,
+        resolution: Option<String>,
+    ) -> Option<Self> {
+        Some(ItemBlock {
+            reason: reason?,
+            blocked_display: blocked_at.map(format_unix_timestamp).unwrap_or_default(),
+            blocked_by_name,
+            resolved_display: resolved_at.map(format_unix_timestamp),
+            resolved_by_name,
+            resolution,
+        })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.resolved_display.is_none()
+    }
+}
+
+/// A blocked item that has not been resolved yet, for the executions page.
+#[derive(Debug, Serialize)]
+pub struct OpenBlock {
+    item_id: Uuid,
+    item_name: String,
+    reason: String,
+    blocked_display: String,
+    blocked_by_name: Option<String>,
+    execution_id: Uuid,
+    action_plan_name: String,
+    run_number: i64,
+    label: Option<String>,
+    is_execution_completed: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BlockItemForm {
+    reason: String,
+    /// How the execution page was grouped, to return to the same view.
+    #[serde(default)]
+    group: ItemGrouping,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResolveBlockForm {
+    /// What was done about it. Required once the execution is completed.
+    #[serde(default)]
+    resolution: String,
+    #[serde(default)]
+    group: ItemGrouping,
+}
+
+/// Unresolved blocks of all executions, oldest first.
+pub async fn fetch_open(db: &SqlitePool) -> Result<Vec<OpenBlock>, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.id as "item_id!: uuid::Uuid",
+            actions.name as "item_name!",
+            action_item_executions.blocked_reason as "reason!",
+            action_item_executions.blocked_at as "blocked_at?",
+            blockers.name as "blocked_by_name?",
+            action_plan_executions.id as "execution_id!: uuid::Uuid",
+            action_plans.name as "action_plan_name!",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label,
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        LEFT JOIN users AS blockers ON blockers.id = action_item_executions.blocked_by
+        WHERE action_item_executions.blocked_reason IS NOT NULL
+            AND action_item_executions.block_resolved_at IS NULL
+        ORDER BY action_item_executions.blocked_at ASC
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| OpenBlock {
+            item_id: row.item_id,
+            item_name: row.item_name,
+            reason: row.reason,
+            blocked_display: row
+                .blocked_at
+                .map(format_unix_timestamp)
+                .unwrap_or_default(),
+            blocked_by_name: row.blocked_by_name,
+            execution_id: row.execution_id,
+            action_plan_name: row.action_plan_name,
+            run_number: row.run_number,
+            label: row.label,
+            is_execution_completed: row.execution_finished.is_some_and(|value| value > 0),
+        })
+        .collect())
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/block",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body(content = BlockItemForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Marks the item as blocked and redirects to its execution"),
+        (status = 404, description = "Execution item not found", content_type = "text/html"),
+        (status = 409, description = "No reason given, the item is checked off or the execution is completed", content_type = "text/html"),
+    )
+)]
+pub async fn block_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<BlockItemForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let reason = form.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(AppError::conflict(
+            "Say why the item is blocked, so it can be followed up.",
+        ));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id: uuid::Uuid",
+            action_item_executions.finished as "finished?",
+            action_item_executions.blocked_reason,
+            action_item_executions.block_resolved_at as "block_resolved_at?",
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        WHERE action_item_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Execution",
+            format!("No execution item exists for id: {}", id),
+        )
+    })?;
+    if item.execution_finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "Items of a completed execution cannot be blocked.",
+        ));
+    }
+    if item.finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(format!(
+            "\"{}\" is already checked off, uncheck it before marking it as blocked.",
+            item.item_name
+        )));
+    }
+
+    let previous_reason = item
+        .blocked_reason
+        .filter(|_| item.block_resolved_at.is_none());
+    let blocked_at = unix_now();
+    sqlx::query!(
+        r#"
+        UPDATE action_item_executions
+        SET
+            blocked_reason = $1,
+            blocked_at = $2,
+            blocked_by = $3,
+            block_resolved_at = NULL,
+            block_resolved_by = NULL,
+            block_resolution = NULL
+        WHERE id = $4
+        "#,
+        reason,
+        blocked_at,
+        current_user.id,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let description = executions::execution_description(&mut tx, item.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            id,
+            AuditAction::Update,
+            format!(
+                "Marked \"{}\" in {} as blocked",
+                item.item_name, description
+            ),
+        )
+        .change(previous_reason, Some(reason)),
+    )
+    .await?;
+    let parent_event = executions::update_parent_item(&mut tx, &current_user, id).await?;
+    tx.commit().await?;
+    if let Some(event) = parent_event {
+        events::publish(&state.execution_events, event);
+    }
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}{}",
+        item.execution_id,
+        form.group.query()
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/resolve-block",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body(content = ResolveBlockForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Resolves the block and redirects to the item's execution"),
+        (status = 404, description = "Execution item not found", content_type = "text/html"),
+        (status = 409, description = "The item is not blocked, or the execution is completed and no resolution was given", content_type = "text/html"),
+    )
+)]
+pub async fn resolve_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<ResolveBlockForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let resolution = Some(form.resolution.trim().to_string()).filter(|value| !value.is_empty());
+
+    let mut tx = state.db.begin().await?;
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id: uuid::Uuid",
+            action_item_executions.blocked_reason,
+            action_item_executions.block_resolved_at as "block_resolved_at?",
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        WHERE action_item_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Execution",
+            format!("No execution item exists for id: {}", id),
+        )
+    })?;
+    if item.blocked_reason.is_none() || item.block_resolved_at.is_some() {
+        return Err(AppError::conflict(format!(
+            "\"{}\" is not blocked.",
+            item.item_name
+        )));
+    }
+    let is_completed = item.execution_finished.is_some_and(|finished| finished > 0);
+    if is_completed && resolution.is_none() {
+        return Err(AppError::conflict(
+            "Say how the block was resolved, as the execution was completed without this item.",
+        ));
+    }
+
+    let resolved_at = unix_now();
+    sqlx::query!(
+        r#"
+        UPDATE action_item_executions
+        SET block_resolved_at = $1, block_resolved_by = $2, block_resolution = $3
+        WHERE id = $4
+        "#,
+        resolved_at,
+        current_user.id,
+        resolution,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let description = executions::execution_description(&mut tx, item.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            id,
+            AuditAction::Update,
+            format!(
+                "Resolved the block of \"{}\" in {}",
+                item.item_name, description
+            ),
+        )
+        .change(None, resolution),
+    )
+    .await?;
+    let parent_event = executions::update_parent_item(&mut tx, &current_user, id).await?;
+    tx.commit().await?;
+    if let Some(event) = parent_event {
+        events::publish(&state.execution_events, event);
+    }
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}{}",
+        item.execution_id,
+        form.group.query()
+    )))
+}
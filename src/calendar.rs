@@ -0,0 +1,250 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, HeaderValue, header},
+    response::{Html, IntoResponse, Redirect},
+};
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser, absolute_url, templates,
+    tokens::{self, TokenScope},
+    unix_now,
+};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CalendarFeedQuery {
+    /// Signed personal feed token from the link on the calendar page.
+    token: String,
+}
+
+#[derive(Serialize)]
+struct CalendarPageView {
+    feed_url: Option<String>,
+    is_admin: bool,
+}
+
+struct OpenExecutionRow {
+    id: Uuid,
+    action_plan_name: String,
+    started: i64,
+    note: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/calendar",
+    tag = "calendar",
+    responses(
+        (status = 200, description = "Calendar subscription page", content_type = "text/html"),
+    )
+)]
+pub async fn index(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    headers: HeaderMap,
+) -> Result<Html<String>, AppError> {
+    let token = sqlx::query_scalar!(
+        r#"SELECT calendar_token as "calendar_token: uuid::Uuid" FROM users WHERE id = $1"#,
+        current_user.id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .flatten();
+
+    let feed_url = token.map(|token_id| {
+        let token =
+            state
+                .token_signer
+                .sign(TokenScope::CalendarFeed, token_id, current_user.id, None);
+        absolute_url(
+            &state.config,
+            &headers,
+            &format!("/calendar.ics?token={}", token),
+        )
+    });
+
+    let rendered = templates::render(
+        &state.jinja,
+        "calendar.html",
+        CalendarPageView {
+            feed_url,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/calendar/token",
+    tag = "calendar",
+    responses(
+        (status = 303, description = "Personal feed token replaced, redirects to the calendar page"),
+    )
+)]
+pub async fn regenerate_token_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Redirect, AppError> {
+    let previous_token = sqlx::query_scalar!(
+        r#"SELECT calendar_token as "calendar_token: uuid::Uuid" FROM users WHERE id = $1"#,
+        current_user.id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .flatten();
+    if let Some(previous_token) = previous_token {
+        tokens::revoke(&state.db, previous_token).await?;
+    }
+
+    let token = Uuid::new_v4();
+    sqlx::query!(
+        "UPDATE users SET calendar_token = $1 WHERE id = $2",
+        token,
+        current_user.id
+    )
+    .execute(&state.db)
+    .await?;
+
+    Ok(Redirect::to("/calendar"))
+}
+
+/// Read-only iCalendar feed of open executions. There are no plan due dates yet, so every
+/// unfinished execution is published as a to-do starting when the execution was started.
+#[utoipa::path(
+    get,
+    path = "/calendar.ics",
+    tag = "calendar",
+    params(CalendarFeedQuery),
+    responses(
+        (status = 200, description = "iCalendar feed of open executions", content_type = "text/calendar"),
+        (status = 401, description = "Unknown calendar token"),
+        (status = 429, description = "The token made too many requests and is locked for a while"),
+    )
+)]
+pub async fn feed(
+    State(state): State<AppState>,
+    Query(query): Query<CalendarFeedQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let verified = state
+        .token_signer
+        .verify(&state.db, &query.token, TokenScope::CalendarFeed)
+        .await?;
+    if let Some(verified) = verified
+        && !tokens::record_request(&state.db, &state.config, verified.id).await?
+    {
+        return Err(AppError::too_many_requests(
+            "This calendar link made too many requests. Try again later.",
+        ));
+    }
+    let user_exists = match verified {
+        Some(verified) => sqlx::query_scalar!(
+            r#"SELECT 1 as "exists!: i64" FROM users WHERE id = $1 AND calendar_token = $2"#,
+            verified.subject,
+            verified.id
+        )
+        .fetch_optional(&state.db)
+        .await?
+        .is_some(),
+        None => false,
+    };
+    if !user_exists {
+        return Err(AppError::unauthorized("Unknown calendar token."));
+    }
+
+    let executions = sqlx::query_as!(
+        OpenExecutionRow,
+        r#"
+        SELECT
+            action_plan_executions.id as "id!: uuid::Uuid",
+            action_plans.name as action_plan_name,
+            action_plan_executions.started as "started!",
+            action_plan_executions.note
+        FROM action_plan_executions
+        JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_plan_executions.finished IS NULL OR action_plan_executions.finished <= 0
+        ORDER BY action_plan_executions.started ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let now = format_ical_timestamp(unix_now());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Maintenance Planner//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:Maintenance Planner".to_string(),
+    ];
+    for execution in executions {
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}@maintenance-planner", execution.id));
+        lines.push(format!("DTSTAMP:{}", now));
+        lines.push(format!(
+            "DTSTART:{}",
+            format_ical_timestamp(execution.started)
+        ));
+        lines.push(format!(
+            "SUMMARY:{}",
+            escape_ical_text(&execution.action_plan_name)
+        ));
+        if let Some(note) = execution.note.filter(|note| !note.trim().is_empty()) {
+            lines.push(format!("DESCRIPTION:{}", escape_ical_text(&note)));
+        }
+        lines.push("STATUS:NEEDS-ACTION".to_string());
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let body = lines
+        .iter()
+        .map(|line| fold_ical_line(line))
+        .collect::<String>();
+
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/calendar; charset=utf-8"),
+        )],
+        body,
+    ))
+}
+
+fn format_ical_timestamp(timestamp: i64) -> String {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+fn escape_ical_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line into CRLF-terminated chunks of at most 75 octets, as RFC 5545 requires.
+fn fold_ical_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_length = 0;
+    for character in line.chars() {
+        if line_length + character.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+        folded.push(character);
+        line_length += character.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
@@ -0,0 +1,216 @@
+//! Export of the action vocabulary with how much each action is used, for tidying it up:
+//! merging near-duplicates and finding actions no plan needs any more. Counts cover the main
+//! database only, so runs moved to the archive are not included.
+
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderValue, header},
+    response::IntoResponse,
+};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    authz::{Action, Resource, authorize},
+    format_unix_timestamp, unix_now,
+};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ActionCatalog {
+    exported_at_unix: i64,
+    actions: Vec<CatalogAction>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CatalogAction {
+    id: Uuid,
+    name: String,
+    /// How often the action was part of an execution.
+    execution_count: i64,
+    /// When the newest of those executions was started.
+    last_used_unix: Option<i64>,
+    plans: Vec<CatalogPlan>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CatalogPlan {
+    id: Uuid,
+    name: String,
+    /// Deleted plans can be restored, so they still count as using the action.
+    is_deleted: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/actions.json",
+    tag = "admin",
+    responses(
+        (status = 200, description = "All actions with usage counts, last use and the plans using them", body = ActionCatalog),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
+pub async fn export_json(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<impl IntoResponse, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+
+    let catalog = ActionCatalog {
+        exported_at_unix: unix_now(),
+        actions: fetch_actions(&state.db).await?,
+    };
+
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static("attachment; filename=\"action-catalog.json\""),
+        )],
+        Json(catalog),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/actions.csv",
+    tag = "admin",
+    responses(
+        (status = 200, description = "All actions with usage counts, last use and the plans using them, one row per action", content_type = "text/csv"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
+pub async fn export_csv(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<impl IntoResponse, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+
+    let mut csv = String::from("id,name,execution_count,last_used,plan_count,plans\r\n");
+    for action in fetch_actions(&state.db).await? {
+        let plans = action
+            .plans
+            .iter()
+            .map(|plan| {
+                if plan.is_deleted {
+                    format!("{} (deleted)", plan.name)
+                } else {
+                    plan.name.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        let fields = [
+            action.id.to_string(),
+            action.name,
+            action.execution_count.to_string(),
+            action
+                .last_used_unix
+                .map(format_unix_timestamp)
+                .unwrap_or_default(),
+            action.plans.len().to_string(),
+            plans,
+        ];
+        let row = fields
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push_str(&row);
+        csv.push_str("\r\n");
+    }
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/csv; charset=utf-8"),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                HeaderValue::from_static("attachment; filename=\"action-catalog.csv\""),
+            ),
+        ],
+        csv,
+    ))
+}
+
+/// Every action alphabetically, including ones no plan uses any more.
+async fn fetch_actions(db: &SqlitePool) -> Result<Vec<CatalogAction>, AppError> {
+    let plan_rows = sqlx::query!(
+        r#"
+        SELECT DISTINCT
+            action_items.action as "action_id!: uuid::Uuid",
+            action_plans.id as "plan_id: uuid::Uuid",
+            action_plans.name as plan_name,
+            action_plans.deleted_at as "deleted_at?"
+        FROM action_items
+        INNER JOIN action_plans ON action_plans.id = action_items.action_plan
+        WHERE action_items.action IS NOT NULL
+        ORDER BY action_plans.name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+    let mut plans_by_action: HashMap<Uuid, Vec<CatalogPlan>> = HashMap::new();
+    for row in plan_rows {
+        plans_by_action
+            .entry(row.action_id)
+            .or_default()
+            .push(CatalogPlan {
+                id: row.plan_id,
+                name: row.plan_name,
+                is_deleted: row.deleted_at.is_some_and(|value| value > 0),
+            });
+    }
+
+    let actions = sqlx::query!(
+        r#"
+        SELECT
+            actions.id as "id!: uuid::Uuid",
+            actions.name as "name!",
+            COUNT(action_item_executions.id) as "execution_count!: i64",
+            MAX(action_plan_executions.started) as "last_used?: i64"
+        FROM actions
+        LEFT JOIN action_item_executions ON action_item_executions.action = actions.id
+        LEFT JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        GROUP BY actions.id
+        ORDER BY actions.name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| CatalogAction {
+        plans: plans_by_action.remove(&row.id).unwrap_or_default(),
+        id: row.id,
+        name: row.name,
+        execution_count: row.execution_count,
+        last_used_unix: row.last_used,
+    })
+    .collect();
+
+    Ok(actions)
+}
+
+/// Quotes a field when it contains a separator, quote or line break, as RFC 4180 describes.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
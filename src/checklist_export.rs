@@ -0,0 +1,202 @@
+//! The state of an execution as text to paste into tickets: plain text, Jira wiki markup or an
+//! HTML table. Only formats what it is given, so anything else that needs a checklist as text
+//! can build a `Checklist` and reuse it.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Text,
+    Jira,
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Text, ExportFormat::Jira, ExportFormat::Html];
+
+    /// The value used in URLs.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "text",
+            ExportFormat::Jira => "jira",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Plain Text",
+            ExportFormat::Jira => "Jira",
+            ExportFormat::Html => "HTML Table",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Text | ExportFormat::Jira => "text/plain; charset=utf-8",
+            ExportFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+pub struct Checklist {
+    pub title: String,
+    /// Label and value pairs shown above the items, e.g. the start time.
+    pub details: Vec<(&'static str, String)>,
+    pub items: Vec<ChecklistItem>,
+}
+
+pub struct ChecklistItem {
+    pub name: String,
+    /// When the item was checked off, `None` while it is open.
+    pub finished_display: Option<String>,
+    /// Extra remarks, e.g. who scanned it.
+    pub remarks: Vec<String>,
+}
+
+impl Checklist {
+    fn progress(&self) -> String {
+        let done = self
+            .items
+            .iter()
+            .filter(|item| item.finished_display.is_some())
+            .count();
+        format!("{} of {} done", done, self.items.len())
+    }
+}
+
+pub fn format(checklist: &Checklist, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Text => format_text(checklist),
+        ExportFormat::Jira => format_jira(checklist),
+        ExportFormat::Html => format_html(checklist),
+    }
+}
+
+fn format_text(checklist: &Checklist) -> String {
+    let mut text = format!("{}\n", checklist.title);
+    for (label, value) in &checklist.details {
+        text.push_str(&format!("{}: {}\n", label, value));
+    }
+    text.push_str(&format!("Progress: {}\n\n", checklist.progress()));
+    for item in &checklist.items {
+        let mark = if item.finished_display.is_some() {
+            "[x]"
+        } else {
+            "[ ]"
+        };
+        text.push_str(&format!("{} {}", mark, item.name));
+        let notes = item_notes(item);
+        if !notes.is_empty() {
+            text.push_str(&format!(" ({})", notes.join(", ")));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn format_jira(checklist: &Checklist) -> String {
+    let mut text = format!("h3. {}\n", escape_jira(&checklist.title));
+    for (label, value) in &checklist.details {
+        text.push_str(&format!("*{}:* {}\n", label, escape_jira(value)));
+    }
+    text.push_str(&format!("*Progress:* {}\n\n", checklist.progress()));
+    text.push_str("||Task||Done||Notes||\n");
+    for item in &checklist.items {
+        let mark = if item.finished_display.is_some() {
+            "(/)"
+        } else {
+            "(x)"
+        };
+        let notes = item_notes(item)
+            .iter()
+            .map(|note| escape_jira(note))
+            .collect::<Vec<_>>()
+            .join(", ");
+        // Jira needs some content in every cell to keep the columns.
+        text.push_str(&format!(
+            "|{}|{}|{}|\n",
+            escape_jira(&item.name),
+            mark,
+            if notes.is_empty() { " " } else { &notes }
+        ));
+    }
+    text
+}
+
+fn format_html(checklist: &Checklist) -> String {
+    let mut html = format!("<h3>{}</h3>\n<p>\n", escape_html(&checklist.title));
+    for (label, value) in &checklist.details {
+        html.push_str(&format!(
+            "<strong>{}:</strong> {}<br>\n",
+            label,
+            escape_html(value)
+        ));
+    }
+    html.push_str(&format!(
+        "<strong>Progress:</strong> {}\n</p>\n",
+        checklist.progress()
+    ));
+    html.push_str(
+        "<table>\n<thead><tr><th>Task</th><th>Done</th><th>Notes</th></tr></thead>\n<tbody>\n",
+    );
+    for item in &checklist.items {
+        let mark = if item.finished_display.is_some() {
+            "&#x2611;"
+        } else {
+            "&#x2610;"
+        };
+        let notes = item_notes(item)
+            .iter()
+            .map(|note| escape_html(note))
+            .collect::<Vec<_>>()
+            .join(", ");
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&item.name),
+            mark,
+            notes
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+fn item_notes(item: &ChecklistItem) -> Vec<String> {
+    item.finished_display
+        .iter()
+        .map(|finished| format!("done {}", finished))
+        .chain(item.remarks.iter().cloned())
+        .collect()
+}
+
+/// Escapes characters Jira would read as markup, so names show up as typed.
+fn escape_jira(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        if matches!(
+            character,
+            '|' | '[' | ']' | '{' | '}' | '*' | '_' | '+' | '^' | '~' | '?' | '!' | '#'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped.replace('\n', " ")
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
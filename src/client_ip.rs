@@ -0,0 +1,310 @@
+//! The address a request comes from. Behind a reverse proxy the connection comes from the
+//! proxy, so when its address is in `trusted_proxies` the client is read from the `Forwarded`
+//! or `X-Forwarded-For` header instead. Entries are read from the right and the first one that
+//! is not a trusted proxy wins, so clients cannot pick their address by sending the header.
+
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{Extensions, HeaderMap, request::Parts},
+};
+use serde::Deserialize;
+
+use crate::{AppError, AppState, config::Config};
+
+/// The client address of a request, for rate limiting and session records.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        resolve(&state.config, &parts.extensions, &parts.headers)
+            .map(ClientIp)
+            .ok_or_else(|| AppError::internal(anyhow::anyhow!("Connection info missing")))
+    }
+}
+
+/// The client address of a request with these extensions and headers, `None` when the server
+/// did not record the connection.
+pub fn resolve(config: &Config, extensions: &Extensions, headers: &HeaderMap) -> Option<IpAddr> {
+    let ConnectInfo(peer) = extensions.get::<ConnectInfo<SocketAddr>>()?;
+    let mut client = peer.ip();
+    if !config.trusted_proxies.contains(client) {
+        return Some(client);
+    }
+    for hop in forwarded_hops(headers).into_iter().rev() {
+        // Anything unreadable ends the chain at the last proxy that is known to be honest.
+        let Some(hop) = hop else {
+            break;
+        };
+        client = hop;
+        if !config.trusted_proxies.contains(client) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+/// Addresses from `Forwarded`, or from `X-Forwarded-For` when that is missing, oldest first.
+/// `None` stands for an entry that is not an address, such as `unknown`.
+fn forwarded_hops(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let forwarded = header_values(headers, "forwarded");
+    if !forwarded.is_empty() {
+        return forwarded
+            .iter()
+            .flat_map(|value| value.split(','))
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, value)| parse_node(value.trim().trim_matches('"')))
+            })
+            .collect();
+    }
+    header_values(headers, "x-forwarded-for")
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(|entry| parse_node(entry.trim()))
+        .collect()
+}
+
+fn header_values<'a>(headers: &'a HeaderMap, name: &str) -> Vec<&'a str> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect()
+}
+
+/// Reads `192.0.2.1`, `192.0.2.1:8080`, `2001:db8::1` and `[2001:db8::1]:8080`.
+fn parse_node(value: &str) -> Option<IpAddr> {
+    if let Ok(address) = value.parse::<IpAddr>() {
+        return Some(address);
+    }
+    if let Ok(address) = value.parse::<SocketAddr>() {
+        return Some(address.ip());
+    }
+    value
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .and_then(|address| address.parse().ok())
+}
+
+/// A network in CIDR notation, e.g. `10.0.0.0/8`. A plain address is a network of one.
+#[derive(Debug, Clone, Copy)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    fn contains(&self, address: IpAddr) -> bool {
+        let address = match address {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(address),
+            IpAddr::V4(_) => address,
+        };
+        match (self.network, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = InvalidIpRange;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidIpRange(value.to_string());
+        let (address, prefix_len) = match value.trim().split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (value.trim(), None),
+        };
+        let network = address.parse::<IpAddr>().map_err(|_| invalid())?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse::<u8>().map_err(|_| invalid())?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(invalid());
+        }
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+}
+
+/// The `trusted_proxies` setting: a list in `config.toml`, comma separated in the environment.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct IpRanges(Vec<IpRange>);
+
+impl IpRanges {
+    pub fn contains(&self, address: IpAddr) -> bool {
+        self.0.iter().any(|range| range.contains(address))
+    }
+}
+
+impl TryFrom<Vec<String>> for IpRanges {
+    type Error = InvalidIpRange;
+
+    fn try_from(values: Vec<String>) -> Result<Self, Self::Error> {
+        values
+            .iter()
+            .map(|value| value.parse())
+            .collect::<Result<_, _>>()
+            .map(IpRanges)
+    }
+}
+
+impl FromStr for IpRanges {
+    type Err = InvalidIpRange;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(IpRanges)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidIpRange(String);
+
+impl fmt::Display for InvalidIpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected an address or a network like 10.0.0.0/8, got {:?}",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidIpRange {}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn client(peer: &str, trusted: &str, headers: &[(&'static str, &'static str)]) -> IpAddr {
+        let config = Config {
+            trusted_proxies: trusted.parse().unwrap(),
+            ..Config::default()
+        };
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 443)));
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            header_map.append(*name, HeaderValue::from_static(value));
+        }
+        resolve(&config, &extensions, &header_map).unwrap()
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn headers_from_untrusted_peers_are_ignored() {
+        let headers = [("x-forwarded-for", "198.51.100.7")];
+        assert_eq!(client("203.0.113.1", "", &headers), ip("203.0.113.1"));
+        assert_eq!(
+            client("203.0.113.1", "10.0.0.0/8", &headers),
+            ip("203.0.113.1")
+        );
+    }
+
+    #[test]
+    fn reads_x_forwarded_for_from_the_right() {
+        // The client made up the first entry, the trusted proxy appended the real one.
+        let headers = [("x-forwarded-for", "192.0.2.99, 198.51.100.7")];
+        assert_eq!(
+            client("10.0.0.2", "10.0.0.0/8", &headers),
+            ip("198.51.100.7")
+        );
+    }
+
+    #[test]
+    fn skips_every_trusted_proxy_in_the_chain() {
+        let headers = [
+            ("x-forwarded-for", "198.51.100.7, 10.1.0.1"),
+            ("x-forwarded-for", "10.2.0.1"),
+        ];
+        assert_eq!(
+            client("10.0.0.2", "10.0.0.0/8", &headers),
+            ip("198.51.100.7")
+        );
+    }
+
+    #[test]
+    fn prefers_forwarded_and_reads_its_node_forms() {
+        let headers = [
+            ("x-forwarded-for", "192.0.2.99"),
+            (
+                "forwarded",
+                "for=192.0.2.1;proto=https, For=\"[2001:db8::1]:8080\"",
+            ),
+        ];
+        assert_eq!(client("10.0.0.2", "10.0.0.2", &headers), ip("2001:db8::1"));
+        let headers = [("forwarded", "for=198.51.100.7:4711;by=10.0.0.2")];
+        assert_eq!(client("10.0.0.2", "10.0.0.2", &headers), ip("198.51.100.7"));
+    }
+
+    #[test]
+    fn unreadable_entries_end_the_chain() {
+        let headers = [("x-forwarded-for", "198.51.100.7, unknown, 10.1.0.1")];
+        assert_eq!(client("10.0.0.2", "10.0.0.0/8", &headers), ip("10.1.0.1"));
+    }
+
+    #[test]
+    fn a_chain_of_trusted_proxies_only_ends_at_the_first() {
+        let headers = [("x-forwarded-for", "10.1.0.1")];
+        assert_eq!(client("10.0.0.2", "10.0.0.0/8", &headers), ip("10.1.0.1"));
+    }
+
+    #[test]
+    fn ranges_match_mapped_addresses_and_reject_bad_prefixes() {
+        let ranges: IpRanges = "10.0.0.0/8, 2001:db8::/32".parse().unwrap();
+        assert!(ranges.contains(ip("10.200.1.1")));
+        assert!(ranges.contains(ip("::ffff:10.0.0.1")));
+        assert!(ranges.contains(ip("2001:db8:ffff::1")));
+        assert!(!ranges.contains(ip("11.0.0.1")));
+        assert!(
+            "0.0.0.0/0"
+                .parse::<IpRange>()
+                .unwrap()
+                .contains(ip("8.8.8.8"))
+        );
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("proxy.local".parse::<IpRange>().is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::extract::ws::{Message, WebSocket};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::{
+    AppState, CurrentUser,
+    authz::{Action, Resource, authorize},
+    events::{self, ExecutionEvent, ExecutionEventPayload},
+    executions,
+};
+
+/// Who is currently connected to which execution, keyed by execution and connection id.
+#[derive(Debug, Clone, Default)]
+pub struct Presence {
+    viewers: Arc<Mutex<HashMap<Uuid, HashMap<Uuid, Viewer>>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Viewer {
+    connection_id: Uuid,
+    user_name: String,
+    focused_item_id: Option<Uuid>,
+}
+
+impl Presence {
+    fn join(&self, execution_id: Uuid, viewer: Viewer) -> Vec<Viewer> {
+        let mut viewers = self.viewers.lock().expect("presence lock poisoned");
+        let execution_viewers = viewers.entry(execution_id).or_default();
+        execution_viewers.insert(viewer.connection_id, viewer);
+        sorted_viewers(execution_viewers)
+    }
+
+    fn focus(&self, execution_id: Uuid, connection_id: Uuid, item_id: Option<Uuid>) -> Vec<Viewer> {
+        let mut viewers = self.viewers.lock().expect("presence lock poisoned");
+        let execution_viewers = viewers.entry(execution_id).or_default();
+        if let Some(viewer) = execution_viewers.get_mut(&connection_id) {
+            viewer.focused_item_id = item_id;
+        }
+        sorted_viewers(execution_viewers)
+    }
+
+    fn leave(&self, execution_id: Uuid, connection_id: Uuid) -> Vec<Viewer> {
+        let mut viewers = self.viewers.lock().expect("presence lock poisoned");
+        let Some(execution_viewers) = viewers.get_mut(&execution_id) else {
+            return Vec::new();
+        };
+        execution_viewers.remove(&connection_id);
+        let remaining = sorted_viewers(execution_viewers);
+        if execution_viewers.is_empty() {
+            viewers.remove(&execution_id);
+        }
+        remaining
+    }
+}
+
+fn sorted_viewers(viewers: &HashMap<Uuid, Viewer>) -> Vec<Viewer> {
+    let mut viewers = viewers.values().cloned().collect::<Vec<_>>();
+    viewers.sort_by(|a, b| {
+        a.user_name
+            .to_lowercase()
+            .cmp(&b.user_name.to_lowercase())
+            .then(a.connection_id.cmp(&b.connection_id))
+    });
+    viewers
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    SetItemFinished {
+        item_id: Uuid,
+        finished: bool,
+        #[serde(default)]
+        value: Option<String>,
+    },
+    Focus {
+        item_id: Option<Uuid>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    Welcome { connection_id: Uuid },
+    Error { message: String },
+}
+
+pub async fn run(
+    state: AppState,
+    execution_id: Uuid,
+    current_user: CurrentUser,
+    mut socket: WebSocket,
+) {
+    let connection_id = Uuid::new_v4();
+    let mut receiver = state.execution_events.subscribe();
+
+    if send_json(&mut socket, &ServerMessage::Welcome { connection_id })
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let viewers = state.presence.join(
+        execution_id,
+        Viewer {
+            connection_id,
+            user_name: current_user.name.clone(),
+            focused_item_id: None,
+        },
+    );
+    publish_presence(&state, execution_id, viewers);
+
+    loop {
+        tokio::select! {
+            message = socket.recv() => {
+                let Some(Ok(message)) = message else {
+                    break;
+                };
+                match message {
+                    Message::Text(text) => {
+                        if let Err(message) =
+                            handle_client_message(&state, execution_id, connection_id, &current_user, text.as_str()).await
+                        {
+                            let reply = ServerMessage::Error { message };
+                            if send_json(&mut socket, &reply).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Message::Close(_) => break,
+                    _ => {}
+                }
+            }
+            event = receiver.recv() => {
+                match event {
+                    Ok(event) if event.execution_id == execution_id => {
+                        if send_json(&mut socket, &event.payload).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+
+    let viewers = state.presence.leave(execution_id, connection_id);
+    publish_presence(&state, execution_id, viewers);
+}
+
+async fn handle_client_message(
+    state: &AppState,
+    execution_id: Uuid,
+    connection_id: Uuid,
+    current_user: &CurrentUser,
+    text: &str,
+) -> Result<(), String> {
+    let message = serde_json::from_str::<ClientMessage>(text)
+        .map_err(|_| "Unsupported message.".to_string())?;
+
+    match message {
+        ClientMessage::SetItemFinished {
+            item_id,
+            finished,
+            value,
+        } => {
+            authorize(current_user, Action::RunExecutions, Resource::Everything)
+                .map_err(|err| err.to_string())?;
+            executions::set_item_finished(
+                state,
+                current_user,
+                Some(execution_id),
+                item_id,
+                finished,
+                value.as_deref(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+        }
+        ClientMessage::Focus { item_id } => {
+            let viewers = state.presence.focus(execution_id, connection_id, item_id);
+            publish_presence(state, execution_id, viewers);
+            Ok(())
+        }
+    }
+}
+
+fn publish_presence(state: &AppState, execution_id: Uuid, viewers: Vec<Viewer>) {
+    events::publish(
+        &state.execution_events,
+        ExecutionEvent {
+            execution_id,
+            payload: ExecutionEventPayload::Presence { viewers },
+        },
+    );
+}
+
+async fn send_json<T: Serialize>(socket: &mut WebSocket, value: &T) -> Result<(), ()> {
+    let text = serde_json::to_string(value).map_err(|_| ())?;
+    socket
+        .send(Message::Text(text.into()))
+        .await
+        .map_err(|_| ())
+}
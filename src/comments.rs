@@ -0,0 +1,295 @@
+//! Comments on an execution, so the people working on it can leave each other messages like
+//! "waiting on spare part" next to the checklist. Comments can be answered, one level deep, and
+//! only their author or an admin can delete them.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, authorize},
+    executions, format_unix_timestamp, unix_now,
+};
+
+/// Longest comment accepted, in characters.
+const MAX_COMMENT_CHARS: usize = 4000;
+
+/// The comments part of the execution page, oldest thread first.
+#[derive(Debug, Serialize)]
+pub struct CommentSection {
+    threads: Vec<CommentView>,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct CommentView {
+    id: Uuid,
+    author_name: String,
+    created_display: String,
+    /// `None` once deleted; shown as a placeholder while it still has replies.
+    body: Option<String>,
+    can_delete: bool,
+    replies: Vec<CommentView>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CommentForm {
+    body: String,
+    /// Id of the comment this answers, empty for a new thread.
+    #[serde(default)]
+    parent: String,
+}
+
+pub async fn fetch_section(
+    db: &SqlitePool,
+    execution_id: Uuid,
+    current_user: &CurrentUser,
+) -> Result<CommentSection, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            parent as "parent: uuid::Uuid",
+            author as "author: uuid::Uuid",
+            author_name,
+            body,
+            created_at,
+            deleted_at as "deleted_at?"
+        FROM execution_comments
+        WHERE action_plan_execution = $1
+        ORDER BY created_at ASC, rowid ASC
+        "#,
+        execution_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut threads: Vec<CommentView> = Vec::new();
+    let mut positions: HashMap<Uuid, usize> = HashMap::new();
+    for row in rows {
+        let is_deleted = row.deleted_at.is_some();
+        let comment = CommentView {
+            id: row.id,
+            author_name: row.author_name,
+            created_display: format_unix_timestamp(row.created_at),
+            body: (!is_deleted).then_some(row.body),
+            can_delete: !is_deleted && (row.author == current_user.id || current_user.is_admin),
+            replies: Vec::new(),
+        };
+        match row.parent.and_then(|parent| positions.get(&parent)) {
+            Some(&position) => {
+                if !is_deleted {
+                    threads[position].replies.push(comment);
+                }
+            }
+            None => {
+                positions.insert(row.id, threads.len());
+                threads.push(comment);
+            }
+        }
+    }
+    threads.retain(|thread| thread.body.is_some() || !thread.replies.is_empty());
+
+    Ok(CommentSection {
+        count: threads
+            .iter()
+            .map(|thread| usize::from(thread.body.is_some()) + thread.replies.len())
+            .sum(),
+        threads,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/executions/{id}/comments",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    request_body(content = CommentForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Adds the comment and redirects to it on the execution page"),
+        (status = 404, description = "Execution or answered comment not found", content_type = "text/html"),
+        (status = 409, description = "The comment is empty or too long", content_type = "text/html"),
+    )
+)]
+pub async fn create_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<CommentForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let body = form.body.trim();
+    if body.is_empty() {
+        return Err(AppError::conflict("A comment cannot be empty."));
+    }
+    if body.chars().count() > MAX_COMMENT_CHARS {
+        return Err(AppError::conflict(format!(
+            "Comments are limited to {} characters.",
+            MAX_COMMENT_CHARS
+        )));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let execution_exists = sqlx::query_scalar!(
+        r#"SELECT id as "id: uuid::Uuid" FROM action_plan_executions WHERE id = $1"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .is_some();
+    if !execution_exists {
+        return Err(AppError::not_found_for(
+            "Execution",
+            format!("No execution exists for id: {}", id),
+        ));
+    }
+
+    let parent = match form.parent.trim() {
+        "" => None,
+        value => {
+            let parent_id = Uuid::parse_str(value).map_err(|_| comment_not_found(value))?;
+            let parent = sqlx::query!(
+                r#"
+                SELECT parent as "parent: uuid::Uuid", author_name
+                FROM execution_comments
+                WHERE id = $1 AND action_plan_execution = $2
+                "#,
+                parent_id,
+                id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| comment_not_found(value))?;
+            // Replies to a reply join the thread instead of nesting deeper.
+            Some((parent.parent.unwrap_or(parent_id), parent.author_name))
+        }
+    };
+
+    let comment_id = Uuid::new_v4();
+    let thread_id = parent.as_ref().map(|(thread_id, _)| *thread_id);
+    let now = unix_now();
+    sqlx::query!(
+        r#"
+        INSERT INTO execution_comments (
+            id, action_plan_execution, parent, author, author_name, body, created_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        comment_id,
+        id,
+        thread_id,
+        current_user.id,
+        current_user.name,
+        body,
+        now
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let description = executions::execution_description(&mut tx, id).await?;
+    let summary = match &parent {
+        Some((_, author_name)) => {
+            format!("Answered a comment by {} on {}", author_name, description)
+        }
+        None => format!("Commented on {}", description),
+    };
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(AuditEntity::Execution, id, AuditAction::Update, summary)
+            .change(None, Some(body.to_string())),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}#comment-{}",
+        id, comment_id
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-comments/{id}/delete",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Comment id")),
+    responses(
+        (status = 303, description = "Deletes the comment and redirects to its execution"),
+        (status = 403, description = "Only the author or an admin can delete a comment", content_type = "text/html"),
+        (status = 404, description = "Comment not found", content_type = "text/html"),
+    )
+)]
+pub async fn delete_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let mut tx = state.db.begin().await?;
+    let comment = sqlx::query!(
+        r#"
+        SELECT
+            action_plan_execution as "execution_id: uuid::Uuid",
+            author as "author: uuid::Uuid",
+            author_name,
+            body
+        FROM execution_comments
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| comment_not_found(&id.to_string()))?;
+    if comment.author != current_user.id && !current_user.is_admin {
+        return Err(AppError::forbidden(
+            "Only the author or an admin can delete a comment.",
+        ));
+    }
+
+    let now = unix_now();
+    sqlx::query!(
+        "UPDATE execution_comments SET body = '', deleted_at = $1 WHERE id = $2",
+        now,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let description = executions::execution_description(&mut tx, comment.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Execution,
+            comment.execution_id,
+            AuditAction::Update,
+            format!(
+                "Deleted a comment by {} on {}",
+                comment.author_name, description
+            ),
+        )
+        .change(Some(comment.body), None),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}#comments",
+        comment.execution_id
+    )))
+}
+
+fn comment_not_found(id: &str) -> AppError {
+    AppError::not_found_for("Comment", format!("No comment exists for id: {}", id))
+}
@@ -0,0 +1,189 @@
+//! Conditional checklist items. An item can depend on an earlier item of its plan and then only
+//! applies once that item was checked off, optionally with a given answer, like a follow-up step
+//! that is only needed when an inspection was answered "yes", or once that item was marked
+//! failed. Items that do not apply are hidden during the run and do not hold up completing it.
+
+use std::collections::HashMap;
+
+/// Longest answer a condition can compare against, in characters.
+pub const MAX_VALUE_CHARS: usize = 200;
+
+/// What conditions need to know about an item of a run.
+pub struct ConditionItem<'a> {
+    pub order_index: i64,
+    /// Order index of the item this one depends on.
+    pub condition_index: Option<i64>,
+    /// Answer the other item needs to have been given. Any answer counts when missing.
+    pub condition_value: Option<&'a str>,
+    /// The other item needs to have been marked failed instead of checked off.
+    pub condition_failed: bool,
+    pub is_finished: bool,
+    pub is_failed: bool,
+    pub value: Option<&'a str>,
+}
+
+/// Whether each item applies, in the order given. An item depending on an item that does not
+/// apply itself does not apply either.
+pub fn applicable(items: &[ConditionItem]) -> Vec<bool> {
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by_key(|position| items[*position].order_index);
+
+    let mut applies = vec![true; items.len()];
+    let mut satisfied_by_index: HashMap<i64, &ConditionItem> = HashMap::new();
+    for position in order {
+        let item = &items[position];
+        applies[position] = match item.condition_index {
+            None => true,
+            Some(condition_index) => satisfied_by_index
+                .get(&condition_index)
+                .is_some_and(|other| is_met(other, item)),
+        };
+        if applies[position] {
+            satisfied_by_index.insert(item.order_index, item);
+        }
+    }
+    applies
+}
+
+/// Answers are compared without regard to case or surrounding whitespace.
+fn is_met(other: &ConditionItem, item: &ConditionItem) -> bool {
+    if item.condition_failed {
+        return other.is_failed;
+    }
+    if !other.is_finished {
+        return false;
+    }
+    match item.condition_value {
+        None => true,
+        Some(expected) => other
+            .value
+            .is_some_and(|value| value.trim().to_lowercase() == expected.trim().to_lowercase()),
+    }
+}
+
+/// `Only if "Check belt" was answered "worn"`, to explain a conditional item.
+pub fn describe(item_name: &str, value: Option<&str>, failed: bool) -> String {
+    match (failed, value) {
+        (true, _) => format!("Only if \"{}\" was marked failed", item_name),
+        (false, Some(value)) => format!("Only if \"{}\" was answered \"{}\"", item_name, value),
+        (false, None) => format!("Only if \"{}\" was checked off", item_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(order_index: i64) -> ConditionItem<'static> {
+        ConditionItem {
+            order_index,
+            condition_index: None,
+            condition_value: None,
+            condition_failed: false,
+            is_finished: false,
+            is_failed: false,
+            value: None,
+        }
+    }
+
+    fn depending_on(order_index: i64, condition_index: i64) -> ConditionItem<'static> {
+        ConditionItem {
+            condition_index: Some(condition_index),
+            ..item(order_index)
+        }
+    }
+
+    #[test]
+    fn items_without_a_condition_apply() {
+        assert_eq!(applicable(&[item(0), item(1)]), vec![true, true]);
+    }
+
+    #[test]
+    fn any_answer_counts_without_a_value() {
+        let open = [item(0), depending_on(1, 0)];
+        assert_eq!(applicable(&open), vec![true, false]);
+        let checked = [
+            ConditionItem {
+                is_finished: true,
+                ..item(0)
+            },
+            depending_on(1, 0),
+        ];
+        assert_eq!(applicable(&checked), vec![true, true]);
+    }
+
+    #[test]
+    fn answers_are_compared_ignoring_case_and_whitespace() {
+        let answered = |value| {
+            [
+                ConditionItem {
+                    is_finished: true,
+                    value: Some(value),
+                    ..item(0)
+                },
+                ConditionItem {
+                    condition_value: Some("Worn"),
+                    ..depending_on(1, 0)
+                },
+            ]
+        };
+        assert_eq!(applicable(&answered(" worn ")), vec![true, true]);
+        assert_eq!(applicable(&answered("fine")), vec![true, false]);
+    }
+
+    #[test]
+    fn failed_conditions_need_the_item_marked_failed() {
+        let after = |first| {
+            [
+                first,
+                ConditionItem {
+                    condition_failed: true,
+                    ..depending_on(1, 0)
+                },
+            ]
+        };
+        let checked = ConditionItem {
+            is_finished: true,
+            ..item(0)
+        };
+        let failed = ConditionItem {
+            is_failed: true,
+            ..item(0)
+        };
+        assert_eq!(applicable(&after(checked)), vec![true, false]);
+        assert_eq!(applicable(&after(failed)), vec![true, true]);
+    }
+
+    #[test]
+    fn items_depending_on_items_that_do_not_apply_do_not_apply() {
+        // Given out of order, with the middle item's condition unmet.
+        let items = [
+            ConditionItem {
+                is_finished: true,
+                ..depending_on(2, 1)
+            },
+            ConditionItem {
+                is_finished: true,
+                ..depending_on(1, 0)
+            },
+            item(0),
+        ];
+        assert_eq!(applicable(&items), vec![false, false, true]);
+    }
+
+    #[test]
+    fn describes_each_kind_of_condition() {
+        assert_eq!(
+            describe("Check belt", Some("worn"), false),
+            "Only if \"Check belt\" was answered \"worn\""
+        );
+        assert_eq!(
+            describe("Check belt", None, false),
+            "Only if \"Check belt\" was checked off"
+        );
+        assert_eq!(
+            describe("Check belt", Some("worn"), true),
+            "Only if \"Check belt\" was marked failed"
+        );
+    }
+}
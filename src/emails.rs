@@ -0,0 +1,265 @@
+//! Email addresses of users and their verification. A new address is stored unverified and
+//! gets a signed link; only once it is opened is the address used for notifications.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser, format_unix_timestamp, mail, public_url, templates,
+    tokens::{self, TokenScope},
+    unix_now,
+};
+
+/// How long a verification link stays usable. A new one can be sent from the account page.
+const VERIFICATION_VALIDITY_SECONDS: i64 = 2 * 24 * 60 * 60;
+
+/// The address section of the account page.
+#[derive(Debug, Serialize)]
+pub struct EmailStatus {
+    email: Option<String>,
+    verified_display: Option<String>,
+    mail_configured: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct VerifyEmailView {
+    email: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EmailForm {
+    /// New address. Empty removes the address.
+    email: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VerifyEmailQuery {
+    /// Signed token from the verification mail.
+    token: String,
+}
+
+struct EmailRow {
+    name: String,
+    email: Option<String>,
+    email_verified_at: Option<i64>,
+    email_verification_id: Option<Uuid>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/me/email",
+    tag = "users",
+    request_body(content = EmailForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Stores the address, mails a verification link to it and redirects to the account page"),
+        (status = 409, description = "The address is not valid, or public_url is not set to build the link", content_type = "text/html"),
+    )
+)]
+pub async fn edit_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Form(form): Form<EmailForm>,
+) -> Result<Redirect, AppError> {
+    let email = normalize_address(&form.email)?;
+    let row = fetch_email_row(&state.db, current_user.id).await?;
+    let unchanged =
+        row.email.as_deref().map(str::to_lowercase) == email.as_deref().map(str::to_lowercase);
+    if unchanged && (email.is_none() || row.email_verified_at.is_some()) {
+        return Ok(Redirect::to("/users/me"));
+    }
+
+    set_address(&state.db, current_user.id, email.as_deref()).await?;
+    send_verification(&state, current_user.id).await?;
+
+    Ok(Redirect::to("/users/me"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/me/email/resend",
+    tag = "users",
+    responses(
+        (status = 303, description = "Mails the verification link again and redirects to the account page"),
+        (status = 409, description = "public_url is not set to build the link", content_type = "text/html"),
+        (status = 429, description = "Too many links were requested for this address", content_type = "text/html"),
+    )
+)]
+pub async fn resend_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Redirect, AppError> {
+    let row = fetch_email_row(&state.db, current_user.id).await?;
+    if let Some(verification_id) = row.email_verification_id
+        && !tokens::record_request(&state.db, &state.config, verification_id).await?
+    {
+        return Err(AppError::too_many_requests(
+            "Too many verification mails were requested. Try again later.",
+        ));
+    }
+    send_verification(&state, current_user.id).await?;
+
+    Ok(Redirect::to("/users/me"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/verify-email",
+    tag = "users",
+    params(VerifyEmailQuery),
+    responses(
+        (status = 200, description = "Marks the address as verified", content_type = "text/html"),
+        (status = 404, description = "The link is invalid, expired or replaced by a newer one", content_type = "text/html"),
+    )
+)]
+pub async fn verify_get(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<Response, AppError> {
+    let verified = state
+        .token_signer
+        .verify(&state.db, &query.token, TokenScope::EmailVerification)
+        .await?;
+    let Some(verified) = verified else {
+        return render_verify(&state, StatusCode::NOT_FOUND, None);
+    };
+
+    let now = unix_now();
+    // Only the latest link of the current address counts, so a link mailed to a replaced
+    // address cannot verify the new one.
+    let email = sqlx::query_scalar!(
+        r#"
+        UPDATE users
+        SET email_verified_at = $1, email_verification_id = NULL
+        WHERE id = $2 AND email_verification_id = $3 AND email IS NOT NULL
+        RETURNING email as "email!: String"
+        "#,
+        now,
+        verified.subject,
+        verified.id
+    )
+    .fetch_optional(&state.db)
+    .await?;
+    let Some(email) = email else {
+        return render_verify(&state, StatusCode::NOT_FOUND, None);
+    };
+    tokens::revoke(&state.db, verified.id).await?;
+
+    tracing::info!(email, "Email address verified");
+    render_verify(&state, StatusCode::OK, Some(email))
+}
+
+/// The user's address and whether it is verified.
+pub async fn fetch_status(state: &AppState, user_id: Uuid) -> Result<EmailStatus, AppError> {
+    let row = fetch_email_row(&state.db, user_id).await?;
+    Ok(EmailStatus {
+        email: row.email,
+        verified_display: row.email_verified_at.map(format_unix_timestamp),
+        mail_configured: state.mailer.is_configured(),
+    })
+}
+
+/// Trims `value` and checks it is a single address. Empty means no address.
+pub fn normalize_address(value: &str) -> Result<Option<String>, AppError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(mail::parse_address(value)?.to_string()))
+}
+
+/// Stores `email` as the user's unverified address, replacing any earlier address and making
+/// its pending link unusable. `None` removes the address.
+pub async fn set_address(
+    db: &SqlitePool,
+    user_id: Uuid,
+    email: Option<&str>,
+) -> Result<(), AppError> {
+    let verification_id = email.map(|_| Uuid::new_v4());
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET email = $1, email_verified_at = NULL, email_verification_id = $2
+        WHERE id = $3
+        "#,
+        email,
+        verification_id,
+        user_id
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Mails a verification link to the user's address. Does nothing when there is no address or
+/// it is verified already.
+pub async fn send_verification(state: &AppState, user_id: Uuid) -> Result<(), AppError> {
+    let row = fetch_email_row(&state.db, user_id).await?;
+    let (Some(email), Some(verification_id)) = (row.email, row.email_verification_id) else {
+        return Ok(());
+    };
+
+    let expires_at = unix_now().saturating_add(VERIFICATION_VALIDITY_SECONDS);
+    let token = state.token_signer.sign(
+        TokenScope::EmailVerification,
+        verification_id,
+        user_id,
+        Some(expires_at),
+    );
+    let Some(url) = public_url(&state.config, &format!("/verify-email?token={}", token)) else {
+        return Err(AppError::conflict(
+            "The verification mail was not sent, as public_url is not set. Ask an admin to set \
+            it to the address of this app, then send the link again.",
+        ));
+    };
+    let body = format!(
+        "Hello {},\n\n\
+        open this link to confirm {} as your email address for Maintenance Planner:\n\n\
+        {}\n\n\
+        The link works until {}. If you did not enter this address, ignore this mail.\n",
+        row.name,
+        email,
+        url,
+        format_unix_timestamp(expires_at)
+    );
+    state
+        .mailer
+        .send(&email, "Confirm your email address", body)
+        .await
+}
+
+async fn fetch_email_row(db: &SqlitePool, user_id: Uuid) -> Result<EmailRow, AppError> {
+    sqlx::query_as!(
+        EmailRow,
+        r#"
+        SELECT
+            name,
+            email,
+            email_verified_at,
+            email_verification_id as "email_verification_id: uuid::Uuid"
+        FROM users
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AppError::not_found_for("User", format!("No user exists for id: {}", user_id)))
+}
+
+fn render_verify(
+    state: &AppState,
+    status: StatusCode,
+    email: Option<String>,
+) -> Result<Response, AppError> {
+    let rendered = templates::render(&state.jinja, "verify_email.html", VerifyEmailView { email })?;
+    Ok((status, Html(rendered)).into_response())
+}
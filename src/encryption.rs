@@ -0,0 +1,153 @@
+//! Encryption of secrets kept in the database, such as two-factor secrets. The key is separate
+//! from the token signing key, so links can be invalidated without losing the secrets, and can
+//! be rotated: stored values name the key they were encrypted with, older keys stay usable for
+//! reading while listed in `previous_encryption_keys`, and startup re-encrypts everything with
+//! the current key.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use chacha20poly1305::{
+    ChaCha20Poly1305,
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload, rand_core::RngCore},
+};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+use crate::config::Config;
+
+const ENCRYPTION_KEY_SECRET_NAME: &str = "encryption_key";
+const GENERATED_KEY_LENGTH: usize = 32;
+/// First byte of every value encrypted here, ahead of the key id, nonce and ciphertext.
+const FORMAT_VERSION: u8 = 1;
+const KEY_ID_BYTES: usize = 4;
+const NONCE_BYTES: usize = 12;
+
+#[derive(Clone)]
+pub struct SecretCipher {
+    current: Arc<EncryptionKey>,
+    previous: Arc<[EncryptionKey]>,
+}
+
+impl std::fmt::Debug for SecretCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretCipher")
+            .field("previous_keys", &self.previous.len())
+            .finish_non_exhaustive()
+    }
+}
+
+struct EncryptionKey {
+    id: [u8; KEY_ID_BYTES],
+    cipher: ChaCha20Poly1305,
+}
+
+impl EncryptionKey {
+    /// Keys of any length are accepted and hashed to the 32 bytes the cipher needs.
+    fn new(material: &[u8]) -> Self {
+        let key: [u8; 32] = Sha256::digest(material).into();
+        let id_hash = Sha256::new()
+            .chain_update(b"key-id")
+            .chain_update(key)
+            .finalize();
+        let mut id = [0u8; KEY_ID_BYTES];
+        id.copy_from_slice(&id_hash[..KEY_ID_BYTES]);
+        Self {
+            id,
+            cipher: ChaCha20Poly1305::new(&key.into()),
+        }
+    }
+}
+
+impl SecretCipher {
+    /// Uses the configured keys, or a random key generated once and stored in the database.
+    /// The generated key stays readable after `encryption_key` is set, so switching to a
+    /// configured key works like any other rotation.
+    pub async fn load(db: &SqlitePool, config: &Config) -> anyhow::Result<Self> {
+        let mut generated = [0u8; GENERATED_KEY_LENGTH];
+        OsRng.fill_bytes(&mut generated);
+        let generated = generated.to_vec();
+        sqlx::query!(
+            "INSERT OR IGNORE INTO server_secrets (name, value) VALUES ($1, $2)",
+            ENCRYPTION_KEY_SECRET_NAME,
+            generated
+        )
+        .execute(db)
+        .await?;
+        let generated = sqlx::query_scalar!(
+            "SELECT value FROM server_secrets WHERE name = $1",
+            ENCRYPTION_KEY_SECRET_NAME
+        )
+        .fetch_one(db)
+        .await
+        .context("failed to load the encryption key")?;
+
+        let mut previous = config
+            .previous_encryption_keys
+            .iter()
+            .map(|key| EncryptionKey::new(key.as_bytes()))
+            .collect::<Vec<_>>();
+        let current = match &config.encryption_key {
+            Some(key) => {
+                previous.push(EncryptionKey::new(&generated));
+                EncryptionKey::new(key.as_bytes())
+            }
+            None => EncryptionKey::new(&generated),
+        };
+        Ok(Self {
+            current: Arc::new(current),
+            previous: previous.into(),
+        })
+    }
+
+    /// `context` is bound to the ciphertext, e.g. the id of the owning user, so a value copied
+    /// to another row does not decrypt.
+    pub fn encrypt(&self, context: &[u8], plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .current
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: context,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("encrypting a secret failed"))?;
+        let mut stored = Vec::with_capacity(1 + KEY_ID_BYTES + NONCE_BYTES + ciphertext.len());
+        stored.push(FORMAT_VERSION);
+        stored.extend_from_slice(&self.current.id);
+        stored.extend_from_slice(&nonce);
+        stored.extend_from_slice(&ciphertext);
+        Ok(stored)
+    }
+
+    /// `None` if none of the configured keys encrypted `stored`.
+    pub fn decrypt(&self, context: &[u8], stored: &[u8]) -> Option<Vec<u8>> {
+        let (&version, rest) = stored.split_first()?;
+        if version != FORMAT_VERSION || rest.len() <= KEY_ID_BYTES + NONCE_BYTES {
+            return None;
+        }
+        let (id, rest) = rest.split_at(KEY_ID_BYTES);
+        let (nonce, ciphertext) = rest.split_at(NONCE_BYTES);
+        let key = std::iter::once(self.current.as_ref())
+            .chain(self.previous.iter())
+            .find(|key| key.id == id)?;
+        key.cipher
+            .decrypt(
+                nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad: context,
+                },
+            )
+            .ok()
+    }
+
+    /// Whether `stored` was encrypted with the current key.
+    pub fn is_current(&self, stored: &[u8]) -> bool {
+        stored.first() == Some(&FORMAT_VERSION)
+            && stored.get(1..1 + KEY_ID_BYTES) == Some(&self.current.id[..])
+    }
+}
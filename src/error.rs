@@ -6,11 +6,20 @@ use axum::{
 };
 use serde::Serialize;
 
+use crate::{config, read_only, templates};
+
+const STORAGE_UNAVAILABLE_MESSAGE: &str = "The database cannot be written to right now, so your change was not saved. Admins have been notified; try again later.";
+
 #[derive(Debug)]
 pub struct AppError {
     status: StatusCode,
     message: String,
     not_found_title: Option<String>,
+    /// Set when a page template failed to render. The details are logged where it happened,
+    /// so the response only shows a generic error page.
+    is_render_failure: bool,
+    /// Set when the database could not be written, which switches on read-only mode.
+    is_storage_failure: bool,
 }
 
 impl AppError {
@@ -18,10 +27,36 @@ impl AppError {
     where
         E: Into<anyhow::Error>,
     {
+        let err = err.into();
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: err.to_string(),
+            not_found_title: None,
+            is_render_failure: false,
+            is_storage_failure: err
+                .downcast_ref::<sqlx::Error>()
+                .is_some_and(read_only::is_storage_error),
+        }
+    }
+
+    /// For changes refused while the database is read-only.
+    pub(crate) fn storage_unavailable() -> Self {
+        Self {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            message: STORAGE_UNAVAILABLE_MESSAGE.to_string(),
+            not_found_title: None,
+            is_render_failure: false,
+            is_storage_failure: true,
+        }
+    }
+
+    pub(crate) fn render_failed() -> Self {
         Self {
             status: StatusCode::INTERNAL_SERVER_ERROR,
-            message: err.into().to_string(),
+            message: "This page could not be displayed. The error has been logged.".to_string(),
             not_found_title: None,
+            is_render_failure: true,
+            is_storage_failure: false,
         }
     }
 
@@ -30,6 +65,8 @@ impl AppError {
             status: StatusCode::NOT_FOUND,
             message: message.into(),
             not_found_title: Some(title.into()),
+            is_render_failure: false,
+            is_storage_failure: false,
         }
     }
 
@@ -38,6 +75,8 @@ impl AppError {
             status: StatusCode::CONFLICT,
             message: message.into(),
             not_found_title: None,
+            is_render_failure: false,
+            is_storage_failure: false,
         }
     }
 
@@ -46,6 +85,8 @@ impl AppError {
             status: StatusCode::FORBIDDEN,
             message: message.into(),
             not_found_title: None,
+            is_render_failure: false,
+            is_storage_failure: false,
         }
     }
 
@@ -54,6 +95,29 @@ impl AppError {
             status: StatusCode::UNAUTHORIZED,
             message: message.into(),
             not_found_title: None,
+            is_render_failure: false,
+            is_storage_failure: false,
+        }
+    }
+
+    pub fn payload_too_large(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            message: message.into(),
+            not_found_title: None,
+            is_render_failure: false,
+            is_storage_failure: false,
+        }
+    }
+
+    /// For endpoints read by programs rather than browsers, so it is answered as plain text.
+    pub fn too_many_requests(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            message: message.into(),
+            not_found_title: None,
+            is_render_failure: false,
+            is_storage_failure: false,
         }
     }
 }
@@ -77,8 +141,31 @@ impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let message = self.message;
 
-        if self.status == StatusCode::NOT_FOUND
+        if self.status.is_server_error() && !self.is_render_failure {
+            tracing::error!("{}", message);
+        }
+
+        if self.is_storage_failure {
+            let mut response = render_error_page(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Changes Cannot Be Saved".to_string(),
+                STORAGE_UNAVAILABLE_MESSAGE.to_string(),
+                "Back Home",
+                "/",
+            )
+            .unwrap_or_else(|| {
+                (StatusCode::SERVICE_UNAVAILABLE, STORAGE_UNAVAILABLE_MESSAGE).into_response()
+            });
+            response
+                .extensions_mut()
+                .insert(read_only::StorageFailure(message));
+            return response;
+        }
+
+        if self.is_render_failure
+            || self.status == StatusCode::NOT_FOUND
             || self.status == StatusCode::CONFLICT
+            || self.status == StatusCode::PAYLOAD_TOO_LARGE
             || self.status == StatusCode::FORBIDDEN
             || self.status == StatusCode::UNAUTHORIZED
         {
@@ -96,35 +183,32 @@ impl IntoResponse for AppError {
                     ("Forbidden".to_string(), "Back Home", "/")
                 } else if self.status == StatusCode::UNAUTHORIZED {
                     ("Unauthorized".to_string(), "Login", "/login")
+                } else if self.is_render_failure {
+                    ("Page Could Not Be Displayed".to_string(), "Back Home", "/")
                 } else {
                     ("Cannot Save Changes".to_string(), "Back Home", "/")
                 };
 
-            let mut jinja = minijinja::Environment::new();
-            minijinja_embed::load_templates!(&mut jinja);
-            let rendered = jinja
-                .get_template("error.html")
-                .expect("template is loaded")
-                .render(ErrorView {
-                    title,
-                    message: message.clone(),
-                    button_label: button_label.to_string(),
-                    button_href: button_href.to_string(),
-                });
-
-            if let Ok(html) = rendered {
-                return (
-                    self.status,
-                    [(
-                        header::CONTENT_TYPE,
-                        HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()),
-                    )],
-                    html,
-                )
-                    .into_response();
+            if let Some(response) = render_error_page(
+                self.status,
+                title,
+                message.clone(),
+                button_label,
+                button_href,
+            ) {
+                return response;
             }
         }
 
+        if self.is_render_failure {
+            // The error page itself failed to render, so fall back to plain text.
+            return (StatusCode::INTERNAL_SERVER_ERROR, message).into_response();
+        }
+
+        if self.status == StatusCode::TOO_MANY_REQUESTS {
+            return (self.status, message).into_response();
+        }
+
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Something went wrong: {}", message),
@@ -133,6 +217,38 @@ impl IntoResponse for AppError {
     }
 }
 
+fn render_error_page(
+    status: StatusCode,
+    title: String,
+    message: String,
+    button_label: &str,
+    button_href: &str,
+) -> Option<Response> {
+    let jinja = templates::environment(config::base_path());
+    let html = templates::render(
+        &jinja,
+        "error.html",
+        ErrorView {
+            title,
+            message,
+            button_label: button_label.to_string(),
+            button_href: format!("{}{}", config::base_path(), button_href),
+        },
+    )
+    .ok()?;
+    Some(
+        (
+            status,
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()),
+            )],
+            html,
+        )
+            .into_response(),
+    )
+}
+
 #[derive(Debug, Serialize)]
 struct ErrorView {
     title: String,
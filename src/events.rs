@@ -0,0 +1,66 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::collab::Viewer;
+
+const EXECUTION_EVENT_CAPACITY: usize = 256;
+
+/// Change to an execution that is pushed to every browser viewing it. Lifecycle changes
+/// (created, completed, reopened, deleted) are also forwarded to the global `/events` stream.
+#[derive(Debug, Clone)]
+pub struct ExecutionEvent {
+    pub execution_id: Uuid,
+    pub payload: ExecutionEventPayload,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecutionEventPayload {
+    Created,
+    ItemFinished {
+        item_id: Uuid,
+        is_finished: bool,
+        finished_display: Option<String>,
+        finished_by_name: Option<String>,
+        /// What was entered for text and numeric items.
+        value: Option<String>,
+        out_of_spec: bool,
+    },
+    ItemNote {
+        item_id: Uuid,
+        note: Option<String>,
+    },
+    Completed {
+        finished_display: String,
+    },
+    Reopened,
+    Deleted,
+    Presence {
+        viewers: Vec<Viewer>,
+    },
+}
+
+impl ExecutionEventPayload {
+    /// Event type for changes to which executions are open or finished, `None` otherwise.
+    pub fn lifecycle_kind(&self) -> Option<&'static str> {
+        match self {
+            ExecutionEventPayload::Created => Some("created"),
+            ExecutionEventPayload::Completed { .. } => Some("completed"),
+            ExecutionEventPayload::Reopened => Some("reopened"),
+            ExecutionEventPayload::Deleted => Some("deleted"),
+            ExecutionEventPayload::ItemFinished { .. }
+            | ExecutionEventPayload::ItemNote { .. }
+            | ExecutionEventPayload::Presence { .. } => None,
+        }
+    }
+}
+
+pub fn execution_channel() -> broadcast::Sender<ExecutionEvent> {
+    broadcast::channel(EXECUTION_EVENT_CAPACITY).0
+}
+
+/// Sends an event to all subscribers. Having no open subscribers is not an error.
+pub fn publish(sender: &broadcast::Sender<ExecutionEvent>, event: ExecutionEvent) {
+    let _ = sender.send(event);
+}
@@ -1,15 +1,56 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::Infallible,
+};
+
 use axum::{
     Json,
-    extract::{Path, Query, State},
-    response::{Html, Redirect},
+    extract::{Path, Query, State, WebSocketUpgrade},
+    http::{HeaderValue, header},
+    response::{
+        Html, IntoResponse, Redirect, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
 };
 use axum_extra::extract::Form;
 use serde::{Deserialize, Serialize};
-use sqlx::prelude::FromRow;
+use sqlx::{SqliteConnection, prelude::FromRow};
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-use crate::{AppError, AppState, CurrentUser, format_unix_timestamp};
+use crate::{
+    AppError, AppState, CurrentUser,
+    action_library::{self, ActionLink},
+    action_plan::format_estimate,
+    archive, assignees,
+    attachments::{self, ItemAttachment},
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, Role, authorize},
+    blocks::{self, ItemBlock, OpenBlock},
+    checklist_export::{self, Checklist, ChecklistItem, ExportFormat},
+    collab, comments,
+    conditions::{self, ConditionItem},
+    config::Config,
+    events::{self, ExecutionEvent, ExecutionEventPayload},
+    failures::ItemFailure,
+    format_duration, format_unix_timestamp,
+    item_types::{self, ItemType},
+    markdown, procedure_changes,
+    skips::ItemSkip,
+    sub_items::{self, SubItem},
+    tasks, templates, transfers, unix_now,
+};
 
+#[utoipa::path(
+    get,
+    path = "/executions",
+    tag = "executions",
+    params(ExecutionListQuery),
+    responses(
+        (status = 200, description = "Execution list", content_type = "text/html"),
+    )
+)]
 pub async fn index(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -25,9 +66,19 @@ pub async fn index(
                 action_plan_executions.id as "id!: uuid::Uuid",
                 action_plans.name as "action_plan_name!",
                 action_plan_executions.started as "started!",
-                action_plan_executions.note
+                action_plan_executions.note,
+                action_plan_executions.run_number as "run_number!",
+                action_plan_executions.label,
+                starters.name as "started_by_name?",
+                (
+                    SELECT COUNT(*)
+                    FROM action_item_executions
+                    WHERE action_item_executions.action_plan_execution = action_plan_executions.id
+                        AND action_item_executions.out_of_spec
+                ) as "out_of_spec_count!: i64"
             FROM action_plan_executions
             INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+            LEFT JOIN users AS starters ON starters.id = action_plan_executions.started_by
             WHERE action_plan_executions.finished IS NULL OR action_plan_executions.finished <= 0
             ORDER BY action_plan_executions.started DESC
             "#
@@ -43,11 +94,24 @@ pub async fn index(
                 action_plan_executions.id as "id!: uuid::Uuid",
                 action_plans.name as "action_plan_name!",
                 action_plan_executions.started as "started!",
-                action_plan_executions.note
+                action_plan_executions.note,
+                action_plan_executions.run_number as "run_number!",
+                action_plan_executions.label,
+                starters.name as "started_by_name?",
+                (
+                    SELECT COUNT(*)
+                    FROM action_item_executions
+                    WHERE action_item_executions.action_plan_execution = action_plan_executions.id
+                        AND action_item_executions.out_of_spec
+                ) as "out_of_spec_count!: i64"
             FROM action_plan_executions
             INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+            LEFT JOIN users AS starters ON starters.id = action_plan_executions.started_by
             WHERE (action_plan_executions.finished IS NULL OR action_plan_executions.finished <= 0)
-                AND LOWER(IFNULL(action_plan_executions.note, '')) LIKE LOWER($1)
+                AND (
+                    LOWER(IFNULL(action_plan_executions.note, '')) LIKE LOWER($1)
+                    OR LOWER(IFNULL(action_plan_executions.label, '')) LIKE LOWER($1)
+                )
             ORDER BY action_plan_executions.started DESC
             "#,
             search_pattern
@@ -65,9 +129,21 @@ pub async fn index(
                 action_plans.name as "action_plan_name!",
                 action_plan_executions.started as "started!",
                 action_plan_executions.finished as "finished!",
-                action_plan_executions.note
+                action_plan_executions.note,
+                action_plan_executions.run_number as "run_number!",
+                action_plan_executions.label,
+                starters.name as "started_by_name?",
+                finishers.name as "finished_by_name?",
+                (
+                    SELECT COUNT(*)
+                    FROM action_item_executions
+                    WHERE action_item_executions.action_plan_execution = action_plan_executions.id
+                        AND action_item_executions.out_of_spec
+                ) as "out_of_spec_count!: i64"
             FROM action_plan_executions
             INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+            LEFT JOIN users AS starters ON starters.id = action_plan_executions.started_by
+            LEFT JOIN users AS finishers ON finishers.id = action_plan_executions.finished_by
             WHERE action_plan_executions.finished > 0
             ORDER BY action_plan_executions.finished DESC
             "#
@@ -84,11 +160,26 @@ pub async fn index(
                 action_plans.name as "action_plan_name!",
                 action_plan_executions.started as "started!",
                 action_plan_executions.finished as "finished!",
-                action_plan_executions.note
+                action_plan_executions.note,
+                action_plan_executions.run_number as "run_number!",
+                action_plan_executions.label,
+                starters.name as "started_by_name?",
+                finishers.name as "finished_by_name?",
+                (
+                    SELECT COUNT(*)
+                    FROM action_item_executions
+                    WHERE action_item_executions.action_plan_execution = action_plan_executions.id
+                        AND action_item_executions.out_of_spec
+                ) as "out_of_spec_count!: i64"
             FROM action_plan_executions
             INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+            LEFT JOIN users AS starters ON starters.id = action_plan_executions.started_by
+            LEFT JOIN users AS finishers ON finishers.id = action_plan_executions.finished_by
             WHERE action_plan_executions.finished > 0
-                AND LOWER(IFNULL(action_plan_executions.note, '')) LIKE LOWER($1)
+                AND (
+                    LOWER(IFNULL(action_plan_executions.note, '')) LIKE LOWER($1)
+                    OR LOWER(IFNULL(action_plan_executions.label, '')) LIKE LOWER($1)
+                )
             ORDER BY action_plan_executions.finished DESC
             "#,
             search_pattern
@@ -103,7 +194,11 @@ pub async fn index(
             id: row.id,
             action_plan_name: row.action_plan_name,
             started_display: format_unix_timestamp(row.started),
+            started_by_name: row.started_by_name,
             note: row.note,
+            run_number: row.run_number,
+            label: row.label,
+            out_of_spec_count: row.out_of_spec_count,
         })
         .collect();
 
@@ -113,31 +208,196 @@ pub async fn index(
             id: row.id,
             action_plan_name: row.action_plan_name,
             started_display: format_unix_timestamp(row.started),
+            started_by_name: row.started_by_name,
             finished_display: format_unix_timestamp(row.finished),
+            finished_by_name: row.finished_by_name,
             note: row.note,
+            run_number: row.run_number,
+            label: row.label,
+            out_of_spec_count: row.out_of_spec_count,
         })
         .collect();
 
-    let template = state
-        .jinja
-        .get_template("action_plan_execution_list.html")
-        .expect("template is loaded");
-    let rendered = template.render(&ActionPlanExecutionList {
-        unfinished_executions,
-        finished_executions,
-        search_query,
-        is_admin: current_user.is_admin,
-    })?;
+    let counts = fetch_execution_counts(&state.db).await?;
+
+    let include_archive = state.archive_enabled && query.archive.unwrap_or(false);
+    let archived_executions = if include_archive {
+        archive::list_executions(&state.db, &search_query).await?
+    } else {
+        Vec::new()
+    };
+
+    let rendered = templates::render(
+        &state.jinja,
+        "action_plan_execution_list.html",
+        &ActionPlanExecutionList {
+            unfinished_executions,
+            finished_executions,
+            counts,
+            search_query,
+            archive_available: state.archive_enabled,
+            include_archive,
+            archived_executions,
+            blocked_items: blocks::fetch_open(&state.db).await?,
+            is_admin: current_user.is_admin,
+        },
+    )?;
 
     Ok(Html(rendered))
 }
 
+#[utoipa::path(
+    post,
+    path = "/action_plan/{id}/execute",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Action plan id")),
+    request_body(content = CreateExecutionForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Starts an execution, links it to `parent_item` if given, and redirects to it"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+    )
+)]
 pub async fn create_post(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
+    Form(form): Form<CreateExecutionForm>,
 ) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let label = normalize_optional_text(form.label);
+    let mut tx = state.db.begin().await?;
+    let execution_id = start_execution(
+        &mut tx,
+        &current_user,
+        id,
+        label.as_deref(),
+        form.parent_item,
+    )
+    .await?;
+    tx.commit().await?;
+
+    events::publish(
+        &state.execution_events,
+        ExecutionEvent {
+            execution_id,
+            payload: ExecutionEventPayload::Created,
+        },
+    );
+
+    Ok(Redirect::to(&format!("/executions/{}", execution_id)))
+}
+
+/// Starts executions of several plans at once, e.g. all plans due at the start of a quarter.
+/// Either every execution is created or, if one plan is missing, none.
+#[utoipa::path(
+    post,
+    path = "/api/executions/batch",
+    tag = "executions",
+    request_body = BatchExecutionRequest,
+    responses(
+        (status = 200, description = "The executions that were started, in the order of the request", body = BatchExecutionResponse),
+        (status = 404, description = "A plan or the tag was not found; nothing was started", content_type = "text/html"),
+        (status = 409, description = "The request selects no plans", content_type = "text/html"),
+    )
+)]
+pub async fn batch_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(body): Json<BatchExecutionRequest>,
+) -> Result<Json<BatchExecutionResponse>, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let label = normalize_optional_text(body.label);
+    let mut plan_ids = body.plan_ids;
+    if let Some(tag_id) = body.tag_id {
+        let tag_exists = sqlx::query_scalar!(
+            r#"SELECT id as "id: uuid::Uuid" FROM tags WHERE id = $1"#,
+            tag_id
+        )
+        .fetch_optional(&state.db)
+        .await?
+        .is_some();
+        if !tag_exists {
+            return Err(AppError::not_found_for(
+                "Tag",
+                format!("No tag exists for id: {}", tag_id),
+            ));
+        }
+        let tagged = sqlx::query_scalar!(
+            r#"
+            SELECT action_plans.id as "id!: uuid::Uuid"
+            FROM action_plans
+            INNER JOIN action_plan_tags ON action_plan_tags.action_plan = action_plans.id
+            WHERE action_plan_tags.tag = $1
+                AND (action_plans.deleted_at IS NULL OR action_plans.deleted_at <= 0)
+            ORDER BY action_plans.name COLLATE NOCASE ASC
+            "#,
+            tag_id
+        )
+        .fetch_all(&state.db)
+        .await?;
+        plan_ids.extend(tagged);
+    }
+    // A plan named directly and through its tag is started once.
+    let mut seen = HashSet::new();
+    plan_ids.retain(|plan_id| seen.insert(*plan_id));
+    if plan_ids.is_empty() {
+        return Err(AppError::conflict("No plans selected to start."));
+    }
+
     let mut tx = state.db.begin().await?;
+    let mut created = Vec::with_capacity(plan_ids.len());
+    for plan_id in plan_ids {
+        let execution_id =
+            start_execution(&mut tx, &current_user, plan_id, label.as_deref(), None).await?;
+        let execution = sqlx::query!(
+            r#"
+            SELECT
+                action_plans.name as "plan_name!",
+                action_plan_executions.run_number as "run_number!"
+            FROM action_plan_executions
+            INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+            WHERE action_plan_executions.id = $1
+            "#,
+            execution_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        created.push(BatchExecutionCreated {
+            plan_id,
+            plan_name: execution.plan_name,
+            execution_id,
+            run_number: execution.run_number,
+        });
+    }
+    tx.commit().await?;
+
+    for execution in &created {
+        events::publish(
+            &state.execution_events,
+            ExecutionEvent {
+                execution_id: execution.execution_id,
+                payload: ExecutionEventPayload::Created,
+            },
+        );
+    }
+    tracing::info!(
+        user = current_user.name,
+        count = created.len(),
+        "Started executions in a batch"
+    );
 
+    Ok(Json(BatchExecutionResponse { created }))
+}
+
+/// Creates an execution of the plan with a copy of its items, optionally linked to the item
+/// of another execution that calls for this plan as a sub-procedure.
+async fn start_execution(
+    conn: &mut SqliteConnection,
+    current_user: &CurrentUser,
+    plan_id: Uuid,
+    label: Option<&str>,
+    parent_item: Option<Uuid>,
+) -> Result<Uuid, AppError> {
     let plan_exists = sqlx::query_scalar!(
         r#"
         SELECT id as "id: uuid::Uuid"
@@ -145,14 +405,14 @@ pub async fn create_post(
         WHERE id = $1
             AND (deleted_at IS NULL OR deleted_at <= 0)
         "#,
-        id
+        plan_id
     )
-    .fetch_optional(&mut *tx)
+    .fetch_optional(&mut *conn)
     .await?;
     if plan_exists.is_none() {
         return Err(AppError::not_found_for(
             "Action Plan",
-            format!("No action plan exists for id: {}", id),
+            format!("No action plan exists for id: {}", plan_id),
         ));
     }
 
@@ -160,51 +420,138 @@ pub async fn create_post(
     let now = unix_now();
 
     sqlx::query!(
-        "INSERT INTO action_plan_executions (id, action_plan, started, finished, note) VALUES ($1, $2, $3, NULL, NULL)",
+        r#"
+        INSERT INTO action_plan_executions (id, action_plan, started, finished, note, label, run_number, started_by)
+        VALUES (
+            $1, $2, $3, NULL, NULL, $4,
+            MAX(
+                (SELECT COALESCE(MAX(run_number), 0) FROM action_plan_executions WHERE action_plan = $2),
+                (SELECT archived_run_number FROM action_plans WHERE id = $2)
+            ) + 1,
+            $5
+        )
+        "#,
         execution_id,
-        id,
+        plan_id,
         now,
+        label,
+        current_user.id,
     )
-    .execute(&mut *tx)
+    .execute(&mut *conn)
     .await?;
 
     let template_items = sqlx::query!(
         r#"
-        SELECT action as "action_id: uuid::Uuid", order_index
+        SELECT
+            action as "action_id: uuid::Uuid",
+            order_index,
+            estimated_minutes,
+            sub_plan as "sub_plan: uuid::Uuid",
+            instructions,
+            item_type,
+            unit,
+            min_value,
+            max_value,
+            condition_index,
+            condition_value,
+            condition_failed,
+            heading,
+            parent_index
         FROM action_items
         WHERE action_plan = $1
         ORDER BY order_index ASC
         "#,
-        id
+        plan_id
     )
-    .fetch_all(&mut *tx)
+    .fetch_all(&mut *conn)
     .await?;
 
+    // Runs keep the heading above each item with the item.
+    let mut section = None;
     for item in template_items {
+        let Some(action_id) = item.action_id else {
+            section = item.heading;
+            continue;
+        };
+        let item_section = section.take();
         let execution_item_id = Uuid::new_v4();
         sqlx::query!(
             r#"
-            INSERT INTO action_item_executions (id, action, order_index, action_plan_execution, finished)
-            VALUES ($1, $2, $3, $4, NULL)
+            INSERT INTO action_item_executions
+                (id, action, order_index, action_plan_execution, finished, estimated_minutes, sub_plan, instructions, item_type, unit, min_value, max_value, condition_index, condition_value, condition_failed, section, parent_index)
+            VALUES ($1, $2, $3, $4, NULL, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             "#,
             execution_item_id,
-            item.action_id,
+            action_id,
             item.order_index,
-            execution_id
+            execution_id,
+            item.estimated_minutes,
+            item.sub_plan,
+            item.instructions,
+            item.item_type,
+            item.unit,
+            item.min_value,
+            item.max_value,
+            item.condition_index,
+            item.condition_value,
+            item.condition_failed,
+            item_section,
+            item.parent_index
         )
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
     }
 
-    tx.commit().await?;
+    // Started from an item that calls for this plan: link the run so the item can be checked
+    // off once it is completed.
+    if let Some(parent_item) = parent_item {
+        sqlx::query!(
+            r#"
+            UPDATE action_item_executions
+            SET linked_execution = $1
+            WHERE id = $2
+                AND sub_plan = $3
+                AND (finished IS NULL OR finished <= 0)
+            "#,
+            execution_id,
+            parent_item,
+            plan_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
 
-    Ok(Redirect::to(&format!("/executions/{}", execution_id)))
+    let description = execution_description(&mut *conn, execution_id).await?;
+    audit::record(
+        &mut *conn,
+        current_user,
+        AuditEntry::new(
+            AuditEntity::Execution,
+            execution_id,
+            AuditAction::Create,
+            format!("Started {}", description),
+        ),
+    )
+    .await?;
+
+    Ok(execution_id)
 }
 
+#[utoipa::path(
+    get,
+    path = "/executions/{id}",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id"), ExecutionShowQuery),
+    responses(
+        (status = 200, description = "Execution checklist", content_type = "text/html"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+    )
+)]
 pub async fn show(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Path(id): Path<Uuid>,
+    Query(query): Query<ExecutionShowQuery>,
 ) -> Result<Html<String>, AppError> {
     let execution = sqlx::query_as!(
         ActionPlanExecutionShowRow,
@@ -216,9 +563,18 @@ pub async fn show(
             action_plans.deleted_at as "action_plan_deleted_at?",
             action_plan_executions.started as "started!",
             action_plan_executions.finished as "finished?",
-            action_plan_executions.note
+            action_plan_executions.note,
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label,
+            merged_plans.name as "merged_from_name?",
+            starters.name as "started_by_name?",
+            finishers.name as "finished_by_name?"
         FROM action_plan_executions
         INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        LEFT JOIN action_plans AS merged_plans
+            ON merged_plans.id = action_plan_executions.merged_from
+        LEFT JOIN users AS starters ON starters.id = action_plan_executions.started_by
+        LEFT JOIN users AS finishers ON finishers.id = action_plan_executions.finished_by
         WHERE action_plan_executions.id = $1
         "#,
         id
@@ -237,14 +593,64 @@ pub async fn show(
         r#"
         SELECT
             action_item_executions.id as "id!: uuid::Uuid",
+            action_item_executions.action as "action_id!: uuid::Uuid",
             actions.name as "name!",
+            actions.description,
+            action_item_executions.instructions,
+            action_item_executions.item_type,
+            action_item_executions.unit,
+            action_item_executions.value,
+            action_item_executions.min_value,
+            action_item_executions.max_value,
+            action_item_executions.out_of_spec as "out_of_spec!: bool",
+            action_item_executions.order_index,
+            action_item_executions.condition_index,
+            action_item_executions.condition_value,
+            action_item_executions.condition_failed as "condition_failed: bool",
+            action_item_executions.section,
+            action_item_executions.parent_index,
             action_item_executions.finished as "finished?",
             CASE
                 WHEN action_item_executions.finished IS NULL OR action_item_executions.finished <= 0 THEN 0
                 ELSE 1
-            END as "is_finished!: i64"
+            END as "is_finished!: i64",
+            action_item_executions.estimated_minutes,
+            action_item_executions.actual_seconds,
+            action_item_executions.sub_plan as "sub_plan: uuid::Uuid",
+            sub_plans.name as "sub_plan_name?",
+            sub_plans.deleted_at as "sub_plan_deleted_at?",
+            linked_executions.id as "linked_execution_id?: uuid::Uuid",
+            linked_executions.run_number as "linked_run_number?",
+            linked_executions.finished as "linked_finished?",
+            scanners.name as "scanned_by_name?",
+            checkers.name as "finished_by_name?",
+            action_item_executions.assigned_to as "assigned_to: uuid::Uuid",
+            assignees.name as "assignee_name?",
+            action_item_executions.note,
+            action_item_executions.blocked_reason,
+            action_item_executions.blocked_at as "blocked_at?",
+            blockers.name as "blocked_by_name?",
+            action_item_executions.block_resolved_at as "block_resolved_at?",
+            resolvers.name as "block_resolved_by_name?",
+            action_item_executions.block_resolution,
+            action_item_executions.skip_reason,
+            action_item_executions.skipped_at as "skipped_at?",
+            skippers.name as "skipped_by_name?",
+            action_item_executions.failure_note,
+            action_item_executions.failed_at as "failed_at?",
+            failers.name as "failed_by_name?"
         FROM action_item_executions
         INNER JOIN actions ON actions.id = action_item_executions.action
+        LEFT JOIN users AS skippers ON skippers.id = action_item_executions.skipped_by
+        LEFT JOIN users AS failers ON failers.id = action_item_executions.failed_by
+        LEFT JOIN users AS scanners ON scanners.id = action_item_executions.scanned_by
+        LEFT JOIN users AS checkers ON checkers.id = action_item_executions.finished_by
+        LEFT JOIN users AS assignees ON assignees.id = action_item_executions.assigned_to
+        LEFT JOIN users AS blockers ON blockers.id = action_item_executions.blocked_by
+        LEFT JOIN users AS resolvers ON resolvers.id = action_item_executions.block_resolved_by
+        LEFT JOIN action_plans AS sub_plans ON sub_plans.id = action_item_executions.sub_plan
+        LEFT JOIN action_plan_executions AS linked_executions
+            ON linked_executions.id = action_item_executions.linked_execution
         WHERE action_item_executions.action_plan_execution = $1
         ORDER BY action_item_executions.order_index ASC
         "#,
@@ -252,216 +658,1140 @@ pub async fn show(
     )
     .fetch_all(&state.db)
     .await?;
+    let sub_procedure_max_age_display = (state.config.sub_procedure_max_age_seconds > 0)
+        .then(|| format_duration(state.config.sub_procedure_max_age_seconds));
+    let is_completed = execution.finished.is_some_and(|value| value > 0);
+    let mut item_attachments =
+        attachments::fetch_for_execution(&state.db, id, &current_user, !is_completed).await?;
+    let item_links = action_library::fetch_for_execution(&state.db, id).await?;
+    let applicable = conditions::applicable(
+        &item_rows
+            .iter()
+            .map(|row| ConditionItem {
+                order_index: row.order_index,
+                condition_index: row.condition_index,
+                condition_value: row.condition_value.as_deref(),
+                condition_failed: row.condition_failed,
+                is_finished: row.is_finished != 0,
+                is_failed: row.failure_note.is_some(),
+                value: row.value.as_deref(),
+            })
+            .collect::<Vec<_>>(),
+    );
+    let open_sub_items = sub_items::open_counts(
+        &item_rows
+            .iter()
+            .zip(&applicable)
+            .map(|(row, applies)| SubItem {
+                parent_index: row.parent_index,
+                is_done: row.is_finished != 0
+                    || row.skip_reason.is_some()
+                    || row.failure_note.is_some()
+                    || (row.blocked_reason.is_some() && row.block_resolved_at.is_none())
+                    || !applies,
+            })
+            .collect::<Vec<_>>(),
+    );
+    let parent_indexes = item_rows
+        .iter()
+        .filter_map(|row| row.parent_index)
+        .collect::<HashSet<_>>();
+    let name_by_index = item_rows
+        .iter()
+        .map(|row| (row.order_index, row.name.clone()))
+        .collect::<HashMap<_, _>>();
     let items: Vec<ExecutionItem> = item_rows
         .into_iter()
-        .map(|row| ExecutionItem {
+        .zip(applicable)
+        .map(|(row, applies)| ExecutionItem {
+            condition_display: row
+                .condition_index
+                .and_then(|index| name_by_index.get(&index))
+                .map(|name| {
+                    conditions::describe(name, row.condition_value.as_deref(), row.condition_failed)
+                }),
+            order_index: row.order_index,
+            condition_index: row.condition_index,
+            condition_value: row.condition_value,
+            condition_failed: row.condition_failed,
+            applies,
+            section: row.section,
+            parent_index: row.parent_index,
+            has_sub_items: parent_indexes.contains(&row.order_index),
+            open_sub_item_count: open_sub_items
+                .get(&row.order_index)
+                .copied()
+                .unwrap_or_default(),
             id: row.id,
             name: row.name,
+            description_html: row.description.as_deref().map(markdown::to_html),
+            instructions: row.instructions,
+            links: item_links.get(&row.action_id).cloned().unwrap_or_default(),
+            item_type: ItemType::from_db(&row.item_type),
+            range_display: item_types::range_display(
+                row.min_value,
+                row.max_value,
+                row.unit.as_deref(),
+            ),
+            unit: row.unit,
+            value: row.value,
+            out_of_spec: row.out_of_spec,
             is_finished: row.is_finished != 0,
             finished_display: row
                 .finished
                 .filter(|value| *value > 0)
                 .map(format_unix_timestamp),
+            estimated_seconds: row.estimated_minutes.map(|minutes| minutes * 60),
+            estimate_display: row.estimated_minutes.map(format_estimate),
+            actual_display: row.actual_seconds.map(format_duration),
+            sub_procedure: row
+                .sub_plan
+                .zip(row.sub_plan_name)
+                .map(|(plan_id, plan_name)| SubProcedure {
+                    plan_id,
+                    plan_name,
+                    is_plan_deleted: row.sub_plan_deleted_at.is_some_and(|value| value > 0),
+                    linked_run: row.linked_execution_id.zip(row.linked_run_number).map(
+                        |(id, run_number)| LinkedRun {
+                            id,
+                            run_number,
+                            finished_display: row
+                                .linked_finished
+                                .filter(|value| *value > 0)
+                                .map(format_unix_timestamp),
+                        },
+                    ),
+                    max_age_display: sub_procedure_max_age_display.clone(),
+                }),
+            scanned_by_name: row.scanned_by_name,
+            finished_by_name: row.finished_by_name,
+            assigned_to: row.assigned_to,
+            assignee_name: row.assigned_to.map(|_| {
+                row.assignee_name
+                    .unwrap_or_else(|| "a deleted user".to_string())
+            }),
+            note: row.note,
+            block: ItemBlock::new(
+                row.blocked_reason,
+                row.blocked_at,
+                row.blocked_by_name,
+                row.block_resolved_at,
+                row.block_resolved_by_name,
+                row.block_resolution,
+            ),
+            skip: ItemSkip::new(row.skip_reason, row.skipped_at, row.skipped_by_name),
+            failure: ItemFailure::new(row.failure_note, row.failed_at, row.failed_by_name),
+            attachments: item_attachments.remove(&row.id).unwrap_or_default(),
         })
         .collect();
+    let estimated_total_seconds = items
+        .iter()
+        .filter_map(|item| item.estimated_seconds)
+        .sum::<i64>();
+    let estimated_remaining_seconds = items
+        .iter()
+        .filter(|item| {
+            !item.is_finished && item.skip.is_none() && item.failure.is_none() && item.applies
+        })
+        .filter_map(|item| item.estimated_seconds)
+        .sum::<i64>();
+    let has_items = !items.is_empty();
+    let can_complete = has_items
+        && items.iter().all(|item| {
+            item.is_finished
+                || item.skip.is_some()
+                || item.failure.is_some()
+                || !item.applies
+                || item.block.as_ref().is_some_and(ItemBlock::is_open)
+        });
+    let has_assignments = items.iter().any(|item| item.assigned_to.is_some());
+    let item_groups = match query.group {
+        ItemGrouping::Plan => group_by_section(items),
+        ItemGrouping::Assignee => group_by_assignee(items),
+    };
+
+    let is_note_missing = state.config.require_execution_note && execution.note.is_none();
 
     let view = ActionPlanExecutionShow {
         id: execution.id,
         action_plan_id: execution.action_plan_id,
         action_plan_name: execution.action_plan_name,
         started_display: format_unix_timestamp(execution.started),
+        started_by_name: execution.started_by_name,
         finished_display: execution
             .finished
             .filter(|value| *value > 0)
             .map(format_unix_timestamp),
+        finished_by_name: execution.finished_by_name,
         note: execution.note,
-        is_completed: execution.finished.map(|value| value > 0).unwrap_or(false),
+        run_number: execution.run_number,
+        label: execution.label,
+        merged_from_name: execution.merged_from_name,
+        is_completed,
         can_reopen: execution
             .finished
-            .map(|value| value > 0 && unix_now().saturating_sub(value) <= 24 * 60 * 60)
+            .map(|value| {
+                value > 0 && unix_now().saturating_sub(value) <= state.config.reopen_window_seconds
+            })
             .unwrap_or(false),
         is_action_plan_deleted: execution
             .action_plan_deleted_at
             .map(|value| value > 0)
             .unwrap_or(false),
-        can_complete: !items.is_empty() && items.iter().all(|item| item.is_finished),
-        items,
+        can_complete,
+        is_note_missing,
+        started_unix: execution.started,
+        estimated_total_display: (estimated_total_seconds > 0)
+            .then(|| format_duration(estimated_total_seconds)),
+        estimated_remaining_display: format_duration(estimated_remaining_seconds),
+        actual_total_display: execution
+            .finished
+            .filter(|value| *value > 0)
+            .map(|finished| format_duration(finished - execution.started)),
+        item_groups,
+        has_items,
+        group: query.group,
+        has_assignments,
+        assignee_options: assignees::fetch_options(&state.db).await?,
+        can_run_executions: current_user.role >= Role::Operator,
+        export_formats: ExportFormat::ALL
+            .into_iter()
+            .map(|format| ExportFormatOption {
+                value: format.as_str(),
+                label: format.label(),
+            })
+            .collect(),
+        handover: transfers::fetch_section(&state.db, id).await?,
+        comments: comments::fetch_section(&state.db, id, &current_user).await?,
+        plan_attachments: attachments::fetch_for_plan(&state.db, execution.action_plan_id).await?,
+        procedure_change: if is_completed || current_user.role < Role::Operator {
+            None
+        } else {
+            procedure_changes::fetch_notice(&state.db, id, &current_user).await?
+        },
+        attachment_accept: state.config.attachment_allowed_types.join(","),
         is_admin: current_user.is_admin,
     };
 
-    let template = state
-        .jinja
-        .get_template("action_plan_execution_show.html")
-        .expect("template is loaded");
-    let rendered = template.render(&view)?;
+    let rendered = templates::render(&state.jinja, "action_plan_execution_show.html", &view)?;
 
     Ok(Html(rendered))
 }
 
-pub async fn update_note_post(
+#[utoipa::path(
+    get,
+    path = "/executions/{id}/export/{format}",
+    tag = "executions",
+    params(
+        ("id" = Uuid, Path, description = "Execution id"),
+        ("format" = String, Path, description = "Format: text, jira or html"),
+    ),
+    responses(
+        (status = 200, description = "The checklist with its current state, for pasting into a ticket", content_type = "text/plain"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+    )
+)]
+pub async fn export_get(
     State(state): State<AppState>,
-    Path(id): Path<Uuid>,
-    Form(form): Form<ExecutionNoteForm>,
-) -> Result<Redirect, AppError> {
-    let note = normalize_note(form.note);
-
-    let result = sqlx::query!(
+    Path((id, format)): Path<(Uuid, ExportFormat)>,
+) -> Result<impl IntoResponse, AppError> {
+    let execution = sqlx::query!(
         r#"
-        UPDATE action_plan_executions
-        SET note = $1
-        WHERE id = $2
+        SELECT
+            action_plans.name as "action_plan_name!",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label,
+            action_plan_executions.started as "started!",
+            action_plan_executions.finished as "finished?",
+            action_plan_executions.note,
+            starters.name as "started_by_name?",
+            finishers.name as "finished_by_name?"
+        FROM action_plan_executions
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        LEFT JOIN users AS starters ON starters.id = action_plan_executions.started_by
+        LEFT JOIN users AS finishers ON finishers.id = action_plan_executions.finished_by
+        WHERE action_plan_executions.id = $1
         "#,
-        note,
-        id
-    )
-    .execute(&state.db)
-    .await?;
-
-    if result.rows_affected() == 0 {
-        return Err(AppError::not_found_for(
-            "Execution",
-            format!("No execution exists for id: {}", id),
-        ));
-    }
-
-    Ok(Redirect::to(&format!("/executions/{}", id)))
-}
-
-pub async fn complete_get(
-    State(state): State<AppState>,
-    Path(id): Path<Uuid>,
-) -> Result<Redirect, AppError> {
-    let execution_exists = sqlx::query_scalar!(
-        r#"SELECT id as "id: uuid::Uuid" FROM action_plan_executions WHERE id = $1"#,
         id
     )
     .fetch_optional(&state.db)
-    .await?;
-    if execution_exists.is_none() {
-        return Err(AppError::not_found_for(
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
             "Execution",
             format!("No todo list exists for execution id: {}", id),
-        ));
-    }
+        )
+    })?;
 
-    let incomplete_count = sqlx::query_scalar!(
+    let items = sqlx::query!(
         r#"
-        SELECT COUNT(*) as "count!: i64"
+        SELECT
+            actions.name as "name!",
+            action_item_executions.finished as "finished?",
+            sub_plans.name as "sub_plan_name?",
+            scanners.name as "scanned_by_name?",
+            checkers.name as "finished_by_name?",
+            action_item_executions.value,
+            action_item_executions.unit,
+            action_item_executions.min_value,
+            action_item_executions.max_value,
+            action_item_executions.out_of_spec as "out_of_spec!: bool",
+            action_item_executions.note,
+            action_item_executions.blocked_reason,
+            action_item_executions.block_resolved_at as "block_resolved_at?",
+            action_item_executions.block_resolution,
+            action_item_executions.skip_reason,
+            action_item_executions.failure_note
         FROM action_item_executions
-        WHERE action_plan_execution = $1
-            AND (finished IS NULL OR finished <= 0)
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        LEFT JOIN users AS scanners ON scanners.id = action_item_executions.scanned_by
+        LEFT JOIN users AS checkers ON checkers.id = action_item_executions.finished_by
+        LEFT JOIN action_plans AS sub_plans ON sub_plans.id = action_item_executions.sub_plan
+        WHERE action_item_executions.action_plan_execution = $1
+        ORDER BY action_item_executions.order_index ASC
         "#,
         id
     )
-    .fetch_one(&state.db)
+    .fetch_all(&state.db)
     .await?;
 
-    if incomplete_count > 0 {
-        return Err(AppError::conflict(
-            "All items must be checked before completing this execution.",
-        ));
+    let mut details = Vec::new();
+    if let Some(label) = execution.label {
+        details.push(("Label", label));
     }
+    details.push(("Started", format_unix_timestamp(execution.started)));
+    if let Some(name) = execution.started_by_name {
+        details.push(("Started by", name));
+    }
+    if let Some(finished) = execution.finished.filter(|value| *value > 0) {
+        details.push(("Completed", format_unix_timestamp(finished)));
+        if let Some(name) = execution.finished_by_name {
+            details.push(("Completed by", name));
+        }
+    }
+    if let Some(note) = execution.note {
+        details.push(("Note", note));
+    }
+    let checklist = Checklist {
+        title: format!(
+            "{} · Run #{}",
+            execution.action_plan_name, execution.run_number
+        ),
+        details,
+        items: items
+            .into_iter()
+            .map(|item| ChecklistItem {
+                name: item.name,
+                finished_display: item
+                    .finished
+                    .filter(|value| *value > 0)
+                    .map(format_unix_timestamp),
+                remarks: item
+                    .value
+                    .map(|value| {
+                        format!(
+                            "value: {}",
+                            item_types::value_display(&value, item.unit.as_deref())
+                        )
+                    })
+                    .into_iter()
+                    .chain(
+                        item_types::range_display(
+                            item.min_value,
+                            item.max_value,
+                            item.unit.as_deref(),
+                        )
+                        .filter(|_| item.out_of_spec)
+                        .map(|range| format!("out of spec, expected {}", range)),
+                    )
+                    .chain(
+                        item.sub_plan_name
+                            .map(|name| format!("sub-procedure: {}", name)),
+                    )
+                    .chain(match item.scanned_by_name {
+                        Some(name) => Some(format!("scanned on site by {}", name)),
+                        None => item
+                            .finished_by_name
+                            .map(|name| format!("checked by {}", name)),
+                    })
+                    .chain(item.note.map(|note| format!("note: {}", note)))
+                    .chain(item.blocked_reason.map(|reason| {
+                        match (item.block_resolved_at, item.block_resolution) {
+                            (None, _) => format!("blocked: {}", reason),
+                            (Some(_), Some(resolution)) => {
+                                format!("was blocked: {}, resolved: {}", reason, resolution)
+                            }
+                            (Some(_), None) => format!("was blocked: {}, resolved", reason),
+                        }
+                    }))
+                    .chain(
+                        item.skip_reason
+                            .map(|reason| format!("skipped: {}", reason)),
+                    )
+                    .chain(item.failure_note.map(|note| format!("failed: {}", note)))
+                    .collect(),
+            })
+            .collect(),
+    };
 
-    let finished_at = unix_now();
-    sqlx::query!(
-        r#"
-        UPDATE action_plan_executions
-        SET finished = $1
-        WHERE id = $2
-            AND (finished IS NULL OR finished <= 0)
-        "#,
-        finished_at,
-        id
-    )
-    .execute(&state.db)
-    .await?;
-
-    Ok(Redirect::to(&format!("/executions/{}", id)))
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(format.content_type()),
+        )],
+        checklist_export::format(&checklist, format),
+    ))
 }
 
-pub async fn reopen_get(
+/// Differences of less than this are reported as "about the same".
+const COMPARE_TOLERANCE_SECONDS: i64 = 60;
+
+#[utoipa::path(
+    get,
+    path = "/executions/{id}/compare",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 200, description = "Item-by-item comparison with the previous run of the same plan", content_type = "text/html"),
+        (status = 404, description = "Execution not found"),
+    )
+)]
+pub async fn compare_get(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
-) -> Result<Redirect, AppError> {
-    let execution = sqlx::query!(
+) -> Result<Html<String>, AppError> {
+    let current = sqlx::query_as!(
+        CompareRunRow,
         r#"
-        SELECT finished as "finished?"
+        SELECT
+            action_plan_executions.id as "id!: uuid::Uuid",
+            action_plan_executions.action_plan as "action_plan_id!: uuid::Uuid",
+            action_plans.name as "action_plan_name!",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.started as "started!",
+            action_plan_executions.finished as "finished?",
+            action_plan_executions.note
         FROM action_plan_executions
-        WHERE id = $1
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_plan_executions.id = $1
         "#,
         id
     )
     .fetch_optional(&state.db)
-    .await?;
-
-    let Some(execution) = execution else {
-        return Err(AppError::not_found_for(
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
             "Execution",
             format!("No todo list exists for execution id: {}", id),
-        ));
-    };
-
-    let Some(finished_at) = execution.finished else {
-        return Err(AppError::conflict("Execution is already open."));
-    };
-
-    if finished_at <= 0 || unix_now().saturating_sub(finished_at) > 24 * 60 * 60 {
-        return Err(AppError::conflict(
-            "Execution can only be reopened within 24 hours of completion.",
-        ));
-    }
+        )
+    })?;
 
-    sqlx::query!(
+    let previous = sqlx::query_as!(
+        CompareRunRow,
         r#"
-        UPDATE action_plan_executions
-        SET finished = NULL
-        WHERE id = $1
+        SELECT
+            action_plan_executions.id as "id!: uuid::Uuid",
+            action_plan_executions.action_plan as "action_plan_id!: uuid::Uuid",
+            action_plans.name as "action_plan_name!",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.started as "started!",
+            action_plan_executions.finished as "finished?",
+            action_plan_executions.note
+        FROM action_plan_executions
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_plan_executions.action_plan = $1 AND action_plan_executions.run_number < $2
+        ORDER BY action_plan_executions.run_number DESC
+        LIMIT 1
         "#,
-        id
+        current.action_plan_id,
+        current.run_number
     )
-    .execute(&state.db)
+    .fetch_optional(&state.db)
     .await?;
 
-    Ok(Redirect::to(&format!("/executions/{}", id)))
-}
+    let current_items = fetch_compare_items(&state.db, &current).await?;
+    let items = match &previous {
+        Some(previous) => {
+            let previous_items = fetch_compare_items(&state.db, previous).await?;
+            compare_items(current_items, previous_items, is_run_finished(&current))
+        }
+        None => Vec::new(),
+    };
 
-pub async fn delete_post(
-    State(state): State<AppState>,
-    Path(id): Path<Uuid>,
-) -> Result<Redirect, AppError> {
-    let mut tx = state.db.begin().await?;
+    let view = ExecutionCompareView {
+        id: current.id,
+        action_plan_name: current.action_plan_name.clone(),
+        current: CompareRunSummary::from_row(&current),
+        notes_differ: previous
+            .as_ref()
+            .is_some_and(|previous| previous.note != current.note),
+        previous: previous.as_ref().map(CompareRunSummary::from_row),
+        items,
+        is_admin: current_user.is_admin,
+    };
 
-    let execution = sqlx::query!(
+    let rendered = templates::render(&state.jinja, "execution_compare.html", &view)?;
+
+    Ok(Html(rendered))
+}
+
+async fn fetch_compare_items(
+    db: &sqlx::SqlitePool,
+    run: &CompareRunRow,
+) -> Result<Vec<CompareItemRow>, AppError> {
+    let rows = sqlx::query!(
         r#"
-        SELECT finished as "finished?"
-        FROM action_plan_executions
-        WHERE id = $1
+        SELECT
+            action_item_executions.action as "action_id!: uuid::Uuid",
+            actions.name as "name!",
+            action_item_executions.finished as "finished?",
+            action_item_executions.value,
+            action_item_executions.unit,
+            action_item_executions.out_of_spec as "out_of_spec!: bool"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        WHERE action_item_executions.action_plan_execution = $1
+        ORDER BY action_item_executions.order_index ASC
         "#,
-        id
+        run.id
     )
-    .fetch_optional(&mut *tx)
+    .fetch_all(db)
     .await?;
 
-    let Some(execution) = execution else {
-        return Err(AppError::not_found_for(
-            "Execution",
-            format!("No todo list exists for execution id: {}", id),
-        ));
-    };
+    Ok(rows
+        .into_iter()
+        .map(|row| CompareItemRow {
+            action_id: row.action_id,
+            name: row.name,
+            elapsed: row
+                .finished
+                .filter(|finished| *finished > 0)
+                .map(|finished| (finished - run.started).max(0)),
+            value_display: row.value.map(|value| {
+                let display = item_types::value_display(&value, row.unit.as_deref());
+                if row.out_of_spec {
+                    format!("{} (out of spec)", display)
+                } else {
+                    display
+                }
+            }),
+        })
+        .collect())
+}
 
-    if execution.finished.map(|value| value > 0).unwrap_or(false) {
-        return Err(AppError::conflict("Only open executions can be deleted."));
+/// Pairs items by action, in the current run's order followed by items only the previous run had.
+fn compare_items(
+    current: Vec<CompareItemRow>,
+    mut previous: Vec<CompareItemRow>,
+    current_is_finished: bool,
+) -> Vec<CompareItem> {
+    let mut compared = Vec::with_capacity(current.len());
+    for item in current {
+        let previous_item = previous
+            .iter()
+            .position(|previous_item| previous_item.action_id == item.action_id)
+            .map(|index| previous.remove(index));
+        let change = match (&previous_item, item.elapsed) {
+            (None, _) => "New in this run".to_string(),
+            (Some(previous_item), Some(elapsed)) => match previous_item.elapsed {
+                Some(previous_elapsed) => describe_time_change(elapsed - previous_elapsed),
+                None => "Skipped last run".to_string(),
+            },
+            (Some(_), None) if !current_is_finished => "Not done yet".to_string(),
+            (Some(previous_item), None) => match previous_item.elapsed {
+                Some(_) => "Skipped this run".to_string(),
+                None => "Skipped in both runs".to_string(),
+            },
+        };
+        compared.push(CompareItem {
+            previous_display: previous_item.map(|previous_item| run_item_display(&previous_item)),
+            current_display: Some(run_item_display(&item)),
+            name: item.name,
+            change,
+        });
     }
 
-    sqlx::query!(
-        r#"
-        DELETE FROM action_item_executions
-        WHERE action_plan_execution = $1
-        "#,
-        id
-    )
-    .execute(&mut *tx)
-    .await?;
+    compared.extend(previous.into_iter().map(|previous_item| CompareItem {
+        previous_display: Some(run_item_display(&previous_item)),
+        name: previous_item.name,
+        current_display: None,
+        change: "Removed from this run".to_string(),
+    }));
+    compared
+}
+
+fn describe_time_change(difference: i64) -> String {
+    if difference >= COMPARE_TOLERANCE_SECONDS {
+        format!("Took {} longer", format_duration(difference))
+    } else if difference <= -COMPARE_TOLERANCE_SECONDS {
+        format!("{} faster", format_duration(difference))
+    } else {
+        "About the same".to_string()
+    }
+}
+
+fn elapsed_display(elapsed: Option<i64>) -> String {
+    match elapsed {
+        Some(elapsed) => format!("Done after {}", format_duration(elapsed)),
+        None => "Not done".to_string(),
+    }
+}
+
+/// When the item was done and, for text and numeric items, what was entered.
+fn run_item_display(item: &CompareItemRow) -> String {
+    match &item.value_display {
+        Some(value) => format!("{} · {}", elapsed_display(item.elapsed), value),
+        None => elapsed_display(item.elapsed),
+    }
+}
+
+fn is_run_finished(run: &CompareRunRow) -> bool {
+    run.finished.is_some_and(|finished| finished > 0)
+}
+
+#[utoipa::path(
+    get,
+    path = "/executions/{id}/events",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 200, description = "Server-sent events with JSON payloads for item changes", content_type = "text/event-stream"),
+    )
+)]
+pub async fn events(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.execution_events.subscribe()).filter_map(
+        move |event| match event {
+            Ok(event) if event.execution_id == id => {
+                Event::default().json_data(&event.payload).ok().map(Ok)
+            }
+            _ => None,
+        },
+    );
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Execution lifecycle change as sent on the global `/events` stream, with fresh totals so
+/// listeners can update their counters without reloading.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GlobalExecutionEvent {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    execution_id: Uuid,
+    open_count: i64,
+    finished_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct ExecutionCounts {
+    open_count: i64,
+    finished_count: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "executions",
+    responses(
+        (status = 200, description = "Server-sent events for executions being created, completed, reopened or deleted", content_type = "text/event-stream", body = GlobalExecutionEvent),
+    )
+)]
+pub async fn global_events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let db = state.db.clone();
+    let stream = BroadcastStream::new(state.execution_events.subscribe())
+        .filter_map(|event| {
+            let event = event.ok()?;
+            let kind = event.payload.lifecycle_kind()?;
+            Some((kind, event.execution_id))
+        })
+        .then(move |(kind, execution_id)| {
+            let db = db.clone();
+            async move {
+                let counts = fetch_execution_counts(&db).await.ok()?;
+                Event::default()
+                    .json_data(GlobalExecutionEvent {
+                        kind,
+                        execution_id,
+                        open_count: counts.open_count,
+                        finished_count: counts.finished_count,
+                    })
+                    .ok()
+            }
+        })
+        .filter_map(|event| event.map(Ok));
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn fetch_execution_counts(db: &sqlx::SqlitePool) -> Result<ExecutionCounts, AppError> {
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+            COALESCE(SUM(CASE WHEN finished IS NULL OR finished <= 0 THEN 1 ELSE 0 END), 0) as "open_count!: i64",
+            COALESCE(SUM(CASE WHEN finished > 0 THEN 1 ELSE 0 END), 0) as "finished_count!: i64"
+        FROM action_plan_executions
+        "#
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(ExecutionCounts {
+        open_count: counts.open_count,
+        finished_count: counts.finished_count,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/executions/{id}/ws",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 101, description = "WebSocket for item changes, completion, reopening and presence"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+    )
+)]
+pub async fn ws_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, AppError> {
+    let execution_exists = sqlx::query_scalar!(
+        r#"SELECT id as "id: uuid::Uuid" FROM action_plan_executions WHERE id = $1"#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?;
+    if execution_exists.is_none() {
+        return Err(AppError::not_found_for(
+            "Execution",
+            format!("No todo list exists for execution id: {}", id),
+        ));
+    }
+
+    Ok(ws.on_upgrade(move |socket| collab::run(state, id, current_user, socket)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/executions/{id}/note",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    request_body(content = ExecutionNoteForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Saves the note and redirects to the execution"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+        (status = 409, description = "The execution is completed", content_type = "text/html"),
+    )
+)]
+pub async fn update_note_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<ExecutionNoteForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let note = normalize_optional_text(form.note);
+    let mut tx = state.db.begin().await?;
+
+    let execution = sqlx::query!(
+        r#"SELECT note, finished as "finished?" FROM action_plan_executions WHERE id = $1"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(execution) = execution else {
+        return Err(AppError::not_found_for(
+            "Execution",
+            format!("No execution exists for id: {}", id),
+        ));
+    };
+    if execution.finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "The note of a completed execution cannot be changed. Reopen it first.",
+        ));
+    }
+    let before = execution.note;
+
+    sqlx::query!(
+        r#"
+        UPDATE action_plan_executions
+        SET note = $1
+        WHERE id = $2
+        "#,
+        note,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if before != note {
+        let description = execution_description(&mut tx, id).await?;
+        audit::record(
+            &mut *tx,
+            &current_user,
+            AuditEntry::new(
+                AuditEntity::Execution,
+                id,
+                AuditAction::Update,
+                format!("Changed the note of {}", description),
+            )
+            .change(before, note),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Redirect::to(&format!("/executions/{}", id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/executions/{id}/label",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    request_body(content = ExecutionLabelForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Saves the label and redirects to the execution"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+    )
+)]
+pub async fn update_label_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<ExecutionLabelForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let label = normalize_optional_text(form.label);
+    let mut tx = state.db.begin().await?;
+
+    let before = sqlx::query_scalar!("SELECT label FROM action_plan_executions WHERE id = $1", id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(before) = before else {
+        return Err(AppError::not_found_for(
+            "Execution",
+            format!("No execution exists for id: {}", id),
+        ));
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE action_plan_executions
+        SET label = $1
+        WHERE id = $2
+        "#,
+        label,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if before != label {
+        let description = execution_description(&mut tx, id).await?;
+        audit::record(
+            &mut *tx,
+            &current_user,
+            AuditEntry::new(
+                AuditEntity::Execution,
+                id,
+                AuditAction::Update,
+                format!("Changed the label of {}", description),
+            )
+            .change(before, label),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Redirect::to(&format!("/executions/{}", id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/executions/{id}/complete",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 303, description = "Completes the execution and redirects to it"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+        (status = 409, description = "Not all items are checked, or the execution has no note although one is required", content_type = "text/html"),
+    )
+)]
+pub async fn complete_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    // The checks and the update share one transaction, so nobody can reopen an item or change
+    // a condition in between.
+    let mut tx = state.db.begin().await?;
+    let execution_note =
+        sqlx::query_scalar!("SELECT note FROM action_plan_executions WHERE id = $1", id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    let Some(execution_note) = execution_note else {
+        return Err(AppError::not_found_for(
+            "Execution",
+            format!("No todo list exists for execution id: {}", id),
+        ));
+    };
+
+    // Blocked items do not hold up completion, they stay open until resolved. Neither do skipped
+    // or failed items and items whose condition is not met.
+    let inapplicable = fetch_inapplicable_items(&mut tx, id).await?;
+    let open_items = sqlx::query!(
+        r#"
+        SELECT
+            id as "id!: uuid::Uuid",
+            blocked_reason IS NOT NULL AND block_resolved_at IS NULL as "is_blocked!: bool"
+        FROM action_item_executions
+        WHERE action_plan_execution = $1
+            AND (finished IS NULL OR finished <= 0)
+            AND skip_reason IS NULL
+            AND failure_note IS NULL
+        "#,
+        id
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .filter(|item| !inapplicable.contains(&item.id))
+    .collect::<Vec<_>>();
+    let blocked_count = open_items.iter().filter(|item| item.is_blocked).count();
+
+    if open_items.len() > blocked_count {
+        return Err(AppError::conflict(
+            "All items must be checked before completing this execution.",
+        ));
+    }
+    if state.config.require_execution_note && execution_note.is_none() {
+        return Err(AppError::conflict(
+            "Add a note summarizing this execution before completing it.",
+        ));
+    }
+
+    let finished_at = unix_now();
+    let result = sqlx::query!(
+        r#"
+        UPDATE action_plan_executions
+        SET finished = $1, finished_by = $2
+        WHERE id = $3
+            AND (finished IS NULL OR finished <= 0)
+        "#,
+        finished_at,
+        current_user.id,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() > 0 {
+        let description = execution_description(&mut tx, id).await?;
+        audit::record(
+            &mut *tx,
+            &current_user,
+            AuditEntry::new(
+                AuditEntity::Execution,
+                id,
+                AuditAction::Complete,
+                match blocked_count {
+                    0 => format!("Completed {}", description),
+                    1 => format!("Completed {} with 1 blocked item", description),
+                    blocked => format!("Completed {} with {} blocked items", description, blocked),
+                },
+            ),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    if result.rows_affected() > 0 {
+        events::publish(
+            &state.execution_events,
+            ExecutionEvent {
+                execution_id: id,
+                payload: ExecutionEventPayload::Completed {
+                    finished_display: format_unix_timestamp(finished_at),
+                },
+            },
+        );
+    }
+
+    Ok(Redirect::to(&format!("/executions/{}", id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/executions/{id}/reopen",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 303, description = "Reopens the execution and redirects to it"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+        (status = 409, description = "Execution is open or the reopen window has passed", content_type = "text/html"),
+    )
+)]
+pub async fn reopen_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let execution = sqlx::query!(
+        r#"
+        SELECT finished as "finished?"
+        FROM action_plan_executions
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?;
+
+    let Some(execution) = execution else {
+        return Err(AppError::not_found_for(
+            "Execution",
+            format!("No todo list exists for execution id: {}", id),
+        ));
+    };
+
+    let Some(finished_at) = execution.finished else {
+        return Err(AppError::conflict("Execution is already open."));
+    };
+
+    let reopen_window_seconds = state.config.reopen_window_seconds;
+    if finished_at <= 0 || unix_now().saturating_sub(finished_at) > reopen_window_seconds {
+        return Err(AppError::conflict(format!(
+            "Execution can only be reopened within {} hours of completion.",
+            reopen_window_seconds / (60 * 60)
+        )));
+    }
+
+    let mut tx = state.db.begin().await?;
+    sqlx::query!(
+        r#"
+        UPDATE action_plan_executions
+        SET finished = NULL, finished_by = NULL
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let description = execution_description(&mut tx, id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Execution,
+            id,
+            AuditAction::Reopen,
+            format!("Reopened {}", description),
+        ),
+    )
+    .await?;
+    tx.commit().await?;
+
+    events::publish(
+        &state.execution_events,
+        ExecutionEvent {
+            execution_id: id,
+            payload: ExecutionEventPayload::Reopened,
+        },
+    );
+
+    Ok(Redirect::to(&format!("/executions/{}", id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/executions/{id}/delete",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 303, description = "Deletes the execution and redirects to the execution list"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+        (status = 409, description = "Only open executions can be deleted", content_type = "text/html"),
+    )
+)]
+pub async fn delete_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let mut tx = state.db.begin().await?;
+
+    let execution = sqlx::query!(
+        r#"
+        SELECT finished as "finished?"
+        FROM action_plan_executions
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(execution) = execution else {
+        return Err(AppError::not_found_for(
+            "Execution",
+            format!("No todo list exists for execution id: {}", id),
+        ));
+    };
+
+    if execution.finished.map(|value| value > 0).unwrap_or(false) {
+        return Err(AppError::conflict("Only open executions can be deleted."));
+    }
+
+    let description = execution_description(&mut tx, id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Execution,
+            id,
+            AuditAction::Delete,
+            format!("Deleted {}", description),
+        ),
+    )
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM action_item_executions
+        WHERE action_plan_execution = $1
+        "#,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM execution_transfers WHERE action_plan_execution = $1",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM execution_comments WHERE action_plan_execution = $1",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let attachment_ids = attachments::delete_for_execution(&mut tx, id).await?;
+    tasks::delete_for_execution(&mut tx, id).await?;
 
     sqlx::query!(
         r#"
@@ -475,22 +1805,45 @@ pub async fn delete_post(
     .await?;
 
     tx.commit().await?;
+    attachments::remove_files(state.storage.as_ref(), attachment_ids).await;
+
+    events::publish(
+        &state.execution_events,
+        ExecutionEvent {
+            execution_id: id,
+            payload: ExecutionEventPayload::Deleted,
+        },
+    );
 
     Ok(Redirect::to("/executions"))
 }
 
+#[utoipa::path(
+    get,
+    path = "/executions/{id}/delete",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 200, description = "Delete confirmation page", content_type = "text/html"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+        (status = 409, description = "Only open executions can be deleted", content_type = "text/html"),
+    )
+)]
 pub async fn delete_get(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Path(id): Path<Uuid>,
 ) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
     let execution = sqlx::query!(
         r#"
         SELECT
             action_plan_executions.id as "id!: uuid::Uuid",
             action_plans.name as "action_plan_name!",
             action_plan_executions.started as "started!",
-            action_plan_executions.finished as "finished?"
+            action_plan_executions.finished as "finished?",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label
         FROM action_plan_executions
         INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
         WHERE action_plan_executions.id = $1
@@ -515,46 +1868,654 @@ pub async fn delete_get(
         id: execution.id,
         action_plan_name: execution.action_plan_name,
         started_display: format_unix_timestamp(execution.started),
+        run_number: execution.run_number,
+        label: execution.label,
         is_admin: current_user.is_admin,
     };
 
-    let template = state
-        .jinja
-        .get_template("execution_delete_confirm.html")
-        .expect("template is loaded");
-    let rendered = template.render(&view)?;
+    let rendered = templates::render(&state.jinja, "execution_delete_confirm.html", &view)?;
 
     Ok(Html(rendered))
 }
 
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/finished",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body = SetItemFinishedRequest,
+    responses(
+        (status = 200, description = "Updated item state", body = SetItemFinishedResponse),
+        (status = 404, description = "Execution item not found", content_type = "text/html"),
+        (status = 409, description = "The item calls for a sub-procedure without a recent enough completed run, or needs a valid value to be checked off", content_type = "text/html"),
+    )
+)]
 pub async fn set_item_finished_post(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
     Json(body): Json<SetItemFinishedRequest>,
 ) -> Result<Json<SetItemFinishedResponse>, AppError> {
-    let finished = if body.finished {
-        Some(unix_now())
-    } else {
-        None
-    };
-    let result = sqlx::query!(
-        "UPDATE action_item_executions SET finished = $1 WHERE id = $2",
-        finished,
-        id
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let FinishedItem {
+        finished_display,
+        value,
+        out_of_spec,
+    } = set_item_finished(
+        &state,
+        &current_user,
+        None,
+        id,
+        body.finished,
+        body.value.as_deref(),
     )
-    .execute(&state.db)
     .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(AppError::not_found_for(
+    Ok(Json(SetItemFinishedResponse {
+        finished_by_name: finished_display.as_ref().map(|_| current_user.name.clone()),
+        finished_display,
+        value,
+        out_of_spec,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/note",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body = SetItemNoteRequest,
+    responses(
+        (status = 200, description = "Saved note", body = SetItemNoteResponse),
+        (status = 404, description = "Execution item not found", content_type = "text/html"),
+        (status = 409, description = "The execution is completed", content_type = "text/html"),
+    )
+)]
+pub async fn set_item_note_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Json(body): Json<SetItemNoteRequest>,
+) -> Result<Json<SetItemNoteResponse>, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let note = normalize_optional_text(Some(body.note));
+    let mut tx = state.db.begin().await?;
+
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id: uuid::Uuid",
+            action_item_executions.note,
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        WHERE action_item_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
             "Execution",
             format!("No execution item exists for id: {}", id),
+        )
+    })?;
+    if item.execution_finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "Notes of a completed execution cannot be changed.",
         ));
     }
 
+    if item.note != note {
+        sqlx::query!(
+            "UPDATE action_item_executions SET note = $1 WHERE id = $2",
+            note,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let description = execution_description(&mut tx, item.execution_id).await?;
+        audit::record(
+            &mut *tx,
+            &current_user,
+            AuditEntry::new(
+                AuditEntity::ExecutionItem,
+                id,
+                AuditAction::Update,
+                format!(
+                    "Changed the note of \"{}\" in {}",
+                    item.item_name, description
+                ),
+            )
+            .change(item.note, note.clone()),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    events::publish(
+        &state.execution_events,
+        ExecutionEvent {
+            execution_id: item.execution_id,
+            payload: ExecutionEventPayload::ItemNote {
+                item_id: id,
+                note: note.clone(),
+            },
+        },
+    );
+
+    Ok(Json(SetItemNoteResponse { note }))
+}
+
+/// Checks or unchecks an execution item and notifies everyone viewing the execution. Text and
+/// numeric items are checked off with `value`, which unchecking clears.
+/// With `execution_id` set, items belonging to other executions are treated as missing.
+pub(crate) async fn set_item_finished(
+    state: &AppState,
+    current_user: &CurrentUser,
+    execution_id: Option<Uuid>,
+    item_id: Uuid,
+    finished: bool,
+    value: Option<&str>,
+) -> Result<FinishedItem, AppError> {
+    // Everything is checked inside the transaction that makes the change, so a skip, block,
+    // failure or completion of the run cannot slip in between.
+    let mut tx = state.db.begin().await?;
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id!: uuid::Uuid",
+            action_item_executions.order_index,
+            action_item_executions.item_type,
+            action_item_executions.unit,
+            action_item_executions.min_value,
+            action_item_executions.max_value,
+            action_item_executions.blocked_reason IS NOT NULL
+                AND action_item_executions.block_resolved_at IS NULL as "is_blocked!: bool",
+            action_item_executions.skip_reason IS NOT NULL as "is_skipped!: bool",
+            action_item_executions.failure_note IS NOT NULL as "is_failed!: bool",
+            actions.name as "name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        WHERE action_item_executions.id = $1
+            AND ($2 IS NULL OR action_item_executions.action_plan_execution = $2)
+        "#,
+        item_id,
+        execution_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Execution",
+            format!("No execution item exists for id: {}", item_id),
+        )
+    })?;
+    if item.execution_finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "Items of a completed execution cannot be changed. Reopen it first.",
+        ));
+    }
+
+    let value = if finished {
+        ItemType::from_db(&item.item_type)
+            .normalize_value(value)
+            .map_err(AppError::conflict)?
+    } else {
+        None
+    };
+    let out_of_spec = value
+        .as_deref()
+        .is_some_and(|value| item_types::is_out_of_spec(value, item.min_value, item.max_value));
+
+    if finished {
+        if item.is_blocked {
+            return Err(AppError::conflict(format!(
+                "\"{}\" is blocked. Resolve the block before checking it off.",
+                item.name
+            )));
+        }
+        if item.is_skipped {
+            return Err(AppError::conflict(format!(
+                "\"{}\" was skipped. Take it back into the run before checking it off.",
+                item.name
+            )));
+        }
+        if item.is_failed {
+            return Err(AppError::conflict(format!(
+                "\"{}\" is marked as failed. Take the failure back before checking it off.",
+                item.name
+            )));
+        }
+        if fetch_inapplicable_items(&mut tx, item.execution_id)
+            .await?
+            .contains(&item_id)
+        {
+            return Err(AppError::conflict(
+                "This item only applies once the step it depends on was answered accordingly.",
+            ));
+        }
+        if let Some(open_count) = fetch_open_sub_items(&mut tx, item.execution_id)
+            .await?
+            .get(&item.order_index)
+        {
+            return Err(AppError::conflict(format!(
+                "{} of this item's sub-items {} still open. Deal with them before checking it off.",
+                open_count,
+                if *open_count == 1 { "is" } else { "are" }
+            )));
+        }
+        link_completed_sub_procedure(&mut tx, &state.config, item_id).await?;
+    }
+
+    let finished = if finished { Some(unix_now()) } else { None };
+    // The time an item took is counted from the last item checked off before it, or from the
+    // start of the run for the first one.
+    sqlx::query!(
+        r#"
+        UPDATE action_item_executions
+        SET
+            finished = $1,
+            finished_by = CASE WHEN $1 IS NULL THEN NULL ELSE $3 END,
+            scanned_by = NULL,
+            value = $4,
+            out_of_spec = $5,
+            actual_seconds = CASE
+                WHEN $1 IS NULL THEN NULL
+                ELSE MAX(0, $1 - MAX(
+                    (
+                        SELECT started
+                        FROM action_plan_executions
+                        WHERE action_plan_executions.id = action_item_executions.action_plan_execution
+                    ),
+                    COALESCE((
+                        SELECT MAX(other_items.finished)
+                        FROM action_item_executions AS other_items
+                        WHERE other_items.action_plan_execution = action_item_executions.action_plan_execution
+                            AND other_items.id != action_item_executions.id
+                            AND other_items.finished > 0
+                    ), 0)
+                ))
+            END
+        WHERE id = $2
+        "#,
+        finished,
+        item_id,
+        current_user.id,
+        value,
+        out_of_spec
+    )
+    .execute(&mut *tx)
+    .await?;
+    let description = execution_description(&mut tx, item.execution_id).await?;
+    let summary = match (finished, &value) {
+        (Some(_), Some(value)) => format!(
+            "Checked off \"{}\" with {}{} in {}",
+            item.name,
+            item_types::value_display(value, item.unit.as_deref()),
+            if out_of_spec { " (out of spec)" } else { "" },
+            description
+        ),
+        (Some(_), None) => format!("Checked off \"{}\" in {}", item.name, description),
+        (None, _) => format!("Unchecked \"{}\" in {}", item.name, description),
+    };
+    audit::record(
+        &mut *tx,
+        current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            item_id,
+            AuditAction::Update,
+            summary,
+        ),
+    )
+    .await?;
+    let parent_event = update_parent_item(&mut tx, current_user, item_id).await?;
+    tx.commit().await?;
+
     let finished_display = finished.map(format_unix_timestamp);
+    events::publish(
+        &state.execution_events,
+        ExecutionEvent {
+            execution_id: item.execution_id,
+            payload: ExecutionEventPayload::ItemFinished {
+                item_id,
+                is_finished: finished.is_some(),
+                finished_display: finished_display.clone(),
+                finished_by_name: finished.map(|_| current_user.name.clone()),
+                value: value.clone(),
+                out_of_spec,
+            },
+        },
+    );
+    if let Some(event) = parent_event {
+        events::publish(&state.execution_events, event);
+    }
+
+    Ok(FinishedItem {
+        finished_display,
+        value,
+        out_of_spec,
+    })
+}
+
+/// Items of a run whose condition is not met.
+async fn fetch_inapplicable_items(
+    conn: &mut SqliteConnection,
+    execution_id: Uuid,
+) -> Result<HashSet<Uuid>, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id as "id!: uuid::Uuid",
+            order_index,
+            condition_index,
+            condition_value,
+            condition_failed as "condition_failed: bool",
+            finished IS NOT NULL AND finished > 0 as "is_finished!: bool",
+            failure_note IS NOT NULL as "is_failed!: bool",
+            value
+        FROM action_item_executions
+        WHERE action_plan_execution = $1
+        "#,
+        execution_id
+    )
+    .fetch_all(conn)
+    .await?;
+    let applicable = conditions::applicable(
+        &rows
+            .iter()
+            .map(|row| ConditionItem {
+                order_index: row.order_index,
+                condition_index: row.condition_index,
+                condition_value: row.condition_value.as_deref(),
+                condition_failed: row.condition_failed,
+                is_finished: row.is_finished,
+                is_failed: row.is_failed,
+                value: row.value.as_deref(),
+            })
+            .collect::<Vec<_>>(),
+    );
+    Ok(rows
+        .iter()
+        .zip(applicable)
+        .filter(|(_, applies)| !applies)
+        .map(|(row, _)| row.id)
+        .collect())
+}
+
+/// Number of open sub-items of a run, by order index of their parent.
+async fn fetch_open_sub_items(
+    conn: &mut SqliteConnection,
+    execution_id: Uuid,
+) -> Result<HashMap<i64, usize>, AppError> {
+    let inapplicable = fetch_inapplicable_items(&mut *conn, execution_id).await?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id as "id!: uuid::Uuid",
+            parent_index,
+            finished IS NOT NULL AND finished > 0 as "is_finished!: bool",
+            skip_reason IS NOT NULL
+                OR failure_note IS NOT NULL
+                OR (blocked_reason IS NOT NULL AND block_resolved_at IS NULL)
+                as "is_set_aside!: bool"
+        FROM action_item_executions
+        WHERE action_plan_execution = $1
+            AND parent_index IS NOT NULL
+        "#,
+        execution_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(sub_items::open_counts(
+        &rows
+            .iter()
+            .map(|row| SubItem {
+                parent_index: row.parent_index,
+                is_done: row.is_finished || row.is_set_aside || inapplicable.contains(&row.id),
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Keeps the parent of `item_id` in step with its sub-items: a plain parent is checked off once
+/// the last sub-item is dealt with, and any checked parent is unchecked when one of them is
+/// reopened. Parents that capture a value, call for a sub-procedure or are held up themselves
+/// are left for someone to check off. Runs in the caller's transaction and returns the event
+/// to publish once it is committed, if the parent changed.
+pub(crate) async fn update_parent_item(
+    conn: &mut SqliteConnection,
+    current_user: &CurrentUser,
+    item_id: Uuid,
+) -> Result<Option<ExecutionEvent>, AppError> {
+    let parent = sqlx::query!(
+        r#"
+        SELECT
+            parents.id as "id!: uuid::Uuid",
+            parents.action_plan_execution as "execution_id!: uuid::Uuid",
+            parents.order_index,
+            parents.finished as "finished?",
+            parents.item_type,
+            parents.sub_plan IS NOT NULL
+                OR parents.skip_reason IS NOT NULL
+                OR parents.failure_note IS NOT NULL
+                OR (parents.blocked_reason IS NOT NULL AND parents.block_resolved_at IS NULL)
+                as "is_manual!: bool",
+            actions.name as "name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions AS children
+        INNER JOIN action_item_executions AS parents
+            ON parents.action_plan_execution = children.action_plan_execution
+            AND parents.order_index = children.parent_index
+        INNER JOIN actions ON actions.id = parents.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = parents.action_plan_execution
+        WHERE children.id = $1
+        "#,
+        item_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(parent) = parent else {
+        return Ok(None);
+    };
+    if parent
+        .execution_finished
+        .is_some_and(|finished| finished > 0)
+    {
+        return Ok(None);
+    }
+    let is_finished = parent.finished.is_some_and(|finished| finished > 0);
+    let has_open_sub_items = fetch_open_sub_items(&mut *conn, parent.execution_id)
+        .await?
+        .contains_key(&parent.order_index);
+    let finished = match (is_finished, has_open_sub_items) {
+        (false, false)
+            if ItemType::from_db(&parent.item_type) == ItemType::Checkbox && !parent.is_manual =>
+        {
+            Some(unix_now())
+        }
+        (true, true) => None,
+        _ => return Ok(None),
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE action_item_executions
+        SET
+            finished = $1,
+            finished_by = CASE WHEN $1 IS NULL THEN NULL ELSE $2 END,
+            scanned_by = NULL,
+            value = NULL,
+            out_of_spec = 0,
+            actual_seconds = NULL
+        WHERE id = $3
+        "#,
+        finished,
+        current_user.id,
+        parent.id
+    )
+    .execute(&mut *conn)
+    .await?;
+    let description = execution_description(&mut *conn, parent.execution_id).await?;
+    let summary = match finished {
+        Some(_) => format!(
+            "Checked off \"{}\" with its last sub-item in {}",
+            parent.name, description
+        ),
+        None => format!(
+            "Unchecked \"{}\" in {} as one of its sub-items is open again",
+            parent.name, description
+        ),
+    };
+    audit::record(
+        &mut *conn,
+        current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            parent.id,
+            AuditAction::Update,
+            summary,
+        ),
+    )
+    .await?;
+
+    Ok(Some(ExecutionEvent {
+        execution_id: parent.execution_id,
+        payload: ExecutionEventPayload::ItemFinished {
+            item_id: parent.id,
+            is_finished: finished.is_some(),
+            finished_display: finished.map(format_unix_timestamp),
+            finished_by_name: finished.map(|_| current_user.name.clone()),
+            value: None,
+            out_of_spec: false,
+        },
+    }))
+}
+
+/// `"Plan" run #3 (label)`, to name an execution in the audit log.
+pub(crate) async fn execution_description(
+    conn: &mut SqliteConnection,
+    execution_id: Uuid,
+) -> Result<String, AppError> {
+    let execution = sqlx::query!(
+        r#"
+        SELECT
+            action_plans.name as "plan_name!",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label
+        FROM action_plan_executions
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_plan_executions.id = $1
+        "#,
+        execution_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let mut description = format!("\"{}\" run #{}", execution.plan_name, execution.run_number);
+    if let Some(label) = execution.label {
+        description.push_str(&format!(" ({})", label));
+    }
+    Ok(description)
+}
+
+/// Items calling for a sub-procedure can only be checked off with a completed run of that plan
+/// that is at most `sub_procedure_max_age_seconds` old. The linked run counts if it qualifies,
+/// otherwise the newest qualifying run of the plan is linked instead.
+async fn link_completed_sub_procedure(
+    conn: &mut SqliteConnection,
+    config: &Config,
+    item_id: Uuid,
+) -> Result<(), AppError> {
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.sub_plan as "sub_plan: uuid::Uuid",
+            action_plans.name as "sub_plan_name?",
+            linked_executions.finished as "linked_finished?",
+            CASE WHEN linked_executions.id IS NULL THEN 0 ELSE 1 END as "has_linked_run!: i64"
+        FROM action_item_executions
+        LEFT JOIN action_plans ON action_plans.id = action_item_executions.sub_plan
+        LEFT JOIN action_plan_executions AS linked_executions
+            ON linked_executions.id = action_item_executions.linked_execution
+        WHERE action_item_executions.id = $1
+        "#,
+        item_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    // Missing items are reported by the caller's update.
+    let Some(item) = item else {
+        return Ok(());
+    };
+    let Some(sub_plan) = item.sub_plan else {
+        return Ok(());
+    };
+
+    let max_age = config.sub_procedure_max_age_seconds;
+    let completed_since = if max_age > 0 {
+        unix_now().saturating_sub(max_age)
+    } else {
+        0
+    };
+    if item
+        .linked_finished
+        .is_some_and(|finished| finished > 0 && finished >= completed_since)
+    {
+        return Ok(());
+    }
+
+    let newest_run = sqlx::query_scalar!(
+        r#"
+        SELECT id as "id: uuid::Uuid"
+        FROM action_plan_executions
+        WHERE action_plan = $1
+            AND finished > 0
+            AND finished >= $2
+        ORDER BY finished DESC
+        LIMIT 1
+        "#,
+        sub_plan,
+        completed_since
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(newest_run) = newest_run {
+        sqlx::query!(
+            "UPDATE action_item_executions SET linked_execution = $1 WHERE id = $2",
+            newest_run,
+            item_id
+        )
+        .execute(&mut *conn)
+        .await?;
+        return Ok(());
+    }
 
-    Ok(Json(SetItemFinishedResponse { finished_display }))
+    let plan_name = item.sub_plan_name.unwrap_or_default();
+    let linked_in_progress =
+        item.has_linked_run != 0 && item.linked_finished.is_none_or(|finished| finished <= 0);
+    Err(AppError::conflict(if linked_in_progress {
+        format!(
+            "Complete the linked run of \"{}\" before checking off this item.",
+            plan_name
+        )
+    } else if max_age > 0 {
+        format!(
+            "This item needs a run of \"{}\" completed within the last {}.",
+            plan_name,
+            format_duration(max_age)
+        )
+    } else {
+        format!("This item needs a completed run of \"{}\".", plan_name)
+    }))
 }
 
 #[derive(Serialize)]
@@ -563,40 +2524,270 @@ struct ActionPlanExecutionShow {
     action_plan_id: Uuid,
     action_plan_name: String,
     started_display: String,
+    started_by_name: Option<String>,
     finished_display: Option<String>,
+    finished_by_name: Option<String>,
     note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    merged_from_name: Option<String>,
     is_completed: bool,
     can_reopen: bool,
     is_action_plan_deleted: bool,
     can_complete: bool,
-    items: Vec<ExecutionItem>,
+    /// A note is required to complete the execution and has not been written yet.
+    is_note_missing: bool,
+    /// Lets the page keep the pacing display up to date without reloading.
+    started_unix: i64,
+    estimated_total_display: Option<String>,
+    /// Estimates of the items still to do, for planning the rest of a shift.
+    estimated_remaining_display: String,
+    actual_total_display: Option<String>,
+    item_groups: Vec<ItemGroup>,
+    has_items: bool,
+    group: ItemGrouping,
+    has_assignments: bool,
+    assignee_options: Vec<assignees::AssigneeOption>,
+    can_run_executions: bool,
+    export_formats: Vec<ExportFormatOption>,
+    handover: transfers::HandoverSection,
+    comments: comments::CommentSection,
+    /// Reference documents of the plan.
+    plan_attachments: Vec<attachments::PlanAttachment>,
+    /// Set while the current user has not reviewed changes to the procedure.
+    procedure_change: Option<procedure_changes::ProcedureChangeNotice>,
+    /// Allowed attachment types for the file picker.
+    attachment_accept: String,
     is_admin: bool,
 }
 
+#[derive(Serialize)]
+struct ExportFormatOption {
+    value: &'static str,
+    label: &'static str,
+}
+
 #[derive(Serialize)]
 struct ExecutionItem {
     id: Uuid,
     name: String,
+    /// The action's description, rendered from Markdown.
+    description_html: Option<String>,
+    /// Instructions the plan gives for this item, as of the start of the run.
+    instructions: Option<String>,
+    /// Reference links of the action.
+    links: Vec<ActionLink>,
+    item_type: ItemType,
+    unit: Option<String>,
+    /// Expected range of a numeric item with its unit.
+    range_display: Option<String>,
+    /// What was entered when checking off a text or numeric item.
+    value: Option<String>,
+    /// Set when the reading lies outside the expected range.
+    out_of_spec: bool,
+    order_index: i64,
+    /// Order index of the earlier item this one depends on, for updating it live.
+    condition_index: Option<i64>,
+    condition_value: Option<String>,
+    condition_failed: bool,
+    condition_display: Option<String>,
+    /// False while the item's condition is not met. Such items are hidden.
+    applies: bool,
+    /// Section heading the plan shows above this item.
+    section: Option<String>,
+    /// Order index of the item this one is nested under.
+    parent_index: Option<i64>,
+    has_sub_items: bool,
+    /// Sub-items that are neither checked off, skipped, failed nor left out by a condition.
+    open_sub_item_count: usize,
     is_finished: bool,
     finished_display: Option<String>,
+    estimated_seconds: Option<i64>,
+    estimate_display: Option<String>,
+    actual_display: Option<String>,
+    sub_procedure: Option<SubProcedure>,
+    /// Set when the item was checked off by scanning its code at the maintenance point.
+    scanned_by_name: Option<String>,
+    finished_by_name: Option<String>,
+    assigned_to: Option<Uuid>,
+    assignee_name: Option<String>,
+    note: Option<String>,
+    block: Option<ItemBlock>,
+    skip: Option<ItemSkip>,
+    failure: Option<ItemFailure>,
+    attachments: Vec<ItemAttachment>,
+}
+
+/// Items under one heading of the checklist. Without grouping there is a single untitled group.
+#[derive(Serialize)]
+struct ItemGroup {
+    title: Option<String>,
+    done_count: usize,
+    items: Vec<ExecutionItem>,
+}
+
+/// Another plan an item calls for, and the run of it that the item is checked off with.
+#[derive(Serialize)]
+struct SubProcedure {
+    plan_id: Uuid,
+    plan_name: String,
+    is_plan_deleted: bool,
+    linked_run: Option<LinkedRun>,
+    /// How recent a completed run must be, `None` when any age is accepted.
+    max_age_display: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LinkedRun {
+    id: Uuid,
+    run_number: i64,
+    /// `None` while the run is in progress.
+    finished_display: Option<String>,
+}
+
+struct CompareRunRow {
+    id: Uuid,
+    action_plan_id: Uuid,
+    action_plan_name: String,
+    run_number: i64,
+    started: i64,
+    finished: Option<i64>,
+    note: Option<String>,
+}
+
+struct CompareItemRow {
+    action_id: Uuid,
+    name: String,
+    /// Seconds from the start of the run until the item was checked off.
+    elapsed: Option<i64>,
+    value_display: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CompareRunSummary {
+    id: Uuid,
+    run_number: i64,
+    started_display: String,
+    duration_display: Option<String>,
+    note: Option<String>,
+}
+
+impl CompareRunSummary {
+    fn from_row(row: &CompareRunRow) -> Self {
+        Self {
+            id: row.id,
+            run_number: row.run_number,
+            started_display: format_unix_timestamp(row.started),
+            duration_display: row
+                .finished
+                .filter(|finished| *finished > 0)
+                .map(|finished| format_duration(finished - row.started)),
+            note: row.note.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CompareItem {
+    name: String,
+    /// `None` when the item was not part of that run.
+    previous_display: Option<String>,
+    current_display: Option<String>,
+    change: String,
+}
+
+#[derive(Serialize)]
+struct ExecutionCompareView {
+    id: Uuid,
+    action_plan_name: String,
+    current: CompareRunSummary,
+    previous: Option<CompareRunSummary>,
+    notes_differ: bool,
+    items: Vec<CompareItem>,
+    is_admin: bool,
 }
 
 #[derive(FromRow)]
 struct ExecutionItemRow {
     id: Uuid,
+    action_id: Uuid,
     name: String,
+    description: Option<String>,
+    instructions: Option<String>,
+    item_type: String,
+    unit: Option<String>,
+    value: Option<String>,
+    min_value: Option<f64>,
+    max_value: Option<f64>,
+    out_of_spec: bool,
+    order_index: i64,
+    condition_index: Option<i64>,
+    condition_value: Option<String>,
+    condition_failed: bool,
+    section: Option<String>,
+    parent_index: Option<i64>,
     finished: Option<i64>,
     is_finished: i64,
+    estimated_minutes: Option<i64>,
+    actual_seconds: Option<i64>,
+    sub_plan: Option<Uuid>,
+    sub_plan_name: Option<String>,
+    sub_plan_deleted_at: Option<i64>,
+    linked_execution_id: Option<Uuid>,
+    linked_run_number: Option<i64>,
+    linked_finished: Option<i64>,
+    scanned_by_name: Option<String>,
+    finished_by_name: Option<String>,
+    assigned_to: Option<Uuid>,
+    assignee_name: Option<String>,
+    note: Option<String>,
+    blocked_reason: Option<String>,
+    blocked_at: Option<i64>,
+    blocked_by_name: Option<String>,
+    block_resolved_at: Option<i64>,
+    block_resolved_by_name: Option<String>,
+    block_resolution: Option<String>,
+    skip_reason: Option<String>,
+    skipped_at: Option<i64>,
+    skipped_by_name: Option<String>,
+    failure_note: Option<String>,
+    failed_at: Option<i64>,
+    failed_by_name: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct SetItemFinishedRequest {
     finished: bool,
+    /// Required to check off text and numeric items, ignored otherwise.
+    #[serde(default)]
+    value: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, ToSchema)]
+pub struct SetItemNoteRequest {
+    /// Empty removes the note.
+    note: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SetItemNoteResponse {
+    note: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct SetItemFinishedResponse {
     finished_display: Option<String>,
+    finished_by_name: Option<String>,
+    value: Option<String>,
+    /// Set when the reading lies outside the item's expected range.
+    out_of_spec: bool,
+}
+
+/// The state of an item after checking or unchecking it.
+pub(crate) struct FinishedItem {
+    pub finished_display: Option<String>,
+    pub value: Option<String>,
+    pub out_of_spec: bool,
 }
 
 #[derive(FromRow)]
@@ -608,13 +2799,23 @@ struct ActionPlanExecutionShowRow {
     started: i64,
     finished: Option<i64>,
     note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    merged_from_name: Option<String>,
+    started_by_name: Option<String>,
+    finished_by_name: Option<String>,
 }
 
 #[derive(Serialize)]
 struct ActionPlanExecutionList {
     unfinished_executions: Vec<UnfinishedExecutionListItem>,
     finished_executions: Vec<FinishedExecutionListItem>,
+    counts: ExecutionCounts,
     search_query: String,
+    archive_available: bool,
+    include_archive: bool,
+    archived_executions: Vec<archive::ArchivedExecutionListItem>,
+    blocked_items: Vec<OpenBlock>,
     is_admin: bool,
 }
 
@@ -623,7 +2824,12 @@ struct UnfinishedExecutionListItem {
     id: Uuid,
     action_plan_name: String,
     started_display: String,
+    started_by_name: Option<String>,
     note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    /// Readings outside their expected range.
+    out_of_spec_count: i64,
 }
 
 #[derive(FromRow, Serialize)]
@@ -631,8 +2837,13 @@ struct FinishedExecutionListItem {
     id: Uuid,
     action_plan_name: String,
     started_display: String,
+    started_by_name: Option<String>,
     finished_display: String,
+    finished_by_name: Option<String>,
     note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    out_of_spec_count: i64,
 }
 
 #[derive(FromRow)]
@@ -641,6 +2852,10 @@ struct UnfinishedExecutionListItemRow {
     action_plan_name: String,
     started: i64,
     note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    started_by_name: Option<String>,
+    out_of_spec_count: i64,
 }
 
 #[derive(FromRow)]
@@ -650,34 +2865,155 @@ struct FinishedExecutionListItemRow {
     started: i64,
     finished: i64,
     note: Option<String>,
+    run_number: i64,
+    label: Option<String>,
+    started_by_name: Option<String>,
+    finished_by_name: Option<String>,
+    out_of_spec_count: i64,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ExecutionNoteForm {
     note: Option<String>,
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct ExecutionLabelForm {
+    label: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BatchExecutionRequest {
+    /// Plans to start, in this order.
+    #[serde(default)]
+    plan_ids: Vec<Uuid>,
+    /// Also starts every plan with this tag, after the ones listed in `plan_ids`.
+    tag_id: Option<Uuid>,
+    /// Label for every new execution, e.g. "Q1 2027".
+    label: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchExecutionResponse {
+    created: Vec<BatchExecutionCreated>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchExecutionCreated {
+    plan_id: Uuid,
+    plan_name: String,
+    execution_id: Uuid,
+    run_number: i64,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateExecutionForm {
+    /// Optional human-readable name such as "KW 34 round".
+    label: Option<String>,
+    /// Execution item calling for this plan as a sub-procedure. The new run is linked to it.
+    parent_item: Option<Uuid>,
+}
+
 #[derive(Serialize)]
 struct DeleteExecutionConfirm {
     id: Uuid,
     action_plan_name: String,
     started_display: String,
+    run_number: i64,
+    label: Option<String>,
     is_admin: bool,
 }
 
-fn unix_now() -> i64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|duration| duration.as_secs() as i64)
-        .unwrap_or(0)
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExecutionShowQuery {
+    /// `assignee` groups the checklist by who the items are assigned to.
+    #[serde(default)]
+    group: ItemGrouping,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemGrouping {
+    /// The order of the plan.
+    #[default]
+    Plan,
+    Assignee,
+}
+
+impl ItemGrouping {
+    /// Query string that shows an execution grouped this way.
+    pub fn query(self) -> &'static str {
+        match self {
+            ItemGrouping::Plan => "",
+            ItemGrouping::Assignee => "?group=assignee",
+        }
+    }
+}
+
+/// One group per section heading of the plan, in plan order. Items before the first heading
+/// form a group without a title.
+fn group_by_section(items: Vec<ExecutionItem>) -> Vec<ItemGroup> {
+    let mut groups: Vec<ItemGroup> = Vec::new();
+    for item in items {
+        let starts_group = groups.is_empty() || item.section.is_some();
+        if starts_group {
+            groups.push(ItemGroup {
+                title: item.section.clone(),
+                done_count: 0,
+                items: Vec::new(),
+            });
+        }
+        let group = groups.last_mut().expect("a group was just added");
+        if item.is_finished {
+            group.done_count += 1;
+        }
+        group.items.push(item);
+    }
+    if groups.is_empty() {
+        groups.push(ItemGroup {
+            title: None,
+            done_count: 0,
+            items: Vec::new(),
+        });
+    }
+    groups
+}
+
+/// One group per assignee alphabetically, unassigned items last, keeping the plan order within
+/// each group.
+fn group_by_assignee(items: Vec<ExecutionItem>) -> Vec<ItemGroup> {
+    let mut groups: BTreeMap<(bool, String, Option<String>), Vec<ExecutionItem>> = BTreeMap::new();
+    for item in items {
+        let key = (
+            item.assignee_name.is_none(),
+            item.assignee_name
+                .as_deref()
+                .map(str::to_lowercase)
+                .unwrap_or_default(),
+            item.assignee_name.clone(),
+        );
+        groups.entry(key).or_default().push(item);
+    }
+    groups
+        .into_iter()
+        .map(|((_, _, name), items)| ItemGroup {
+            title: Some(name.unwrap_or_else(|| "Unassigned".to_string())),
+            done_count: items.iter().filter(|item| item.is_finished).count(),
+            items,
+        })
+        .collect()
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ExecutionListQuery {
     q: Option<String>,
+    /// Also list executions moved to the archive database.
+    archive: Option<bool>,
 }
 
-fn normalize_note(note: Option<String>) -> Option<String> {
+fn normalize_optional_text(note: Option<String>) -> Option<String> {
     note.and_then(|value| {
         let trimmed = value.trim();
         if trimmed.is_empty() {
@@ -0,0 +1,281 @@
+//! Marking items as failed when they were done but did not pass, like a pump that fails its
+//! pressure test. A failed item counts as dealt with when completing the execution, and failing
+//! it opens a follow-up task so the defect is not forgotten afterwards.
+
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, authorize},
+    events,
+    executions::{self, ItemGrouping},
+    format_unix_timestamp, tasks, unix_now,
+};
+
+/// Longest failure note accepted, in characters.
+const MAX_NOTE_CHARS: usize = 1000;
+
+/// What was wrong with a failed item and who found it.
+#[derive(Debug, Serialize)]
+pub struct ItemFailure {
+    note: String,
+    failed_display: String,
+    failed_by_name: Option<String>,
+}
+
+impl ItemFailure {
+    pub fn new(
+        note: Option<String>,
+        failed_at: Option<i64>,
+        failed_by_name: Option<String>,
+    ) -> Option<Self> {
+        Some(ItemFailure {
+            note: note?,
+            failed_display: failed_at.map(format_unix_timestamp).unwrap_or_default(),
+            failed_by_name,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FailItemForm {
+    /// What is wrong, e.g. "pressure drops to 1.2 bar within a minute".
+    note: String,
+    /// How the execution page was grouped, to return to the same view.
+    #[serde(default)]
+    group: ItemGrouping,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UnfailItemForm {
+    #[serde(default)]
+    group: ItemGrouping,
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/fail",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body(content = FailItemForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Marks the item as failed, opens a follow-up task and redirects to its execution"),
+        (status = 404, description = "Execution item not found", content_type = "text/html"),
+        (status = 409, description = "No note given, the item is checked off, skipped or failed already, or the execution is completed", content_type = "text/html"),
+    )
+)]
+pub async fn fail_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<FailItemForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let note = form.note.trim().to_string();
+    if note.is_empty() {
+        return Err(AppError::conflict(
+            "Describe what is wrong, so the defect can be followed up.",
+        ));
+    }
+    if note.chars().count() > MAX_NOTE_CHARS {
+        return Err(AppError::conflict(format!(
+            "Failure notes can have at most {} characters.",
+            MAX_NOTE_CHARS
+        )));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id: uuid::Uuid",
+            action_item_executions.finished as "finished?",
+            action_item_executions.skip_reason,
+            action_item_executions.failure_note,
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        WHERE action_item_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Execution",
+            format!("No execution item exists for id: {}", id),
+        )
+    })?;
+    if item.execution_finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "Items of a completed execution cannot be marked as failed.",
+        ));
+    }
+    if item.finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(format!(
+            "\"{}\" is already checked off, uncheck it before marking it as failed.",
+            item.item_name
+        )));
+    }
+    if item.skip_reason.is_some() {
+        return Err(AppError::conflict(format!(
+            "\"{}\" was skipped. Take it back into the run before marking it as failed.",
+            item.item_name
+        )));
+    }
+    if item.failure_note.is_some() {
+        return Err(AppError::conflict(format!(
+            "\"{}\" is already marked as failed.",
+            item.item_name
+        )));
+    }
+
+    let failed_at = unix_now();
+    sqlx::query!(
+        r#"
+        UPDATE action_item_executions
+        SET failure_note = $1, failed_at = $2, failed_by = $3
+        WHERE id = $4
+        "#,
+        note,
+        failed_at,
+        current_user.id,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tasks::create_for_failed_item(&mut tx, &current_user, id, &note).await?;
+
+    let description = executions::execution_description(&mut tx, item.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            id,
+            AuditAction::Update,
+            format!("Marked \"{}\" in {} as failed", item.item_name, description),
+        )
+        .change(None, Some(note)),
+    )
+    .await?;
+    let parent_event = executions::update_parent_item(&mut tx, &current_user, id).await?;
+    tx.commit().await?;
+    if let Some(event) = parent_event {
+        events::publish(&state.execution_events, event);
+    }
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}{}",
+        item.execution_id,
+        form.group.query()
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/unfail",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body(content = UnfailItemForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Takes the failure back, removes its follow-up task and redirects to the item's execution"),
+        (status = 404, description = "Execution item not found", content_type = "text/html"),
+        (status = 409, description = "The item is not failed or the execution is completed", content_type = "text/html"),
+    )
+)]
+pub async fn unfail_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<UnfailItemForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let mut tx = state.db.begin().await?;
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id: uuid::Uuid",
+            action_item_executions.failure_note,
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        WHERE action_item_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Execution",
+            format!("No execution item exists for id: {}", id),
+        )
+    })?;
+    let Some(note) = item.failure_note else {
+        return Err(AppError::conflict(format!(
+            "\"{}\" is not marked as failed.",
+            item.item_name
+        )));
+    };
+    if item.execution_finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "Failed items of a completed execution stay on record. Reopen the execution first.",
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE action_item_executions
+        SET failure_note = NULL, failed_at = NULL, failed_by = NULL
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tasks::delete_for_item(&mut tx, id).await?;
+
+    let description = executions::execution_description(&mut tx, item.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            id,
+            AuditAction::Update,
+            format!(
+                "Took back the failure of \"{}\" in {}",
+                item.item_name, description
+            ),
+        )
+        .change(Some(note), None),
+    )
+    .await?;
+    let parent_event = executions::update_parent_item(&mut tx, &current_user, id).await?;
+    tx.commit().await?;
+    if let Some(event) = parent_event {
+        events::publish(&state.execution_events, event);
+    }
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}{}",
+        item.execution_id,
+        form.group.query()
+    )))
+}
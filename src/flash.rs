@@ -0,0 +1,51 @@
+use axum_extra::extract::cookie::CookieJar;
+use uuid::Uuid;
+
+use crate::{config::Config, users};
+
+/// Seconds during which a deleted action plan can still be restored from the undo notice.
+pub const UNDO_GRACE_PERIOD_SECONDS: i64 = 60 * 5;
+
+/// One-shot message carried across a redirect and consumed by the next page render.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flash {
+    PlanDeleted { plan_id: Uuid },
+}
+
+impl Flash {
+    fn encode(&self) -> String {
+        match self {
+            Flash::PlanDeleted { plan_id } => format!("plan_deleted:{}", plan_id),
+        }
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        let (kind, payload) = value.split_once(':')?;
+        match kind {
+            "plan_deleted" => Uuid::parse_str(payload)
+                .ok()
+                .map(|plan_id| Flash::PlanDeleted { plan_id }),
+            _ => None,
+        }
+    }
+}
+
+pub fn set(jar: CookieJar, config: &Config, flash: Flash) -> CookieJar {
+    jar.add(users::session_scoped_cookie(
+        config,
+        config.flash_cookie_name(),
+        flash.encode(),
+    ))
+}
+
+/// Reads the pending flash, if any, and removes it from the jar so it is only shown once.
+pub fn take(jar: CookieJar, config: &Config) -> (CookieJar, Option<Flash>) {
+    let name = config.flash_cookie_name();
+    let Some(cookie) = jar.get(&name) else {
+        return (jar, None);
+    };
+    let flash = Flash::decode(cookie.value());
+
+    let removal_cookie = users::session_scoped_cookie(config, name, String::new());
+    (jar.remove(removal_cookie), flash)
+}
@@ -0,0 +1,288 @@
+//! Photos attached to execution items, gathered on one page: all photos of a run, or those of
+//! a plan across its runs, like the filter photos of the last six months. Only attachments
+//! with a thumbnail count as photos, so quarantined files never show up here.
+
+use axum::{
+    extract::{Path, Query, State},
+    response::Html,
+};
+use chrono::{Local, Months};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::{AppError, AppState, CurrentUser, action_plan, format_unix_timestamp, templates};
+
+/// Periods offered on the plan gallery, in months. 0 stands for all runs.
+const MONTH_OPTIONS: [u32; 5] = [1, 3, 6, 12, 0];
+const DEFAULT_MONTHS: u32 = 6;
+/// Photos shown at most on the plan gallery, newest first.
+const PLAN_GALLERY_LIMIT: i64 = 500;
+
+#[derive(Debug, Serialize)]
+struct GalleryPhoto {
+    id: Uuid,
+    file_name: String,
+    caption: Option<String>,
+    item_name: String,
+    uploaded_display: String,
+    uploaded_by_name: String,
+    /// The run the photo was taken in, on the plan gallery.
+    run: Option<GalleryRun>,
+}
+
+#[derive(Debug, Serialize)]
+struct GalleryRun {
+    execution_id: Uuid,
+    run_number: i64,
+    label: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExecutionGalleryView {
+    execution_id: Uuid,
+    action_plan_id: Uuid,
+    action_plan_name: String,
+    run_number: i64,
+    photos: Vec<GalleryPhoto>,
+    is_admin: bool,
+}
+
+#[derive(Serialize)]
+struct PlanGalleryView {
+    action_plan_id: Uuid,
+    action_plan_name: String,
+    months: u32,
+    month_options: [u32; 5],
+    /// Items with photos in the period, to narrow the gallery down to one.
+    actions: Vec<GalleryAction>,
+    selected_action: Option<Uuid>,
+    photos: Vec<GalleryPhoto>,
+    is_truncated: bool,
+    is_admin: bool,
+}
+
+#[derive(Serialize)]
+struct GalleryAction {
+    id: Uuid,
+    name: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PlanGalleryQuery {
+    /// Months back from today to show photos of, 6 by default. 0 shows all runs.
+    months: Option<u32>,
+    /// Only photos of this item.
+    #[serde(default, deserialize_with = "action_plan::deserialize_optional_uuid")]
+    action: Option<Uuid>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/executions/{id}/gallery",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 200, description = "All photos attached to the items of the execution, in plan order, with their captions and upload times", content_type = "text/html"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+    )
+)]
+pub async fn execution_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Html<String>, AppError> {
+    let execution = sqlx::query!(
+        r#"
+        SELECT
+            action_plan_executions.action_plan as "action_plan_id: uuid::Uuid",
+            action_plans.name as "action_plan_name!",
+            action_plan_executions.run_number as "run_number!"
+        FROM action_plan_executions
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_plan_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for("Execution", format!("No execution exists for id: {}", id))
+    })?;
+
+    let photos = sqlx::query!(
+        r#"
+        SELECT
+            execution_item_attachments.id as "id: uuid::Uuid",
+            execution_item_attachments.file_name,
+            execution_item_attachments.caption,
+            execution_item_attachments.uploaded_at,
+            execution_item_attachments.uploaded_by_name,
+            actions.name as "item_name!"
+        FROM execution_item_attachments
+        INNER JOIN action_item_executions
+            ON action_item_executions.id = execution_item_attachments.action_item_execution
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        WHERE execution_item_attachments.action_plan_execution = $1
+            AND execution_item_attachments.has_thumbnail = 1
+            AND execution_item_attachments.quarantine_reason IS NULL
+        ORDER BY
+            action_item_executions.order_index ASC,
+            execution_item_attachments.uploaded_at ASC,
+            execution_item_attachments.rowid ASC
+        "#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|row| GalleryPhoto {
+        id: row.id,
+        file_name: row.file_name,
+        caption: row.caption,
+        item_name: row.item_name,
+        uploaded_display: format_unix_timestamp(row.uploaded_at),
+        uploaded_by_name: row.uploaded_by_name,
+        run: None,
+    })
+    .collect();
+
+    let rendered = templates::render(
+        &state.jinja,
+        "execution_gallery.html",
+        ExecutionGalleryView {
+            execution_id: id,
+            action_plan_id: execution.action_plan_id,
+            action_plan_name: execution.action_plan_name,
+            run_number: execution.run_number,
+            photos,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    get,
+    path = "/action_plan/{id}/gallery",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan id"), PlanGalleryQuery),
+    responses(
+        (status = 200, description = "Photos from the runs of the plan, newest first, optionally of one item only", content_type = "text/html"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+    )
+)]
+pub async fn plan_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Query(query): Query<PlanGalleryQuery>,
+) -> Result<Html<String>, AppError> {
+    let action_plan_name = sqlx::query_scalar!("SELECT name FROM action_plans WHERE id = $1", id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| {
+            AppError::not_found_for(
+                "Action Plan",
+                format!("No action plan exists for id: {}", id),
+            )
+        })?;
+
+    let months = query.months.unwrap_or(DEFAULT_MONTHS);
+    let since = if months == 0 {
+        0
+    } else {
+        Local::now()
+            .checked_sub_months(Months::new(months))
+            .map_or(0, |since| since.timestamp())
+    };
+
+    let actions = sqlx::query_as!(
+        GalleryAction,
+        r#"
+        SELECT DISTINCT actions.id as "id: uuid::Uuid", actions.name
+        FROM execution_item_attachments
+        INNER JOIN action_item_executions
+            ON action_item_executions.id = execution_item_attachments.action_item_execution
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = execution_item_attachments.action_plan_execution
+        WHERE action_plan_executions.action_plan = $1
+            AND execution_item_attachments.has_thumbnail = 1
+            AND execution_item_attachments.quarantine_reason IS NULL
+            AND execution_item_attachments.uploaded_at >= $2
+        ORDER BY actions.name COLLATE NOCASE ASC
+        "#,
+        id,
+        since
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            execution_item_attachments.id as "id: uuid::Uuid",
+            execution_item_attachments.file_name,
+            execution_item_attachments.caption,
+            execution_item_attachments.uploaded_at,
+            execution_item_attachments.uploaded_by_name,
+            actions.name as "item_name!",
+            action_plan_executions.id as "execution_id!: uuid::Uuid",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label
+        FROM execution_item_attachments
+        INNER JOIN action_item_executions
+            ON action_item_executions.id = execution_item_attachments.action_item_execution
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = execution_item_attachments.action_plan_execution
+        WHERE action_plan_executions.action_plan = $1
+            AND execution_item_attachments.has_thumbnail = 1
+            AND execution_item_attachments.quarantine_reason IS NULL
+            AND execution_item_attachments.uploaded_at >= $2
+            AND ($3 IS NULL OR action_item_executions.action = $3)
+        ORDER BY execution_item_attachments.uploaded_at DESC, execution_item_attachments.rowid DESC
+        LIMIT $4
+        "#,
+        id,
+        since,
+        query.action,
+        PLAN_GALLERY_LIMIT
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let rendered = templates::render(
+        &state.jinja,
+        "plan_gallery.html",
+        PlanGalleryView {
+            action_plan_id: id,
+            action_plan_name,
+            months,
+            month_options: MONTH_OPTIONS,
+            actions,
+            selected_action: query.action,
+            is_truncated: rows.len() as i64 == PLAN_GALLERY_LIMIT,
+            photos: rows
+                .into_iter()
+                .map(|row| GalleryPhoto {
+                    id: row.id,
+                    file_name: row.file_name,
+                    caption: row.caption,
+                    item_name: row.item_name,
+                    uploaded_display: format_unix_timestamp(row.uploaded_at),
+                    uploaded_by_name: row.uploaded_by_name,
+                    run: Some(GalleryRun {
+                        execution_id: row.execution_id,
+                        run_number: row.run_number,
+                        label: row.label,
+                    }),
+                })
+                .collect(),
+            is_admin: current_user.is_admin,
+        },
+    )?;
+    Ok(Html(rendered))
+}
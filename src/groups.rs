@@ -0,0 +1,502 @@
+//! Named groups of users, such as "Electricians" or "Night shift", so a team can be addressed
+//! as a whole instead of listing its members one by one.
+
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::{Path, State},
+    response::{Html, Redirect},
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    authz::{Action, Resource, authorize},
+    templates, unix_now,
+};
+
+const MAX_GROUP_NAME_LENGTH: usize = 80;
+/// Longest automatic logout time a group can set, one day.
+const MAX_AUTO_LOGOUT_MINUTES: i64 = 24 * 60;
+
+#[derive(Debug, Serialize)]
+struct GroupsPageView {
+    groups: Vec<GroupListItem>,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct GroupListItem {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    auto_logout_minutes: Option<i64>,
+    member_names: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct EditGroupView {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    auto_logout_minutes: Option<i64>,
+    users: Vec<GroupMemberOption>,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct GroupMemberOption {
+    id: Uuid,
+    name: String,
+    is_member: bool,
+    is_disabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct DeleteGroupConfirmView {
+    id: Uuid,
+    name: String,
+    member_count: i64,
+    is_admin: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateGroupForm {
+    name: String,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditGroupForm {
+    name: String,
+    description: Option<String>,
+    /// Minutes without activity after which members are logged out, empty for none.
+    #[serde(default)]
+    auto_logout_minutes: String,
+    /// Checked users; everyone else is removed from the group.
+    user_ids: Option<Vec<Uuid>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/groups",
+    tag = "users",
+    responses(
+        (status = 200, description = "Group management page", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
+pub async fn index(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let groups = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            name,
+            description,
+            auto_logout_minutes
+        FROM user_groups
+        ORDER BY name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let members = sqlx::query!(
+        r#"
+        SELECT
+            user_group_members.user_group as "user_group: uuid::Uuid",
+            users.name
+        FROM user_group_members
+        INNER JOIN users ON users.id = user_group_members.user_id
+        ORDER BY users.name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?;
+    let mut member_names: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for member in members {
+        member_names
+            .entry(member.user_group)
+            .or_default()
+            .push(member.name);
+    }
+
+    let rendered = templates::render(
+        &state.jinja,
+        "groups.html",
+        GroupsPageView {
+            groups: groups
+                .into_iter()
+                .map(|group| GroupListItem {
+                    member_names: member_names.remove(&group.id).unwrap_or_default(),
+                    id: group.id,
+                    name: group.name,
+                    description: group.description,
+                    auto_logout_minutes: group.auto_logout_minutes,
+                })
+                .collect(),
+            is_admin: true,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/groups",
+    tag = "users",
+    request_body(content = CreateGroupForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Creates the group and redirects to its edit page to add members"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 409, description = "Group name is empty, too long or already taken", content_type = "text/html"),
+    )
+)]
+pub async fn create_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Form(form): Form<CreateGroupForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let name = normalize_group_name(&form.name)?;
+    ensure_name_available(&state.db, &name, None).await?;
+    let description = normalize_description(form.description);
+
+    let group_id = Uuid::new_v4();
+    let created_at = unix_now();
+    sqlx::query!(
+        "INSERT INTO user_groups (id, name, description, created_at) VALUES ($1, $2, $3, $4)",
+        group_id,
+        name,
+        description,
+        created_at
+    )
+    .execute(&state.db)
+    .await?;
+
+    Ok(Redirect::to(&format!("/groups/{}/edit", group_id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/groups/{id}/edit",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "Group id")),
+    responses(
+        (status = 200, description = "Group edit form with member selection", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "Group not found", content_type = "text/html"),
+    )
+)]
+pub async fn edit_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let group = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            name,
+            description,
+            auto_logout_minutes
+        FROM user_groups
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| group_not_found(id))?;
+
+    let member_ids: HashSet<Uuid> = fetch_member_ids(&state.db, id).await?.into_iter().collect();
+    let users = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            name,
+            is_disabled
+        FROM users
+        ORDER BY name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|user| GroupMemberOption {
+        is_member: member_ids.contains(&user.id),
+        is_disabled: user.is_disabled != 0,
+        id: user.id,
+        name: user.name,
+    })
+    .collect();
+
+    let rendered = templates::render(
+        &state.jinja,
+        "group_edit.html",
+        EditGroupView {
+            id: group.id,
+            name: group.name,
+            description: group.description,
+            auto_logout_minutes: group.auto_logout_minutes,
+            users,
+            is_admin: true,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/groups/{id}/edit",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "Group id")),
+    request_body(content = EditGroupForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Saves the group and its members and redirects to the group list"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "Group not found", content_type = "text/html"),
+        (status = 409, description = "Group name is empty, too long or already taken", content_type = "text/html"),
+    )
+)]
+pub async fn edit_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<EditGroupForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let name = normalize_group_name(&form.name)?;
+    ensure_name_available(&state.db, &name, Some(id)).await?;
+    let description = normalize_description(form.description);
+    let auto_logout_minutes = parse_auto_logout_minutes(&form.auto_logout_minutes)?;
+    let user_ids: HashSet<Uuid> = form.user_ids.unwrap_or_default().into_iter().collect();
+
+    let mut tx = state.db.begin().await?;
+    let result = sqlx::query!(
+        "UPDATE user_groups SET name = $1, description = $2, auto_logout_minutes = $3 WHERE id = $4",
+        name,
+        description,
+        auto_logout_minutes,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(group_not_found(id));
+    }
+
+    sqlx::query!("DELETE FROM user_group_members WHERE user_group = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    for user_id in user_ids {
+        // Ids of users deleted while the form was open are skipped.
+        sqlx::query!(
+            r#"
+            INSERT INTO user_group_members (user_group, user_id)
+            SELECT $1, id FROM users WHERE id = $2
+            "#,
+            id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Redirect::to("/groups"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/groups/{id}/delete",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "Group id")),
+    responses(
+        (status = 200, description = "Delete confirmation page", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "Group not found", content_type = "text/html"),
+    )
+)]
+pub async fn delete_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let group = sqlx::query!(
+        r#"
+        SELECT
+            user_groups.id as "id: uuid::Uuid",
+            user_groups.name,
+            COUNT(user_group_members.user_id) as "member_count!: i64"
+        FROM user_groups
+        LEFT JOIN user_group_members ON user_group_members.user_group = user_groups.id
+        WHERE user_groups.id = $1
+        GROUP BY user_groups.id, user_groups.name
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| group_not_found(id))?;
+
+    let rendered = templates::render(
+        &state.jinja,
+        "group_delete_confirm.html",
+        DeleteGroupConfirmView {
+            id: group.id,
+            name: group.name,
+            member_count: group.member_count,
+            is_admin: true,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/groups/{id}/delete",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "Group id")),
+    responses(
+        (status = 303, description = "Deletes the group, keeping its members as users, and redirects to the group list"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "Group not found", content_type = "text/html"),
+    )
+)]
+pub async fn delete_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let mut tx = state.db.begin().await?;
+    sqlx::query!("DELETE FROM user_group_members WHERE user_group = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    let result = sqlx::query!("DELETE FROM user_groups WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(group_not_found(id));
+    }
+    tx.commit().await?;
+
+    Ok(Redirect::to("/groups"))
+}
+
+/// Users in a group, for features that address a whole team.
+pub async fn fetch_member_ids(db: &SqlitePool, group_id: Uuid) -> Result<Vec<Uuid>, AppError> {
+    Ok(sqlx::query_scalar!(
+        r#"SELECT user_id as "user_id: uuid::Uuid" FROM user_group_members WHERE user_group = $1"#,
+        group_id
+    )
+    .fetch_all(db)
+    .await?)
+}
+
+/// Group names per user, alphabetically, for the user list.
+pub async fn fetch_group_names_by_user(
+    db: &SqlitePool,
+) -> Result<HashMap<Uuid, Vec<String>>, AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            user_group_members.user_id as "user_id: uuid::Uuid",
+            user_groups.name
+        FROM user_group_members
+        INNER JOIN user_groups ON user_groups.id = user_group_members.user_group
+        ORDER BY user_groups.name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut names: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for row in rows {
+        names.entry(row.user_id).or_default().push(row.name);
+    }
+    Ok(names)
+}
+
+fn normalize_group_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::conflict("Group name cannot be empty."));
+    }
+    if name.chars().count() > MAX_GROUP_NAME_LENGTH {
+        return Err(AppError::conflict(format!(
+            "Group name cannot be longer than {} characters.",
+            MAX_GROUP_NAME_LENGTH
+        )));
+    }
+    Ok(name.to_string())
+}
+
+fn parse_auto_logout_minutes(value: &str) -> Result<Option<i64>, AppError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse::<i64>() {
+        Ok(minutes) if (1..=MAX_AUTO_LOGOUT_MINUTES).contains(&minutes) => Ok(Some(minutes)),
+        _ => Err(AppError::conflict(format!(
+            "The automatic logout must be between 1 and {} minutes, or empty for none.",
+            MAX_AUTO_LOGOUT_MINUTES
+        ))),
+    }
+}
+
+fn normalize_description(description: Option<String>) -> Option<String> {
+    description
+        .map(|description| description.trim().to_string())
+        .filter(|description| !description.is_empty())
+}
+
+async fn ensure_name_available(
+    db: &SqlitePool,
+    name: &str,
+    except_id: Option<Uuid>,
+) -> Result<(), AppError> {
+    let taken = sqlx::query_scalar!(
+        r#"
+        SELECT id as "id: uuid::Uuid"
+        FROM user_groups
+        WHERE name = $1 COLLATE NOCASE
+            AND ($2 IS NULL OR id != $2)
+        "#,
+        name,
+        except_id
+    )
+    .fetch_optional(db)
+    .await?
+    .is_some();
+
+    if taken {
+        return Err(AppError::conflict("A group with this name already exists."));
+    }
+    Ok(())
+}
+
+fn group_not_found(id: Uuid) -> AppError {
+    AppError::not_found_for("Group", format!("No group exists for id: {}", id))
+}
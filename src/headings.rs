@@ -0,0 +1,96 @@
+//! Section headings like "Before shutdown" that group the items of a plan. They are rows of
+//! `action_items` without an action, ordered among the items by `order_index`, and do not count
+//! as steps. A heading starts the section of the items after it, up to the next heading. Runs
+//! keep the heading above each item in `section`, as the plan may change while they go on.
+
+use std::collections::HashMap;
+
+use sqlx::Sqlite;
+use uuid::Uuid;
+
+/// Longest heading, in characters.
+pub const MAX_CHARS: usize = 200;
+
+/// Heading shown right above each item, by order index of the item. Takes the rows of a plan
+/// in order, with the heading of heading rows and `None` for items.
+pub fn above_items(rows: impl IntoIterator<Item = (i64, Option<String>)>) -> HashMap<i64, String> {
+    let mut headings = HashMap::new();
+    let mut pending = None;
+    for (order_index, heading) in rows {
+        match heading {
+            Some(heading) => pending = Some(heading),
+            None => {
+                if let Some(heading) = pending.take() {
+                    headings.insert(order_index, heading);
+                }
+            }
+        }
+    }
+    headings
+}
+
+/// [`above_items`] for a stored plan.
+pub async fn fetch_above_items<'e, E>(
+    executor: E,
+    plan_id: Uuid,
+) -> Result<HashMap<i64, String>, sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let rows = sqlx::query!(
+        r#"
+        SELECT order_index, heading
+        FROM action_items
+        WHERE action_plan = $1
+        ORDER BY order_index ASC
+        "#,
+        plan_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(above_items(
+        rows.into_iter().map(|row| (row.order_index, row.heading)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headings_go_to_the_next_item() {
+        let rows = [
+            (0, Some("Before shutdown".to_string())),
+            (1, None),
+            (2, None),
+            (3, Some("After shutdown".to_string())),
+            (4, None),
+        ];
+        assert_eq!(
+            above_items(rows),
+            HashMap::from([
+                (1, "Before shutdown".to_string()),
+                (4, "After shutdown".to_string())
+            ])
+        );
+    }
+
+    #[test]
+    fn the_last_of_consecutive_headings_wins() {
+        let rows = [
+            (0, Some("Unused".to_string())),
+            (1, Some("Checks".to_string())),
+            (2, None),
+        ];
+        assert_eq!(
+            above_items(rows),
+            HashMap::from([(2, "Checks".to_string())])
+        );
+    }
+
+    #[test]
+    fn trailing_headings_are_dropped() {
+        let rows = [(0, None), (1, Some("Nothing below".to_string()))];
+        assert!(above_items(rows).is_empty());
+    }
+}
@@ -0,0 +1,23 @@
+use axum::{extract::State, http::StatusCode};
+
+use crate::AppState;
+
+/// Liveness probe for load balancers and container orchestrators. Reachable without a session.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses(
+        (status = 200, description = "The server is up and the database answers", content_type = "text/plain"),
+        (status = 503, description = "The database is unreachable", content_type = "text/plain"),
+    )
+)]
+pub async fn healthz(State(state): State<AppState>) -> (StatusCode, &'static str) {
+    match sqlx::query("SELECT 1").execute(&state.db).await {
+        Ok(_) => (StatusCode::OK, "ok"),
+        Err(err) => {
+            tracing::error!("Health check failed: {}", err);
+            (StatusCode::SERVICE_UNAVAILABLE, "database unavailable")
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! The few outgoing HTTP requests the server makes, such as pulling synced plans and calling
+//! webhooks. HTTPS uses the bundled web PKI roots, so it works without system certificates.
+
+use std::{
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use anyhow::{Context, anyhow, bail};
+use axum::{
+    body::Bytes,
+    http::{HeaderName, Method, Request, Response, StatusCode, Uri, header},
+};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Incoming;
+use hyper_util::rt::TokioIo;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{ClientConfig, RootCertStore, pki_types::ServerName},
+};
+
+/// Shared by every request, as loading the roots takes a while.
+static TLS_CONFIG: LazyLock<Arc<ClientConfig>> = LazyLock::new(|| {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = ClientConfig::builder_with_provider(Arc::new(
+        tokio_rustls::rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .expect("ring supports the default protocol versions")
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Arc::new(config)
+});
+
+/// One request to `url`, with `body` sent as is. Fails if nothing answered within `timeout`
+/// or the response body is larger than `max_response_bytes`.
+pub async fn send(
+    method: Method,
+    url: &str,
+    headers: &[(HeaderName, String)],
+    body: Bytes,
+    max_response_bytes: usize,
+    timeout: Duration,
+) -> anyhow::Result<(StatusCode, Bytes)> {
+    let exchange = async {
+        let response = request(method, url, headers, body).await?;
+        let status = response.status();
+        let body = Limited::new(response.into_body(), max_response_bytes)
+            .collect()
+            .await
+            .map_err(|err| anyhow!("reading the response failed: {}", err))?
+            .to_bytes();
+        Ok((status, body))
+    };
+    tokio::time::timeout(timeout, exchange)
+        .await
+        .map_err(|_| anyhow!("{} did not answer within {:?}", url, timeout))?
+}
+
+/// Like [`send`], but hands over the response once its headers arrived, so a large body can be
+/// passed on without holding it in memory. `timeout` only covers getting the headers.
+pub async fn open(
+    method: Method,
+    url: &str,
+    headers: &[(HeaderName, String)],
+    body: Bytes,
+    timeout: Duration,
+) -> anyhow::Result<Response<Incoming>> {
+    tokio::time::timeout(timeout, request(method, url, headers, body))
+        .await
+        .map_err(|_| anyhow!("{} did not answer within {:?}", url, timeout))?
+}
+
+async fn request(
+    method: Method,
+    url: &str,
+    headers: &[(HeaderName, String)],
+    body: Bytes,
+) -> anyhow::Result<Response<Incoming>> {
+    let uri: Uri = url
+        .parse()
+        .with_context(|| format!("{} is not a valid URL", url))?;
+    let is_https = match uri.scheme_str() {
+        Some("https") => true,
+        Some("http") => false,
+        _ => bail!("{} must start with http:// or https://", url),
+    };
+    let authority = uri
+        .authority()
+        .with_context(|| format!("{} has no host", url))?
+        .to_string();
+    let host = uri
+        .host()
+        .with_context(|| format!("{} has no host", url))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = uri.port_u16().unwrap_or(if is_https { 443 } else { 80 });
+    let path = uri
+        .path_and_query()
+        .map_or("/", |path_and_query| path_and_query.as_str());
+    let mut request = Request::builder()
+        .method(method)
+        .uri(path)
+        .header(header::HOST, authority);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let request = request.body(Full::new(body))?;
+
+    let stream = TcpStream::connect((host.as_str(), port))
+        .await
+        .with_context(|| format!("connecting to {} failed", url))?;
+    if is_https {
+        let server_name = ServerName::try_from(host)?;
+        let stream = TlsConnector::from(TLS_CONFIG.clone())
+            .connect(server_name, stream)
+            .await
+            .with_context(|| format!("TLS handshake with {} failed", url))?;
+        exchange(stream, request).await
+    } else {
+        exchange(stream, request).await
+    }
+}
+
+async fn exchange<S>(io: S, request: Request<Full<Bytes>>) -> anyhow::Result<Response<Incoming>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(io)).await?;
+    tokio::spawn(async move {
+        if let Err(err) = connection.await {
+            tracing::debug!("Outgoing connection closed with an error: {}", err);
+        }
+    });
+    Ok(sender.send_request(request).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Address of a server that answers one request with `response` and hands back the request.
+    async fn answer_once(response: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = String::new();
+            let mut buffer = [0; 4096];
+            // Headers and body may come in separate writes.
+            while !is_complete(&request) {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.push_str(&String::from_utf8_lossy(&buffer[..read]));
+            }
+            stream.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+        (format!("http://{}", address), server)
+    }
+
+    fn is_complete(request: &str) -> bool {
+        let Some((head, body)) = request.split_once("\r\n\r\n") else {
+            return false;
+        };
+        let length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length: "))
+            .map_or(0, |length| length.parse().unwrap());
+        body.len() >= length
+    }
+
+    #[tokio::test]
+    async fn sends_the_request_and_reads_the_answer() {
+        let (base, server) =
+            answer_once("HTTP/1.1 201 Created\r\ncontent-length: 5\r\n\r\nhello").await;
+        let (status, body) = send(
+            Method::PUT,
+            &format!("{}/bucket/key?x=1", base),
+            &[(HeaderName::from_static("x-test"), "yes".to_string())],
+            Bytes::from_static(b"data"),
+            1024,
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body, "hello");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("PUT /bucket/key?x=1 HTTP/1.1\r\n"));
+        assert!(request.contains(&format!("host: {}\r\n", base.trim_start_matches("http://"))));
+        assert!(request.contains("x-test: yes\r\n"));
+        assert!(request.ends_with("\r\n\r\ndata"));
+    }
+
+    #[tokio::test]
+    async fn rejects_answers_over_the_limit() {
+        let (base, _) = answer_once("HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello").await;
+        let result = send(
+            Method::GET,
+            &base,
+            &[],
+            Bytes::new(),
+            4,
+            Duration::from_secs(5),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_other_schemes() {
+        let result = open(
+            Method::GET,
+            "ftp://example.com/file",
+            &[],
+            Bytes::new(),
+            Duration::from_secs(5),
+        )
+        .await;
+        assert!(result.unwrap_err().to_string().contains("http://"));
+    }
+}
@@ -0,0 +1,485 @@
+//! Invite links for new users. The admin picks the name and role, the invitee opens the signed
+//! link and chooses their own password, so no admin ever knows or sends it. Each link works
+//! once and expires after `invite_validity_seconds`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser, absolute_url,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, Role, authorize},
+    client_ip::ClientIp,
+    emails, format_unix_timestamp, templates,
+    tokens::{self, TokenScope},
+    unix_now,
+    users::{hash_password, user_snapshot},
+};
+
+const INVALID_INVITE_MESSAGE: &str =
+    "This invite link is invalid, expired or has already been used. Ask an admin for a new one.";
+
+/// An invite that has not been used yet, with its link for the admin to pass on.
+#[derive(Debug, Serialize)]
+pub struct PendingInvite {
+    id: Uuid,
+    name: String,
+    role_label: &'static str,
+    email: Option<String>,
+    expires_display: String,
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AcceptInviteView {
+    token: String,
+    name: Option<String>,
+    role_label: Option<&'static str>,
+    error_message: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateInviteForm {
+    name: String,
+    role: Role,
+    /// Optional; set on the account when the invite is used and verified like any new address.
+    #[serde(default)]
+    email: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InviteQuery {
+    /// Signed invite token from the invite link.
+    token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AcceptInviteForm {
+    token: String,
+    password: String,
+    password_confirm: String,
+}
+
+struct InviteRow {
+    id: Uuid,
+    name: String,
+    role: String,
+    email: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/invites",
+    tag = "users",
+    request_body(content = CreateInviteForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Creates the invite and redirects to the user list, which shows its link"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 409, description = "Invalid name or email address, or a user or pending invite with this name exists", content_type = "text/html"),
+    )
+)]
+pub async fn create_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Form(form): Form<CreateInviteForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Err(AppError::conflict("User name cannot be empty."));
+    }
+    ensure_name_available(&state.db, name).await?;
+    let email = emails::normalize_address(&form.email)?;
+    let now = unix_now();
+    let pending_invite = sqlx::query_scalar!(
+        r#"
+        SELECT id as "id: uuid::Uuid"
+        FROM user_invites
+        WHERE LOWER(name) = LOWER($1)
+            AND accepted_at IS NULL
+            AND expires_at > $2
+        "#,
+        name,
+        now
+    )
+    .fetch_optional(&state.db)
+    .await?;
+    if pending_invite.is_some() {
+        return Err(AppError::conflict(
+            "An invite for this name is still pending. Revoke it first to send a new one.",
+        ));
+    }
+
+    let invite_id = Uuid::new_v4();
+    let role = form.role.as_str();
+    let expires_at = now.saturating_add(state.config.invite_validity_seconds);
+    let mut tx = state.db.begin().await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO user_invites (id, name, role, email, created_by, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        invite_id,
+        name,
+        role,
+        email,
+        current_user.id,
+        now,
+        expires_at
+    )
+    .execute(&mut *tx)
+    .await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Invite,
+            invite_id,
+            AuditAction::Create,
+            format!("Invited \"{}\"", name),
+        )
+        .change(None, Some(user_snapshot(name, form.role, email.as_deref()))),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Redirect::to("/users"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/invites/{id}/revoke",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "Invite id")),
+    responses(
+        (status = 303, description = "Makes the invite link unusable and redirects to the user list"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
+pub async fn revoke_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let mut tx = state.db.begin().await?;
+    let name = sqlx::query_scalar!(
+        "DELETE FROM user_invites WHERE id = $1 AND accepted_at IS NULL RETURNING name",
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(name) = name {
+        audit::record(
+            &mut *tx,
+            &current_user,
+            AuditEntry::new(
+                AuditEntity::Invite,
+                id,
+                AuditAction::Delete,
+                format!("Revoked the invite for \"{}\"", name),
+            ),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    tokens::revoke(&state.db, id).await?;
+
+    Ok(Redirect::to("/users"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/invite",
+    tag = "users",
+    params(InviteQuery),
+    responses(
+        (status = 200, description = "Form to choose a password for the invited account", content_type = "text/html"),
+        (status = 404, description = "The link is invalid, expired or already used", content_type = "text/html"),
+    )
+)]
+pub async fn accept_get(
+    State(state): State<AppState>,
+    Query(query): Query<InviteQuery>,
+) -> Result<Response, AppError> {
+    let Some(invite) = fetch_valid_invite(&state, &query.token).await? else {
+        return render_invalid(&state, query.token);
+    };
+
+    render_accept(&state, query.token, &invite, None)
+}
+
+#[utoipa::path(
+    post,
+    path = "/invite",
+    tag = "users",
+    request_body(content = AcceptInviteForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Creates the account and redirects to the login"),
+        (status = 200, description = "Form with an error message", content_type = "text/html"),
+        (status = 404, description = "The link is invalid, expired or already used", content_type = "text/html"),
+        (status = 429, description = "The link made too many requests and is locked for a while", content_type = "text/html"),
+    )
+)]
+pub async fn accept_post(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    Form(form): Form<AcceptInviteForm>,
+) -> Result<Response, AppError> {
+    let Some(invite) = fetch_valid_invite(&state, &form.token).await? else {
+        return render_invalid(&state, form.token);
+    };
+    if !tokens::record_request(&state.db, &state.config, invite.id).await? {
+        return Err(AppError::too_many_requests(
+            "This invite link made too many requests. Try again later.",
+        ));
+    }
+
+    if form.password.len() < 8 {
+        return render_accept(
+            &state,
+            form.token,
+            &invite,
+            Some("Password must be at least 8 characters."),
+        );
+    }
+    if form.password != form.password_confirm {
+        return render_accept(&state, form.token, &invite, Some("Passwords do not match."));
+    }
+    if let Err(err) = ensure_name_available(&state.db, &invite.name).await {
+        tracing::warn!(
+            user = invite.name,
+            "Invite used for a name that is taken by now"
+        );
+        return Err(err);
+    }
+
+    let user_id = Uuid::new_v4();
+    let now = unix_now();
+    let password_hash = hash_password(&form.password)?;
+    let mut tx = state.db.begin().await?;
+    // Claims the invite first, so two submissions of the same link create one account.
+    let claimed = sqlx::query!(
+        r#"
+        UPDATE user_invites
+        SET accepted_at = $1, accepted_user = $2
+        WHERE id = $3 AND accepted_at IS NULL
+        "#,
+        now,
+        user_id,
+        invite.id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if claimed == 0 {
+        return render_invalid(&state, form.token);
+    }
+    sqlx::query!(
+        "INSERT INTO users (id, name, role, created_at, password_hash) VALUES ($1, $2, $3, $4, $5)",
+        user_id,
+        invite.name,
+        invite.role,
+        now,
+        password_hash
+    )
+    .execute(&mut *tx)
+    .await?;
+    let role = Role::from_db(&invite.role);
+    audit::record_as(
+        &mut *tx,
+        user_id,
+        &invite.name,
+        Some(client_ip),
+        AuditEntry::new(
+            AuditEntity::User,
+            user_id,
+            AuditAction::Create,
+            format!("Accepted the invite for \"{}\"", invite.name),
+        )
+        .change(
+            None,
+            Some(user_snapshot(&invite.name, role, invite.email.as_deref())),
+        ),
+    )
+    .await?;
+    tx.commit().await?;
+    tokens::revoke(&state.db, invite.id).await?;
+
+    if let Some(email) = &invite.email {
+        emails::set_address(&state.db, user_id, Some(email)).await?;
+        // The account exists either way; the link can be sent again from the email page.
+        if let Err(err) = emails::send_verification(&state, user_id).await {
+            tracing::error!("Sending the verification mail failed: {}", err);
+        }
+    }
+
+    tracing::info!(user = invite.name, "Invite accepted");
+    Ok(Redirect::to("/login").into_response())
+}
+
+/// Unused, unexpired invites with their links, newest first.
+pub async fn fetch_pending(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Vec<PendingInvite>, AppError> {
+    let now = unix_now();
+    let invites = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            name,
+            role,
+            email,
+            expires_at
+        FROM user_invites
+        WHERE accepted_at IS NULL
+            AND expires_at > $1
+        ORDER BY created_at DESC
+        "#,
+        now
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(invites
+        .into_iter()
+        .map(|invite| {
+            let token = state.token_signer.sign(
+                TokenScope::UserInvite,
+                invite.id,
+                invite.id,
+                Some(invite.expires_at),
+            );
+            PendingInvite {
+                id: invite.id,
+                name: invite.name,
+                role_label: Role::from_db(&invite.role).label(),
+                email: invite.email,
+                expires_display: format_unix_timestamp(invite.expires_at),
+                url: absolute_url(&state.config, headers, &format!("/invite?token={}", token)),
+            }
+        })
+        .collect())
+}
+
+/// Removes invites that expired without being used. Used ones are kept as a record of who
+/// invited whom.
+pub async fn cleanup_expired(conn: &mut SqliteConnection) -> Result<u64, AppError> {
+    let now = unix_now();
+    let result = sqlx::query!(
+        "DELETE FROM user_invites WHERE accepted_at IS NULL AND expires_at <= $1",
+        now
+    )
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Names of up to `limit` invites `cleanup_expired` would delete.
+pub async fn expired_invite_names(
+    conn: &mut SqliteConnection,
+    limit: i64,
+) -> Result<Vec<String>, AppError> {
+    let now = unix_now();
+    Ok(sqlx::query_scalar!(
+        r#"
+        SELECT name
+        FROM user_invites
+        WHERE accepted_at IS NULL AND expires_at <= $1
+        ORDER BY expires_at ASC
+        LIMIT $2
+        "#,
+        now,
+        limit
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+async fn fetch_valid_invite(state: &AppState, token: &str) -> Result<Option<InviteRow>, AppError> {
+    let Some(verified) = state
+        .token_signer
+        .verify(&state.db, token, TokenScope::UserInvite)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let now = unix_now();
+    Ok(sqlx::query_as!(
+        InviteRow,
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            name,
+            role,
+            email
+        FROM user_invites
+        WHERE id = $1
+            AND accepted_at IS NULL
+            AND expires_at > $2
+        "#,
+        verified.id,
+        now
+    )
+    .fetch_optional(&state.db)
+    .await?)
+}
+
+async fn ensure_name_available(db: &SqlitePool, name: &str) -> Result<(), AppError> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT id as "id: uuid::Uuid" FROM users WHERE LOWER(name) = LOWER($1)"#,
+        name
+    )
+    .fetch_optional(db)
+    .await?;
+    if exists.is_some() {
+        return Err(AppError::conflict("A user with this name already exists."));
+    }
+    Ok(())
+}
+
+fn render_accept(
+    state: &AppState,
+    token: String,
+    invite: &InviteRow,
+    error_message: Option<&str>,
+) -> Result<Response, AppError> {
+    let rendered = templates::render(
+        &state.jinja,
+        "invite_accept.html",
+        AcceptInviteView {
+            token,
+            name: Some(invite.name.clone()),
+            role_label: Some(Role::from_db(&invite.role).label()),
+            error_message: error_message.map(str::to_string),
+        },
+    )?;
+    Ok(Html(rendered).into_response())
+}
+
+fn render_invalid(state: &AppState, token: String) -> Result<Response, AppError> {
+    let rendered = templates::render(
+        &state.jinja,
+        "invite_accept.html",
+        AcceptInviteView {
+            token,
+            name: None,
+            role_label: None,
+            error_message: Some(INVALID_INVITE_MESSAGE.to_string()),
+        },
+    )?;
+    Ok((StatusCode::NOT_FOUND, Html(rendered)).into_response())
+}
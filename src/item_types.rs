@@ -0,0 +1,138 @@
+//! What a checklist item captures when it is checked off. Plain items are only ticked; text and
+//! numeric items also record what was entered, like a meter reading.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Longest value accepted for text items, in characters.
+pub const MAX_VALUE_CHARS: usize = 500;
+/// Longest unit accepted for numeric items, in characters.
+pub const MAX_UNIT_CHARS: usize = 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemType {
+    #[default]
+    Checkbox,
+    Text,
+    Number,
+}
+
+/// An item type as offered in the plan editor.
+#[derive(Debug, Serialize)]
+pub struct ItemTypeOption {
+    value: &'static str,
+    label: &'static str,
+}
+
+impl ItemType {
+    pub const ALL: [ItemType; 3] = [ItemType::Checkbox, ItemType::Text, ItemType::Number];
+
+    /// The value stored in `item_type` columns and sent by forms.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ItemType::Checkbox => "checkbox",
+            ItemType::Text => "text",
+            ItemType::Number => "number",
+        }
+    }
+
+    /// Unknown values, like from a newer backup, fall back to a plain checkbox.
+    pub fn from_db(value: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|item_type| item_type.as_str() == value)
+            .unwrap_or_default()
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ItemType::Checkbox => "Checkbox",
+            ItemType::Text => "Text",
+            ItemType::Number => "Reading",
+        }
+    }
+
+    /// Whether checking off the item needs a value.
+    pub fn captures_value(self) -> bool {
+        self != ItemType::Checkbox
+    }
+
+    /// The entered value as stored, or a message saying what is wrong with it. Numbers may
+    /// use a decimal comma and are stored with a point.
+    pub fn normalize_value(self, value: Option<&str>) -> Result<Option<String>, String> {
+        if !self.captures_value() {
+            return Ok(None);
+        }
+        let value = value.map(str::trim).unwrap_or_default();
+        if value.is_empty() {
+            return Err("Enter a value before checking off this item.".to_string());
+        }
+        match self {
+            ItemType::Checkbox => Ok(None),
+            ItemType::Text if value.chars().count() > MAX_VALUE_CHARS => Err(format!(
+                "Values can have at most {} characters.",
+                MAX_VALUE_CHARS
+            )),
+            ItemType::Text => Ok(Some(value.to_string())),
+            ItemType::Number => {
+                let value = value.replace(',', ".");
+                match value.parse::<f64>() {
+                    Ok(number) if number.is_finite() => Ok(Some(value)),
+                    _ => Err(format!("\"{}\" is not a number.", value)),
+                }
+            }
+        }
+    }
+
+    pub fn options() -> Vec<ItemTypeOption> {
+        Self::ALL
+            .into_iter()
+            .map(|item_type| ItemTypeOption {
+                value: item_type.as_str(),
+                label: item_type.label(),
+            })
+            .collect()
+    }
+}
+
+/// Reads a range bound from a form. Like readings, bounds may use a decimal comma.
+pub fn parse_bound(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|bound| bound.is_finite())
+}
+
+/// Whether a stored reading lies outside the expected range. Values that are not numbers are
+/// never out of spec.
+pub fn is_out_of_spec(value: &str, min: Option<f64>, max: Option<f64>) -> bool {
+    let Ok(reading) = value.parse::<f64>() else {
+        return false;
+    };
+    min.is_some_and(|min| reading < min) || max.is_some_and(|max| reading > max)
+}
+
+/// The expected range with its unit, like `1.5–3 bar` or `at least 2 bar`, if there is one.
+pub fn range_display(min: Option<f64>, max: Option<f64>, unit: Option<&str>) -> Option<String> {
+    let range = match (min, max) {
+        (Some(min), Some(max)) => format!("{}–{}", min, max),
+        (Some(min), None) => format!("at least {}", min),
+        (None, Some(max)) => format!("at most {}", max),
+        (None, None) => return None,
+    };
+    Some(match unit {
+        Some(unit) => format!("{} {}", range, unit),
+        None => range,
+    })
+}
+
+/// A recorded value with its unit, for display.
+pub fn value_display(value: &str, unit: Option<&str>) -> String {
+    match unit {
+        Some(unit) => format!("{} {}", value, unit),
+        None => value.to_string(),
+    }
+}
@@ -0,0 +1,278 @@
+//! The background jobs that remove or move data. The schedulers and the admin page run them
+//! through `run`, which does all of a job's work in one transaction. A dry run rolls that
+//! transaction back, so its report shows exactly what a real run would have removed.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{AppError, archive, config::Config, format_unix_timestamp, invites, tokens, users};
+
+/// Examples listed per kind of removed record, so a preview stays readable on large databases.
+const SAMPLE_LIMIT: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Job {
+    /// Deletes actions no plan or execution refers to anymore.
+    ActionGc,
+    /// Deletes expired sessions, invites and signed link request counters.
+    SessionGc,
+    /// Moves old finished executions into the archive database.
+    Archive,
+}
+
+impl Job {
+    pub const ALL: [Job; 3] = [Job::ActionGc, Job::SessionGc, Job::Archive];
+
+    /// The value used in URLs.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Job::ActionGc => "action_gc",
+            Job::SessionGc => "session_gc",
+            Job::Archive => "archive",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Job::ActionGc => "Action Cleanup",
+            Job::SessionGc => "Session Cleanup",
+            Job::Archive => "Archive",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Job::ActionGc => "Deletes item names no plan or execution uses anymore.",
+            Job::SessionGc => {
+                "Deletes expired sessions and invites and request counters of signed links."
+            }
+            Job::Archive => "Moves finished executions past the retention period to the archive.",
+        }
+    }
+
+    /// Seconds between scheduled runs, `None` when the job is turned off.
+    pub fn interval_seconds(self, config: &Config) -> Option<u64> {
+        match self {
+            Job::ActionGc => Some(config.action_gc_interval_seconds),
+            Job::SessionGc => Some(config.session_gc_interval_seconds),
+            Job::Archive => {
+                (config.archive_after_years > 0).then_some(config.archive_interval_seconds)
+            }
+        }
+    }
+}
+
+/// What a job removed, or would remove in a dry run.
+#[derive(Debug, Serialize)]
+pub struct JobReport {
+    pub job: Job,
+    pub dry_run: bool,
+    pub removed: Vec<RemovedRecords>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemovedRecords {
+    pub label: &'static str,
+    pub count: u64,
+    /// Up to `SAMPLE_LIMIT` of the affected records, for a quick check of what they are.
+    pub samples: Vec<String>,
+}
+
+impl JobReport {
+    pub fn total(&self) -> u64 {
+        self.removed.iter().map(|records| records.count).sum()
+    }
+}
+
+impl fmt::Display for JobReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.total() == 0 {
+            return write!(f, "nothing to do");
+        }
+        let verb = if self.dry_run {
+            "would remove"
+        } else {
+            "removed"
+        };
+        let parts = self
+            .removed
+            .iter()
+            .filter(|records| records.count > 0)
+            .map(|records| format!("{} {}", records.count, records.label))
+            .collect::<Vec<_>>();
+        write!(f, "{} {}", verb, parts.join(", "))?;
+        let samples = self
+            .removed
+            .iter()
+            .flat_map(|records| records.samples.iter())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !samples.is_empty() {
+            write!(f, " (e.g. {})", samples.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `job` once. With `dry_run` nothing is changed.
+pub async fn run(
+    db: &SqlitePool,
+    config: &Config,
+    job: Job,
+    dry_run: bool,
+) -> Result<JobReport, AppError> {
+    let mut tx = db.begin().await?;
+    let removed = match job {
+        Job::ActionGc => vec![remove_unused_actions(&mut tx).await?],
+        Job::SessionGc => remove_expired_sessions(&mut tx, config).await?,
+        Job::Archive => {
+            if config.archive_after_years == 0 {
+                return Err(AppError::conflict(
+                    "Archiving is turned off. Set archive_after_years to use it.",
+                ));
+            }
+            vec![archive_old_executions(&mut tx, config).await?]
+        }
+    };
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+
+    Ok(JobReport {
+        job,
+        dry_run,
+        removed,
+    })
+}
+
+/// Runs `job` for its scheduler and logs the outcome.
+pub async fn run_scheduled(db: &SqlitePool, config: &Config, job: Job) {
+    match run(db, config, job, false).await {
+        Ok(report) => tracing::info!("{}: {}.", job.label(), report),
+        Err(err) => tracing::error!("{} failed: {}", job.label(), err),
+    }
+}
+
+async fn remove_unused_actions(conn: &mut SqliteConnection) -> Result<RemovedRecords, AppError> {
+    let unused_actions = sqlx::query_scalar!(
+        r#"
+        SELECT actions.name
+        FROM actions
+        WHERE NOT EXISTS (
+            SELECT 1
+            FROM action_items
+            WHERE action_items.action = actions.id
+        )
+        AND NOT EXISTS (
+            SELECT 1
+            FROM action_item_executions
+            WHERE action_item_executions.action = actions.id
+        )
+        ORDER BY actions.name ASC
+        LIMIT $1
+        "#,
+        SAMPLE_LIMIT
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let count = sqlx::query!(
+        r#"
+        DELETE FROM actions
+        WHERE NOT EXISTS (
+            SELECT 1
+            FROM action_items
+            WHERE action_items.action = actions.id
+        )
+        AND NOT EXISTS (
+            SELECT 1
+            FROM action_item_executions
+            WHERE action_item_executions.action = actions.id
+        )
+        "#
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    Ok(RemovedRecords {
+        label: "unused actions",
+        count,
+        samples: unused_actions,
+    })
+}
+
+async fn remove_expired_sessions(
+    conn: &mut SqliteConnection,
+    config: &Config,
+) -> Result<Vec<RemovedRecords>, AppError> {
+    let session_samples = users::expired_session_samples(&mut *conn, config, SAMPLE_LIMIT).await?;
+    let sessions = users::cleanup_expired_sessions(&mut *conn, config).await?;
+    let invite_samples = invites::expired_invite_names(&mut *conn, SAMPLE_LIMIT).await?;
+    let invites = invites::cleanup_expired(&mut *conn).await?;
+    let counters = tokens::cleanup_request_counts(&mut *conn, config).await?;
+
+    Ok(vec![
+        RemovedRecords {
+            label: "expired sessions",
+            count: sessions,
+            samples: session_samples,
+        },
+        RemovedRecords {
+            label: "expired invites",
+            count: invites,
+            samples: invite_samples,
+        },
+        RemovedRecords {
+            label: "signed link request counters",
+            count: counters,
+            samples: Vec::new(),
+        },
+    ])
+}
+
+async fn archive_old_executions(
+    conn: &mut SqliteConnection,
+    config: &Config,
+) -> Result<RemovedRecords, AppError> {
+    let cutoff = archive::cutoff(config);
+    let samples = sqlx::query!(
+        r#"
+        SELECT
+            action_plans.name as "plan_name!",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.finished as "finished!: i64"
+        FROM action_plan_executions
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_plan_executions.finished > 0 AND action_plan_executions.finished < $1
+        ORDER BY action_plan_executions.finished ASC
+        LIMIT $2
+        "#,
+        cutoff,
+        SAMPLE_LIMIT
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|execution| {
+        format!(
+            "{} run #{} finished {}",
+            execution.plan_name,
+            execution.run_number,
+            format_unix_timestamp(execution.finished)
+        )
+    })
+    .collect();
+    let count = archive::archive_finished_before(&mut *conn, cutoff).await?;
+
+    Ok(RemovedRecords {
+        label: "executions moved to the archive",
+        count,
+        samples,
+    })
+}
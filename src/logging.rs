@@ -0,0 +1,124 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use crate::AppState;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Installs the global subscriber. `RUST_LOG` overrides the default of `info`.
+pub fn init() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+}
+
+/// Wraps every request in a span carrying a fresh request id and the matched route, logs
+/// method, path, status and duration once the response is ready, and echoes the id back in
+/// the `x-request-id` header. Slow query warnings from sqlx are logged inside this span, so
+/// they show which route ran them.
+pub async fn request_span_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let request_id = Uuid::new_v4();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    // Unmatched paths share one entry so random URLs cannot grow the timings table.
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| matched.as_str().to_string())
+        .unwrap_or_else(|| "(unmatched)".to_string());
+    let span = tracing::info_span!("request", %request_id, %method, %route, %path);
+
+    let started = Instant::now();
+    let mut response = next.run(request).instrument(span.clone()).await;
+    let duration_ms = started.elapsed().as_millis();
+    state
+        .endpoint_timings
+        .record(format!("{} {}", method, route), duration_ms);
+
+    span.in_scope(|| {
+        let status = response.status().as_u16();
+        if response.status().is_server_error() {
+            tracing::error!(status, duration_ms, "request failed");
+        } else {
+            tracing::info!(status, duration_ms, "request finished");
+        }
+    });
+
+    if let Ok(value) = HeaderValue::from_str(&request_id.to_string()) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Request durations per route since startup, for the slowest endpoints panel.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointTimings {
+    endpoints: Arc<Mutex<HashMap<String, EndpointStats>>>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointStats {
+    count: u64,
+    total_ms: u128,
+    max_ms: u128,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EndpointSummary {
+    endpoint: String,
+    count: u64,
+    average_ms: u128,
+    max_ms: u128,
+}
+
+impl EndpointTimings {
+    fn record(&self, endpoint: String, duration_ms: u128) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let stats = endpoints.entry(endpoint).or_default();
+        stats.count += 1;
+        stats.total_ms += duration_ms;
+        stats.max_ms = stats.max_ms.max(duration_ms);
+    }
+
+    /// Endpoints with the highest average duration first.
+    pub fn slowest(&self, limit: usize) -> Vec<EndpointSummary> {
+        let endpoints = self.endpoints.lock().unwrap();
+        let mut summaries = endpoints
+            .iter()
+            .map(|(endpoint, stats)| EndpointSummary {
+                endpoint: endpoint.clone(),
+                count: stats.count,
+                average_ms: stats.total_ms / u128::from(stats.count.max(1)),
+                max_ms: stats.max_ms,
+            })
+            .collect::<Vec<_>>();
+        summaries.sort_by(|a, b| {
+            b.average_ms
+                .cmp(&a.average_ms)
+                .then_with(|| b.max_ms.cmp(&a.max_ms))
+                .then_with(|| a.endpoint.cmp(&b.endpoint))
+        });
+        summaries.truncate(limit);
+        summaries
+    }
+}
@@ -0,0 +1,84 @@
+//! Outgoing mail. Sent through `smtp_url` when configured; otherwise each mail is written to
+//! the log, so links in it can still be passed on by hand on instances without a mail server.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use lettre::{
+    Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, message::Mailbox,
+};
+
+use crate::{AppError, config::Config};
+
+#[derive(Clone)]
+pub struct Mailer {
+    from: Mailbox,
+    transport: Option<Arc<AsyncSmtpTransport<Tokio1Executor>>>,
+}
+
+impl std::fmt::Debug for Mailer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mailer")
+            .field("from", &self.from.to_string())
+            .field("smtp", &self.transport.is_some())
+            .finish()
+    }
+}
+
+impl Mailer {
+    /// Fails on an unparsable `mail_from` or `smtp_url`. The server is only contacted when
+    /// the first mail is sent.
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let from = config
+            .mail_from
+            .parse::<Mailbox>()
+            .with_context(|| format!("mail_from is not a valid address: {}", config.mail_from))?;
+        let transport = config
+            .smtp_url
+            .as_deref()
+            .map(|url| {
+                AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+                    .map(|builder| Arc::new(builder.build()))
+                    .context("smtp_url is not a valid SMTP URL")
+            })
+            .transpose()?;
+        Ok(Self { from, transport })
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), AppError> {
+        let Some(transport) = &self.transport else {
+            tracing::warn!(
+                to,
+                subject,
+                "No smtp_url configured, mail not sent:\n{}",
+                body
+            );
+            return Ok(());
+        };
+
+        let to = parse_address(to)?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(Mailbox::new(None, to))
+            .subject(subject)
+            .body(body)
+            .map_err(|err| AppError::internal(anyhow::anyhow!("Building mail failed: {}", err)))?;
+        transport
+            .send(message)
+            .await
+            .map_err(|err| AppError::internal(anyhow::anyhow!("Sending mail failed: {}", err)))?;
+        Ok(())
+    }
+}
+
+/// Parses a single address such as `jane@example.com`, rejecting display names and lists.
+pub fn parse_address(value: &str) -> Result<Address, AppError> {
+    value
+        .trim()
+        .parse::<Address>()
+        .map_err(|_| AppError::conflict(format!("\"{}\" is not a valid email address.", value)))
+}
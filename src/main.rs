@@ -1,92 +1,387 @@
-use std::{path::Path, sync::Arc};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::Arc,
+};
 
 use axum::{
     Router,
-    extract::{FromRequestParts, Request, State},
+    extract::{DefaultBodyLimit, FromRequestParts, Request, State},
     http::request::Parts,
-    http::{HeaderValue, header},
+    http::{HeaderMap, HeaderValue, header},
     middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::{get, post},
 };
 use axum_extra::extract::cookie::CookieJar;
 use chrono::{Local, TimeZone};
-use sqlx::{Sqlite, SqlitePool, migrate::MigrateDatabase};
-use tokio::{signal, time::Duration};
+use sqlx::{
+    ConnectOptions, Sqlite, SqlitePool,
+    migrate::MigrateDatabase,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+};
+use tokio::{signal, sync::broadcast, time::Duration};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 use uuid::Uuid;
 
+mod action_library;
 mod action_plan;
+mod admin;
+mod archive;
+mod assets;
+mod assignees;
+mod attachments;
+mod audit;
+mod authz;
+mod auto_logout;
 mod backup;
+mod blocks;
+mod calendar;
+mod catalog;
+mod checklist_export;
+mod client_ip;
+mod collab;
+mod comments;
+mod conditions;
+mod config;
+mod emails;
+mod encryption;
 mod error;
+mod events;
 mod executions;
+mod failures;
+mod flash;
+mod gallery;
+mod groups;
+mod headings;
+mod health;
+mod http_client;
+mod invites;
+mod item_types;
+mod jobs;
+mod logging;
+mod mail;
+mod markdown;
+mod openapi;
+mod procedure_changes;
+mod rate_limit;
+mod read_only;
+mod reports;
+mod scan;
+mod security_headers;
+mod self_test;
+mod skips;
+mod stats;
+mod storage;
+mod sub_items;
+mod sync;
 mod tags;
+mod tasks;
+mod templates;
+#[cfg(test)]
+mod tests;
+mod tls;
+mod tokens;
+mod transfers;
+mod two_factor;
+mod usage;
 mod users;
+mod virus_scan;
+mod webhooks;
 pub use error::AppError;
 
-const DB_PATH: &str = "./db/db.sqlite";
-
 #[derive(Debug, Clone)]
 struct AppState {
     db: SqlitePool,
     jinja: Arc<minijinja::Environment<'static>>,
+    config: Arc<config::Config>,
+    execution_events: broadcast::Sender<events::ExecutionEvent>,
+    presence: collab::Presence,
+    token_signer: tokens::TokenSigner,
+    secret_cipher: encryption::SecretCipher,
+    mailer: mail::Mailer,
+    /// Where attachments, scheduled backups and archived reports are kept.
+    storage: Arc<dyn storage::Storage>,
+    login_rate_limiter: rate_limit::LoginRateLimiter,
+    /// Whether the archive database is attached to every connection.
+    archive_enabled: bool,
+    endpoint_timings: logging::EndpointTimings,
+    /// Problems found by the startup checks, listed on the admin page.
+    startup_warnings: Arc<Vec<self_test::StartupWarning>>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct CurrentUser {
     pub(crate) id: Uuid,
     pub(crate) name: String,
+    pub(crate) role: authz::Role,
+    /// Same as `role == Admin`; kept for the templates, which all check it for the admin nav.
     pub(crate) is_admin: bool,
+    #[serde(skip)]
+    pub(crate) session_id: Uuid,
+    /// Where the request came from, for the audit log.
+    #[serde(skip)]
+    pub(crate) ip_address: Option<IpAddr>,
+    /// Seconds without activity after which the session is logged out, if any.
+    #[serde(skip)]
+    pub(crate) auto_logout_seconds: Option<i64>,
 }
 
 #[tokio::main]
 async fn main() {
-    if !tokio::fs::try_exists(DB_PATH).await.unwrap() {
-        tokio::fs::create_dir_all(Path::new(DB_PATH).parent().unwrap())
+    logging::init();
+
+    let config = match config::Config::load() {
+        Ok(config) => Arc::new(config),
+        Err(err) => {
+            tracing::error!("Loading configuration failed: {:#}", err);
+            std::process::exit(1);
+        }
+    };
+    let mut startup_warnings = match self_test::check_environment(&config).await {
+        Ok(warnings) => warnings,
+        Err(err) => {
+            tracing::error!("Startup check failed: {:#}", err);
+            std::process::exit(1);
+        }
+    };
+    let db_path = config.db_path.as_str();
+
+    if !tokio::fs::try_exists(db_path).await.unwrap() {
+        tokio::fs::create_dir_all(Path::new(db_path).parent().unwrap())
             .await
             .unwrap();
-        Sqlite::create_database(DB_PATH).await.unwrap();
+        Sqlite::create_database(db_path).await.unwrap();
     }
 
-    let db = SqlitePool::connect(DB_PATH).await.unwrap();
+    // WAL lets readers proceed while a write is in progress, and the busy timeout makes
+    // concurrent writers wait for the lock instead of failing with "database is locked".
+    let connect_options = SqliteConnectOptions::new()
+        .filename(db_path)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(Duration::from_millis(config.db_busy_timeout_ms))
+        .foreign_keys(true)
+        .log_slow_statements(
+            if config.slow_query_threshold_ms == 0 {
+                log::LevelFilter::Off
+            } else {
+                log::LevelFilter::Warn
+            },
+            Duration::from_millis(config.slow_query_threshold_ms),
+        );
+    // Attached databases are opened with the main connection's flags, which don't allow
+    // creating files, so the archive has to exist before the pool connects.
+    let archive_enabled = archive::is_enabled(&config);
+    if archive_enabled
+        && !tokio::fs::try_exists(&config.archive_db_path)
+            .await
+            .unwrap()
+    {
+        tokio::fs::create_dir_all(Path::new(&config.archive_db_path).parent().unwrap())
+            .await
+            .unwrap();
+        Sqlite::create_database(&config.archive_db_path)
+            .await
+            .unwrap();
+    }
+    let archive_path = config.archive_db_path.clone();
+    let db = SqlitePoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .after_connect(move |conn, _meta| {
+            let archive_path = archive_path.clone();
+            Box::pin(async move {
+                if archive_enabled {
+                    archive::attach(conn, &archive_path).await?;
+                }
+                Ok(())
+            })
+        })
+        .connect_with(connect_options)
+        .await
+        .unwrap();
     if let Err(err) = sqlx::migrate!("./migrations").run(&db).await {
-        eprintln!(
+        tracing::error!(
             "Database migration failed: {}",
             format_migration_error(&err)
         );
         std::process::exit(1);
     }
-    run_action_gc(&db).await;
-    run_session_gc(&db).await;
-    tokio::spawn(run_action_gc_scheduler(db.clone()));
-    tokio::spawn(run_session_gc_scheduler(db.clone()));
+    if archive_enabled && let Err(err) = archive::prepare(&db).await {
+        tracing::error!("Preparing the archive database failed: {}", err);
+        std::process::exit(1);
+    }
+    match action_plan::assign_missing_slugs(&db).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("Assigned slugs to {} action plan(s).", count),
+        Err(err) => tracing::error!("Assigning action plan slugs failed: {}", err),
+    }
+    match self_test::check_database(&db).await {
+        Ok(warnings) => startup_warnings.extend(warnings),
+        Err(err) => {
+            tracing::error!("Startup database check failed: {}", err);
+            std::process::exit(1);
+        }
+    }
+    jobs::run_scheduled(&db, &config, jobs::Job::ActionGc).await;
+    jobs::run_scheduled(&db, &config, jobs::Job::SessionGc).await;
+    tokio::spawn(run_action_gc_scheduler(db.clone(), config.clone()));
+    tokio::spawn(run_session_gc_scheduler(db.clone(), config.clone()));
+    tokio::spawn(archive::run_scheduler(db.clone(), config.clone()));
+    tokio::spawn(sync::run_scheduler(db.clone(), config.clone()));
+
+    let storage = match storage::from_config(&config) {
+        Ok(storage) => storage,
+        Err(err) => {
+            tracing::error!("Setting up the storage backend failed: {:#}", err);
+            std::process::exit(1);
+        }
+    };
+    tokio::spawn(backup::run_scheduler(
+        db.clone(),
+        config.clone(),
+        storage.clone(),
+    ));
+
+    let token_signer = match tokens::TokenSigner::load(&db, &config).await {
+        Ok(signer) => signer,
+        Err(err) => {
+            tracing::error!("Loading the token signing key failed: {:#}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let secret_cipher = match encryption::SecretCipher::load(&db, &config).await {
+        Ok(cipher) => cipher,
+        Err(err) => {
+            tracing::error!("Loading the encryption key failed: {:#}", err);
+            std::process::exit(1);
+        }
+    };
+    match two_factor::reencrypt_secrets(&db, &secret_cipher, &token_signer).await {
+        Ok((0, 0)) => {}
+        Ok((reencrypted, unreadable)) => {
+            if reencrypted > 0 {
+                tracing::info!(
+                    "Re-encrypted {} stored secret(s) with the current key.",
+                    reencrypted
+                );
+            }
+            if unreadable > 0 {
+                startup_warnings.push(self_test::StartupWarning {
+                    check: "Encryption key",
+                    message: format!(
+                        "{} two-factor secret(s) cannot be read with the configured keys. Add the old key to previous_encryption_keys, or reset two-factor authentication for those users.",
+                        unreadable
+                    ),
+                });
+            }
+        }
+        Err(err) => tracing::error!("Re-encrypting stored secrets failed: {:#}", err),
+    }
+
+    let mailer = match mail::Mailer::from_config(&config) {
+        Ok(mailer) => mailer,
+        Err(err) => {
+            tracing::error!("Setting up outgoing mail failed: {:#}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let jinja = templates::environment(&config.base_path);
+
+    let jinja = Arc::new(jinja);
+    tokio::spawn(reports::run_scheduler(
+        db.clone(),
+        jinja.clone(),
+        storage.clone(),
+    ));
+
+    let tls_acceptor = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => match tls::load_acceptor(cert_path, key_path) {
+            Ok(acceptor) => Some(acceptor),
+            Err(err) => {
+                startup_warnings.push(self_test::StartupWarning {
+                    check: "HTTPS",
+                    message: format!(
+                        "Loading the TLS certificate failed, serving plain HTTP: {:#}",
+                        err
+                    ),
+                });
+                None
+            }
+        },
+        (None, None) => None,
+        _ => {
+            startup_warnings.push(self_test::StartupWarning {
+                check: "HTTPS",
+                message: "Both tls_cert_path and tls_key_path are required for HTTPS, serving plain HTTP."
+                    .to_string(),
+            });
+            None
+        }
+    };
 
-    let mut jinja = minijinja::Environment::new();
-    minijinja_embed::load_templates!(&mut jinja);
+    for warning in &startup_warnings {
+        tracing::warn!("Startup check ({}): {}", warning.check, warning.message);
+    }
 
     let state = AppState {
         db: db.clone(),
-        jinja: Arc::new(jinja),
+        jinja,
+        config: config.clone(),
+        execution_events: events::execution_channel(),
+        presence: collab::Presence::default(),
+        token_signer,
+        secret_cipher,
+        mailer,
+        storage,
+        login_rate_limiter: rate_limit::LoginRateLimiter::new(
+            config.login_attempts_per_address,
+            config.login_attempts_per_user,
+            config.login_attempts_refill_seconds,
+        ),
+        archive_enabled,
+        endpoint_timings: logging::EndpointTimings::default(),
+        startup_warnings: Arc::new(startup_warnings),
     };
 
-    // build our application with a route
-    let app = router()
-        .layer(middleware::from_fn_with_state(
-            state.clone(),
-            auth_middleware,
-        ))
-        .with_state(state);
+    let app = app(state);
+    let app = if config.base_path.is_empty() {
+        app
+    } else {
+        // Nesting at "/prefix/" keeps "/prefix/" as the home page; the bare prefix redirects there.
+        let home = format!("{}/", config.base_path);
+        Router::new()
+            .route(
+                &config.base_path,
+                get(move || async move { axum::response::Redirect::permanent(&home) }),
+            )
+            .nest(&format!("{}/", config.base_path), app)
+    };
 
-    // run our app with hyper, listening globally on port 3000
-    let addr = "0.0.0.0:4040";
+    let addr = config.bind_address.as_str();
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    println!("Starting webserver on: http://{}", addr);
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async {
-            let _ = signal::ctrl_c().await;
-        })
-        .await
-        .unwrap();
-    println!("Shutting down");
+    let shutdown = async {
+        let _ = signal::ctrl_c().await;
+    };
+    match tls_acceptor {
+        Some(acceptor) => {
+            tracing::info!("Starting webserver on: https://{}", addr);
+            tls::serve(listener, acceptor, app, shutdown).await;
+        }
+        None => {
+            tracing::info!("Starting webserver on: http://{}", addr);
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(shutdown)
+            .await
+            .unwrap();
+        }
+    }
+    tracing::info!("Shutting down");
     db.close().await;
 }
 
@@ -95,7 +390,7 @@ fn format_migration_error(err: &sqlx::migrate::MigrateError) -> String {
         sqlx::migrate::MigrateError::VersionMismatch(version) => format!(
             "migration {} was already applied but the file has changed. \
              Restore the original migration file, or create a new migration for changes. \
-             For local/dev-only data, you can also delete ./db/db.sqlite and restart.",
+             For local/dev-only data, you can also delete the database file and restart.",
             version
         ),
         sqlx::migrate::MigrateError::VersionMissing(version) => format!(
@@ -110,26 +405,145 @@ fn format_migration_error(err: &sqlx::migrate::MigrateError) -> String {
     }
 }
 
-fn router() -> Router<AppState> {
+/// The routes with the middleware every request goes through, without the base path.
+fn app(state: AppState) -> Router {
+    router(&state.config.base_path)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            read_only::middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            prefix_redirect_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            security_headers::middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            logging::request_span_middleware,
+        ))
+        .with_state(state)
+}
+
+fn router(base_path: &str) -> Router<AppState> {
     let admin_routes = Router::new()
+        .route("/admin", get(admin::index))
+        .route("/admin/activity", get(audit::activity_get))
+        .route("/admin/actions.csv", get(catalog::export_csv))
+        .route("/admin/actions.json", get(catalog::export_json))
+        .route("/admin/usage", get(usage::index))
+        .route("/admin/usage.json", get(usage::index_json))
+        .route("/admin/sync", post(sync::sync_now_post))
+        .route(
+            "/admin/jobs/{job}",
+            get(admin::job_preview_get).post(admin::job_run_post),
+        )
+        .route("/admin/quarantine", get(attachments::quarantine_get))
+        .route(
+            "/admin/quarantine/{id}/release",
+            post(attachments::quarantine_release_post),
+        )
+        .route(
+            "/admin/quarantine/{id}/delete",
+            post(attachments::quarantine_delete_post),
+        )
+        .route("/attachments/{id}/original", get(attachments::original_get))
         .route("/backup", get(backup::index))
         .route("/backup/export.json", get(backup::export_json))
         .route("/backup/import", post(backup::import_json))
-        .route("/users", get(users::index).post(users::create_post))
+        .route("/users", get(users::index))
+        .route("/users/invites", post(invites::create_post))
+        .route("/users/invites/{id}/revoke", post(invites::revoke_post))
         .route(
             "/users/{id}/delete",
             get(users::delete_get).post(users::delete_post),
         )
+        .route(
+            "/users/{id}/edit",
+            get(users::edit_get).post(users::edit_post),
+        )
+        .route("/users/{id}/unlock", post(users::unlock_post))
+        .route("/users/{id}/disable", post(users::disable_post))
+        .route("/users/{id}/enable", post(users::enable_post))
+        .route("/users/{id}/2fa/reset", post(two_factor::reset_post))
+        .route("/groups", get(groups::index).post(groups::create_post))
+        .route(
+            "/groups/{id}/edit",
+            get(groups::edit_get).post(groups::edit_post),
+        )
+        .route(
+            "/groups/{id}/delete",
+            get(groups::delete_get).post(groups::delete_post),
+        )
+        .route(
+            "/action_plan/{id}/merge",
+            get(action_plan::merge_get).post(action_plan::merge_post),
+        )
         .route_layer(middleware::from_extractor::<RequireAdmin>());
 
-    Router::new()
-        // `GET /` goes to `root`
-        .route("/", get(action_plan::index))
-        .route("/executions", get(executions::index))
-        .route("/executions/{id}", get(executions::show))
+    let plan_editor_routes = Router::new()
+        .route("/action_plan/{id}/delete", post(action_plan::delete_post))
+        .route(
+            "/action_plan/{id}/undelete",
+            post(action_plan::undelete_post),
+        )
+        .route("/action_plan/new", get(action_plan::new_get))
+        .route("/action_plan/new", post(action_plan::new_post))
+        .route("/action_plan/{id}/edit", get(action_plan::edit_get))
+        .route("/action_plan/{id}/edit", post(action_plan::edit_post))
+        .route("/action_plan/{id}/scan-codes", get(scan::codes_get))
+        .route("/action_plan/{id}/sync/reset", post(sync::reset_post))
+        .route(
+            "/action_plan/{id}/attachments",
+            post(attachments::plan_upload_post).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/plan-attachments/{id}/delete",
+            post(attachments::plan_delete_post),
+        )
+        .route(
+            "/actions/{id}/links",
+            post(action_library::link_create_post),
+        )
+        .route(
+            "/action-links/{id}/delete",
+            post(action_library::link_delete_post),
+        )
+        .route("/tags/new", post(tags::create_post))
+        .route("/tags/{id}/delete", get(tags::delete_get))
+        .route("/tags/{id}/edit", post(tags::edit_post))
+        .route("/tags/{id}/delete", post(tags::delete_post))
+        .route_layer(middleware::from_extractor::<RequirePlanEditor>());
+
+    let operator_routes = Router::new()
         .route("/executions/{id}/note", post(executions::update_note_post))
+        .route(
+            "/executions/{id}/label",
+            post(executions::update_label_post),
+        )
         .route("/executions/{id}/complete", get(executions::complete_get))
         .route("/executions/{id}/reopen", get(executions::reopen_get))
+        .route("/executions/{id}/transfer", post(transfers::transfer_post))
+        .route("/executions/{id}/comments", post(comments::create_post))
+        .route(
+            "/executions/{id}/acknowledge-procedure",
+            post(procedure_changes::acknowledge_post),
+        )
+        .route(
+            "/execution-items/{id}/attachments",
+            post(attachments::upload_post).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/attachments/{id}/delete", post(attachments::delete_post))
+        .route(
+            "/execution-comments/{id}/delete",
+            post(comments::delete_post),
+        )
         .route(
             "/executions/{id}/delete",
             get(executions::delete_get).post(executions::delete_post),
@@ -138,89 +552,119 @@ fn router() -> Router<AppState> {
             "/execution-items/{id}/finished",
             post(executions::set_item_finished_post),
         )
-        .route("/action_plan_execution/{id}", get(executions::show))
-        .route("/action_plan/{id}", get(action_plan::show_action_plan))
+        .route(
+            "/execution-items/{id}/note",
+            post(executions::set_item_note_post),
+        )
+        .route(
+            "/execution-items/{id}/assignee",
+            post(assignees::assign_post),
+        )
+        .route("/execution-items/{id}/block", post(blocks::block_post))
+        .route(
+            "/execution-items/{id}/resolve-block",
+            post(blocks::resolve_post),
+        )
+        .route("/execution-items/{id}/skip", post(skips::skip_post))
+        .route("/execution-items/{id}/unskip", post(skips::unskip_post))
+        .route("/execution-items/{id}/fail", post(failures::fail_post))
+        .route("/execution-items/{id}/unfail", post(failures::unfail_post))
         .route("/action_plan/{id}/execute", post(executions::create_post))
-        .route("/action_plan/{id}/delete", post(action_plan::delete_post))
+        .route("/api/executions/batch", post(executions::batch_post))
+        .route("/scan", get(scan::scan_get).post(scan::scan_post))
+        .route("/tasks/{id}/assignee", post(tasks::assign_post))
+        .route("/tasks/{id}/close", post(tasks::close_post))
+        .route_layer(middleware::from_extractor::<RequireOperator>());
+
+    Router::new()
+        // `GET /` goes to `root`
+        .route("/", get(action_plan::home))
+        .route("/plans", get(action_plan::index))
+        .route("/events", get(executions::global_events))
+        .route("/executions", get(executions::index))
+        .route("/executions/my-items", get(assignees::my_items_get))
+        .route("/executions/{id}", get(executions::show))
+        .route("/executions/archive/{id}", get(archive::show))
+        .route("/executions/{id}/events", get(executions::events))
+        .route("/executions/{id}/compare", get(executions::compare_get))
+        .route("/executions/{id}/gallery", get(gallery::execution_get))
+        .route("/action_plan/{id}/gallery", get(gallery::plan_get))
         .route(
-            "/action_plan/{id}/undelete",
-            post(action_plan::undelete_post),
+            "/executions/{id}/export/{format}",
+            get(executions::export_get),
         )
-        .route("/action_plan/new", get(action_plan::new_get))
-        .route("/action_plan/new", post(action_plan::new_post))
-        .route("/action_plan/{id}/edit", get(action_plan::edit_get))
-        .route("/action_plan/{id}/edit", post(action_plan::edit_post))
+        .route("/executions/{id}/ws", get(executions::ws_get))
+        .route("/action_plan_execution/{id}", get(executions::show))
+        .route("/action_plan/{id}", get(action_plan::show_action_plan))
+        .route("/plans/{slug}", get(action_plan::slug_get))
+        .route("/actions", get(action_library::index))
+        .route("/actions/{id}", get(action_library::show_get))
         .route("/actions/search", get(action_plan::search_actions))
+        .route("/action_plan/search", get(action_plan::search_plans))
+        .route(
+            "/action_plan/items/search",
+            get(action_plan::search_plan_items),
+        )
+        .route("/api/plans/{id}/markdown", get(action_plan::markdown_get))
+        .route("/calendar", get(calendar::index))
+        .route("/calendar/token", post(calendar::regenerate_token_post))
+        .route("/calendar.ics", get(calendar::feed))
+        .route("/attachments/{id}", get(attachments::file_get))
+        .route("/plan-attachments/{id}", get(attachments::plan_file_get))
+        .route(
+            "/attachments/{id}/thumbnail",
+            get(attachments::thumbnail_get),
+        )
+        .route("/api/sync/plans", get(sync::plans_get))
+        .route("/stats/heatmap", get(stats::heatmap))
+        .route("/stats/heatmap.json", get(stats::heatmap_json))
+        .route("/reports", get(reports::index))
+        .route("/reports/{id}", get(reports::show))
         .route("/tags", get(tags::index))
         .route("/tags/search", get(tags::search))
-        .route("/tags/new", post(tags::create_post))
-        .route("/tags/{id}/delete", get(tags::delete_get))
-        .route("/tags/{id}/edit", post(tags::edit_post))
-        .route("/tags/{id}/delete", post(tags::delete_post))
+        .route("/tasks", get(tasks::index))
         .route("/setup", get(users::setup_get).post(users::setup_post))
         .route("/login", get(users::login_get).post(users::login_post))
+        .route("/login/2fa", post(users::login_two_factor_post))
         .route("/logout", post(users::logout_post))
-        .merge(admin_routes)
-        .route(
-            "/static/style.css",
-            get((
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static(mime::TEXT_CSS_UTF_8.as_ref()),
-                )],
-                include_bytes!("../assets/static/style.css"),
-            )),
-        )
+        .route(auto_logout::EVENTS_PATH, get(auto_logout::events_get))
+        .route("/session/keep-alive", post(auto_logout::keep_alive_post))
         .route(
-            "/static/script.js",
-            get((
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static(mime::APPLICATION_JAVASCRIPT_UTF_8.as_ref()),
-                )],
-                include_bytes!("../assets/static/script.js"),
-            )),
+            "/invite",
+            get(invites::accept_get).post(invites::accept_post),
         )
+        .route("/verify-email", get(emails::verify_get))
+        .route("/users/me", get(users::account_get))
+        .route("/users/me/home", post(users::home_page_post))
+        .route("/users/me/email", post(emails::edit_post))
+        .route("/users/me/email/resend", post(emails::resend_post))
         .route(
-            "/static/action_item_search.js",
-            get((
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static(mime::APPLICATION_JAVASCRIPT_UTF_8.as_ref()),
-                )],
-                include_bytes!("../assets/static/action_item_search.js"),
-            )),
+            "/users/me/2fa",
+            get(two_factor::setup_get).post(two_factor::setup_post),
         )
+        .route("/users/me/2fa/disable", post(two_factor::disable_post))
         .route(
-            "/static/action_plan_reorder.js",
-            get((
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static(mime::APPLICATION_JAVASCRIPT_UTF_8.as_ref()),
-                )],
-                include_bytes!("../assets/static/action_plan_reorder.js"),
-            )),
+            "/users/me/2fa/recovery-codes",
+            post(two_factor::recovery_codes_post),
         )
+        .route("/users/me/sessions", get(users::sessions_get))
         .route(
-            "/static/tag_filter.js",
-            get((
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static(mime::APPLICATION_JAVASCRIPT_UTF_8.as_ref()),
-                )],
-                include_bytes!("../assets/static/tag_filter.js"),
-            )),
+            "/users/me/sessions/{id}/revoke",
+            post(users::revoke_session_post),
         )
-        .route(
-            "/static/tag_picker.js",
-            get((
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static(mime::APPLICATION_JAVASCRIPT_UTF_8.as_ref()),
-                )],
-                include_bytes!("../assets/static/tag_picker.js"),
-            )),
+        .route("/healthz", get(health::healthz))
+        .merge(admin_routes)
+        .merge(plan_editor_routes)
+        .merge(operator_routes)
+        .merge(
+            SwaggerUi::new("/api/docs")
+                .url("/api/openapi.json", openapi::ApiDoc::openapi())
+                .config(utoipa_swagger_ui::Config::new([format!(
+                    "{}/api/openapi.json",
+                    base_path
+                )])),
         )
+        .route("/static/{file}", get(assets::serve))
 }
 
 struct RequireAdmin;
@@ -232,22 +676,50 @@ where
     type Rejection = AppError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        let current_user = parts
-            .extensions
-            .get::<CurrentUser>()
-            .cloned()
-            .ok_or_else(|| AppError::unauthorized("Authentication required."))?;
-
-        if current_user.is_admin {
-            Ok(Self)
-        } else {
-            Err(AppError::forbidden(
-                "Only admin users can access this endpoint.",
-            ))
-        }
+        require_action(parts, authz::Action::AccessAdminPages)?;
+        Ok(Self)
+    }
+}
+
+/// Limits creating, editing and deleting plans and tags to admins.
+struct RequirePlanEditor;
+
+impl<S> FromRequestParts<S> for RequirePlanEditor
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        require_action(parts, authz::Action::EditPlans)?;
+        Ok(Self)
+    }
+}
+
+/// Keeps viewers out of the routes that start or change executions.
+struct RequireOperator;
+
+impl<S> FromRequestParts<S> for RequireOperator
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        require_action(parts, authz::Action::RunExecutions)?;
+        Ok(Self)
     }
 }
 
+fn require_action(parts: &Parts, action: authz::Action) -> Result<(), AppError> {
+    let current_user = parts
+        .extensions
+        .get::<CurrentUser>()
+        .ok_or_else(|| AppError::unauthorized("Authentication required."))?;
+
+    authz::authorize(current_user, action, authz::Resource::Everything)
+}
+
 impl FromRequestParts<AppState> for CurrentUser {
     type Rejection = AppError;
 
@@ -263,6 +735,42 @@ impl FromRequestParts<AppState> for CurrentUser {
     }
 }
 
+enum PublicPath {
+    Exact(&'static str),
+    Prefix(&'static str),
+}
+
+impl PublicPath {
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            PublicPath::Exact(exact) => path == *exact,
+            PublicPath::Prefix(prefix) => path.starts_with(prefix),
+        }
+    }
+}
+
+/// Served without a session, even before setup has created the first user.
+const ALWAYS_PUBLIC_PATHS: &[PublicPath] = &[
+    PublicPath::Prefix("/static/"),
+    PublicPath::Exact("/healthz"),
+];
+
+/// Served without a session once setup is done. Anything listed here must do its own access
+/// checks, e.g. the calendar feed, invites, email verification, the second login step and the
+/// published plans for plan sync, which are opened with a token instead of a login.
+const PUBLIC_PATHS: &[PublicPath] = &[
+    PublicPath::Exact("/login"),
+    PublicPath::Exact("/login/2fa"),
+    PublicPath::Exact("/calendar.ics"),
+    PublicPath::Exact("/invite"),
+    PublicPath::Exact("/verify-email"),
+    PublicPath::Exact("/api/sync/plans"),
+];
+
+fn is_public(paths: &[PublicPath], path: &str) -> bool {
+    paths.iter().any(|public_path| public_path.matches(path))
+}
+
 async fn auth_middleware(
     State(state): State<AppState>,
     jar: CookieJar,
@@ -270,7 +778,7 @@ async fn auth_middleware(
     next: Next,
 ) -> Response {
     let path = request.uri().path().to_string();
-    if path.starts_with("/static/") {
+    if is_public(ALWAYS_PUBLIC_PATHS, &path) {
         return next.run(request).await;
     }
 
@@ -290,23 +798,97 @@ async fn auth_middleware(
         return axum::response::Redirect::to("/login").into_response();
     }
 
-    if path == "/login" {
+    if is_public(PUBLIC_PATHS, &path) {
         return next.run(request).await;
     }
 
-    let session_id = match users::read_session_cookie(&jar) {
+    let session_id = match users::read_session_cookie(&jar, &state.config) {
         Some(id) => id,
         None => return axum::response::Redirect::to("/login").into_response(),
     };
 
-    let current_user = match users::resolve_current_user_from_session(&state.db, session_id).await {
+    let client = users::SessionClient::new(
+        client_ip::resolve(&state.config, request.extensions(), request.headers()),
+        request.headers(),
+    );
+    let current_user = match users::resolve_current_user_from_session(
+        &state.db,
+        session_id,
+        &client,
+        &state.config,
+        path != auto_logout::EVENTS_PATH,
+    )
+    .await
+    {
         Ok(Some(user)) => user,
         Ok(None) => return axum::response::Redirect::to("/login").into_response(),
         Err(err) => return err.into_response(),
     };
 
+    let auto_logout_seconds = current_user.auto_logout_seconds;
     request.extensions_mut().insert(current_user);
-    next.run(request).await
+    auto_logout::scope(auto_logout_seconds, next.run(request)).await
+}
+
+/// Handlers redirect to root-relative paths; prefix them with the configured base path.
+async fn prefix_redirect_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if state.config.base_path.is_empty() {
+        return response;
+    }
+
+    let prefixed = response
+        .headers()
+        .get(header::LOCATION)
+        .and_then(|value| value.to_str().ok())
+        .filter(|location| location.starts_with('/') && !location.starts_with("//"))
+        .and_then(|location| {
+            HeaderValue::from_str(&format!("{}{}", state.config.base_path, location)).ok()
+        });
+    if let Some(location) = prefixed {
+        response.headers_mut().insert(header::LOCATION, location);
+    }
+    response
+}
+
+/// Absolute link to `path` for use outside the app, such as calendar feeds and invites. Uses
+/// `public_url` when set, otherwise the Host header of the request, so the link matches the
+/// address the user reached us at. Links sent to others use [`public_url`] instead.
+pub fn absolute_url(config: &config::Config, headers: &HeaderMap, path: &str) -> String {
+    if let Some(url) = public_url(config, path) {
+        return url;
+    }
+    let host = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or(config.bind_address.as_str());
+    let scheme = if config.tls_cert_path.is_some() || config.session_cookie_secure() {
+        "https"
+    } else {
+        "http"
+    };
+    format!("{}://{}{}{}", scheme, host, config.base_path, path)
+}
+
+/// Absolute link to `path` under `public_url`, for mails. `None` when it is not set, as the
+/// Host header of a request could point the recipient anywhere.
+pub fn public_url(config: &config::Config, path: &str) -> Option<String> {
+    config
+        .public_url
+        .as_deref()
+        .map(|base| format!("{}{}", base.trim_end_matches('/'), path))
+}
+
+/// Current time in seconds since the Unix epoch, as stored in the database.
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 pub fn format_unix_timestamp(timestamp: i64) -> String {
@@ -320,106 +902,42 @@ pub fn format_unix_timestamp(timestamp: i64) -> String {
     }
 }
 
-#[derive(Debug)]
-struct UnusedAction {
-    id: Uuid,
-    name: String,
+/// Whole minutes, switching to hours from 60 minutes on, e.g. "1 h 5 min".
+pub fn format_duration(seconds: i64) -> String {
+    let minutes = seconds.abs() / 60;
+    if minutes < 60 {
+        format!("{} min", minutes)
+    } else {
+        format!("{} h {} min", minutes / 60, minutes % 60)
+    }
 }
 
-async fn run_action_gc_scheduler(db: SqlitePool) {
-    let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+async fn run_action_gc_scheduler(db: SqlitePool, config: Arc<config::Config>) {
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(config.action_gc_interval_seconds));
     interval.tick().await;
 
     loop {
         interval.tick().await;
-        run_action_gc(&db).await;
+        jobs::run_scheduled(&db, &config, jobs::Job::ActionGc).await;
+        run_db_optimize(&db).await;
     }
 }
 
-async fn run_session_gc_scheduler(db: SqlitePool) {
-    let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+async fn run_session_gc_scheduler(db: SqlitePool, config: Arc<config::Config>) {
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(config.session_gc_interval_seconds));
     interval.tick().await;
 
     loop {
         interval.tick().await;
-        run_session_gc(&db).await;
+        jobs::run_scheduled(&db, &config, jobs::Job::SessionGc).await;
     }
 }
 
-async fn run_action_gc(db: &SqlitePool) {
-    match collect_and_delete_unused_actions(db).await {
-        Ok(unused_actions) if unused_actions.is_empty() => {
-            println!("Action GC: no unused actions found.");
-        }
-        Ok(unused_actions) => {
-            let action_labels = unused_actions
-                .iter()
-                .map(|action| format!("{} ({})", action.name, action.id))
-                .collect::<Vec<_>>()
-                .join(", ");
-            println!(
-                "Action GC: deleted {} unused action(s): {}",
-                unused_actions.len(),
-                action_labels
-            );
-        }
-        Err(err) => {
-            eprintln!("Action GC failed: {}", err);
-        }
+/// Lets SQLite refresh query planner statistics for tables whose usage changed since the last run.
+async fn run_db_optimize(db: &SqlitePool) {
+    if let Err(err) = sqlx::query("PRAGMA optimize").execute(db).await {
+        tracing::error!("Database optimize failed: {}", err);
     }
 }
-
-async fn run_session_gc(db: &SqlitePool) {
-    match users::cleanup_expired_sessions(db).await {
-        Ok(0) => {
-            println!("Session GC: no expired sessions found.");
-        }
-        Ok(count) => {
-            println!("Session GC: deleted {} expired session(s).", count);
-        }
-        Err(err) => {
-            eprintln!("Session GC failed: {}", err);
-        }
-    }
-}
-
-async fn collect_and_delete_unused_actions(db: &SqlitePool) -> anyhow::Result<Vec<UnusedAction>> {
-    let mut tx = db.begin().await?;
-
-    let unused_actions = sqlx::query!(
-        r#"
-        SELECT
-            actions.id as "id: uuid::Uuid",
-            actions.name
-        FROM actions
-        WHERE NOT EXISTS (
-            SELECT 1
-            FROM action_items
-            WHERE action_items.action = actions.id
-        )
-        AND NOT EXISTS (
-            SELECT 1
-            FROM action_item_executions
-            WHERE action_item_executions.action = actions.id
-        )
-        "#
-    )
-    .fetch_all(&mut *tx)
-    .await?;
-
-    for action in &unused_actions {
-        sqlx::query!("DELETE FROM actions WHERE id = $1", action.id)
-            .execute(&mut *tx)
-            .await?;
-    }
-
-    tx.commit().await?;
-
-    Ok(unused_actions
-        .into_iter()
-        .map(|action| UnusedAction {
-            id: action.id,
-            name: action.name,
-        })
-        .collect())
-}
@@ -0,0 +1,376 @@
+//! A small Markdown renderer for action descriptions. Everything is HTML-escaped first and only
+//! the tags produced here make it into the output, so descriptions cannot inject markup. It
+//! covers what instructions need: paragraphs, headings, lists, quotes, code, emphasis and
+//! links. Single line breaks are kept, as people write instructions line by line.
+
+/// Longest description accepted, in characters.
+pub const MAX_SOURCE_CHARS: usize = 10_000;
+
+/// Renders `source` to sanitized HTML.
+pub fn to_html(source: &str) -> String {
+    let mut html = String::new();
+    let lines: Vec<&str> = source.lines().map(str::trim_end).collect();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            index += 1;
+            continue;
+        }
+
+        if trimmed.starts_with("```") {
+            let mut code = Vec::new();
+            index += 1;
+            while index < lines.len() && !lines[index].trim_start().starts_with("```") {
+                code.push(lines[index]);
+                index += 1;
+            }
+            index += 1;
+            html.push_str("<pre><code>");
+            html.push_str(&escape(&code.join("\n")));
+            html.push_str("</code></pre>");
+            continue;
+        }
+
+        if let Some((level, text)) = heading(trimmed) {
+            // Descriptions sit under an item, so their headings start below the page's own.
+            let tag = format!("h{}", level + 3);
+            html.push_str(&format!("<{}>{}</{}>", tag, inline(text), tag));
+            index += 1;
+            continue;
+        }
+
+        if list_item(trimmed).is_some() {
+            let ordered = list_item(trimmed).is_some_and(|(ordered, _)| ordered);
+            let tag = if ordered { "ol" } else { "ul" };
+            html.push_str(&format!("<{}>", tag));
+            while index < lines.len() {
+                match list_item(lines[index].trim_start()) {
+                    Some((item_ordered, text)) if item_ordered == ordered => {
+                        html.push_str(&format!("<li>{}</li>", inline(text)));
+                        index += 1;
+                    }
+                    _ => break,
+                }
+            }
+            html.push_str(&format!("</{}>", tag));
+            continue;
+        }
+
+        if trimmed.starts_with('>') {
+            let mut quoted = Vec::new();
+            while index < lines.len() {
+                let Some(text) = lines[index].trim_start().strip_prefix('>') else {
+                    break;
+                };
+                quoted.push(inline(text.trim_start()));
+                index += 1;
+            }
+            html.push_str(&format!("<blockquote>{}</blockquote>", quoted.join("<br>")));
+            continue;
+        }
+
+        let mut paragraph = Vec::new();
+        while index < lines.len() {
+            let trimmed = lines[index].trim_start();
+            if trimmed.is_empty()
+                || trimmed.starts_with("```")
+                || trimmed.starts_with('>')
+                || heading(trimmed).is_some()
+                || list_item(trimmed).is_some()
+            {
+                break;
+            }
+            paragraph.push(inline(trimmed));
+            index += 1;
+        }
+        html.push_str(&format!("<p>{}</p>", paragraph.join("<br>")));
+    }
+    html
+}
+
+/// `# Title` up to `### Title`, as the level and text.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line
+        .chars()
+        .take_while(|character| *character == '#')
+        .count();
+    if !(1..=3).contains(&level) {
+        return None;
+    }
+    line[level..]
+        .strip_prefix(' ')
+        .map(|text| (level, text.trim()))
+}
+
+/// `- item`, `* item` or `1. item`, as whether it is numbered and the text.
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    if let Some(text) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return Some((false, text));
+    }
+    let digits = line
+        .chars()
+        .take_while(|character| character.is_ascii_digit())
+        .count();
+    if digits == 0 || digits > 3 {
+        return None;
+    }
+    line[digits..].strip_prefix(". ").map(|text| (true, text))
+}
+
+/// Renders code spans, links, bold and italic text within a line.
+fn inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let markers = Markers::new(&chars);
+    let mut html = String::new();
+    render_inline(&chars, &markers, 0, chars.len(), &mut html);
+    html
+}
+
+/// Renders `chars[start..end]`. Closing markers are looked up in `markers` and spans only
+/// nest within their own range, so every character is looked at once and a line full of
+/// unclosed markers takes no longer than any other.
+fn render_inline(chars: &[char], markers: &Markers, start: usize, end: usize, html: &mut String) {
+    let mut index = start;
+    while index < end {
+        let character = chars[index];
+        match character {
+            '`' => {
+                if let Some(close) = markers.find(Marker::Backtick, index + 1, end) {
+                    let code: String = chars[index + 1..close].iter().collect();
+                    html.push_str(&format!("<code>{}</code>", escape(&code)));
+                    index = close + 1;
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some(label_end) = markers.find(Marker::LinkMiddle, index + 1, end)
+                    && let Some(url_end) = markers.find(Marker::CloseParen, label_end + 2, end)
+                {
+                    let url: String = chars[label_end + 2..url_end].iter().collect();
+                    let url = url.trim();
+                    if is_safe_url(url) {
+                        html.push_str(&format!(
+                            "<a href=\"{}\" rel=\"noopener noreferrer\" target=\"_blank\">",
+                            escape(url)
+                        ));
+                        render_inline(chars, markers, index + 1, label_end, html);
+                        html.push_str("</a>");
+                        index = url_end + 1;
+                        continue;
+                    }
+                }
+            }
+            '*' if index + 1 < end && chars[index + 1] == '*' => {
+                if let Some(close) = markers
+                    .find(Marker::DoubleStar, index + 2, end)
+                    .filter(|close| *close > index + 2)
+                {
+                    html.push_str("<strong>");
+                    render_inline(chars, markers, index + 2, close, html);
+                    html.push_str("</strong>");
+                    index = close + 2;
+                    continue;
+                }
+            }
+            // Underscores inside words, like in file names, are not emphasis.
+            '*' | '_'
+                if character == '*' || index == start || !chars[index - 1].is_alphanumeric() =>
+            {
+                let marker = if character == '*' {
+                    Marker::Star
+                } else {
+                    Marker::Underscore
+                };
+                if let Some(close) = markers
+                    .find(marker, index + 1, end)
+                    .filter(|close| *close > index + 1)
+                {
+                    html.push_str("<em>");
+                    render_inline(chars, markers, index + 1, close, html);
+                    html.push_str("</em>");
+                    index = close + 1;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        html.push_str(&escape(&character.to_string()));
+        index += 1;
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Marker {
+    Backtick,
+    Star,
+    DoubleStar,
+    Underscore,
+    /// The `](` between the label and the URL of a link.
+    LinkMiddle,
+    CloseParen,
+}
+
+impl Marker {
+    const ALL: [Marker; 6] = [
+        Marker::Backtick,
+        Marker::Star,
+        Marker::DoubleStar,
+        Marker::Underscore,
+        Marker::LinkMiddle,
+        Marker::CloseParen,
+    ];
+
+    fn text(self) -> &'static [char] {
+        match self {
+            Marker::Backtick => &['`'],
+            Marker::Star => &['*'],
+            Marker::DoubleStar => &['*', '*'],
+            Marker::Underscore => &['_'],
+            Marker::LinkMiddle => &[']', '('],
+            Marker::CloseParen => &[')'],
+        }
+    }
+}
+
+/// For each marker and position of a line, where the next occurrence of the marker starts,
+/// filled in by one pass from the end.
+struct Markers {
+    next: [Vec<usize>; 6],
+}
+
+impl Markers {
+    fn new(chars: &[char]) -> Self {
+        let next = Marker::ALL.map(|marker| {
+            let text = marker.text();
+            let mut next = vec![usize::MAX; chars.len() + 1];
+            for position in (0..chars.len()).rev() {
+                next[position] = if chars[position..].starts_with(text) {
+                    position
+                } else {
+                    next[position + 1]
+                };
+            }
+            next
+        });
+        Self { next }
+    }
+
+    /// Position of the next `marker` at or after `from` that ends by `end`.
+    fn find(&self, marker: Marker, from: usize, end: usize) -> Option<usize> {
+        let next = &self.next[marker as usize];
+        let position = *next.get(from)?;
+        (position != usize::MAX && position + marker.text().len() <= end).then_some(position)
+    }
+}
+
+/// Web and mail links, and links within this instance. Browsers read a backslash like a slash
+/// and drop tabs, so `/\evil.example` or `/<tab>/evil.example` would leave the instance.
+fn is_safe_url(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    lower.starts_with("https://")
+        || lower.starts_with("http://")
+        || lower.starts_with("mailto:")
+        || (url.starts_with('/')
+            && !url.starts_with("//")
+            && !url
+                .chars()
+                .any(|character| character == '\\' || character.is_control()))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(
+            to_html("<script>alert('x')</script> & \"quotes\""),
+            "<p>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; &quot;quotes&quot;</p>"
+        );
+        assert_eq!(
+            to_html("`<b>` and **<i>**"),
+            "<p><code>&lt;b&gt;</code> and <strong>&lt;i&gt;</strong></p>"
+        );
+        assert_eq!(
+            to_html("```\n<b>*not emphasis*</b>\n```"),
+            "<pre><code>&lt;b&gt;*not emphasis*&lt;/b&gt;</code></pre>"
+        );
+    }
+
+    #[test]
+    fn renders_safe_links() {
+        assert_eq!(
+            to_html("[Manual](https://example.com/a?b=1&c=\"2\")"),
+            "<p><a href=\"https://example.com/a?b=1&amp;c=&quot;2&quot;\" rel=\"noopener noreferrer\" target=\"_blank\">Manual</a></p>"
+        );
+        assert_eq!(
+            to_html("[Plans](/plans)"),
+            "<p><a href=\"/plans\" rel=\"noopener noreferrer\" target=\"_blank\">Plans</a></p>"
+        );
+    }
+
+    #[test]
+    fn leaves_unsafe_links_as_text() {
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "data:text/html,x",
+            "//evil.example",
+            "/\\evil.example",
+            "/\t/evil.example",
+            "evil.example",
+        ] {
+            let html = to_html(&format!("[click]({})", url));
+            assert!(!html.contains("<a "), "{:?} became a link: {}", url, html);
+        }
+    }
+
+    #[test]
+    fn nests_emphasis_and_links() {
+        assert_eq!(
+            to_html("**bold *and italic* text**"),
+            "<p><strong>bold <em>and italic</em> text</strong></p>"
+        );
+        assert_eq!(
+            to_html("_check **all** bolts_"),
+            "<p><em>check <strong>all</strong> bolts</em></p>"
+        );
+        assert_eq!(
+            to_html("**see [the *manual*](/manual)**"),
+            "<p><strong>see <a href=\"/manual\" rel=\"noopener noreferrer\" target=\"_blank\">the <em>manual</em></a></strong></p>"
+        );
+        assert_eq!(to_html("*[a*](/b)"), "<p><em>[a</em>](/b)</p>");
+    }
+
+    #[test]
+    fn keeps_underscores_inside_words_and_unclosed_markers() {
+        assert_eq!(to_html("pump_log_2024.csv"), "<p>pump_log_2024.csv</p>");
+        assert_eq!(to_html("a ** b ` [x]("), "<p>a ** b ` [x](</p>");
+    }
+
+    #[test]
+    fn lines_of_unclosed_markers_render_quickly() {
+        let source = "[(".repeat(MAX_SOURCE_CHARS / 2);
+        let started = std::time::Instant::now();
+        let html = to_html(&source);
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+        assert_eq!(html, format!("<p>{}</p>", source));
+    }
+}
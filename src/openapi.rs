@@ -0,0 +1,169 @@
+use utoipa::OpenApi;
+
+use crate::{
+    action_library, action_plan, admin, archive, assignees, attachments, audit, auto_logout,
+    backup, blocks, calendar, catalog, comments, emails, executions, failures, gallery, groups,
+    health, invites, procedure_changes, reports, scan, skips, stats, sync, tags, tasks, transfers,
+    two_factor, usage, users,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Maintenance Planner",
+        description = "Routes for action plans, executions, statistics, tags, users and backups. \
+                       All routes except setup, login, the health check, the calendar feed, invite links and email verification require a session cookie."
+    ),
+    paths(
+        action_plan::home,
+        action_plan::index,
+        action_plan::show_action_plan,
+        action_plan::new_get,
+        action_plan::new_post,
+        action_plan::edit_get,
+        action_plan::edit_post,
+        action_plan::delete_post,
+        action_plan::undelete_post,
+        action_plan::search_actions,
+        action_plan::search_plans,
+        action_plan::search_plan_items,
+        action_plan::markdown_get,
+        action_plan::merge_get,
+        action_plan::merge_post,
+        action_plan::slug_get,
+        action_library::index,
+        action_library::show_get,
+        action_library::link_create_post,
+        action_library::link_delete_post,
+        executions::index,
+        archive::show,
+        admin::index,
+        admin::job_preview_get,
+        admin::job_run_post,
+        audit::activity_get,
+        catalog::export_csv,
+        catalog::export_json,
+        usage::index,
+        usage::index_json,
+        sync::plans_get,
+        sync::sync_now_post,
+        sync::reset_post,
+        executions::create_post,
+        executions::batch_post,
+        executions::show,
+        executions::events,
+        executions::compare_get,
+        gallery::execution_get,
+        gallery::plan_get,
+        executions::export_get,
+        executions::global_events,
+        executions::ws_get,
+        executions::update_note_post,
+        executions::update_label_post,
+        executions::complete_get,
+        executions::reopen_get,
+        transfers::transfer_post,
+        comments::create_post,
+        procedure_changes::acknowledge_post,
+        comments::delete_post,
+        attachments::upload_post,
+        attachments::file_get,
+        attachments::thumbnail_get,
+        attachments::original_get,
+        attachments::quarantine_get,
+        attachments::quarantine_release_post,
+        attachments::quarantine_delete_post,
+        attachments::delete_post,
+        attachments::plan_upload_post,
+        attachments::plan_file_get,
+        attachments::plan_delete_post,
+        executions::delete_get,
+        executions::delete_post,
+        executions::set_item_finished_post,
+        executions::set_item_note_post,
+        assignees::assign_post,
+        assignees::my_items_get,
+        blocks::block_post,
+        blocks::resolve_post,
+        skips::skip_post,
+        skips::unskip_post,
+        failures::fail_post,
+        failures::unfail_post,
+        scan::codes_get,
+        scan::scan_get,
+        scan::scan_post,
+        calendar::index,
+        calendar::regenerate_token_post,
+        calendar::feed,
+        stats::heatmap,
+        stats::heatmap_json,
+        reports::index,
+        reports::show,
+        tags::index,
+        tags::search,
+        tags::create_post,
+        tags::edit_post,
+        tags::delete_get,
+        tags::delete_post,
+        tasks::index,
+        tasks::assign_post,
+        tasks::close_post,
+        users::setup_get,
+        users::setup_post,
+        users::login_get,
+        users::login_post,
+        users::login_two_factor_post,
+        users::logout_post,
+        users::sessions_get,
+        users::revoke_session_post,
+        users::account_get,
+        auto_logout::events_get,
+        auto_logout::keep_alive_post,
+        users::home_page_post,
+        emails::edit_post,
+        emails::resend_post,
+        emails::verify_get,
+        two_factor::setup_get,
+        two_factor::setup_post,
+        two_factor::disable_post,
+        two_factor::recovery_codes_post,
+        users::index,
+        invites::create_post,
+        invites::revoke_post,
+        invites::accept_get,
+        invites::accept_post,
+        users::edit_get,
+        users::edit_post,
+        users::unlock_post,
+        users::disable_post,
+        users::enable_post,
+        two_factor::reset_post,
+        users::delete_get,
+        users::delete_post,
+        groups::index,
+        groups::create_post,
+        groups::edit_get,
+        groups::edit_post,
+        groups::delete_get,
+        groups::delete_post,
+        backup::index,
+        backup::export_json,
+        backup::import_json,
+        health::healthz,
+    ),
+    tags(
+        (name = "action_plans", description = "Action plan templates"),
+        (name = "actions", description = "The action library with descriptions and reference links"),
+        (name = "executions", description = "Running and finished executions of action plans"),
+        (name = "calendar", description = "Token-protected iCalendar feed"),
+        (name = "stats", description = "Statistics about completed executions"),
+        (name = "reports", description = "Archived monthly reports"),
+        (name = "tags", description = "Tags for organizing action plans"),
+        (name = "tasks", description = "Follow-up tasks for defects found during executions"),
+        (name = "users", description = "Setup, login, user and group administration"),
+        (name = "backup", description = "JSON backup export and import"),
+        (name = "admin", description = "Diagnostics for admins"),
+        (name = "health", description = "Unauthenticated health check"),
+    )
+)]
+pub struct ApiDoc;
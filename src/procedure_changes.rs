@@ -0,0 +1,276 @@
+//! Telling people when a procedure changed since they last worked through it. When a user opens
+//! an execution whose items differ from the plan's procedure they last acknowledged (or, if they
+//! never acknowledged one, from their previous run of the plan), the page lists what changed
+//! until they confirm having reviewed it. Plan pages show who is up to date with the current
+//! procedure, for following up on training.
+
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+};
+use serde::Serialize;
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, authorize},
+    format_unix_timestamp, unix_now,
+};
+
+/// What changed between the procedure a user knows and the items of an execution.
+#[derive(Debug, Serialize)]
+pub struct ProcedureChangeNotice {
+    added: Vec<String>,
+    removed: Vec<String>,
+    /// Set when the same items now come in another order.
+    is_reordered: bool,
+    /// When the known version was acknowledged or run.
+    since_display: String,
+    /// Whether the known version is an acknowledgment rather than a previous run.
+    is_acknowledged: bool,
+}
+
+/// A user's acknowledgment as listed on the plan page.
+#[derive(Debug, Serialize)]
+pub struct ProcedureAcknowledgment {
+    user_name: String,
+    acknowledged_display: String,
+    /// Whether it covers the plan's current items.
+    is_current: bool,
+}
+
+/// The change notice for `current_user` on an open execution, `None` when they know the
+/// procedure as it is or have never worked through the plan before.
+pub async fn fetch_notice(
+    db: &SqlitePool,
+    execution_id: Uuid,
+    current_user: &CurrentUser,
+) -> Result<Option<ProcedureChangeNotice>, AppError> {
+    let mut conn = db.acquire().await?;
+    let execution = sqlx::query!(
+        r#"
+        SELECT action_plan as "plan_id: uuid::Uuid", started
+        FROM action_plan_executions
+        WHERE id = $1
+        "#,
+        execution_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let acknowledged = sqlx::query!(
+        r#"
+        SELECT items, acknowledged_at
+        FROM procedure_acknowledgments
+        WHERE action_plan = $1 AND user_id = $2
+        "#,
+        execution.plan_id,
+        current_user.id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let (known_items, since, is_acknowledged) = match acknowledged {
+        Some(acknowledged) => (
+            split_items(&acknowledged.items),
+            acknowledged.acknowledged_at,
+            true,
+        ),
+        None => {
+            let previous_run = sqlx::query!(
+                r#"
+                SELECT id as "id!: uuid::Uuid", started as "started!"
+                FROM action_plan_executions
+                WHERE action_plan = $1
+                    AND started_by = $2
+                    AND id != $3
+                    AND started <= $4
+                ORDER BY started DESC
+                LIMIT 1
+                "#,
+                execution.plan_id,
+                current_user.id,
+                execution_id,
+                execution.started
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+            let Some(previous_run) = previous_run else {
+                return Ok(None);
+            };
+            (
+                execution_items(&mut conn, previous_run.id).await?,
+                previous_run.started,
+                false,
+            )
+        }
+    };
+
+    let items = execution_items(&mut conn, execution_id).await?;
+    if items == known_items {
+        return Ok(None);
+    }
+    let known: HashSet<&String> = known_items.iter().collect();
+    let current: HashSet<&String> = items.iter().collect();
+    let added: Vec<String> = items
+        .iter()
+        .filter(|item| !known.contains(item))
+        .cloned()
+        .collect();
+    let removed: Vec<String> = known_items
+        .iter()
+        .filter(|item| !current.contains(item))
+        .cloned()
+        .collect();
+
+    Ok(Some(ProcedureChangeNotice {
+        is_reordered: added.is_empty() && removed.is_empty(),
+        added,
+        removed,
+        since_display: format_unix_timestamp(since),
+        is_acknowledged,
+    }))
+}
+
+/// Acknowledgments of a plan's procedure, most recent first.
+pub async fn fetch_for_plan(
+    db: &SqlitePool,
+    plan_id: Uuid,
+) -> Result<Vec<ProcedureAcknowledgment>, AppError> {
+    let current_items = sqlx::query_scalar!(
+        r#"
+        SELECT actions.name as "name!"
+        FROM action_items
+        INNER JOIN actions ON actions.id = action_items.action
+        WHERE action_items.action_plan = $1
+        ORDER BY action_items.order_index ASC
+        "#,
+        plan_id
+    )
+    .fetch_all(db)
+    .await?
+    .join("\n");
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            users.name as "user_name!",
+            procedure_acknowledgments.items,
+            procedure_acknowledgments.acknowledged_at
+        FROM procedure_acknowledgments
+        INNER JOIN users ON users.id = procedure_acknowledgments.user_id
+        WHERE procedure_acknowledgments.action_plan = $1
+        ORDER BY procedure_acknowledgments.acknowledged_at DESC
+        "#,
+        plan_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ProcedureAcknowledgment {
+            user_name: row.user_name,
+            acknowledged_display: format_unix_timestamp(row.acknowledged_at),
+            is_current: row.items == current_items,
+        })
+        .collect())
+}
+
+#[utoipa::path(
+    post,
+    path = "/executions/{id}/acknowledge-procedure",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 303, description = "Records that the current user reviewed the procedure as run in this execution and redirects back to it"),
+        (status = 404, description = "Execution not found", content_type = "text/html"),
+    )
+)]
+pub async fn acknowledge_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let mut tx = state.db.begin().await?;
+    let execution = sqlx::query!(
+        r#"
+        SELECT
+            action_plan_executions.action_plan as "plan_id: uuid::Uuid",
+            action_plans.name as "plan_name!"
+        FROM action_plan_executions
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_plan_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for("Execution", format!("No execution exists for id: {}", id))
+    })?;
+
+    let items = execution_items(&mut tx, id).await?.join("\n");
+    let now = unix_now();
+    sqlx::query!(
+        r#"
+        INSERT INTO procedure_acknowledgments (action_plan, user_id, items, acknowledged_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (action_plan, user_id)
+        DO UPDATE SET items = excluded.items, acknowledged_at = excluded.acknowledged_at
+        "#,
+        execution.plan_id,
+        current_user.id,
+        items,
+        now
+    )
+    .execute(&mut *tx)
+    .await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Plan,
+            execution.plan_id,
+            AuditAction::Update,
+            format!(
+                "Acknowledged the changed procedure of \"{}\"",
+                execution.plan_name
+            ),
+        ),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Redirect::to(&format!("/executions/{}", id)))
+}
+
+/// Item names of an execution in checklist order.
+async fn execution_items(
+    conn: &mut SqliteConnection,
+    execution_id: Uuid,
+) -> Result<Vec<String>, AppError> {
+    Ok(sqlx::query_scalar!(
+        r#"
+        SELECT actions.name as "name!"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        WHERE action_item_executions.action_plan_execution = $1
+        ORDER BY action_item_executions.order_index ASC
+        "#,
+        execution_id
+    )
+    .fetch_all(&mut *conn)
+    .await?)
+}
+
+fn split_items(items: &str) -> Vec<String> {
+    if items.is_empty() {
+        return Vec::new();
+    }
+    items.split('\n').map(str::to_string).collect()
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// Buckets are only pruned once this many keys are tracked, to keep the common path cheap.
+/// It is also the most kept: when every bucket is still in use, the least recently used ones
+/// are dropped down to `KEPT_AFTER_EVICTION`, so made-up keys cannot grow the map without end.
+const PRUNE_THRESHOLD: usize = 10_000;
+const KEPT_AFTER_EVICTION: usize = PRUNE_THRESHOLD * 3 / 4;
+
+/// In-memory token buckets keyed by client address or user name. Each key may make `capacity`
+/// attempts in a burst; spent attempts come back evenly over `refill_seconds`.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(capacity: u32, refill_seconds: u64) -> Self {
+        let capacity = f64::from(capacity.max(1));
+        Self {
+            capacity,
+            refill_per_second: capacity / refill_seconds.max(1) as f64,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Takes one attempt from `key`'s bucket. Returns `false` when the bucket is empty.
+    pub fn try_acquire(&self, key: &str) -> bool {
+        self.try_acquire_at(key, Instant::now())
+    }
+
+    fn try_acquire_at(&self, key: &str, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| self.refilled(bucket, now) < self.capacity);
+        }
+        if buckets.len() >= PRUNE_THRESHOLD {
+            let mut updated = buckets
+                .values()
+                .map(|bucket| bucket.updated)
+                .collect::<Vec<_>>();
+            let evicted = buckets.len() - KEPT_AFTER_EVICTION;
+            let (_, &mut cutoff, _) = updated.select_nth_unstable(evicted);
+            buckets.retain(|_, bucket| bucket.updated >= cutoff);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity)
+    }
+}
+
+/// Separate limits per client address and per user name, so neither one address trying many
+/// names nor many addresses trying one name can guess passwords quickly.
+#[derive(Debug, Clone)]
+pub struct LoginRateLimiter {
+    per_address: RateLimiter,
+    per_user: RateLimiter,
+}
+
+impl LoginRateLimiter {
+    pub fn new(attempts_per_address: u32, attempts_per_user: u32, refill_seconds: u64) -> Self {
+        Self {
+            per_address: RateLimiter::new(attempts_per_address, refill_seconds),
+            per_user: RateLimiter::new(attempts_per_user, refill_seconds),
+        }
+    }
+
+    /// Records a login attempt. Returns `false` if either limit is exhausted. An address over
+    /// its limit is turned away before the name is looked at, so it cannot use up the
+    /// attempts of other people's names or fill the per-user map.
+    pub fn try_acquire(&self, address: &str, user_name: &str) -> bool {
+        self.per_address.try_acquire(address)
+            && self.per_user.try_acquire(&user_name.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn buckets_refill_evenly_up_to_capacity() {
+        let limiter = RateLimiter::new(2, 60);
+        let start = Instant::now();
+        assert!(limiter.try_acquire_at("192.0.2.1", start));
+        assert!(limiter.try_acquire_at("192.0.2.1", start));
+        assert!(!limiter.try_acquire_at("192.0.2.1", start));
+        assert!(limiter.try_acquire_at("192.0.2.2", start));
+
+        // One attempt comes back every 30 seconds.
+        assert!(!limiter.try_acquire_at("192.0.2.1", start + Duration::from_secs(29)));
+        assert!(limiter.try_acquire_at("192.0.2.1", start + Duration::from_secs(59)));
+        assert!(!limiter.try_acquire_at("192.0.2.1", start + Duration::from_secs(59)));
+
+        // Waiting long does not save up more than a full bucket.
+        let later = start + Duration::from_secs(3600);
+        assert!(limiter.try_acquire_at("192.0.2.1", later));
+        assert!(limiter.try_acquire_at("192.0.2.1", later));
+        assert!(!limiter.try_acquire_at("192.0.2.1", later));
+    }
+
+    #[test]
+    fn full_buckets_are_pruned_first() {
+        let limiter = RateLimiter::new(1, 60);
+        let start = Instant::now();
+        for key in 0..PRUNE_THRESHOLD {
+            limiter.try_acquire_at(&key.to_string(), start);
+        }
+        limiter.try_acquire_at("late", start + Duration::from_secs(60));
+        // Every earlier bucket had refilled, so only the new one is left.
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn the_least_recently_used_buckets_are_evicted_when_all_are_in_use() {
+        let limiter = RateLimiter::new(1, 3600);
+        let start = Instant::now();
+        for key in 0..PRUNE_THRESHOLD {
+            let at = start + Duration::from_millis(key as u64);
+            limiter.try_acquire_at(&key.to_string(), at);
+        }
+        let now = start + Duration::from_secs(20);
+        assert!(limiter.try_acquire_at("late", now));
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), KEPT_AFTER_EVICTION + 1);
+        assert!(!buckets.contains_key("0"));
+        assert!(buckets.contains_key(&(PRUNE_THRESHOLD - 1).to_string()));
+        drop(buckets);
+        // Buckets that were kept still remember their spent attempt.
+        assert!(!limiter.try_acquire_at(&(PRUNE_THRESHOLD - 1).to_string(), now));
+    }
+
+    #[test]
+    fn user_names_are_limited_regardless_of_case_and_address() {
+        let limiter = LoginRateLimiter::new(10, 2, 60);
+        assert!(limiter.try_acquire("192.0.2.1", "Alice"));
+        assert!(limiter.try_acquire("192.0.2.2", "alice"));
+        assert!(!limiter.try_acquire("192.0.2.3", "ALICE"));
+        assert!(limiter.try_acquire("192.0.2.3", "bob"));
+    }
+}
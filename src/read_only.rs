@@ -0,0 +1,190 @@
+//! Read-only mode for when the database cannot be written, e.g. because the disk is full or
+//! the filesystem was remounted read-only. The first failed write switches it on: pages keep
+//! working for reading, every page shows a banner, changes are refused with a clear message
+//! instead of an internal error, and admins with a verified address get one mail. A probe
+//! retries a small write every `PROBE_INTERVAL` and switches back once it succeeds.
+
+use std::sync::RwLock;
+
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::SqlitePool;
+use tokio::time::Duration;
+
+use crate::{AppError, AppState, format_unix_timestamp, mail::Mailer, unix_now};
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+// SQLite primary result codes that mean the storage itself is the problem.
+const SQLITE_READONLY: i32 = 8;
+const SQLITE_IOERR: i32 = 10;
+const SQLITE_FULL: i32 = 13;
+const SQLITE_CANTOPEN: i32 = 14;
+
+/// Process-wide like the base path, so error pages and templates without access to
+/// `AppState` can show the banner.
+static OUTAGE: RwLock<Option<Outage>> = RwLock::new(None);
+
+#[derive(Debug, Clone)]
+struct Outage {
+    since: i64,
+    reason: String,
+}
+
+/// Marks a response whose handler failed because the database could not be written, with the
+/// error it failed with.
+#[derive(Debug, Clone)]
+pub struct StorageFailure(pub String);
+
+/// Whether `err` comes from the database being unwritable rather than from the query.
+pub fn is_storage_error(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Io(_) => true,
+        sqlx::Error::Database(db_err) => db_err
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            // Extended result codes keep the primary code in the low byte.
+            .is_some_and(|code| {
+                matches!(
+                    code & 0xff,
+                    SQLITE_READONLY | SQLITE_IOERR | SQLITE_FULL | SQLITE_CANTOPEN
+                )
+            }),
+        _ => false,
+    }
+}
+
+pub fn is_active() -> bool {
+    OUTAGE
+        .read()
+        .map(|outage| outage.is_some())
+        .unwrap_or(false)
+}
+
+/// Banner text for the layout, `None` while the database is writable.
+pub fn notice() -> Option<String> {
+    let outage = OUTAGE.read().ok()?.clone()?;
+    Some(format!(
+        "Read-only mode since {}: the database cannot be written to, so changes cannot be saved. Viewing works as usual.",
+        format_unix_timestamp(outage.since)
+    ))
+}
+
+/// Refuses changes while read-only and switches read-only mode on when a handler fails to write.
+pub async fn middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let is_change = !matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    if is_change && is_active() {
+        return AppError::storage_unavailable().into_response();
+    }
+
+    let response = next.run(request).await;
+    if let Some(StorageFailure(reason)) = response.extensions().get::<StorageFailure>() {
+        enter(&state, reason);
+    }
+    response
+}
+
+fn enter(state: &AppState, reason: &str) {
+    {
+        let Ok(mut outage) = OUTAGE.write() else {
+            return;
+        };
+        if outage.is_some() {
+            return;
+        }
+        *outage = Some(Outage {
+            since: unix_now(),
+            reason: reason.to_string(),
+        });
+    }
+    tracing::error!(
+        "Switching to read-only mode after a failed write: {}",
+        reason
+    );
+
+    tokio::spawn(alert_admins(state.db.clone(), state.mailer.clone()));
+    tokio::spawn(probe_until_writable(state.db.clone()));
+}
+
+async fn alert_admins(db: SqlitePool, mailer: Mailer) {
+    let admins = match sqlx::query!(
+        r#"
+        SELECT name, email as "email!"
+        FROM users
+        WHERE role = 'admin'
+            AND is_disabled = 0
+            AND email IS NOT NULL
+            AND email_verified_at IS NOT NULL
+        "#
+    )
+    .fetch_all(&db)
+    .await
+    {
+        Ok(admins) => admins,
+        Err(err) => {
+            tracing::error!(
+                "Looking up admins to alert about read-only mode failed: {}",
+                err
+            );
+            return;
+        }
+    };
+
+    let Some(outage) = OUTAGE.read().ok().and_then(|outage| outage.clone()) else {
+        return;
+    };
+    for admin in admins {
+        let body = format!(
+            "Hello {},\n\nthe maintenance planner switched to read-only mode at {} because a write \
+to the database failed:\n\n{}\n\nNobody can save changes until the database is writable again; \
+check the free disk space and whether the filesystem is mounted read-write. The server checks \
+every {} seconds and switches back on its own.\n",
+            admin.name,
+            format_unix_timestamp(outage.since),
+            outage.reason,
+            PROBE_INTERVAL.as_secs()
+        );
+        if let Err(err) = mailer
+            .send(&admin.email, "Maintenance planner is read-only", body)
+            .await
+        {
+            tracing::error!("Sending the read-only alert failed: {}", err);
+        }
+    }
+}
+
+async fn probe_until_writable(db: SqlitePool) {
+    let mut interval = tokio::time::interval(PROBE_INTERVAL);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        let now = unix_now();
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO storage_probe (id, checked_at) VALUES (1, $1)
+            ON CONFLICT (id) DO UPDATE SET checked_at = excluded.checked_at
+            "#,
+            now
+        )
+        .execute(&db)
+        .await;
+        match result {
+            Ok(_) => {
+                if let Ok(mut outage) = OUTAGE.write() {
+                    *outage = None;
+                }
+                tracing::info!("The database is writable again, leaving read-only mode");
+                return;
+            }
+            Err(err) => tracing::warn!("Database still not writable: {}", err),
+        }
+    }
+}
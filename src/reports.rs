@@ -0,0 +1,389 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    response::Html,
+};
+use chrono::{DateTime, Datelike, Local, Months, NaiveDate, TimeZone};
+use minijinja::Environment;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser, format_duration, format_unix_timestamp, item_types,
+    storage::Storage, templates, unix_now,
+};
+
+/// How often the scheduler checks for a finished month without a report.
+const REPORT_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize)]
+struct ReportListItem {
+    id: Uuid,
+    title: String,
+    generated_display: String,
+}
+
+#[derive(Serialize)]
+struct ReportListView {
+    reports: Vec<ReportListItem>,
+    is_admin: bool,
+}
+
+#[derive(Serialize)]
+struct ReportShowView {
+    title: String,
+    generated_display: String,
+    content: String,
+    is_admin: bool,
+}
+
+#[derive(Serialize)]
+struct ReportContentView {
+    period_display: String,
+    started_count: i64,
+    finished_count: i64,
+    open_at_end_count: i64,
+    plans: Vec<ReportPlanSummary>,
+    executions: Vec<ReportExecution>,
+    out_of_spec_readings: Vec<ReportOutOfSpecReading>,
+}
+
+#[derive(Serialize)]
+struct ReportPlanSummary {
+    name: String,
+    finished_count: i64,
+    average_duration_display: String,
+}
+
+#[derive(Serialize)]
+struct ReportExecution {
+    action_plan_name: String,
+    run_number: i64,
+    label: Option<String>,
+    started_display: String,
+    finished_display: String,
+    duration_display: String,
+    out_of_spec_count: i64,
+}
+
+/// A reading outside its expected range in a run finished during the month.
+#[derive(Serialize)]
+struct ReportOutOfSpecReading {
+    action_plan_name: String,
+    run_number: i64,
+    item_name: String,
+    value_display: String,
+    range_display: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports",
+    tag = "reports",
+    responses(
+        (status = 200, description = "Archived monthly reports", content_type = "text/html"),
+    )
+)]
+pub async fn index(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    let reports = sqlx::query!(
+        r#"
+        SELECT id as "id!: uuid::Uuid", title, generated
+        FROM reports
+        ORDER BY period_start DESC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|row| ReportListItem {
+        id: row.id,
+        title: row.title,
+        generated_display: format_unix_timestamp(row.generated),
+    })
+    .collect();
+
+    let rendered = templates::render(
+        &state.jinja,
+        "reports.html",
+        ReportListView {
+            reports,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/{id}",
+    tag = "reports",
+    params(
+        ("id" = Uuid, Path, description = "Report id")
+    ),
+    responses(
+        (status = 200, description = "Archived report as generated", content_type = "text/html"),
+        (status = 404, description = "Report not found"),
+    )
+)]
+pub async fn show(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Html<String>, AppError> {
+    let report = sqlx::query!(
+        r#"SELECT title, generated, content FROM reports WHERE id = $1"#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::not_found_for("Report", format!("No report with id {}", id)))?;
+
+    let rendered = templates::render(
+        &state.jinja,
+        "report_show.html",
+        ReportShowView {
+            title: report.title,
+            generated_display: format_unix_timestamp(report.generated),
+            content: report.content,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+/// Archives a report for every finished month since the first execution that does not have one
+/// yet. Reports are rendered once and never regenerated, so they stay as auditors first saw them.
+/// Each is also archived in the storage backend as `reports/<yyyy-mm>.html`.
+pub async fn run_scheduler(
+    db: SqlitePool,
+    jinja: Arc<Environment<'static>>,
+    storage: Arc<dyn Storage>,
+) {
+    let mut interval = tokio::time::interval(REPORT_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(err) = generate_missing_reports(&db, &jinja, storage.as_ref()).await {
+            tracing::error!("Report generation failed: {:#}", err);
+        }
+    }
+}
+
+async fn generate_missing_reports(
+    db: &SqlitePool,
+    jinja: &Environment<'_>,
+    storage: &dyn Storage,
+) -> anyhow::Result<()> {
+    let first_started = sqlx::query_scalar!(
+        r#"SELECT MIN(started) as "first_started: i64" FROM action_plan_executions"#
+    )
+    .fetch_one(db)
+    .await?;
+    let Some(first_started) = first_started else {
+        return Ok(());
+    };
+    let Some(first_started) = Local.timestamp_opt(first_started, 0).single() else {
+        return Ok(());
+    };
+
+    let current_month = first_of_month(Local::now().date_naive());
+    let mut month = first_of_month(first_started.date_naive());
+    while month < current_month {
+        let next_month = month + Months::new(1);
+        let (Some(period_start), Some(period_end)) =
+            (local_midnight(month), local_midnight(next_month))
+        else {
+            month = next_month;
+            continue;
+        };
+
+        let exists = sqlx::query_scalar!(
+            r#"SELECT 1 as "exists!: i64" FROM reports WHERE period_start = $1"#,
+            period_start
+        )
+        .fetch_optional(db)
+        .await?
+        .is_some();
+        if !exists {
+            let title = format!("Monthly report {}", month.format("%B %Y"));
+            let content = render_report(db, jinja, month, period_start, period_end).await?;
+            // Stored first, so a failure leaves the month without a report to retry next time.
+            storage
+                .put(
+                    &format!("reports/{}.html", month.format("%Y-%m")),
+                    content.clone().into_bytes(),
+                )
+                .await?;
+            let id = Uuid::new_v4();
+            let generated = unix_now();
+            sqlx::query!(
+                r#"
+                INSERT OR IGNORE INTO reports (id, period_start, period_end, title, generated, content)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                id,
+                period_start,
+                period_end,
+                title,
+                generated,
+                content
+            )
+            .execute(db)
+            .await?;
+            tracing::info!("Generated {}", title);
+        }
+
+        month = next_month;
+    }
+
+    Ok(())
+}
+
+async fn render_report(
+    db: &SqlitePool,
+    jinja: &Environment<'_>,
+    month: NaiveDate,
+    period_start: i64,
+    period_end: i64,
+) -> anyhow::Result<String> {
+    let started_count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!: i64"
+        FROM action_plan_executions
+        WHERE started >= $1 AND started < $2
+        "#,
+        period_start,
+        period_end
+    )
+    .fetch_one(db)
+    .await?;
+
+    let open_at_end_count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!: i64"
+        FROM action_plan_executions
+        WHERE started < $1 AND (finished IS NULL OR finished <= 0 OR finished >= $1)
+        "#,
+        period_end
+    )
+    .fetch_one(db)
+    .await?;
+
+    let finished = sqlx::query!(
+        r#"
+        SELECT
+            action_plans.name as action_plan_name,
+            action_plan_executions.run_number as "run_number!: i64",
+            action_plan_executions.label,
+            action_plan_executions.started as "started!: i64",
+            action_plan_executions.finished as "finished!: i64",
+            (
+                SELECT COUNT(*)
+                FROM action_item_executions
+                WHERE action_item_executions.action_plan_execution = action_plan_executions.id
+                    AND action_item_executions.out_of_spec
+            ) as "out_of_spec_count!: i64"
+        FROM action_plan_executions
+        JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_plan_executions.finished >= $1 AND action_plan_executions.finished < $2
+        ORDER BY action_plan_executions.finished ASC
+        "#,
+        period_start,
+        period_end
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut durations_by_plan: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+    let mut executions = Vec::with_capacity(finished.len());
+    for row in finished {
+        let duration = (row.finished - row.started).max(0);
+        durations_by_plan
+            .entry(row.action_plan_name.clone())
+            .or_default()
+            .push(duration);
+        executions.push(ReportExecution {
+            action_plan_name: row.action_plan_name,
+            run_number: row.run_number,
+            label: row.label,
+            started_display: format_unix_timestamp(row.started),
+            finished_display: format_unix_timestamp(row.finished),
+            duration_display: format_duration(duration),
+            out_of_spec_count: row.out_of_spec_count,
+        });
+    }
+    let plans = durations_by_plan
+        .into_iter()
+        .map(|(name, durations)| ReportPlanSummary {
+            name,
+            finished_count: durations.len() as i64,
+            average_duration_display: format_duration(
+                durations.iter().sum::<i64>() / durations.len() as i64,
+            ),
+        })
+        .collect();
+
+    let out_of_spec_readings = sqlx::query!(
+        r#"
+        SELECT
+            action_plans.name as action_plan_name,
+            action_plan_executions.run_number as "run_number!: i64",
+            actions.name as item_name,
+            action_item_executions.value as "value!",
+            action_item_executions.unit,
+            action_item_executions.min_value,
+            action_item_executions.max_value
+        FROM action_item_executions
+        JOIN actions ON actions.id = action_item_executions.action
+        JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_item_executions.out_of_spec
+            AND action_item_executions.value IS NOT NULL
+            AND action_plan_executions.finished >= $1 AND action_plan_executions.finished < $2
+        ORDER BY action_plan_executions.finished ASC, action_item_executions.order_index ASC
+        "#,
+        period_start,
+        period_end
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| ReportOutOfSpecReading {
+        action_plan_name: row.action_plan_name,
+        run_number: row.run_number,
+        item_name: row.item_name,
+        value_display: item_types::value_display(&row.value, row.unit.as_deref()),
+        range_display: item_types::range_display(row.min_value, row.max_value, row.unit.as_deref()),
+    })
+    .collect();
+
+    let view = ReportContentView {
+        period_display: month.format("%B %Y").to_string(),
+        started_count,
+        finished_count: executions.len() as i64,
+        open_at_end_count,
+        plans,
+        executions,
+        out_of_spec_readings,
+    };
+    Ok(jinja.get_template("report_content.html")?.render(view)?)
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+fn local_midnight(date: NaiveDate) -> Option<i64> {
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|datetime: DateTime<Local>| datetime.timestamp())
+}
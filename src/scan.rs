@@ -0,0 +1,389 @@
+//! QR codes printed at each maintenance point. Scanning one with a phone opens `/scan`, which
+//! checks off the matching item of the open execution and records who scanned it, as proof
+//! that someone was at the point. The codes are signed per action, so they survive plan edits
+//! and cannot be made up without the server secret, but renaming an item needs a new label.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::Form;
+use qrcode::{QrCode, render::svg};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser, absolute_url,
+    authz::{Action, Resource, authorize},
+    executions, format_unix_timestamp, templates,
+    tokens::TokenScope,
+};
+
+/// Printed size of a code; large enough for phone cameras from arm's length.
+const CODE_SIZE_PIXELS: u32 = 180;
+
+#[derive(Debug, Serialize)]
+struct ScanCodesView {
+    plan_id: Uuid,
+    plan_name: String,
+    codes: Vec<ScanCodeLabel>,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ScanCodeLabel {
+    name: String,
+    /// Inline SVG, generated here and safe to embed unescaped.
+    svg: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ScanChoiceView {
+    code: String,
+    action_name: Option<String>,
+    candidates: Vec<ScanCandidate>,
+    message: Option<String>,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ScanCandidate {
+    execution_id: Uuid,
+    plan_name: String,
+    run_number: i64,
+    label: Option<String>,
+    started_display: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ScanQuery {
+    /// Signed code from the printed label.
+    code: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ScanForm {
+    code: String,
+    /// The open execution to check the item off in, when several contain it.
+    execution: Uuid,
+}
+
+struct CandidateRow {
+    item_id: Uuid,
+    execution_id: Uuid,
+    plan_name: String,
+    run_number: i64,
+    label: Option<String>,
+    started: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/action_plan/{id}/scan-codes",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Action plan id")),
+    responses(
+        (status = 200, description = "Printable QR codes, one per item of the plan", content_type = "text/html"),
+        (status = 403, description = "Only users who can edit plans can print codes", content_type = "text/html"),
+        (status = 404, description = "Action plan not found", content_type = "text/html"),
+    )
+)]
+pub async fn codes_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
+
+    let plan_name = sqlx::query_scalar!(
+        "SELECT name FROM action_plans WHERE id = $1 AND deleted_at IS NULL",
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Action Plan",
+            format!("No action plan exists for id: {}", id),
+        )
+    })?;
+
+    // An action listed twice in the plan shares one label.
+    let actions = sqlx::query!(
+        r#"
+        SELECT
+            actions.id as "id!: uuid::Uuid",
+            actions.name as "name!"
+        FROM action_items
+        INNER JOIN actions ON actions.id = action_items.action
+        WHERE action_items.action_plan = $1
+        GROUP BY actions.id
+        ORDER BY MIN(action_items.order_index) ASC
+        "#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut codes = Vec::with_capacity(actions.len());
+    for action in actions {
+        let token = state
+            .token_signer
+            .sign(TokenScope::ScanCode, action.id, action.id, None);
+        let url = absolute_url(&state.config, &headers, &format!("/scan?code={}", token));
+        codes.push(ScanCodeLabel {
+            name: action.name,
+            svg: qr_code_svg(&url)?,
+        });
+    }
+
+    let rendered = templates::render(
+        &state.jinja,
+        "scan_codes.html",
+        ScanCodesView {
+            plan_id: id,
+            plan_name,
+            codes,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    get,
+    path = "/scan",
+    tag = "executions",
+    params(ScanQuery),
+    responses(
+        (status = 303, description = "Checks off the scanned item in the only open execution containing it and redirects there"),
+        (status = 200, description = "Lets the user pick the execution when several open ones contain the item", content_type = "text/html"),
+        (status = 404, description = "The code is invalid or no open execution has this item unchecked", content_type = "text/html"),
+        (status = 409, description = "The item calls for a sub-procedure without a recent enough completed run", content_type = "text/html"),
+    )
+)]
+pub async fn scan_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Query(query): Query<ScanQuery>,
+) -> Result<Response, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+
+    let Some(action_id) = verify_code(&state, &query.code).await? else {
+        return render_choice(
+            &state,
+            &current_user,
+            StatusCode::NOT_FOUND,
+            query.code,
+            None,
+            Vec::new(),
+            Some("This code is not valid here. It may be printed for another instance or damaged."),
+        );
+    };
+
+    let mut candidates = fetch_candidates(&state, action_id).await?;
+    let action_name = fetch_action_name(&state, action_id).await?;
+    match candidates.len() {
+        0 => render_choice(
+            &state,
+            &current_user,
+            StatusCode::NOT_FOUND,
+            query.code,
+            action_name,
+            Vec::new(),
+            Some(
+                "No open execution has this item left to do. Start the execution first, then scan again.",
+            ),
+        ),
+        1 => {
+            let candidate = candidates.remove(0);
+            check_off(&state, &current_user, &candidate).await?;
+            Ok(Redirect::to(&format!("/executions/{}", candidate.execution_id)).into_response())
+        }
+        _ => render_choice(
+            &state,
+            &current_user,
+            StatusCode::OK,
+            query.code,
+            action_name,
+            candidates,
+            None,
+        ),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/scan",
+    tag = "executions",
+    request_body(content = ScanForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Checks off the scanned item in the chosen execution and redirects there"),
+        (status = 404, description = "The code is invalid or the execution has this item no longer unchecked", content_type = "text/html"),
+        (status = 409, description = "The item calls for a sub-procedure without a recent enough completed run", content_type = "text/html"),
+    )
+)]
+pub async fn scan_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Form(form): Form<ScanForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+
+    let not_found = || {
+        AppError::not_found_for(
+            "Execution",
+            "The chosen execution has no unchecked item for this code.",
+        )
+    };
+    let action_id = verify_code(&state, &form.code)
+        .await?
+        .ok_or_else(not_found)?;
+    let candidate = fetch_candidates(&state, action_id)
+        .await?
+        .into_iter()
+        .find(|candidate| candidate.execution_id == form.execution)
+        .ok_or_else(not_found)?;
+    check_off(&state, &current_user, &candidate).await?;
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}",
+        candidate.execution_id
+    )))
+}
+
+async fn verify_code(state: &AppState, code: &str) -> Result<Option<Uuid>, AppError> {
+    Ok(state
+        .token_signer
+        .verify(&state.db, code, TokenScope::ScanCode)
+        .await?
+        .map(|verified| verified.subject))
+}
+
+/// The first unchecked item with this action in each open execution.
+async fn fetch_candidates(
+    state: &AppState,
+    action_id: Uuid,
+) -> Result<Vec<CandidateRow>, AppError> {
+    Ok(sqlx::query_as!(
+        CandidateRow,
+        r#"
+        SELECT
+            (
+                SELECT items.id
+                FROM action_item_executions AS items
+                WHERE items.action_plan_execution = action_plan_executions.id
+                    AND items.action = $1
+                    AND (items.finished IS NULL OR items.finished <= 0)
+                ORDER BY items.order_index ASC
+                LIMIT 1
+            ) as "item_id!: uuid::Uuid",
+            action_plan_executions.id as "execution_id!: uuid::Uuid",
+            action_plans.name as "plan_name!",
+            action_plan_executions.run_number as "run_number!",
+            action_plan_executions.label,
+            action_plan_executions.started as "started!"
+        FROM action_plan_executions
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE (action_plan_executions.finished IS NULL OR action_plan_executions.finished <= 0)
+            AND EXISTS (
+                SELECT 1
+                FROM action_item_executions AS items
+                WHERE items.action_plan_execution = action_plan_executions.id
+                    AND items.action = $1
+                    AND (items.finished IS NULL OR items.finished <= 0)
+            )
+        ORDER BY action_plan_executions.started DESC
+        "#,
+        action_id
+    )
+    .fetch_all(&state.db)
+    .await?)
+}
+
+async fn fetch_action_name(state: &AppState, action_id: Uuid) -> Result<Option<String>, AppError> {
+    Ok(
+        sqlx::query_scalar!("SELECT name FROM actions WHERE id = $1", action_id)
+            .fetch_optional(&state.db)
+            .await?,
+    )
+}
+
+async fn check_off(
+    state: &AppState,
+    current_user: &CurrentUser,
+    candidate: &CandidateRow,
+) -> Result<(), AppError> {
+    executions::set_item_finished(
+        state,
+        current_user,
+        Some(candidate.execution_id),
+        candidate.item_id,
+        true,
+        None,
+    )
+    .await?;
+    sqlx::query!(
+        "UPDATE action_item_executions SET scanned_by = $1 WHERE id = $2",
+        current_user.id,
+        candidate.item_id
+    )
+    .execute(&state.db)
+    .await?;
+    tracing::info!(
+        user = current_user.name,
+        execution = %candidate.execution_id,
+        "Item checked off by scan"
+    );
+    Ok(())
+}
+
+/// `data` as a QR code in inline SVG, without the XML declaration so it can be embedded.
+pub fn qr_code_svg(data: &str) -> Result<String, AppError> {
+    let svg = QrCode::new(data.as_bytes())
+        .map_err(|err| AppError::internal(anyhow::anyhow!("Building QR code failed: {}", err)))?
+        .render::<svg::Color>()
+        .min_dimensions(CODE_SIZE_PIXELS, CODE_SIZE_PIXELS)
+        .build();
+    Ok(match svg.find("<svg") {
+        Some(start) => svg[start..].to_string(),
+        None => svg,
+    })
+}
+
+fn render_choice(
+    state: &AppState,
+    current_user: &CurrentUser,
+    status: StatusCode,
+    code: String,
+    action_name: Option<String>,
+    candidates: Vec<CandidateRow>,
+    message: Option<&str>,
+) -> Result<Response, AppError> {
+    let rendered = templates::render(
+        &state.jinja,
+        "scan_choice.html",
+        ScanChoiceView {
+            code,
+            action_name,
+            candidates: candidates
+                .into_iter()
+                .map(|candidate| ScanCandidate {
+                    execution_id: candidate.execution_id,
+                    plan_name: candidate.plan_name,
+                    run_number: candidate.run_number,
+                    label: candidate.label,
+                    started_display: format_unix_timestamp(candidate.started),
+                })
+                .collect(),
+            message: message.map(str::to_string),
+            is_admin: current_user.is_admin,
+        },
+    )?;
+    Ok((status, Html(rendered)).into_response())
+}
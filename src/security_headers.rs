@@ -0,0 +1,60 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, header},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::AppState;
+
+/// Scripts and styles only come from `/static/`. Inline styles stay allowed because tag colors
+/// and the heatmap are set through `style` attributes.
+pub const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; script-src 'self'; \
+     style-src 'self' 'unsafe-inline'; img-src 'self' data:; connect-src 'self'; \
+     object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'";
+pub const DEFAULT_FRAME_OPTIONS: &str = "DENY";
+pub const DEFAULT_REFERRER_POLICY: &str = "same-origin";
+
+/// Adds `X-Content-Type-Options` to every response, and the CSP, frame and referrer headers to
+/// HTML pages. Headers a handler already set are left alone.
+pub async fn middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers
+        .entry(header::X_CONTENT_TYPE_OPTIONS)
+        .or_insert(HeaderValue::from_static("nosniff"));
+
+    let is_html = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    if !is_html {
+        return response;
+    }
+
+    let configured = [
+        (
+            header::CONTENT_SECURITY_POLICY,
+            &state.config.content_security_policy,
+        ),
+        (header::X_FRAME_OPTIONS, &state.config.frame_options),
+        (header::REFERRER_POLICY, &state.config.referrer_policy),
+    ];
+    for (name, value) in configured {
+        insert_configured(response.headers_mut(), name, value);
+    }
+    response
+}
+
+/// An empty value in the config turns the header off.
+fn insert_configured(headers: &mut axum::http::HeaderMap, name: HeaderName, value: &str) {
+    if value.is_empty() || headers.contains_key(&name) {
+        return;
+    }
+    match HeaderValue::from_str(value) {
+        Ok(value) => {
+            headers.insert(name, value);
+        }
+        Err(_) => tracing::warn!("Ignoring invalid configured value for {}", name),
+    }
+}
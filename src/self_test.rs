@@ -0,0 +1,151 @@
+//! Checks run once at startup, so a misconfigured instance fails right away or tells the
+//! admins on the admin page instead of breaking at the first use of the affected feature.
+
+use std::path::Path;
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{config::Config, format_unix_timestamp, unix_now};
+
+/// 2025-01-01. A clock before this has certainly not been set.
+const EARLIEST_PLAUSIBLE_UNIX: i64 = 1_735_689_600;
+/// Stored timestamps this far in the future mean the clock has been turned back.
+const CLOCK_TOLERANCE_SECONDS: i64 = 5 * 60;
+const MIN_TOKEN_SECRET_LENGTH: usize = 32;
+
+/// A problem the server can run with, shown on the admin page.
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupWarning {
+    pub check: &'static str,
+    pub message: String,
+}
+
+impl StartupWarning {
+    fn new(check: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks that need no database. Fails when the server could not work at all, e.g. when the
+/// database directory is not writable.
+pub async fn check_environment(config: &Config) -> anyhow::Result<Vec<StartupWarning>> {
+    ensure_writable_directory(&config.db_path, "db_path").await?;
+    if config.archive_after_years > 0 {
+        ensure_writable_directory(&config.archive_db_path, "archive_db_path").await?;
+    }
+
+    let mut warnings = Vec::new();
+
+    let now = unix_now();
+    if now < EARLIEST_PLAUSIBLE_UNIX {
+        warnings.push(StartupWarning::new(
+            "Clock",
+            format!(
+                "The system clock reads {}, which cannot be right. Execution times, sessions and signed links depend on it.",
+                format_unix_timestamp(now)
+            ),
+        ));
+    }
+
+    let tls_configured = config.tls_cert_path.is_some() || config.tls_key_path.is_some();
+    if tls_configured && !config.session_cookie_secure() {
+        warnings.push(StartupWarning::new(
+            "Cookies",
+            "HTTPS is configured but session_cookie_secure is off, so browsers also send the session cookie over plain HTTP.",
+        ));
+    }
+
+    if let Some(secret) = &config.token_secret
+        && secret.len() < MIN_TOKEN_SECRET_LENGTH
+    {
+        warnings.push(StartupWarning::new(
+            "Signed links",
+            format!(
+                "token_secret is shorter than {} characters and easier to guess. Changing it invalidates issued calendar links.",
+                MIN_TOKEN_SECRET_LENGTH
+            ),
+        ));
+    }
+
+    if config.smtp_url.is_none() {
+        warnings.push(StartupWarning::new(
+            "Mail",
+            "smtp_url is not set, so mails such as email verification links are only written to the log.",
+        ));
+    }
+    if config.public_url.is_none() {
+        warnings.push(StartupWarning::new(
+            "Mail",
+            "public_url is not set, so no mails with links are sent, such as email verification and handover notices.",
+        ));
+    }
+
+    Ok(warnings)
+}
+
+/// Compares the clock with the newest timestamp the database has seen. A clock behind it
+/// makes new runs sort before old ones and ends sessions early.
+pub async fn check_database(db: &SqlitePool) -> Result<Vec<StartupWarning>, sqlx::Error> {
+    let latest = sqlx::query_scalar!(
+        r#"
+        SELECT MAX(
+            (SELECT COALESCE(MAX(started), 0) FROM action_plan_executions),
+            (SELECT COALESCE(MAX(finished), 0) FROM action_plan_executions),
+            (SELECT COALESCE(MAX(last_seen), 0) FROM user_sessions)
+        ) as "latest!: i64"
+        "#
+    )
+    .fetch_one(db)
+    .await?;
+
+    let mut warnings = Vec::new();
+    if latest - unix_now() > CLOCK_TOLERANCE_SECONDS {
+        warnings.push(StartupWarning::new(
+            "Clock",
+            format!(
+                "The database holds timestamps up to {}, later than the system clock. Check the server's time settings.",
+                format_unix_timestamp(latest)
+            ),
+        ));
+    }
+    Ok(warnings)
+}
+
+/// Creates the parent directory of `file_path` if needed and proves it accepts new files.
+async fn ensure_writable_directory(file_path: &str, setting: &str) -> anyhow::Result<()> {
+    let directory = Path::new(file_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    tokio::fs::create_dir_all(directory).await.map_err(|err| {
+        anyhow::anyhow!(
+            "{} directory {} cannot be created: {}",
+            setting,
+            directory.display(),
+            err
+        )
+    })?;
+
+    let probe = directory.join(format!(".write-test-{}", Uuid::new_v4()));
+    tokio::fs::write(&probe, b"").await.map_err(|err| {
+        anyhow::anyhow!(
+            "{} directory {} is not writable: {}",
+            setting,
+            directory.display(),
+            err
+        )
+    })?;
+    let _ = tokio::fs::remove_file(&probe).await;
+
+    if let Ok(metadata) = tokio::fs::metadata(file_path).await
+        && metadata.permissions().readonly()
+    {
+        anyhow::bail!("{} file {} is read-only", setting, file_path);
+    }
+    Ok(())
+}
@@ -0,0 +1,262 @@
+//! Skipping items that do not need doing in a run, like a filter change when the filter was
+//! replaced last week. A skip needs a reason, counts as dealt with when completing the execution
+//! and stays on record with the run.
+
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, authorize},
+    events,
+    executions::{self, ItemGrouping},
+    format_unix_timestamp, unix_now,
+};
+
+/// Longest skip reason accepted, in characters.
+const MAX_REASON_CHARS: usize = 500;
+
+/// Why an item was skipped and by whom.
+#[derive(Debug, Serialize)]
+pub struct ItemSkip {
+    reason: String,
+    skipped_display: String,
+    skipped_by_name: Option<String>,
+}
+
+impl ItemSkip {
+    pub fn new(
+        reason: Option<String>,
+        skipped_at: Option<i64>,
+        skipped_by_name: Option<String>,
+    ) -> Option<Self> {
+        Some(ItemSkip {
+            reason: reason?,
+            skipped_display: skipped_at.map(format_unix_timestamp).unwrap_or_default(),
+            skipped_by_name,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SkipItemForm {
+    reason: String,
+    /// How the execution page was grouped, to return to the same view.
+    #[serde(default)]
+    group: ItemGrouping,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UnskipItemForm {
+    #[serde(default)]
+    group: ItemGrouping,
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/skip",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body(content = SkipItemForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Marks the item as skipped and redirects to its execution"),
+        (status = 404, description = "Execution item not found", content_type = "text/html"),
+        (status = 409, description = "No reason given, the item is checked off or the execution is completed", content_type = "text/html"),
+    )
+)]
+pub async fn skip_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<SkipItemForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let reason = form.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(AppError::conflict(
+            "Say why the item is skipped, so the run stays traceable.",
+        ));
+    }
+    if reason.chars().count() > MAX_REASON_CHARS {
+        return Err(AppError::conflict(format!(
+            "Skip reasons can have at most {} characters.",
+            MAX_REASON_CHARS
+        )));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id: uuid::Uuid",
+            action_item_executions.finished as "finished?",
+            action_item_executions.skip_reason,
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        WHERE action_item_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Execution",
+            format!("No execution item exists for id: {}", id),
+        )
+    })?;
+    if item.execution_finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "Items of a completed execution cannot be skipped.",
+        ));
+    }
+    if item.finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(format!(
+            "\"{}\" is already checked off, uncheck it before skipping it.",
+            item.item_name
+        )));
+    }
+
+    let skipped_at = unix_now();
+    sqlx::query!(
+        r#"
+        UPDATE action_item_executions
+        SET skip_reason = $1, skipped_at = $2, skipped_by = $3
+        WHERE id = $4
+        "#,
+        reason,
+        skipped_at,
+        current_user.id,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let description = executions::execution_description(&mut tx, item.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            id,
+            AuditAction::Update,
+            format!("Skipped \"{}\" in {}", item.item_name, description),
+        )
+        .change(item.skip_reason, Some(reason)),
+    )
+    .await?;
+    let parent_event = executions::update_parent_item(&mut tx, &current_user, id).await?;
+    tx.commit().await?;
+    if let Some(event) = parent_event {
+        events::publish(&state.execution_events, event);
+    }
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}{}",
+        item.execution_id,
+        form.group.query()
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/execution-items/{id}/unskip",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution item id")),
+    request_body(content = UnskipItemForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Takes the item back into the run and redirects to its execution"),
+        (status = 404, description = "Execution item not found", content_type = "text/html"),
+        (status = 409, description = "The item is not skipped or the execution is completed", content_type = "text/html"),
+    )
+)]
+pub async fn unskip_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<UnskipItemForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let mut tx = state.db.begin().await?;
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id: uuid::Uuid",
+            action_item_executions.skip_reason,
+            actions.name as "item_name!",
+            action_plan_executions.finished as "execution_finished?"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        WHERE action_item_executions.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Execution",
+            format!("No execution item exists for id: {}", id),
+        )
+    })?;
+    let Some(reason) = item.skip_reason else {
+        return Err(AppError::conflict(format!(
+            "\"{}\" is not skipped.",
+            item.item_name
+        )));
+    };
+    if item.execution_finished.is_some_and(|finished| finished > 0) {
+        return Err(AppError::conflict(
+            "Skipped items of a completed execution stay on record. Reopen the execution first.",
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE action_item_executions
+        SET skip_reason = NULL, skipped_at = NULL, skipped_by = NULL
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let description = executions::execution_description(&mut tx, item.execution_id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::ExecutionItem,
+            id,
+            AuditAction::Update,
+            format!("Took \"{}\" back into {}", item.item_name, description),
+        )
+        .change(Some(reason), None),
+    )
+    .await?;
+    let parent_event = executions::update_parent_item(&mut tx, &current_user, id).await?;
+    tx.commit().await?;
+    if let Some(event) = parent_event {
+        events::publish(&state.execution_events, event);
+    }
+
+    Ok(Redirect::to(&format!(
+        "/executions/{}{}",
+        item.execution_id,
+        form.group.query()
+    )))
+}
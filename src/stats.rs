@@ -0,0 +1,116 @@
+use axum::{Json, extract::State, response::Html};
+use chrono::{Datelike, Local, TimeZone, Timelike};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+use crate::{AppError, AppState, CurrentUser, templates};
+
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Completed executions bucketed by the server's local weekday and hour of completion.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Heatmap {
+    total: i64,
+    max_count: i64,
+    /// One row per weekday, Monday first.
+    weekdays: Vec<HeatmapWeekday>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HeatmapWeekday {
+    name: String,
+    /// Completed executions per hour of the day, index 0 is 00:00-00:59.
+    counts: Vec<i64>,
+}
+
+#[derive(Serialize)]
+struct HeatmapPageView {
+    heatmap: Heatmap,
+    hours: Vec<u32>,
+    is_admin: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats/heatmap",
+    tag = "stats",
+    responses(
+        (status = 200, description = "Weekday/hour table of completed executions", content_type = "text/html"),
+    )
+)]
+pub async fn heatmap(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    let heatmap = build_heatmap(&state.db).await?;
+    let rendered = templates::render(
+        &state.jinja,
+        "stats_heatmap.html",
+        HeatmapPageView {
+            heatmap,
+            hours: (0..24).collect(),
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats/heatmap.json",
+    tag = "stats",
+    responses(
+        (status = 200, description = "Completed executions per weekday and hour", body = Heatmap),
+    )
+)]
+pub async fn heatmap_json(State(state): State<AppState>) -> Result<Json<Heatmap>, AppError> {
+    Ok(Json(build_heatmap(&state.db).await?))
+}
+
+async fn build_heatmap(db: &SqlitePool) -> Result<Heatmap, AppError> {
+    let finished_timestamps = sqlx::query_scalar!(
+        r#"
+        SELECT finished as "finished!"
+        FROM action_plan_executions
+        WHERE finished > 0
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut counts = [[0i64; 24]; 7];
+    for finished in finished_timestamps {
+        let Some(datetime) = Local.timestamp_opt(finished, 0).single() else {
+            continue;
+        };
+        let weekday = datetime.weekday().num_days_from_monday() as usize;
+        counts[weekday][datetime.hour() as usize] += 1;
+    }
+
+    let total = counts.iter().flatten().sum();
+    let max_count = counts.iter().flatten().copied().max().unwrap_or(0);
+    let weekdays = WEEKDAY_NAMES
+        .iter()
+        .zip(counts)
+        .map(|(name, counts)| HeatmapWeekday {
+            name: name.to_string(),
+            counts: counts.to_vec(),
+        })
+        .collect();
+
+    Ok(Heatmap {
+        total,
+        max_count,
+        weekdays,
+    })
+}
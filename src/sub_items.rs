@@ -0,0 +1,56 @@
+//! Sub-items. An item can have one level of items nested under it, like the single bolts of
+//! "Tighten flange". The parent can only be checked off once each of its sub-items is dealt
+//! with, and plain parents are checked off by themselves when the last one is. A blocked
+//! sub-item counts as dealt with, just as it does not hold up completing the run.
+
+use std::collections::HashMap;
+
+/// What sub-items need to know about an item of a run.
+pub struct SubItem {
+    /// Order index of the item this one is nested under.
+    pub parent_index: Option<i64>,
+    /// Checked off, skipped, failed, blocked or not applying.
+    pub is_done: bool,
+}
+
+/// Number of sub-items still to do, by order index of their parent.
+pub fn open_counts(items: &[SubItem]) -> HashMap<i64, usize> {
+    let mut counts = HashMap::new();
+    for item in items.iter().filter(|item| !item.is_done) {
+        if let Some(parent_index) = item.parent_index {
+            *counts.entry(parent_index).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(parent_index: Option<i64>, is_done: bool) -> SubItem {
+        SubItem {
+            parent_index,
+            is_done,
+        }
+    }
+
+    #[test]
+    fn counts_open_sub_items_by_parent() {
+        let items = [
+            item(None, false),
+            item(Some(0), false),
+            item(Some(0), true),
+            item(Some(0), false),
+            item(None, true),
+            item(Some(4), false),
+        ];
+        assert_eq!(open_counts(&items), HashMap::from([(0, 2), (4, 1)]));
+    }
+
+    #[test]
+    fn parents_with_every_sub_item_done_are_left_out() {
+        let items = [item(None, false), item(Some(0), true), item(Some(0), true)];
+        assert!(open_counts(&items).is_empty());
+    }
+}
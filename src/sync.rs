@@ -0,0 +1,952 @@
+//! Keeping plans in step across sites. A central instance publishes its plans as canonical
+//! templates under `/api/sync/plans`; site instances pointed at it pull them on a schedule
+//! instead of passing backup files around. A pulled plan that was edited on the site keeps its
+//! local changes: later pulls leave it alone and its page says a newer central version is
+//! waiting, until an admin takes the central version again.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, bail};
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, Method, StatusCode, header},
+    response::Redirect,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    action_plan::{self, plan_snapshot},
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, authorize},
+    config::Config,
+    format_duration, format_unix_timestamp, http_client,
+    item_types::ItemType,
+    unix_now,
+};
+
+/// Name the audit log shows for changes made by scheduled pulls.
+const SYNC_ACTOR_NAME: &str = "Plan sync";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_BUNDLE_BYTES: usize = 16 * 1024 * 1024;
+/// Pulls kept for the admin page.
+const KEPT_RUNS: i64 = 100;
+const SHOWN_RUNS: i64 = 10;
+
+/// The plans a central instance publishes.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncBundle {
+    published_at_unix: i64,
+    plans: Vec<SyncPlan>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncPlan {
+    id: Uuid,
+    name: String,
+    /// Changes whenever the name, the headings or the items change.
+    revision: String,
+    /// Section headings, ordered among the items by their order index.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    headings: Vec<SyncHeading>,
+    items: Vec<SyncPlanItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncHeading {
+    order_index: i64,
+    heading: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncPlanItem {
+    /// Position in the plan among items and headings, which conditions refer to. Bundles that
+    /// do not carry it list the items in order from 0.
+    #[serde(default)]
+    order_index: Option<i64>,
+    action_name: String,
+    /// Markdown description of the action. Actions are shared by name, so a pull sets the
+    /// description for every plan using the action.
+    #[serde(default)]
+    description: Option<String>,
+    estimated_minutes: Option<i64>,
+    /// Id of the plan on the publishing instance this item calls for as a sub-procedure.
+    sub_plan: Option<Uuid>,
+    /// What to mind about the item in this plan only.
+    #[serde(default)]
+    instructions: Option<String>,
+    /// Reference links of the action, replacing the ones here like the description does.
+    #[serde(default)]
+    links: Vec<SyncActionLink>,
+    /// What the item captures when it is checked off.
+    #[serde(default)]
+    item_type: ItemType,
+    /// Unit of a numeric item.
+    #[serde(default)]
+    unit: Option<String>,
+    /// Expected range of a numeric item.
+    #[serde(default)]
+    min_value: Option<f64>,
+    #[serde(default)]
+    max_value: Option<f64>,
+    /// Order index of the earlier item this one depends on.
+    #[serde(default)]
+    condition_index: Option<i64>,
+    /// Answer the earlier item needs for this one to apply.
+    #[serde(default)]
+    condition_value: Option<String>,
+    /// The earlier item needs to have been marked failed instead. Left out when not set, so
+    /// revisions of plans without such conditions stay what they were before it existed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    condition_failed: bool,
+    /// Order index of the item this one is nested under.
+    #[serde(default)]
+    parent_index: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncActionLink {
+    label: Option<String>,
+    url: String,
+}
+
+/// What a pull changed.
+#[derive(Debug, Default)]
+struct SyncReport {
+    created: u64,
+    updated: u64,
+    unchanged: u64,
+    removed: u64,
+    /// Plans with local changes that a newer central version was not applied to.
+    kept_local: Vec<String>,
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} created, {} updated, {} unchanged, {} removed",
+            self.created, self.updated, self.unchanged, self.removed
+        )?;
+        if !self.kept_local.is_empty() {
+            write!(f, ", local changes kept in {}", self.kept_local.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Sync settings and recent pulls for the admin page.
+#[derive(Debug, Serialize)]
+pub struct SyncStatus {
+    is_publishing: bool,
+    source_url: Option<String>,
+    interval_display: String,
+    synced_plan_count: i64,
+    runs: Vec<SyncRun>,
+}
+
+#[derive(Debug, Serialize)]
+struct SyncRun {
+    ran_display: String,
+    succeeded: bool,
+    summary: String,
+}
+
+/// Where a plan came from, for its page.
+#[derive(Debug, Serialize)]
+pub struct PlanSyncInfo {
+    synced_display: Option<String>,
+    has_local_changes: bool,
+    has_pending_update: bool,
+    /// Pulling is configured, so the central version can be taken again.
+    can_reset: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/plans",
+    tag = "action_plans",
+    responses(
+        (status = 200, description = "All active plans of this instance for site instances to pull", body = SyncBundle),
+        (status = 401, description = "No `Authorization: Bearer` header with the sync_publish_key", content_type = "text/html"),
+        (status = 404, description = "This instance does not publish its plans", content_type = "text/html"),
+    )
+)]
+pub async fn plans_get(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<SyncBundle>, AppError> {
+    let Some(key) = state.config.sync_publish_key.as_deref() else {
+        return Err(AppError::not_found_for(
+            "Plan Sync",
+            "This instance does not publish its plans.",
+        ));
+    };
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // Comparing digests keeps the time taken independent of how much of the key matched.
+    if !presented.is_some_and(|presented| {
+        Sha256::digest(presented.as_bytes()) == Sha256::digest(key.as_bytes())
+    }) {
+        return Err(AppError::unauthorized("Missing or wrong sync key."));
+    }
+
+    let mut conn = state.db.acquire().await?;
+    let plan_rows = sqlx::query!(
+        r#"
+        SELECT id as "id: uuid::Uuid", name
+        FROM action_plans
+        WHERE deleted_at IS NULL OR deleted_at <= 0
+        ORDER BY name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut plans = Vec::with_capacity(plan_rows.len());
+    for row in plan_rows {
+        let (headings, items) = fetch_items(&mut conn, row.id).await?;
+        plans.push(SyncPlan {
+            id: row.id,
+            revision: revision(&row.name, &headings, &items),
+            name: row.name,
+            headings,
+            items,
+        });
+    }
+
+    Ok(Json(SyncBundle {
+        published_at_unix: unix_now(),
+        plans,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sync",
+    tag = "admin",
+    responses(
+        (status = 303, description = "Pulls plans from the central instance now and redirects to the admin page, which shows the outcome"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 409, description = "No central instance is configured", content_type = "text/html"),
+    )
+)]
+pub async fn sync_now_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Redirect, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+    if state.config.sync_source_url.is_none() {
+        return Err(AppError::conflict(
+            "No central instance is configured. Set sync_source_url to pull plans.",
+        ));
+    }
+
+    pull(&state.db, &state.config).await?;
+    Ok(Redirect::to("/admin"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/action_plan/{id}/sync/reset",
+    tag = "action_plans",
+    params(("id" = Uuid, Path, description = "Action plan id")),
+    responses(
+        (status = 303, description = "Drops the local changes of a pulled plan, pulls the central version and redirects to the plan"),
+        (status = 404, description = "Action plan not found or not pulled from a central instance", content_type = "text/html"),
+        (status = 409, description = "No central instance is configured", content_type = "text/html"),
+    )
+)]
+pub async fn reset_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
+    if state.config.sync_source_url.is_none() {
+        return Err(AppError::conflict(
+            "No central instance is configured. Set sync_source_url to pull plans.",
+        ));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let plan = sqlx::query!(
+        r#"
+        SELECT name
+        FROM action_plans
+        WHERE id = $1 AND sync_source_id IS NOT NULL
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::not_found_for(
+            "Action Plan",
+            format!(
+                "No plan pulled from a central instance exists for id: {}",
+                id
+            ),
+        )
+    })?;
+
+    // Taking the current content as the unchanged baseline and forgetting the applied central
+    // revision makes the next pull overwrite the plan, restoring it if it was deleted here.
+    let local_revision = local_revision(&mut tx, id).await?;
+    sqlx::query!(
+        r#"
+        UPDATE action_plans
+        SET deleted_at = NULL, sync_local_revision = $1, sync_source_revision = NULL
+        WHERE id = $2
+        "#,
+        local_revision,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Plan,
+            id,
+            AuditAction::Update,
+            format!(
+                "Dropped the local changes of plan \"{}\" for the central version",
+                plan.name
+            ),
+        ),
+    )
+    .await?;
+    tx.commit().await?;
+
+    pull(&state.db, &state.config).await?;
+    Ok(Redirect::to(&format!("/action_plan/{}", id)))
+}
+
+pub async fn run_scheduler(db: SqlitePool, config: Arc<Config>) {
+    if config.sync_source_url.is_none() {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(config.sync_interval_seconds));
+    loop {
+        interval.tick().await;
+        if let Err(err) = pull(&db, &config).await {
+            tracing::error!("Plan sync failed: {}", err);
+        }
+    }
+}
+
+pub async fn fetch_status(db: &SqlitePool, config: &Config) -> Result<SyncStatus, AppError> {
+    let synced_plan_count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!: i64"
+        FROM action_plans
+        WHERE sync_source_id IS NOT NULL AND (deleted_at IS NULL OR deleted_at <= 0)
+        "#
+    )
+    .fetch_one(db)
+    .await?;
+    let runs = sqlx::query!(
+        r#"
+        SELECT ran_at, succeeded, summary
+        FROM plan_sync_runs
+        ORDER BY ran_at DESC
+        LIMIT $1
+        "#,
+        SHOWN_RUNS
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|run| SyncRun {
+        ran_display: format_unix_timestamp(run.ran_at),
+        succeeded: run.succeeded != 0,
+        summary: run.summary,
+    })
+    .collect();
+
+    Ok(SyncStatus {
+        is_publishing: config.sync_publish_key.is_some(),
+        source_url: config.sync_source_url.clone(),
+        interval_display: format_duration(config.sync_interval_seconds as i64),
+        synced_plan_count,
+        runs,
+    })
+}
+
+/// `None` for plans that were not pulled from a central instance.
+pub async fn fetch_plan_info(
+    db: &SqlitePool,
+    config: &Config,
+    plan_id: Uuid,
+) -> Result<Option<PlanSyncInfo>, AppError> {
+    let mut conn = db.acquire().await?;
+    let plan = sqlx::query!(
+        r#"
+        SELECT
+            synced_at as "synced_at?",
+            sync_local_revision,
+            sync_pending_revision
+        FROM action_plans
+        WHERE id = $1 AND sync_source_id IS NOT NULL
+        "#,
+        plan_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(plan) = plan else {
+        return Ok(None);
+    };
+
+    Ok(Some(PlanSyncInfo {
+        synced_display: plan.synced_at.map(format_unix_timestamp),
+        has_local_changes: has_local_changes(
+            &mut conn,
+            plan_id,
+            plan.sync_local_revision.as_deref(),
+        )
+        .await?,
+        has_pending_update: plan.sync_pending_revision.is_some(),
+        can_reset: config.sync_source_url.is_some(),
+    }))
+}
+
+/// Pulls once and records the outcome for the admin page. Only failing to record it is an
+/// error; an unreachable central instance is a failed run.
+async fn pull(db: &SqlitePool, config: &Config) -> Result<(), AppError> {
+    let outcome = match fetch_bundle(config).await {
+        Ok(bundle) => apply(db, &bundle).await.map_err(|err| err.to_string()),
+        Err(err) => Err(format!("{:#}", err)),
+    };
+    let (succeeded, summary) = match outcome {
+        Ok(report) => {
+            tracing::info!("Plan sync: {}.", report);
+            (true, report.to_string())
+        }
+        Err(message) => {
+            tracing::warn!("Plan sync failed: {}", message);
+            (false, message)
+        }
+    };
+
+    let id = Uuid::new_v4();
+    let now = unix_now();
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "INSERT INTO plan_sync_runs (id, ran_at, succeeded, summary) VALUES ($1, $2, $3, $4)",
+        id,
+        now,
+        succeeded,
+        summary
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM plan_sync_runs
+        WHERE id NOT IN (SELECT id FROM plan_sync_runs ORDER BY ran_at DESC LIMIT $1)
+        "#,
+        KEPT_RUNS
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Brings the pulled plans in line with `bundle` in one transaction.
+async fn apply(db: &SqlitePool, bundle: &SyncBundle) -> Result<SyncReport, AppError> {
+    let now = unix_now();
+    let mut report = SyncReport::default();
+    let mut tx = db.begin().await?;
+
+    let existing = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            name,
+            deleted_at as "deleted_at?",
+            sync_source_id as "sync_source_id!: uuid::Uuid",
+            sync_source_revision,
+            sync_local_revision
+        FROM action_plans
+        WHERE sync_source_id IS NOT NULL
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let mut local_ids: HashMap<Uuid, Uuid> = existing
+        .iter()
+        .map(|plan| (plan.sync_source_id, plan.id))
+        .collect();
+
+    // Plans are created before any items are written, so items can call for plans that come
+    // later in the bundle.
+    let mut to_write: Vec<(Uuid, &SyncPlan, Option<String>)> = Vec::new();
+    for plan in &bundle.plans {
+        let Some(local) = existing
+            .iter()
+            .find(|local| local.sync_source_id == plan.id)
+        else {
+            let id = Uuid::new_v4();
+            sqlx::query!(
+                "INSERT INTO action_plans (id, name, sync_source_id) VALUES ($1, $2, $3)",
+                id,
+                plan.name,
+                plan.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            action_plan::assign_slug(&mut tx, id, &plan.name).await?;
+            local_ids.insert(plan.id, id);
+            to_write.push((id, plan, None));
+            report.created += 1;
+            continue;
+        };
+
+        if local.sync_source_revision.as_deref() == Some(plan.revision.as_str()) {
+            report.unchanged += 1;
+            continue;
+        }
+        let is_deleted = local.deleted_at.is_some_and(|value| value > 0);
+        if is_deleted
+            || has_local_changes(&mut tx, local.id, local.sync_local_revision.as_deref()).await?
+        {
+            sqlx::query!(
+                "UPDATE action_plans SET sync_pending_revision = $1 WHERE id = $2",
+                plan.revision,
+                local.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            report.kept_local.push(local.name.clone());
+            continue;
+        }
+
+        let before = plan_snapshot(&mut tx, local.id).await?;
+        if local.name != plan.name {
+            let rename_id = Uuid::new_v4();
+            sqlx::query!(
+                "UPDATE action_plans SET name = $1 WHERE id = $2",
+                plan.name,
+                local.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                INSERT INTO action_plan_renames (id, action_plan, old_name, new_name, renamed_at)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                rename_id,
+                local.id,
+                local.name,
+                plan.name,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+            action_plan::assign_slug(&mut tx, local.id, &plan.name).await?;
+        }
+        to_write.push((local.id, plan, Some(before)));
+        report.updated += 1;
+    }
+
+    for (plan_id, plan, before) in to_write {
+        write_items(&mut tx, plan_id, &plan.headings, &plan.items, &local_ids).await?;
+        let local_revision = local_revision(&mut tx, plan_id).await?;
+        sqlx::query!(
+            r#"
+            UPDATE action_plans
+            SET
+                sync_source_revision = $1,
+                sync_local_revision = $2,
+                sync_pending_revision = NULL,
+                synced_at = $3
+            WHERE id = $4
+            "#,
+            plan.revision,
+            local_revision,
+            now,
+            plan_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let after = plan_snapshot(&mut tx, plan_id).await?;
+        let (action, summary) = match before {
+            Some(_) => (
+                AuditAction::Update,
+                format!("Updated plan \"{}\" from the central instance", plan.name),
+            ),
+            None => (
+                AuditAction::Create,
+                format!("Created plan \"{}\" from the central instance", plan.name),
+            ),
+        };
+        audit::record_as(
+            &mut *tx,
+            Uuid::nil(),
+            SYNC_ACTOR_NAME,
+            None,
+            AuditEntry::new(AuditEntity::Plan, plan_id, action, summary)
+                .change(before, Some(after)),
+        )
+        .await?;
+    }
+
+    // Plans the central instance no longer publishes are deleted here too, unless they were
+    // changed here. Deleted plans can be restored, so nothing is lost.
+    let published: HashSet<Uuid> = bundle.plans.iter().map(|plan| plan.id).collect();
+    for local in &existing {
+        if published.contains(&local.sync_source_id)
+            || local.deleted_at.is_some_and(|value| value > 0)
+        {
+            continue;
+        }
+        if has_local_changes(&mut tx, local.id, local.sync_local_revision.as_deref()).await? {
+            report.kept_local.push(local.name.clone());
+            continue;
+        }
+
+        sqlx::query!(
+            "UPDATE action_plans SET deleted_at = $1 WHERE id = $2",
+            now,
+            local.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let before = plan_snapshot(&mut tx, local.id).await?;
+        audit::record_as(
+            &mut *tx,
+            Uuid::nil(),
+            SYNC_ACTOR_NAME,
+            None,
+            AuditEntry::new(
+                AuditEntity::Plan,
+                local.id,
+                AuditAction::Delete,
+                format!(
+                    "Deleted plan \"{}\" as the central instance no longer publishes it",
+                    local.name
+                ),
+            )
+            .change(Some(before), None),
+        )
+        .await?;
+        report.removed += 1;
+    }
+
+    tx.commit().await?;
+    Ok(report)
+}
+
+/// Replaces the headings and items of a pulled plan. Sub-procedures are mapped to the local
+/// copies of the central plans; ones this instance has no copy of are dropped.
+async fn write_items(
+    conn: &mut SqliteConnection,
+    plan_id: Uuid,
+    headings: &[SyncHeading],
+    items: &[SyncPlanItem],
+    local_ids: &HashMap<Uuid, Uuid>,
+) -> Result<(), AppError> {
+    sqlx::query!("DELETE FROM action_items WHERE action_plan = $1", plan_id)
+        .execute(&mut *conn)
+        .await?;
+
+    for heading in headings {
+        let heading_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO action_items (id, order_index, action_plan, heading) VALUES ($1, $2, $3, $4)",
+            heading_id,
+            heading.order_index,
+            plan_id,
+            heading.heading
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    for (order, item) in items.iter().enumerate() {
+        let action = sqlx::query_scalar!(
+            r#"SELECT id as "id: uuid::Uuid" FROM actions WHERE name = $1"#,
+            item.action_name
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        let action = match action {
+            Some(action) => action,
+            None => {
+                let action_id = Uuid::new_v4();
+                sqlx::query!(
+                    "INSERT INTO actions (id, name) VALUES ($1, $2)",
+                    action_id,
+                    item.action_name
+                )
+                .execute(&mut *conn)
+                .await?;
+                action_id
+            }
+        };
+        sqlx::query!(
+            "UPDATE actions SET description = $1 WHERE id = $2",
+            item.description,
+            action
+        )
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query!("DELETE FROM action_links WHERE action = $1", action)
+            .execute(&mut *conn)
+            .await?;
+        let now = unix_now();
+        for link in &item.links {
+            let link_id = Uuid::new_v4();
+            sqlx::query!(
+                r#"
+                INSERT INTO action_links (id, action, label, url, created_at)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                link_id,
+                action,
+                link.label,
+                link.url,
+                now
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        let item_id = Uuid::new_v4();
+        let order = item.order_index.unwrap_or(order as i64);
+        let item_type = item.item_type.as_str();
+        let sub_plan = item
+            .sub_plan
+            .and_then(|central_id| local_ids.get(&central_id).copied());
+        sqlx::query!(
+            r#"
+            INSERT INTO action_items (id, order_index, action_plan, action, estimated_minutes, sub_plan, instructions, item_type, unit, min_value, max_value, condition_index, condition_value, condition_failed, parent_index)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            "#,
+            item_id,
+            order,
+            plan_id,
+            action,
+            item.estimated_minutes,
+            sub_plan,
+            item.instructions,
+            item_type,
+            item.unit,
+            item.min_value,
+            item.max_value,
+            item.condition_index,
+            item.condition_value,
+            item.condition_failed,
+            item.parent_index
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Headings and items of a plan as the bundle carries them. Order indexes are renumbered from 0,
+/// so gaps left by earlier edits do not change the revision.
+async fn fetch_items(
+    conn: &mut SqliteConnection,
+    plan_id: Uuid,
+) -> Result<(Vec<SyncHeading>, Vec<SyncPlanItem>), AppError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            action_items.heading,
+            actions.id as "action_id?: uuid::Uuid",
+            actions.name as "action_name?",
+            actions.description,
+            action_items.estimated_minutes,
+            action_items.sub_plan as "sub_plan: uuid::Uuid",
+            action_items.instructions,
+            action_items.item_type,
+            action_items.unit,
+            action_items.min_value,
+            action_items.max_value,
+            action_items.order_index,
+            action_items.condition_index,
+            action_items.condition_value,
+            action_items.condition_failed as "condition_failed: bool",
+            action_items.parent_index
+        FROM action_items
+        LEFT JOIN actions ON actions.id = action_items.action
+        WHERE action_items.action_plan = $1
+        ORDER BY action_items.order_index ASC
+        "#,
+        plan_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let position_by_index = rows
+        .iter()
+        .enumerate()
+        .map(|(position, row)| (row.order_index, position as i64))
+        .collect::<HashMap<_, _>>();
+    let position = |index: i64| position_by_index.get(&index).copied();
+
+    let mut headings = Vec::new();
+    let mut items = Vec::with_capacity(rows.len());
+    for row in rows {
+        let (Some(action_id), Some(action_name)) = (row.action_id, row.action_name) else {
+            if let Some(heading) = row.heading {
+                headings.push(SyncHeading {
+                    order_index: position(row.order_index).unwrap_or_default(),
+                    heading,
+                });
+            }
+            continue;
+        };
+        // Links keep the order they were added in, which the pull recreates.
+        let links = sqlx::query_as!(
+            SyncActionLink,
+            r#"
+            SELECT label, url
+            FROM action_links
+            WHERE action = $1
+            ORDER BY created_at ASC, rowid ASC
+            "#,
+            action_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        items.push(SyncPlanItem {
+            order_index: position(row.order_index),
+            action_name,
+            description: row.description,
+            estimated_minutes: row.estimated_minutes,
+            sub_plan: row.sub_plan,
+            instructions: row.instructions,
+            links,
+            item_type: ItemType::from_db(&row.item_type),
+            unit: row.unit,
+            min_value: row.min_value,
+            max_value: row.max_value,
+            condition_index: row.condition_index.and_then(position),
+            condition_value: row.condition_value,
+            condition_failed: row.condition_failed,
+            parent_index: row.parent_index.and_then(position),
+        });
+    }
+    Ok((headings, items))
+}
+
+/// Revision of a plan as it is stored on this instance.
+async fn local_revision(conn: &mut SqliteConnection, plan_id: Uuid) -> Result<String, AppError> {
+    let name = sqlx::query_scalar!("SELECT name FROM action_plans WHERE id = $1", plan_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let (headings, items) = fetch_items(conn, plan_id).await?;
+    Ok(revision(&name, &headings, &items))
+}
+
+/// Whether a pulled plan was changed here since `stored`, the revision the last pull left it
+/// at.
+async fn has_local_changes(
+    conn: &mut SqliteConnection,
+    plan_id: Uuid,
+    stored: Option<&str>,
+) -> Result<bool, AppError> {
+    let Some(stored) = stored else {
+        return Ok(true);
+    };
+    let name = sqlx::query_scalar!("SELECT name FROM action_plans WHERE id = $1", plan_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let (headings, items) = fetch_items(conn, plan_id).await?;
+    // Revisions stored before the bundle carried the whole item only covered the action,
+    // estimate and sub-procedure. Such plans count as unchanged while those still match, so
+    // the next pull fills in the rest instead of taking them for local edits.
+    Ok(stored != revision(&name, &headings, &items) && stored != legacy_revision(&name, &items))
+}
+
+/// SHA-256 of the name, headings and items, in hex. Every field of the items counts, so
+/// changing any of them centrally is pulled by the sites. Plans without headings hash like
+/// they did before the bundle carried headings.
+fn revision(name: &str, headings: &[SyncHeading], items: &[SyncPlanItem]) -> String {
+    if headings.is_empty() {
+        digest(&serde_json::json!({ "name": name, "items": items }))
+    } else {
+        digest(&serde_json::json!({ "name": name, "headings": headings, "items": items }))
+    }
+}
+
+/// The revision as computed before items carried more than their action, estimate and
+/// sub-procedure.
+fn legacy_revision(name: &str, items: &[SyncPlanItem]) -> String {
+    let items = items
+        .iter()
+        .map(|item| {
+            serde_json::json!({
+                "action_name": item.action_name,
+                "estimated_minutes": item.estimated_minutes,
+                "sub_plan": item.sub_plan,
+            })
+        })
+        .collect::<Vec<_>>();
+    digest(&serde_json::json!({ "name": name, "items": items }))
+}
+
+fn digest(content: &serde_json::Value) -> String {
+    Sha256::digest(content.to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Fetches the published plans from `sync_source_url`.
+async fn fetch_bundle(config: &Config) -> anyhow::Result<SyncBundle> {
+    let base = config
+        .sync_source_url
+        .as_deref()
+        .context("sync_source_url is not set")?;
+    let key = config
+        .sync_source_key
+        .as_deref()
+        .context("sync_source_key is not set")?;
+    let (status, body) = http_client::send(
+        Method::GET,
+        &format!("{}/api/sync/plans", base.trim_end_matches('/')),
+        &[
+            (header::AUTHORIZATION, format!("Bearer {}", key)),
+            (header::ACCEPT, "application/json".to_string()),
+        ],
+        Bytes::new(),
+        MAX_BUNDLE_BYTES,
+        FETCH_TIMEOUT,
+    )
+    .await
+    .context("sync_source_url could not be reached")?;
+
+    match status {
+        StatusCode::OK => {}
+        StatusCode::UNAUTHORIZED => bail!("{} refused sync_source_key", base),
+        StatusCode::NOT_FOUND => bail!("{} does not publish its plans", base),
+        status => bail!("{} answered with {}", base, status),
+    }
+    serde_json::from_slice(&body).with_context(|| format!("{} sent no valid plan list", base))
+}
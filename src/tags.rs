@@ -9,11 +9,16 @@ use axum_extra::extract::Form;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use sqlx::prelude::FromRow;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-use crate::{AppError, AppState, CurrentUser};
+use crate::{
+    AppError, AppState, CurrentUser,
+    authz::{Action, Resource, authorize},
+    templates,
+};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct TagBadge {
     pub id: Uuid,
     pub name: String,
@@ -40,42 +45,63 @@ struct TagRow {
     name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateTagForm {
     name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdateTagForm {
     name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct TagSearchQuery {
     q: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "tags",
+    responses(
+        (status = 200, description = "Tag management page", content_type = "text/html"),
+    )
+)]
 pub async fn index(
     State(state): State<AppState>,
     current_user: CurrentUser,
 ) -> Result<Html<String>, AppError> {
     let tags = fetch_all_badges(&state.db).await?;
-    let template = state
-        .jinja
-        .get_template("tags.html")
-        .expect("template is loaded");
-    let rendered = template.render(TagsPageView {
-        tags,
-        is_admin: current_user.is_admin,
-    })?;
+    let rendered = templates::render(
+        &state.jinja,
+        "tags.html",
+        TagsPageView {
+            tags,
+            is_admin: current_user.is_admin,
+        },
+    )?;
 
     Ok(Html(rendered))
 }
 
+#[utoipa::path(
+    post,
+    path = "/tags/new",
+    tag = "tags",
+    request_body(content = CreateTagForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Creates the tag and redirects to the tag list"),
+        (status = 409, description = "Tag name is empty or already taken", content_type = "text/html"),
+    )
+)]
 pub async fn create_post(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Form(form): Form<CreateTagForm>,
 ) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
     let name = normalize_tag_name(&form.name)?;
     ensure_name_available(&state.db, &name, None).await?;
     let tag_id = Uuid::new_v4();
@@ -87,11 +113,25 @@ pub async fn create_post(
     Ok(Redirect::to("/tags"))
 }
 
+#[utoipa::path(
+    post,
+    path = "/tags/{id}/edit",
+    tag = "tags",
+    params(("id" = Uuid, Path, description = "Tag id")),
+    request_body(content = UpdateTagForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Renames the tag and redirects to the tag list"),
+        (status = 404, description = "Tag not found", content_type = "text/html"),
+        (status = 409, description = "Tag name is empty or already taken", content_type = "text/html"),
+    )
+)]
 pub async fn edit_post(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
     Form(form): Form<UpdateTagForm>,
 ) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
     let name = normalize_tag_name(&form.name)?;
     ensure_name_available(&state.db, &name, Some(id)).await?;
 
@@ -109,11 +149,22 @@ pub async fn edit_post(
     Ok(Redirect::to("/tags"))
 }
 
+#[utoipa::path(
+    get,
+    path = "/tags/{id}/delete",
+    tag = "tags",
+    params(("id" = Uuid, Path, description = "Tag id")),
+    responses(
+        (status = 200, description = "Delete confirmation page", content_type = "text/html"),
+        (status = 404, description = "Tag not found", content_type = "text/html"),
+    )
+)]
 pub async fn delete_get(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Path(id): Path<Uuid>,
 ) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
     let tag = sqlx::query!(
         r#"
         SELECT
@@ -138,24 +189,36 @@ pub async fn delete_get(
         ));
     };
 
-    let template = state
-        .jinja
-        .get_template("tag_delete_confirm.html")
-        .expect("template is loaded");
-    let rendered = template.render(DeleteTagConfirmView {
-        id: tag.id,
-        name: tag.name,
-        usage_count: tag.usage_count,
-        is_admin: current_user.is_admin,
-    })?;
+    let rendered = templates::render(
+        &state.jinja,
+        "tag_delete_confirm.html",
+        DeleteTagConfirmView {
+            id: tag.id,
+            name: tag.name,
+            usage_count: tag.usage_count,
+            is_admin: current_user.is_admin,
+        },
+    )?;
 
     Ok(Html(rendered))
 }
 
+#[utoipa::path(
+    post,
+    path = "/tags/{id}/delete",
+    tag = "tags",
+    params(("id" = Uuid, Path, description = "Tag id")),
+    responses(
+        (status = 303, description = "Deletes the tag and redirects to the tag list"),
+        (status = 404, description = "Tag not found", content_type = "text/html"),
+    )
+)]
 pub async fn delete_post(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
 ) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::EditPlans, Resource::Everything)?;
     let mut tx = state.db.begin().await?;
 
     sqlx::query!("DELETE FROM action_plan_tags WHERE tag = $1", id)
@@ -178,6 +241,15 @@ pub async fn delete_post(
     Ok(Redirect::to("/tags"))
 }
 
+#[utoipa::path(
+    get,
+    path = "/tags/search",
+    tag = "tags",
+    params(TagSearchQuery),
+    responses(
+        (status = 200, description = "Matching tags", body = Vec<TagBadge>),
+    )
+)]
 pub async fn search(
     State(state): State<AppState>,
     Query(query): Query<TagSearchQuery>,
@@ -365,13 +437,13 @@ async fn ensure_name_available(
     .fetch_optional(db)
     .await?;
 
-    if let Some(tag) = existing {
-        if Some(tag.id) != existing_id {
-            return Err(AppError::conflict(format!(
-                "A tag named \"{}\" already exists.",
-                name
-            )));
-        }
+    if let Some(tag) = existing
+        && Some(tag.id) != existing_id
+    {
+        return Err(AppError::conflict(format!(
+            "A tag named \"{}\" already exists.",
+            name
+        )));
     }
 
     Ok(())
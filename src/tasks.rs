@@ -0,0 +1,439 @@
+//! Follow-up tasks for defects found during a run, so they are not forgotten once the execution
+//! is completed. Tasks keep the plan, run and item they came from by name, which keeps them
+//! readable after the run is archived. The task page lists them for assigning and closing.
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{Html, Redirect},
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    assignees::{self, AssigneeOption},
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, Role, authorize},
+    format_unix_timestamp, templates, unix_now,
+};
+
+/// Longest closing note accepted, in characters.
+const MAX_RESOLUTION_CHARS: usize = 1000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskListQuery {
+    /// List closed tasks instead of open ones.
+    closed: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TaskAssigneeForm {
+    /// User id, empty to unassign.
+    assignee: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CloseTaskForm {
+    /// What was done about it, e.g. "replaced seal".
+    #[serde(default)]
+    resolution: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TasksView {
+    tasks: Vec<TaskListItem>,
+    show_closed: bool,
+    open_count: i64,
+    assignee_options: Vec<AssigneeOption>,
+    can_run_executions: bool,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskListItem {
+    id: Uuid,
+    title: String,
+    note: String,
+    action_plan_name: String,
+    run_number: i64,
+    item_name: String,
+    /// Where the run can still be looked at, if anywhere.
+    execution_url: Option<String>,
+    created_display: String,
+    created_by_name: Option<String>,
+    assigned_to: Option<Uuid>,
+    assignee_name: Option<String>,
+    closed_display: Option<String>,
+    closed_by_name: Option<String>,
+    resolution: Option<String>,
+}
+
+/// Number of tasks nobody closed yet, for the home page.
+pub async fn count_open(db: &SqlitePool) -> Result<i64, AppError> {
+    Ok(sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!: i64" FROM tasks WHERE closed_at IS NULL"#
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+#[utoipa::path(
+    get,
+    path = "/tasks",
+    tag = "tasks",
+    params(TaskListQuery),
+    responses(
+        (status = 200, description = "Open follow-up tasks, oldest first, or closed ones, newest first", content_type = "text/html"),
+    )
+)]
+pub async fn index(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Query(query): Query<TaskListQuery>,
+) -> Result<Html<String>, AppError> {
+    let show_closed = query.closed.unwrap_or(false);
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            tasks.id as "id!: uuid::Uuid",
+            tasks.title,
+            tasks.note,
+            tasks.action_plan_execution as "execution_id!: uuid::Uuid",
+            tasks.action_plan_name,
+            tasks.run_number,
+            tasks.item_name,
+            tasks.created_at,
+            creators.name as "created_by_name?",
+            tasks.assigned_to as "assigned_to?: uuid::Uuid",
+            assignees.name as "assignee_name?",
+            tasks.closed_at,
+            closers.name as "closed_by_name?",
+            tasks.resolution,
+            action_plan_executions.id IS NOT NULL as "is_execution_live!: bool"
+        FROM tasks
+        LEFT JOIN users AS creators ON creators.id = tasks.created_by
+        LEFT JOIN users AS assignees ON assignees.id = tasks.assigned_to
+        LEFT JOIN users AS closers ON closers.id = tasks.closed_by
+        LEFT JOIN action_plan_executions
+            ON action_plan_executions.id = tasks.action_plan_execution
+        WHERE (tasks.closed_at IS NOT NULL) = $1
+        ORDER BY
+            CASE WHEN $1 THEN -tasks.closed_at ELSE tasks.created_at END ASC
+        "#,
+        show_closed
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let tasks = rows
+        .into_iter()
+        .map(|row| TaskListItem {
+            id: row.id,
+            title: row.title,
+            note: row.note,
+            action_plan_name: row.action_plan_name,
+            run_number: row.run_number,
+            item_name: row.item_name,
+            execution_url: if row.is_execution_live {
+                Some(format!("/executions/{}", row.execution_id))
+            } else if state.archive_enabled {
+                Some(format!("/executions/archive/{}", row.execution_id))
+            } else {
+                None
+            },
+            created_display: format_unix_timestamp(row.created_at),
+            created_by_name: row.created_by_name,
+            assigned_to: row.assigned_to,
+            assignee_name: row.assignee_name,
+            closed_display: row.closed_at.map(format_unix_timestamp),
+            closed_by_name: row.closed_by_name,
+            resolution: row.resolution,
+        })
+        .collect();
+
+    let can_run_executions = current_user.role >= Role::Operator;
+    let rendered = templates::render(
+        &state.jinja,
+        "tasks.html",
+        TasksView {
+            tasks,
+            show_closed,
+            open_count: count_open(&state.db).await?,
+            assignee_options: if can_run_executions {
+                assignees::fetch_options(&state.db).await?
+            } else {
+                Vec::new()
+            },
+            can_run_executions,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tasks/{id}/assignee",
+    tag = "tasks",
+    params(("id" = Uuid, Path, description = "Task id")),
+    request_body(content = TaskAssigneeForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Assigns or unassigns the task and redirects to the task list"),
+        (status = 404, description = "Task or user not found", content_type = "text/html"),
+        (status = 409, description = "The task is closed or the user cannot run executions", content_type = "text/html"),
+    )
+)]
+pub async fn assign_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<TaskAssigneeForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let assignee = match form.assignee.trim() {
+        "" => None,
+        value => Some(Uuid::parse_str(value).map_err(|_| {
+            AppError::not_found_for("User", format!("No user exists for id: {}", value))
+        })?),
+    };
+
+    let mut tx = state.db.begin().await?;
+    let task = sqlx::query!(
+        r#"
+        SELECT
+            tasks.title,
+            tasks.closed_at,
+            assignees.name as "assignee_name?"
+        FROM tasks
+        LEFT JOIN users AS assignees ON assignees.id = tasks.assigned_to
+        WHERE tasks.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::not_found_for("Task", format!("No task exists for id: {}", id)))?;
+    if task.closed_at.is_some() {
+        return Err(AppError::conflict("Closed tasks cannot be reassigned."));
+    }
+
+    let assignee_name = match assignee {
+        Some(user_id) => {
+            let user = sqlx::query!(
+                "SELECT name, role, is_disabled FROM users WHERE id = $1",
+                user_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                AppError::not_found_for("User", format!("No user exists for id: {}", user_id))
+            })?;
+            if user.is_disabled != 0 || Role::from_db(&user.role) < Role::Operator {
+                return Err(AppError::conflict(format!(
+                    "{} cannot run executions, so tasks cannot be assigned to them.",
+                    user.name
+                )));
+            }
+            Some(user.name)
+        }
+        None => None,
+    };
+
+    sqlx::query!(
+        "UPDATE tasks SET assigned_to = $1 WHERE id = $2",
+        assignee,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if task.assignee_name != assignee_name {
+        let summary = match &assignee_name {
+            Some(name) => format!("Assigned task \"{}\" to {}", task.title, name),
+            None => format!("Unassigned task \"{}\"", task.title),
+        };
+        audit::record(
+            &mut *tx,
+            &current_user,
+            AuditEntry::new(AuditEntity::Task, id, AuditAction::Update, summary)
+                .change(task.assignee_name, assignee_name),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Redirect::to("/tasks"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tasks/{id}/close",
+    tag = "tasks",
+    params(("id" = Uuid, Path, description = "Task id")),
+    request_body(content = CloseTaskForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Closes the task and redirects to the task list"),
+        (status = 404, description = "Task not found", content_type = "text/html"),
+        (status = 409, description = "The task is closed already or the note is too long", content_type = "text/html"),
+    )
+)]
+pub async fn close_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<CloseTaskForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let resolution = Some(form.resolution.trim().to_string()).filter(|value| !value.is_empty());
+    if resolution
+        .as_ref()
+        .is_some_and(|value| value.chars().count() > MAX_RESOLUTION_CHARS)
+    {
+        return Err(AppError::conflict(format!(
+            "Closing notes can have at most {} characters.",
+            MAX_RESOLUTION_CHARS
+        )));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let task = sqlx::query!("SELECT title, closed_at FROM tasks WHERE id = $1", id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::not_found_for("Task", format!("No task exists for id: {}", id)))?;
+    if task.closed_at.is_some() {
+        return Err(AppError::conflict(format!(
+            "\"{}\" is closed already.",
+            task.title
+        )));
+    }
+
+    let closed_at = unix_now();
+    sqlx::query!(
+        r#"
+        UPDATE tasks
+        SET closed_at = $1, closed_by = $2, resolution = $3
+        WHERE id = $4
+        "#,
+        closed_at,
+        current_user.id,
+        resolution,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Task,
+            id,
+            AuditAction::Complete,
+            format!("Closed task \"{}\"", task.title),
+        )
+        .change(None, resolution),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Redirect::to("/tasks"))
+}
+
+/// Opens a follow-up task for an item that failed, with the failure note as its description.
+pub async fn create_for_failed_item(
+    conn: &mut SqliteConnection,
+    current_user: &CurrentUser,
+    item_id: Uuid,
+    note: &str,
+) -> Result<Uuid, AppError> {
+    let item = sqlx::query!(
+        r#"
+        SELECT
+            action_item_executions.action_plan_execution as "execution_id!: uuid::Uuid",
+            actions.name as "item_name!",
+            action_plans.name as "action_plan_name!",
+            action_plan_executions.run_number as "run_number!"
+        FROM action_item_executions
+        INNER JOIN actions ON actions.id = action_item_executions.action
+        INNER JOIN action_plan_executions
+            ON action_plan_executions.id = action_item_executions.action_plan_execution
+        INNER JOIN action_plans ON action_plans.id = action_plan_executions.action_plan
+        WHERE action_item_executions.id = $1
+        "#,
+        item_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let task_id = Uuid::new_v4();
+    let title = format!("Follow up on \"{}\"", item.item_name);
+    let created_at = unix_now();
+    sqlx::query!(
+        r#"
+        INSERT INTO tasks
+            (id, title, note, action_plan_execution, action_item_execution, action_plan_name, run_number, item_name, created_at, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+        task_id,
+        title,
+        note,
+        item.execution_id,
+        item_id,
+        item.action_plan_name,
+        item.run_number,
+        item.item_name,
+        created_at,
+        current_user.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(task_id)
+}
+
+/// Removes the open follow-up tasks of an item whose failure was taken back. Closed ones stay
+/// on record.
+pub async fn delete_for_item(conn: &mut SqliteConnection, item_id: Uuid) -> Result<(), AppError> {
+    sqlx::query!(
+        "DELETE FROM tasks WHERE action_item_execution = $1 AND closed_at IS NULL",
+        item_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Points tasks at an item's new id after the items of a run were rebuilt from the edited plan.
+pub async fn move_to_item(
+    conn: &mut SqliteConnection,
+    previous_item_id: Uuid,
+    item_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE tasks SET action_item_execution = $1 WHERE action_item_execution = $2",
+        item_id,
+        previous_item_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Removes the tasks of an execution that is deleted.
+pub async fn delete_for_execution(
+    conn: &mut SqliteConnection,
+    execution_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "DELETE FROM tasks WHERE action_plan_execution = $1",
+        execution_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
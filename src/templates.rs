@@ -0,0 +1,44 @@
+use minijinja::Environment;
+use serde::Serialize;
+
+use crate::{AppError, assets, auto_logout, read_only};
+
+/// Instructions a single render may execute before it is aborted. Generous enough for
+/// pages listing thousands of plans, but stops runaway loops from tying up a worker.
+const TEMPLATE_FUEL: u64 = 5_000_000;
+/// Nesting depth for includes, macros and blocks; the embedded templates need far less.
+const TEMPLATE_RECURSION_LIMIT: usize = 100;
+
+/// Builds the template environment with the embedded templates and render limits applied.
+pub fn environment(base_path: &str) -> Environment<'static> {
+    let mut jinja = Environment::new();
+    minijinja_embed::load_templates!(&mut jinja);
+    jinja.set_fuel(Some(TEMPLATE_FUEL));
+    jinja.set_recursion_limit(TEMPLATE_RECURSION_LIMIT);
+    jinja.add_global("base_path", base_path.to_string());
+    jinja.add_function("asset_url", assets::asset_url);
+    jinja.add_function("read_only_notice", read_only::notice);
+    jinja.add_function("auto_logout_seconds", auto_logout::layout_limit);
+    jinja
+}
+
+/// Renders `name`, logging the full template error with its source location on failure and
+/// returning an error that shows the generic error page instead of the raw message.
+pub fn render<S: Serialize>(
+    jinja: &Environment<'_>,
+    name: &str,
+    context: S,
+) -> Result<String, AppError> {
+    jinja
+        .get_template(name)
+        .and_then(|template| template.render(context))
+        .map_err(|err| {
+            tracing::error!(
+                template = name,
+                "Rendering template failed: {:#}\n{}",
+                err,
+                err.display_debug_info()
+            );
+            AppError::render_failed()
+        })
+}
@@ -0,0 +1,617 @@
+//! Request-level checks of the whole router, run against a fresh in-memory database.
+
+use std::{str::FromStr, sync::Arc};
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+    response::Response,
+};
+use sqlx::{
+    SqlitePool,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+use tower::ServiceExt;
+use utoipa::OpenApi;
+use uuid::Uuid;
+
+use crate::{
+    AppState, authz::Role, collab, config, encryption, events, logging, mail, openapi, rate_limit,
+    storage, templates, tokens,
+};
+
+/// Least role each route needs beyond being logged in. Routes not listed are open to viewers.
+const GUARDED_ROUTES: &[(&str, &str, Role)] = &[
+    ("GET", "/admin", Role::Admin),
+    ("GET", "/admin/activity", Role::Admin),
+    ("GET", "/admin/actions.csv", Role::Admin),
+    ("GET", "/admin/actions.json", Role::Admin),
+    ("GET", "/admin/usage", Role::Admin),
+    ("GET", "/admin/usage.json", Role::Admin),
+    ("POST", "/admin/sync", Role::Admin),
+    ("GET", "/admin/jobs/{job}", Role::Admin),
+    ("POST", "/admin/jobs/{job}", Role::Admin),
+    ("GET", "/admin/quarantine", Role::Admin),
+    ("POST", "/admin/quarantine/{id}/release", Role::Admin),
+    ("POST", "/admin/quarantine/{id}/delete", Role::Admin),
+    ("GET", "/attachments/{id}/original", Role::Admin),
+    ("GET", "/backup", Role::Admin),
+    ("GET", "/backup/export.json", Role::Admin),
+    ("POST", "/backup/import", Role::Admin),
+    ("GET", "/users", Role::Admin),
+    ("POST", "/users/invites", Role::Admin),
+    ("POST", "/users/invites/{id}/revoke", Role::Admin),
+    ("GET", "/users/{id}/delete", Role::Admin),
+    ("POST", "/users/{id}/delete", Role::Admin),
+    ("GET", "/users/{id}/edit", Role::Admin),
+    ("POST", "/users/{id}/edit", Role::Admin),
+    ("POST", "/users/{id}/unlock", Role::Admin),
+    ("POST", "/users/{id}/disable", Role::Admin),
+    ("POST", "/users/{id}/enable", Role::Admin),
+    ("POST", "/users/{id}/2fa/reset", Role::Admin),
+    ("GET", "/groups", Role::Admin),
+    ("POST", "/groups", Role::Admin),
+    ("GET", "/groups/{id}/edit", Role::Admin),
+    ("POST", "/groups/{id}/edit", Role::Admin),
+    ("GET", "/groups/{id}/delete", Role::Admin),
+    ("POST", "/groups/{id}/delete", Role::Admin),
+    ("GET", "/action_plan/{id}/merge", Role::Admin),
+    ("POST", "/action_plan/{id}/merge", Role::Admin),
+    ("POST", "/action_plan/{id}/delete", Role::Admin),
+    ("POST", "/action_plan/{id}/undelete", Role::Admin),
+    ("GET", "/action_plan/new", Role::Admin),
+    ("POST", "/action_plan/new", Role::Admin),
+    ("GET", "/action_plan/{id}/edit", Role::Admin),
+    ("POST", "/action_plan/{id}/edit", Role::Admin),
+    ("GET", "/action_plan/{id}/scan-codes", Role::Admin),
+    ("POST", "/action_plan/{id}/sync/reset", Role::Admin),
+    ("POST", "/action_plan/{id}/attachments", Role::Admin),
+    ("POST", "/plan-attachments/{id}/delete", Role::Admin),
+    ("POST", "/actions/{id}/links", Role::Admin),
+    ("POST", "/action-links/{id}/delete", Role::Admin),
+    ("POST", "/tags/new", Role::Admin),
+    ("GET", "/tags/{id}/delete", Role::Admin),
+    ("POST", "/tags/{id}/edit", Role::Admin),
+    ("POST", "/tags/{id}/delete", Role::Admin),
+    ("POST", "/executions/{id}/note", Role::Operator),
+    ("POST", "/executions/{id}/label", Role::Operator),
+    ("GET", "/executions/{id}/complete", Role::Operator),
+    ("GET", "/executions/{id}/reopen", Role::Operator),
+    ("POST", "/executions/{id}/transfer", Role::Operator),
+    ("POST", "/executions/{id}/comments", Role::Operator),
+    (
+        "POST",
+        "/executions/{id}/acknowledge-procedure",
+        Role::Operator,
+    ),
+    ("POST", "/execution-items/{id}/attachments", Role::Operator),
+    ("POST", "/attachments/{id}/delete", Role::Operator),
+    ("POST", "/execution-comments/{id}/delete", Role::Operator),
+    ("GET", "/executions/{id}/delete", Role::Operator),
+    ("POST", "/executions/{id}/delete", Role::Operator),
+    ("POST", "/execution-items/{id}/finished", Role::Operator),
+    ("POST", "/execution-items/{id}/note", Role::Operator),
+    ("POST", "/execution-items/{id}/assignee", Role::Operator),
+    ("POST", "/execution-items/{id}/block", Role::Operator),
+    (
+        "POST",
+        "/execution-items/{id}/resolve-block",
+        Role::Operator,
+    ),
+    ("POST", "/execution-items/{id}/skip", Role::Operator),
+    ("POST", "/execution-items/{id}/unskip", Role::Operator),
+    ("POST", "/execution-items/{id}/fail", Role::Operator),
+    ("POST", "/execution-items/{id}/unfail", Role::Operator),
+    ("POST", "/action_plan/{id}/execute", Role::Operator),
+    ("POST", "/api/executions/batch", Role::Operator),
+    ("GET", "/scan", Role::Operator),
+    ("POST", "/scan", Role::Operator),
+    ("POST", "/tasks/{id}/assignee", Role::Operator),
+    ("POST", "/tasks/{id}/close", Role::Operator),
+];
+
+/// Served outside the documented API, checked along with it for logins.
+const UNDOCUMENTED_ROUTES: &[(&str, &str)] = &[
+    ("GET", "/static/{file}"),
+    ("GET", "/api/docs/"),
+    ("GET", "/api/openapi.json"),
+];
+
+/// Routes the matrix leaves out: they end the session or keep the connection open.
+const UNCHECKED_ROUTES: &[(&str, &str)] = &[
+    ("POST", "/logout"),
+    ("GET", "/events"),
+    ("GET", "/executions/{id}/events"),
+    ("GET", "/executions/{id}/ws"),
+    ("GET", "/session/idle-events"),
+];
+
+async fn test_state() -> AppState {
+    let options = SqliteConnectOptions::from_str(&format!(
+        "sqlite:file:{}?mode=memory&cache=shared",
+        Uuid::new_v4()
+    ))
+    .unwrap()
+    .foreign_keys(true);
+    // The database lives as long as one connection to it is open.
+    let db = SqlitePoolOptions::new()
+        .min_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(options)
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&db).await.unwrap();
+
+    let config = Arc::new(config::Config::default());
+    AppState {
+        jinja: Arc::new(templates::environment(&config.base_path)),
+        execution_events: events::execution_channel(),
+        presence: collab::Presence::default(),
+        token_signer: tokens::TokenSigner::load(&db, &config).await.unwrap(),
+        secret_cipher: encryption::SecretCipher::load(&db, &config).await.unwrap(),
+        mailer: mail::Mailer::from_config(&config).unwrap(),
+        storage: storage::from_config(&config).unwrap(),
+        login_rate_limiter: rate_limit::LoginRateLimiter::new(
+            config.login_attempts_per_address,
+            config.login_attempts_per_user,
+            config.login_attempts_refill_seconds,
+        ),
+        archive_enabled: false,
+        endpoint_timings: logging::EndpointTimings::default(),
+        startup_warnings: Arc::new(Vec::new()),
+        db,
+        config,
+    }
+}
+
+/// Adds a user with `role` and returns the id of a session for them.
+async fn log_in(db: &SqlitePool, role: Role) -> Uuid {
+    let now = chrono::Utc::now().timestamp();
+    let user_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO users (id, name, role, created_at) VALUES ($1, $2, $3, $4)")
+        .bind(user_id)
+        .bind(role.as_str())
+        .bind(role.as_str())
+        .bind(now)
+        .execute(db)
+        .await
+        .unwrap();
+    let session_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO user_sessions (id, user_id, created_at, last_seen) VALUES ($1, $2, $3, $3)",
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .bind(now)
+    .execute(db)
+    .await
+    .unwrap();
+    session_id
+}
+
+/// Every documented route as method and path template.
+fn documented_routes() -> Vec<(&'static str, String)> {
+    let mut routes = Vec::new();
+    for (path, item) in openapi::ApiDoc::openapi().paths.paths {
+        if item.get.is_some() {
+            routes.push(("GET", path.clone()));
+        }
+        if item.post.is_some() {
+            routes.push(("POST", path));
+        }
+    }
+    routes
+}
+
+/// The template with every parameter filled in with something that exists nowhere.
+fn concrete_path(template: &str) -> String {
+    template
+        .split('/')
+        .map(|segment| match segment {
+            "{job}" => "no-such-job".to_string(),
+            "{format}" => "csv".to_string(),
+            "{slug}" => "no-such-plan".to_string(),
+            "{file}" => "no-such-file.css".to_string(),
+            segment if segment.starts_with('{') => Uuid::new_v4().to_string(),
+            segment => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+async fn send(app: &Router, method: &str, path: &str, session_id: Option<Uuid>) -> Response {
+    send_form(app, method, path, session_id, "").await
+}
+
+/// Sends `body` as an urlencoded form.
+async fn send_form(
+    app: &Router,
+    method: &str,
+    path: &str,
+    session_id: Option<Uuid>,
+    body: &str,
+) -> Response {
+    let mut request = Request::builder()
+        .method(Method::from_str(method).unwrap())
+        .uri(path)
+        .header(
+            header::CONTENT_TYPE,
+            mime::APPLICATION_WWW_FORM_URLENCODED.as_ref(),
+        );
+    if let Some(session_id) = session_id {
+        request = request.header(
+            header::COOKIE,
+            format!(
+                "{}={}",
+                config::Config::default().session_cookie_name(),
+                session_id
+            ),
+        );
+    }
+    app.clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn guarded_routes_are_documented() {
+    let routes = documented_routes();
+    for (method, path, _) in GUARDED_ROUTES {
+        assert!(
+            routes.iter().any(|(m, p)| m == method && p == path),
+            "{} {} is not a documented route",
+            method,
+            path
+        );
+    }
+}
+
+#[tokio::test]
+async fn routes_allow_exactly_the_roles_they_need() {
+    for role in Role::ALL {
+        let state = test_state().await;
+        let db = state.db.clone();
+        let session_id = log_in(&db, role).await;
+        let app = crate::app(state);
+
+        for (method, template) in documented_routes() {
+            if UNCHECKED_ROUTES
+                .iter()
+                .any(|(m, p)| *m == method && *p == template)
+            {
+                continue;
+            }
+            let required = GUARDED_ROUTES
+                .iter()
+                .find(|(m, p, _)| *m == method && *p == template)
+                .map(|(_, _, required)| *required)
+                .unwrap_or(Role::Viewer);
+
+            let status = send(&app, method, &concrete_path(&template), Some(session_id))
+                .await
+                .status();
+            if role >= required {
+                assert_ne!(
+                    status,
+                    StatusCode::FORBIDDEN,
+                    "{} {} refused for {:?}",
+                    method,
+                    template,
+                    role
+                );
+            } else {
+                assert_eq!(
+                    status,
+                    StatusCode::FORBIDDEN,
+                    "{} {} allowed for {:?}",
+                    method,
+                    template,
+                    role
+                );
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn only_public_paths_are_served_without_a_login() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let app = crate::app(state);
+    // Before setup everything but the always public paths goes to setup, so add a user first.
+    log_in(&db, Role::Admin).await;
+
+    let routes = documented_routes().into_iter().chain(
+        UNDOCUMENTED_ROUTES
+            .iter()
+            .map(|(method, path)| (*method, path.to_string())),
+    );
+    for (method, template) in routes {
+        let path = concrete_path(&template);
+        if crate::is_public(crate::ALWAYS_PUBLIC_PATHS, &path)
+            || crate::is_public(crate::PUBLIC_PATHS, &path)
+        {
+            continue;
+        }
+
+        let response = send(&app, method, &path, None).await;
+        let status = response.status();
+        let location = response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|value| value.to_str().ok());
+        assert!(
+            status == StatusCode::UNAUTHORIZED
+                || (status.is_redirection() && location == Some("/login")),
+            "{} {} answered {} without a login",
+            method,
+            template,
+            status
+        );
+    }
+}
+
+/// The id at the end of the redirect `response` points to.
+fn redirect_id(response: &Response) -> Uuid {
+    let location = response.headers()[header::LOCATION].to_str().unwrap();
+    Uuid::parse_str(location.rsplit('/').next().unwrap()).unwrap()
+}
+
+async fn parent_finished(db: &SqlitePool, execution_id: Uuid) -> bool {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT finished FROM action_item_executions WHERE action_plan_execution = $1 AND order_index = 0",
+    )
+    .bind(execution_id)
+    .fetch_one(db)
+    .await
+    .unwrap()
+        > 0
+}
+
+#[tokio::test]
+async fn blocked_sub_items_count_as_dealt_with_for_their_parent() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let session_id = Some(log_in(&db, Role::Admin).await);
+    let app = crate::app(state);
+
+    let response = send_form(
+        &app,
+        "POST",
+        "/action_plan/new",
+        session_id,
+        "name=Pump&items=Lubricate&items=Grease+bearing&nestings=&nestings=sub",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let plan_id = redirect_id(&response);
+    let response = send_form(
+        &app,
+        "POST",
+        &format!("/action_plan/{}/execute", plan_id),
+        session_id,
+        "label=",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let execution_id = redirect_id(&response);
+    let child_id: Uuid = sqlx::query_scalar(
+        "SELECT id FROM action_item_executions WHERE action_plan_execution = $1 AND order_index = 1",
+    )
+    .bind(execution_id)
+    .fetch_one(&db)
+    .await
+    .unwrap();
+
+    let block = format!("/execution-items/{}/block", child_id);
+    let response = send_form(&app, "POST", &block, session_id, "reason=No+grease").await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(parent_finished(&db, execution_id).await);
+
+    let resolve = format!("/execution-items/{}/resolve-block", child_id);
+    let response = send_form(&app, "POST", &resolve, session_id, "resolution=").await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert!(!parent_finished(&db, execution_id).await);
+
+    send_form(&app, "POST", &block, session_id, "reason=No+grease").await;
+    let complete = format!("/executions/{}/complete", execution_id);
+    let response = send(&app, "GET", &complete, session_id).await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let finished: Option<i64> =
+        sqlx::query_scalar("SELECT finished FROM action_plan_executions WHERE id = $1")
+            .bind(execution_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert!(finished.is_some());
+}
+
+#[tokio::test]
+async fn wrong_two_factor_codes_lock_signed_in_users_out() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let threshold = state.config.lockout_threshold;
+    let session_id = log_in(&db, Role::Viewer).await;
+    let user_id: Uuid = sqlx::query_scalar("SELECT user_id FROM user_sessions WHERE id = $1")
+        .bind(session_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    let secret = state
+        .secret_cipher
+        .encrypt(user_id.as_bytes(), &[7; 20])
+        .unwrap();
+    sqlx::query("UPDATE users SET totp_secret = $1, totp_enabled_at = 1 WHERE id = $2")
+        .bind(secret)
+        .bind(user_id)
+        .execute(&db)
+        .await
+        .unwrap();
+    let app = crate::app(state);
+
+    // Shaped like a recovery code, so it cannot match by chance.
+    let wrong_code = "code=AAAAA-AAAAA";
+    for path in ["/users/me/2fa/disable", "/users/me/2fa/recovery-codes"]
+        .into_iter()
+        .cycle()
+        .take(threshold as usize)
+    {
+        let response = send_form(&app, "POST", path, Some(session_id), wrong_code).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+    let locked_until: Option<i64> =
+        sqlx::query_scalar("SELECT locked_until FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert!(locked_until.is_some_and(|locked_until| locked_until > crate::unix_now()));
+
+    let failed_count: i64 =
+        sqlx::query_scalar("SELECT failed_login_count FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    send_form(
+        &app,
+        "POST",
+        "/users/me/2fa/disable",
+        Some(session_id),
+        wrong_code,
+    )
+    .await;
+    let failed_count_after: i64 =
+        sqlx::query_scalar("SELECT failed_login_count FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    // Once locked, codes are not even checked.
+    assert_eq!(failed_count_after, failed_count);
+    let enabled: Option<i64> =
+        sqlx::query_scalar("SELECT totp_enabled_at FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert!(enabled.is_some());
+}
+
+#[tokio::test]
+async fn deleting_a_plan_shows_the_undo_notice_on_the_plan_list() {
+    let mut state = test_state().await;
+    state.config = Arc::new(config::Config {
+        session_cookie_secure: true,
+        ..config::Config::default()
+    });
+    let db = state.db.clone();
+    let session_id = log_in(&db, Role::Admin).await;
+    let app = crate::app(state);
+
+    let response = send_form(
+        &app,
+        "POST",
+        "/action_plan/new",
+        Some(session_id),
+        "name=Pump",
+    )
+    .await;
+    let plan_id = redirect_id(&response);
+    let response = send(
+        &app,
+        "POST",
+        &format!("/action_plan/{}/delete", plan_id),
+        Some(session_id),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(response.headers()[header::LOCATION], "/plans");
+    let flash = response.headers()[header::SET_COOKIE].to_str().unwrap();
+    let flash_name = config::Config::default().flash_cookie_name();
+    assert!(flash.starts_with(&format!("{}=", flash_name)));
+    assert!(flash.contains("Secure"));
+
+    let flash_pair = flash.split(';').next().unwrap();
+    let request = Request::builder()
+        .uri("/plans")
+        .header(
+            header::COOKIE,
+            format!(
+                "{}={}; {}",
+                config::Config::default().session_cookie_name(),
+                session_id,
+                flash_pair
+            ),
+        )
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let removal = response.headers()[header::SET_COOKIE].to_str().unwrap();
+    assert!(removal.starts_with(&format!("{}=;", flash_name)));
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("Deleted \"Pump\"."));
+}
+
+#[tokio::test]
+async fn verification_mails_need_a_public_url() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let session_id = Some(log_in(&db, Role::Viewer).await);
+    let app = crate::app(state);
+    let response = send_form(
+        &app,
+        "POST",
+        "/users/me/email",
+        session_id,
+        "email=first%40example.com",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let mut state = test_state().await;
+    state.config = Arc::new(config::Config {
+        public_url: Some("https://planner.example.com/".to_string()),
+        ..config::Config::default()
+    });
+    let db = state.db.clone();
+    let session_id = Some(log_in(&db, Role::Viewer).await);
+    let app = crate::app(state);
+    let response = send_form(
+        &app,
+        "POST",
+        "/users/me/email",
+        session_id,
+        "email=first%40example.com",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+}
+
+#[test]
+fn links_ignore_the_host_header_once_public_url_is_set() {
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert(header::HOST, "evil.example".parse().unwrap());
+    let config = config::Config {
+        public_url: Some("https://planner.example.com/maintenance/".to_string()),
+        ..config::Config::default()
+    };
+    assert_eq!(
+        crate::absolute_url(&config, &headers, "/invite?token=x"),
+        "https://planner.example.com/maintenance/invite?token=x"
+    );
+    assert_eq!(
+        crate::public_url(&config, "/executions/1").as_deref(),
+        Some("https://planner.example.com/maintenance/executions/1")
+    );
+    assert_eq!(
+        crate::public_url(&config::Config::default(), "/executions/1"),
+        None
+    );
+}
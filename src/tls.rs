@@ -0,0 +1,101 @@
+use std::{future::Future, net::SocketAddr, path::Path, sync::Arc};
+
+use anyhow::Context;
+use axum::{Extension, Router, extract::ConnectInfo};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use tokio::{net::TcpListener, sync::watch};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        ServerConfig,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    },
+};
+
+/// Builds a TLS acceptor from a PEM certificate chain and a PEM private key.
+pub fn load_acceptor(cert_path: &str, key_path: &str) -> anyhow::Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(Path::new(cert_path))
+        .with_context(|| format!("failed to open certificate {}", cert_path))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("failed to parse certificate {}", cert_path))?;
+    if certs.is_empty() {
+        anyhow::bail!("certificate {} contains no certificates", cert_path);
+    }
+
+    let key = PrivateKeyDer::from_pem_file(Path::new(key_path))
+        .with_context(|| format!("failed to read private key {}", key_path))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(
+        tokio_rustls::rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .context("certificate and private key do not match")?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Serves `app` over TLS until `shutdown` resolves, then lets open connections finish.
+pub async fn serve(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    app: Router,
+    shutdown: impl Future<Output = ()>,
+) {
+    let (shutdown_tx, shutdown_rx) = watch::channel(());
+    tokio::pin!(shutdown);
+
+    loop {
+        let (stream, peer_addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    tracing::warn!("Accepting connection failed: {}", err);
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+
+        let acceptor = acceptor.clone();
+        let service = TowerToHyperService::new(
+            app.clone()
+                .layer(Extension(ConnectInfo::<SocketAddr>(peer_addr))),
+        );
+        let mut shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    tracing::debug!("TLS handshake with {} failed: {}", peer_addr, err);
+                    return;
+                }
+            };
+
+            let builder = auto::Builder::new(TokioExecutor::new());
+            let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+            tokio::pin!(connection);
+
+            let result = tokio::select! {
+                result = connection.as_mut() => result,
+                _ = shutdown_rx.changed() => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(err) = result {
+                tracing::debug!("Connection with {} closed with error: {}", peer_addr, err);
+            }
+        });
+    }
+
+    drop(shutdown_rx);
+    let _ = shutdown_tx.send(());
+    shutdown_tx.closed().await;
+}
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+use crate::{AppError, config::Config, unix_now};
+
+const SIGNING_KEY_SECRET_NAME: &str = "token_signing_key";
+const GENERATED_KEY_LENGTH: usize = 32;
+
+/// What a token grants access to. A token signed for one scope is rejected by every other.
+#[derive(Debug, Clone, Copy)]
+pub enum TokenScope {
+    CalendarFeed,
+    UserInvite,
+    EmailVerification,
+    ScanCode,
+    LoginChallenge,
+}
+
+impl TokenScope {
+    fn as_str(self) -> &'static str {
+        match self {
+            TokenScope::CalendarFeed => "calendar_feed",
+            TokenScope::UserInvite => "user_invite",
+            TokenScope::EmailVerification => "email_verification",
+            TokenScope::ScanCode => "scan_code",
+            TokenScope::LoginChallenge => "login_challenge",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TokenClaims {
+    /// Identifies the token for revocation.
+    id: Uuid,
+    scope: String,
+    /// The user (or other record) the token acts for.
+    subject: Uuid,
+    /// Unix timestamp after which the token is rejected. `None` never expires.
+    expires: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VerifiedToken {
+    pub id: Uuid,
+    pub subject: Uuid,
+}
+
+/// Signs and verifies tokens for links that work without a login, such as calendar feeds.
+/// A token is the base64url-encoded claims and their HMAC-SHA256, so it cannot be guessed or
+/// altered without the server secret.
+#[derive(Clone)]
+pub struct TokenSigner {
+    key: Arc<[u8]>,
+}
+
+impl std::fmt::Debug for TokenSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenSigner").finish_non_exhaustive()
+    }
+}
+
+impl TokenSigner {
+    /// Uses the configured secret, or a random key generated once and stored in the database.
+    pub async fn load(db: &SqlitePool, config: &Config) -> anyhow::Result<Self> {
+        if let Some(secret) = &config.token_secret {
+            return Ok(Self {
+                key: secret.as_bytes().into(),
+            });
+        }
+
+        let mut generated = [0u8; GENERATED_KEY_LENGTH];
+        OsRng.fill_bytes(&mut generated);
+        let generated = generated.to_vec();
+        sqlx::query!(
+            "INSERT OR IGNORE INTO server_secrets (name, value) VALUES ($1, $2)",
+            SIGNING_KEY_SECRET_NAME,
+            generated
+        )
+        .execute(db)
+        .await?;
+
+        let key = sqlx::query_scalar!(
+            "SELECT value FROM server_secrets WHERE name = $1",
+            SIGNING_KEY_SECRET_NAME
+        )
+        .fetch_one(db)
+        .await
+        .context("failed to load the token signing key")?;
+
+        Ok(Self { key: key.into() })
+    }
+
+    pub fn sign(&self, scope: TokenScope, id: Uuid, subject: Uuid, expires: Option<i64>) -> String {
+        let claims = TokenClaims {
+            id,
+            scope: scope.as_str().to_string(),
+            subject,
+            expires,
+        };
+        let payload = URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&claims).expect("token claims always serialize"));
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Returns the token's claims if it is correctly signed for `scope`, not expired and not
+    /// revoked, and `None` otherwise.
+    pub async fn verify(
+        &self,
+        db: &SqlitePool,
+        token: &str,
+        scope: TokenScope,
+    ) -> Result<Option<VerifiedToken>, AppError> {
+        let Some(claims) = self.decode(token) else {
+            return Ok(None);
+        };
+        if claims.scope != scope.as_str() {
+            return Ok(None);
+        }
+        if claims.expires.is_some_and(|expires| expires <= unix_now()) {
+            return Ok(None);
+        }
+
+        let revoked = sqlx::query_scalar!(
+            r#"SELECT 1 as "revoked!: i64" FROM revoked_tokens WHERE id = $1"#,
+            claims.id
+        )
+        .fetch_optional(db)
+        .await?
+        .is_some();
+        if revoked {
+            return Ok(None);
+        }
+
+        Ok(Some(VerifiedToken {
+            id: claims.id,
+            subject: claims.subject,
+        }))
+    }
+
+    fn decode(&self, token: &str) -> Option<TokenClaims> {
+        let (payload, signature) = token.split_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        // Constant-time comparison, so the signature cannot be guessed byte by byte.
+        self.mac(payload).verify_slice(&signature).ok()?;
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()
+    }
+
+    /// A key for `purpose` derived from the signing key. It changes with `token_secret`, like
+    /// every signed link, so it is only used to read two-factor secrets stored before they got
+    /// their own encryption key.
+    pub fn derive_key(&self, purpose: &str) -> [u8; 32] {
+        self.mac(purpose).finalize().into_bytes().into()
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+/// Rejects the token with this id from now on, even though its signature stays valid.
+pub async fn revoke(db: &SqlitePool, id: Uuid) -> Result<(), AppError> {
+    let now = unix_now();
+    sqlx::query!(
+        "INSERT OR IGNORE INTO revoked_tokens (id, revoked) VALUES ($1, $2)",
+        id,
+        now
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Counts a request made with the token `id` and returns `false` if the token is locked out,
+/// either already or because this request exceeded `token_requests_per_window`.
+pub async fn record_request(db: &SqlitePool, config: &Config, id: Uuid) -> Result<bool, AppError> {
+    if config.token_requests_per_window == 0 {
+        return Ok(true);
+    }
+
+    let now = unix_now();
+    let locked_until = sqlx::query_scalar!(
+        "SELECT locked_until FROM token_request_counts WHERE token_id = $1",
+        id
+    )
+    .fetch_optional(db)
+    .await?
+    .flatten();
+    if locked_until.is_some_and(|locked_until| locked_until > now) {
+        return Ok(false);
+    }
+
+    let window_start = now.saturating_sub(config.token_request_window_seconds);
+    let request_count = sqlx::query_scalar!(
+        r#"
+        INSERT INTO token_request_counts (token_id, window_start, request_count)
+        VALUES ($1, $2, 1)
+        ON CONFLICT (token_id) DO UPDATE SET
+            request_count = CASE
+                WHEN window_start > $3 THEN request_count + 1
+                ELSE 1
+            END,
+            window_start = CASE
+                WHEN window_start > $3 THEN window_start
+                ELSE $2
+            END,
+            locked_until = NULL
+        RETURNING request_count as "request_count!: i64"
+        "#,
+        id,
+        now,
+        window_start
+    )
+    .fetch_one(db)
+    .await?;
+    if request_count <= i64::from(config.token_requests_per_window) {
+        return Ok(true);
+    }
+
+    let locked_until = now.saturating_add(config.token_lockout_seconds);
+    sqlx::query!(
+        "UPDATE token_request_counts SET locked_until = $2, request_count = 0, window_start = 0 WHERE token_id = $1",
+        id,
+        locked_until
+    )
+    .execute(db)
+    .await?;
+    tracing::warn!(token = %id, "Signed link locked after too many requests");
+    Ok(false)
+}
+
+/// Removes request counters whose window and lockout have both passed.
+pub async fn cleanup_request_counts(
+    conn: &mut SqliteConnection,
+    config: &Config,
+) -> Result<u64, AppError> {
+    let now = unix_now();
+    let window_start = now.saturating_sub(config.token_request_window_seconds);
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM token_request_counts
+        WHERE window_start <= $1 AND (locked_until IS NULL OR locked_until <= $2)
+        "#,
+        window_start,
+        now
+    )
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,455 @@
+//! Handing an open execution over to another user or group, e.g. at a shift change. Every
+//! transfer needs a note for whoever takes over, mails the new assignee and stays on the
+//! execution, so the chain of responsibility can be traced afterwards.
+
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+};
+use axum_extra::extract::Form;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, Role, authorize},
+    executions, format_unix_timestamp,
+    mail::Mailer,
+    public_url, unix_now,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assignee {
+    User(Uuid),
+    Group(Uuid),
+}
+
+impl Assignee {
+    /// Form values look like `user:<id>` or `group:<id>`.
+    fn parse(value: &str) -> Option<Self> {
+        let (kind, id) = value.split_once(':')?;
+        let id = Uuid::parse_str(id).ok()?;
+        match kind {
+            "user" => Some(Assignee::User(id)),
+            "group" => Some(Assignee::Group(id)),
+            _ => None,
+        }
+    }
+
+    fn as_value(self) -> String {
+        match self {
+            Assignee::User(id) => format!("user:{}", id),
+            Assignee::Group(id) => format!("group:{}", id),
+        }
+    }
+}
+
+/// The handover part of the execution page.
+#[derive(Debug, Serialize)]
+pub struct HandoverSection {
+    assignee_name: String,
+    transfers: Vec<TransferListItem>,
+    users: Vec<AssigneeOption>,
+    groups: Vec<AssigneeOption>,
+}
+
+#[derive(Debug, Serialize)]
+struct TransferListItem {
+    transferred_display: String,
+    transferred_by_name: String,
+    from_name: String,
+    to_name: String,
+    note: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AssigneeOption {
+    value: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransferForm {
+    /// `user:<id>` or `group:<id>`.
+    assignee: String,
+    /// What the next person needs to know. Required.
+    note: String,
+}
+
+struct Assignment {
+    assignee: Option<Assignee>,
+    name: String,
+    is_finished: bool,
+}
+
+/// Current assignee, handovers so far and who the execution can be handed to.
+pub async fn fetch_section(
+    db: &SqlitePool,
+    execution_id: Uuid,
+) -> Result<HandoverSection, AppError> {
+    let mut conn = db.acquire().await?;
+    let assignment = fetch_assignment(&mut conn, execution_id).await?;
+
+    let transfers = sqlx::query!(
+        r#"
+        SELECT transferred_at, transferred_by_name, from_name, to_name, note
+        FROM execution_transfers
+        WHERE action_plan_execution = $1
+        ORDER BY transferred_at ASC, rowid ASC
+        "#,
+        execution_id
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| TransferListItem {
+        transferred_display: format_unix_timestamp(row.transferred_at),
+        transferred_by_name: row.transferred_by_name,
+        from_name: row.from_name,
+        to_name: row.to_name,
+        note: row.note,
+    })
+    .collect();
+
+    let current = assignment
+        .as_ref()
+        .and_then(|assignment| assignment.assignee);
+    let users = sqlx::query!(
+        r#"
+        SELECT id as "id: uuid::Uuid", name
+        FROM users
+        WHERE is_disabled = 0 AND role IN ('operator', 'admin')
+        ORDER BY name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .filter(|row| current != Some(Assignee::User(row.id)))
+    .map(|row| AssigneeOption {
+        value: Assignee::User(row.id).as_value(),
+        name: row.name,
+    })
+    .collect();
+    let groups = sqlx::query!(
+        r#"
+        SELECT id as "id: uuid::Uuid", name
+        FROM user_groups
+        ORDER BY name COLLATE NOCASE ASC
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .filter(|row| current != Some(Assignee::Group(row.id)))
+    .map(|row| AssigneeOption {
+        value: Assignee::Group(row.id).as_value(),
+        name: row.name,
+    })
+    .collect();
+
+    Ok(HandoverSection {
+        assignee_name: assignment
+            .map(|assignment| assignment.name)
+            .unwrap_or_default(),
+        transfers,
+        users,
+        groups,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/executions/{id}/transfer",
+    tag = "executions",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    request_body(content = TransferForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Hands the execution over, mails the new assignee when public_url is set and redirects to the execution"),
+        (status = 404, description = "Execution, user or group not found", content_type = "text/html"),
+        (status = 409, description = "No note, already assigned there, the user cannot run executions or the execution is completed", content_type = "text/html"),
+    )
+)]
+pub async fn transfer_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<TransferForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::RunExecutions, Resource::Everything)?;
+    let Some(target) = Assignee::parse(&form.assignee) else {
+        return Err(AppError::conflict(
+            "Choose a user or group to hand the execution over to.",
+        ));
+    };
+    let note = form.note.trim();
+    if note.is_empty() {
+        return Err(AppError::conflict(
+            "A handover note is required, so the next person knows where things stand.",
+        ));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let Some(assignment) = fetch_assignment(&mut tx, id).await? else {
+        return Err(execution_not_found(id));
+    };
+    if assignment.is_finished {
+        return Err(AppError::conflict(
+            "Completed executions cannot be handed over.",
+        ));
+    }
+    if assignment.assignee == Some(target) {
+        return Err(AppError::conflict(format!(
+            "This execution is already assigned to {}.",
+            assignment.name
+        )));
+    }
+
+    let (to_user, to_group, to_name) = match target {
+        Assignee::User(user_id) => {
+            let user = sqlx::query!(
+                "SELECT name, role, is_disabled FROM users WHERE id = $1",
+                user_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                AppError::not_found_for("User", format!("No user exists for id: {}", user_id))
+            })?;
+            if user.is_disabled != 0 || Role::from_db(&user.role) < Role::Operator {
+                return Err(AppError::conflict(format!(
+                    "{} cannot run executions, so the execution cannot be handed over to them.",
+                    user.name
+                )));
+            }
+            (Some(user_id), None, user.name)
+        }
+        Assignee::Group(group_id) => {
+            let name = sqlx::query_scalar!("SELECT name FROM user_groups WHERE id = $1", group_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| {
+                    AppError::not_found_for(
+                        "Group",
+                        format!("No group exists for id: {}", group_id),
+                    )
+                })?;
+            (None, Some(group_id), name)
+        }
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE action_plan_executions
+        SET assigned_user = $1, assigned_group = $2
+        WHERE id = $3
+        "#,
+        to_user,
+        to_group,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let transfer_id = Uuid::new_v4();
+    let now = unix_now();
+    sqlx::query!(
+        r#"
+        INSERT INTO execution_transfers (
+            id, action_plan_execution, transferred_at, transferred_by, transferred_by_name,
+            from_name, to_user, to_group, to_name, note
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+        transfer_id,
+        id,
+        now,
+        current_user.id,
+        current_user.name,
+        assignment.name,
+        to_user,
+        to_group,
+        to_name,
+        note
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let description = executions::execution_description(&mut tx, id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::Execution,
+            id,
+            AuditAction::Transfer,
+            format!("Handed over {}: {}", description, note),
+        )
+        .change(Some(assignment.name.clone()), Some(to_name.clone())),
+    )
+    .await?;
+    tx.commit().await?;
+
+    // The handover stands either way; only the mail needs a link to point to.
+    let Some(url) = public_url(&state.config, &format!("/executions/{}", id)) else {
+        tracing::warn!("public_url is not set, so no handover mail was sent");
+        return Ok(Redirect::to(&format!("/executions/{}", id)));
+    };
+    let recipients = fetch_recipients(&state.db, target, current_user.id).await?;
+    tokio::spawn(notify(
+        state.mailer.clone(),
+        recipients,
+        HandoverMail {
+            description,
+            from_name: current_user.name,
+            to_name,
+            is_group: to_group.is_some(),
+            note: note.to_string(),
+            url,
+        },
+    ));
+
+    Ok(Redirect::to(&format!("/executions/{}", id)))
+}
+
+/// The execution's assignee. Without a transfer that is whoever started it.
+async fn fetch_assignment(
+    conn: &mut SqliteConnection,
+    execution_id: Uuid,
+) -> Result<Option<Assignment>, AppError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            action_plan_executions.finished,
+            action_plan_executions.started_by as "started_by: uuid::Uuid",
+            action_plan_executions.assigned_user as "assigned_user: uuid::Uuid",
+            action_plan_executions.assigned_group as "assigned_group: uuid::Uuid",
+            starters.name as "starter_name?",
+            assigned_users.name as "assigned_user_name?",
+            user_groups.name as "assigned_group_name?"
+        FROM action_plan_executions
+        LEFT JOIN users AS starters ON starters.id = action_plan_executions.started_by
+        LEFT JOIN users AS assigned_users
+            ON assigned_users.id = action_plan_executions.assigned_user
+        LEFT JOIN user_groups ON user_groups.id = action_plan_executions.assigned_group
+        WHERE action_plan_executions.id = $1
+        "#,
+        execution_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(row.map(|row| {
+        let (assignee, name) = if let Some(group_id) = row.assigned_group {
+            (Some(Assignee::Group(group_id)), row.assigned_group_name)
+        } else if let Some(user_id) = row.assigned_user {
+            (Some(Assignee::User(user_id)), row.assigned_user_name)
+        } else {
+            (row.started_by.map(Assignee::User), row.starter_name)
+        };
+        let name = match (assignee, name) {
+            (_, Some(name)) => name,
+            (Some(Assignee::Group(_)), None) => "a deleted group".to_string(),
+            (Some(Assignee::User(_)), None) => "a deleted user".to_string(),
+            (None, None) => "nobody".to_string(),
+        };
+        Assignment {
+            assignee,
+            name,
+            is_finished: row.finished.is_some_and(|finished| finished > 0),
+        }
+    }))
+}
+
+/// Verified addresses of the new assignee, or of the group's active members. Whoever hands the
+/// execution over already knows.
+async fn fetch_recipients(
+    db: &SqlitePool,
+    target: Assignee,
+    sender_id: Uuid,
+) -> Result<Vec<Recipient>, AppError> {
+    let recipients = match target {
+        Assignee::User(user_id) => {
+            sqlx::query_as!(
+                Recipient,
+                r#"
+            SELECT name, email as "email!"
+            FROM users
+            WHERE id = $1
+                AND id != $2
+                AND email IS NOT NULL
+                AND email_verified_at IS NOT NULL
+            "#,
+                user_id,
+                sender_id
+            )
+            .fetch_all(db)
+            .await?
+        }
+        Assignee::Group(group_id) => {
+            sqlx::query_as!(
+                Recipient,
+                r#"
+            SELECT users.name, users.email as "email!"
+            FROM user_group_members
+            INNER JOIN users ON users.id = user_group_members.user_id
+            WHERE user_group_members.user_group = $1
+                AND users.id != $2
+                AND users.is_disabled = 0
+                AND users.email IS NOT NULL
+                AND users.email_verified_at IS NOT NULL
+            "#,
+                group_id,
+                sender_id
+            )
+            .fetch_all(db)
+            .await?
+        }
+    };
+    Ok(recipients)
+}
+
+struct Recipient {
+    name: String,
+    email: String,
+}
+
+struct HandoverMail {
+    description: String,
+    from_name: String,
+    to_name: String,
+    is_group: bool,
+    note: String,
+    url: String,
+}
+
+async fn notify(mailer: Mailer, recipients: Vec<Recipient>, mail: HandoverMail) {
+    let handed_to = if mail.is_group {
+        format!("your group {}", mail.to_name)
+    } else {
+        "you".to_string()
+    };
+    for recipient in recipients {
+        let body = format!(
+            "Hello {},\n\n{} handed {} over to {} with this note:\n\n{}\n\nOpen the execution:\n{}\n",
+            recipient.name, mail.from_name, mail.description, handed_to, mail.note, mail.url
+        );
+        if let Err(err) = mailer
+            .send(
+                &recipient.email,
+                &format!("Execution handed over: {}", mail.description),
+                body,
+            )
+            .await
+        {
+            tracing::error!("Sending the handover mail failed: {}", err);
+        }
+    }
+}
+
+fn execution_not_found(id: Uuid) -> AppError {
+    AppError::not_found_for("Execution", format!("No execution exists for id: {}", id))
+}
@@ -0,0 +1,709 @@
+//! Optional TOTP second factor (RFC 6238 with the usual authenticator app settings: SHA-1,
+//! 30 second steps, 6 digits). Secrets are stored encrypted with the encryption key, which can
+//! be rotated by keeping the old one in `previous_encryption_keys` until the next start has
+//! re-encrypted them. Losing every key that can read a secret means an admin has to reset
+//! two-factor authentication for that user.
+//!
+//! Enabling it also hands out single-use recovery codes, accepted wherever a code is asked
+//! for, so a lost phone does not lock anyone out. They are stored as SHA-256 hashes; being
+//! long random strings, they need no slow password hash.
+
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::Form;
+use chacha20poly1305::{
+    ChaCha20Poly1305,
+    aead::{Aead, KeyInit, OsRng, Payload, rand_core::RngCore},
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, authorize},
+    encryption::SecretCipher,
+    format_unix_timestamp, scan, templates,
+    tokens::TokenSigner,
+    unix_now, users,
+};
+
+const STEP_SECONDS: i64 = 30;
+const CODE_DIGITS: u32 = 6;
+/// Codes from one step before or after the current one are accepted, for clock drift.
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+const SECRET_BYTES: usize = 20;
+const NONCE_BYTES: usize = 12;
+const LEGACY_KEY_PURPOSE: &str = "two-factor-secret";
+const ISSUER: &str = "Maintenance Planner";
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Base32 characters per recovery code, 50 random bits.
+const RECOVERY_CODE_LENGTH: usize = 10;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+#[derive(Debug, Serialize)]
+struct TwoFactorView {
+    enabled_display: Option<String>,
+    /// Shown while enrolling, until a code confirms the secret.
+    enrollment: Option<Enrollment>,
+    /// Newly generated recovery codes, shown only on the response that created them.
+    recovery_codes: Vec<String>,
+    recovery_codes_left: i64,
+    error_message: Option<String>,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Enrollment {
+    /// Inline SVG, generated here and safe to embed unescaped.
+    qr_svg: String,
+    /// Base32 secret for apps that cannot scan the code.
+    secret: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CodeForm {
+    /// The current 6 digit code from the authenticator app.
+    code: String,
+}
+
+struct SecretsRow {
+    name: String,
+    totp_secret: Option<Vec<u8>>,
+    totp_pending_secret: Option<Vec<u8>>,
+    totp_enabled_at: Option<i64>,
+    totp_last_step: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/me/2fa",
+    tag = "users",
+    responses(
+        (status = 200, description = "Two-factor status, or a QR code to enroll an authenticator app", content_type = "text/html"),
+    )
+)]
+pub async fn setup_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    render_setup(&state, &current_user, Vec::new(), None).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/me/2fa",
+    tag = "users",
+    request_body(content = CodeForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Turns on two-factor authentication and shows the recovery codes once, or shows the enrollment page again when the code did not match", content_type = "text/html"),
+        (status = 303, description = "Nothing to confirm; redirects to the two-factor page"),
+    )
+)]
+pub async fn setup_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Form(form): Form<CodeForm>,
+) -> Result<Response, AppError> {
+    let row = fetch_secrets(&state.db, current_user.id).await?;
+    let pending = match (row.totp_enabled_at, row.totp_pending_secret) {
+        (None, Some(pending)) => decrypt(&state, current_user.id, &pending)?,
+        _ => return Ok(Redirect::to("/users/me/2fa").into_response()),
+    };
+    let Some(step) = matching_step(&pending, &form.code, None) else {
+        return render_setup(
+            &state,
+            &current_user,
+            Vec::new(),
+            Some("The code did not match. Check the time on your phone and try again."),
+        )
+        .await
+        .map(IntoResponse::into_response);
+    };
+
+    let now = unix_now();
+    let enabled = sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_secret = totp_pending_secret,
+            totp_pending_secret = NULL,
+            totp_enabled_at = $1,
+            totp_last_step = $2
+        WHERE id = $3 AND totp_enabled_at IS NULL
+        "#,
+        now,
+        step,
+        current_user.id
+    )
+    .execute(&state.db)
+    .await?
+    .rows_affected();
+    if enabled == 0 {
+        return Ok(Redirect::to("/users/me/2fa").into_response());
+    }
+    tracing::info!(
+        user = current_user.name,
+        "Two-factor authentication enabled"
+    );
+
+    let recovery_codes = replace_recovery_codes(&state.db, current_user.id).await?;
+    render_setup(&state, &current_user, recovery_codes, None)
+        .await
+        .map(IntoResponse::into_response)
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/me/2fa/recovery-codes",
+    tag = "users",
+    request_body(content = CodeForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Replaces all recovery codes and shows the new ones once", content_type = "text/html"),
+        (status = 409, description = "The code did not match, or the account is locked after too many wrong codes", content_type = "text/html"),
+    )
+)]
+pub async fn recovery_codes_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Form(form): Form<CodeForm>,
+) -> Result<Html<String>, AppError> {
+    verify_code_limited(
+        &state,
+        current_user.id,
+        &form.code,
+        "The code did not match. The recovery codes were not changed.",
+    )
+    .await?;
+    let recovery_codes = replace_recovery_codes(&state.db, current_user.id).await?;
+    tracing::info!(user = current_user.name, "Recovery codes regenerated");
+
+    render_setup(&state, &current_user, recovery_codes, None).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/me/2fa/disable",
+    tag = "users",
+    request_body(content = CodeForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Turns off two-factor authentication and redirects to the account page"),
+        (status = 409, description = "The code did not match, or the account is locked after too many wrong codes", content_type = "text/html"),
+    )
+)]
+pub async fn disable_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Form(form): Form<CodeForm>,
+) -> Result<Redirect, AppError> {
+    verify_code_limited(
+        &state,
+        current_user.id,
+        &form.code,
+        "The code did not match. Two-factor authentication stays on.",
+    )
+    .await?;
+    let mut tx = state.db.begin().await?;
+    clear(&mut tx, current_user.id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::User,
+            current_user.id,
+            AuditAction::Update,
+            format!(
+                "Turned off two-factor authentication for \"{}\"",
+                current_user.name
+            ),
+        ),
+    )
+    .await?;
+    tx.commit().await?;
+    tracing::info!(
+        user = current_user.name,
+        "Two-factor authentication disabled"
+    );
+
+    Ok(Redirect::to("/users/me"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/2fa/reset",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 303, description = "Turns off two-factor authentication for the user, e.g. after a lost phone, and redirects to the user list"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "User not found", content_type = "text/html"),
+    )
+)]
+pub async fn reset_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let row = fetch_secrets(&state.db, id).await?;
+    let mut tx = state.db.begin().await?;
+    clear(&mut tx, id).await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::User,
+            id,
+            AuditAction::Update,
+            format!("Reset two-factor authentication for \"{}\"", row.name),
+        ),
+    )
+    .await?;
+    tx.commit().await?;
+    tracing::info!(
+        user = row.name,
+        admin = current_user.name,
+        "Two-factor authentication reset"
+    );
+
+    Ok(Redirect::to("/users"))
+}
+
+/// Checks `code` against the user's enrolled secret, or uses it up as a recovery code. Either
+/// kind is accepted only once, so a code seen over someone's shoulder cannot be used again.
+pub async fn verify_code(state: &AppState, user_id: Uuid, code: &str) -> Result<bool, AppError> {
+    let row = fetch_secrets(&state.db, user_id).await?;
+    let Some(secret) = row.totp_secret.filter(|_| row.totp_enabled_at.is_some()) else {
+        return Ok(false);
+    };
+    if let Some(recovery_code) = normalize_recovery_code(code) {
+        return use_recovery_code(&state.db, user_id, &row.name, &recovery_code).await;
+    }
+    let secret = decrypt(state, user_id, &secret)?;
+    let Some(step) = matching_step(&secret, code, row.totp_last_step) else {
+        return Ok(false);
+    };
+
+    // Guarded again here so two requests with the same code cannot both succeed.
+    let accepted = sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_last_step = $1
+        WHERE id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)
+        "#,
+        step,
+        user_id
+    )
+    .execute(&state.db)
+    .await?
+    .rows_affected();
+    Ok(accepted > 0)
+}
+
+/// [`verify_code`] for a signed in user. Wrong codes count like failed logins and run into the
+/// same lockout, so a left open session cannot be used to guess codes; `mismatch` is the
+/// error for a wrong code that did not lock the account.
+async fn verify_code_limited(
+    state: &AppState,
+    user_id: Uuid,
+    code: &str,
+    mismatch: &str,
+) -> Result<(), AppError> {
+    if users::is_locked(&state.db, user_id).await? {
+        return Err(AppError::conflict(users::ACCOUNT_LOCKED_MESSAGE));
+    }
+    if verify_code(state, user_id, code).await? {
+        return users::reset_failed_logins(&state.db, user_id).await;
+    }
+    if users::record_failed_login(&state.db, &state.config, user_id).await? {
+        tracing::warn!(%user_id, "Account locked after repeated wrong two-factor codes");
+        return Err(AppError::conflict(users::ACCOUNT_LOCKED_MESSAGE));
+    }
+    Err(AppError::conflict(mismatch))
+}
+
+async fn render_setup(
+    state: &AppState,
+    current_user: &CurrentUser,
+    recovery_codes: Vec<String>,
+    error_message: Option<&str>,
+) -> Result<Html<String>, AppError> {
+    let row = fetch_secrets(&state.db, current_user.id).await?;
+    let enrollment = match row.totp_enabled_at {
+        Some(_) => None,
+        None => {
+            let secret = match row.totp_pending_secret {
+                Some(pending) => decrypt(state, current_user.id, &pending)?,
+                None => start_enrollment(state, current_user.id).await?,
+            };
+            let encoded = base32(&secret);
+            let uri = format!(
+                "otpauth://totp/{issuer}:{name}?secret={secret}&issuer={issuer}&digits={digits}&period={period}",
+                issuer = percent_encode(ISSUER),
+                name = percent_encode(&row.name),
+                secret = encoded,
+                digits = CODE_DIGITS,
+                period = STEP_SECONDS,
+            );
+            Some(Enrollment {
+                qr_svg: scan::qr_code_svg(&uri)?,
+                secret: encoded,
+            })
+        }
+    };
+
+    let rendered = templates::render(
+        &state.jinja,
+        "two_factor.html",
+        TwoFactorView {
+            enabled_display: row.totp_enabled_at.map(format_unix_timestamp),
+            enrollment,
+            recovery_codes,
+            recovery_codes_left: count_recovery_codes_left(&state.db, current_user.id).await?,
+            error_message: error_message.map(str::to_string),
+            is_admin: current_user.is_admin,
+        },
+    )?;
+    Ok(Html(rendered))
+}
+
+/// Stores a new random secret as pending and returns it. It becomes active once a code made
+/// from it is entered, so a half finished enrollment never locks anyone out.
+async fn start_enrollment(state: &AppState, user_id: Uuid) -> Result<Vec<u8>, AppError> {
+    let mut secret = vec![0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut secret);
+    let encrypted = encrypt(state, user_id, &secret)?;
+    sqlx::query!(
+        "UPDATE users SET totp_pending_secret = $1 WHERE id = $2",
+        encrypted,
+        user_id
+    )
+    .execute(&state.db)
+    .await?;
+    Ok(secret)
+}
+
+/// Deletes the user's recovery codes and stores a new set, returned in readable form.
+async fn replace_recovery_codes(db: &SqlitePool, user_id: Uuid) -> Result<Vec<String>, AppError> {
+    let codes = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut code = String::with_capacity(RECOVERY_CODE_LENGTH);
+            for _ in 0..RECOVERY_CODE_LENGTH {
+                let index = OsRng.next_u32() as usize % BASE32_ALPHABET.len();
+                code.push(BASE32_ALPHABET[index] as char);
+            }
+            code
+        })
+        .collect::<Vec<_>>();
+
+    let mut tx = db.begin().await?;
+    delete_recovery_codes(&mut tx, user_id).await?;
+    for code in &codes {
+        let id = Uuid::new_v4();
+        let code_hash = hash_recovery_code(code);
+        sqlx::query!(
+            "INSERT INTO user_recovery_codes (id, user_id, code_hash) VALUES ($1, $2, $3)",
+            id,
+            user_id,
+            code_hash
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    // Split in two halves, which is easier to read off a printout. The dash is optional when
+    // entering a code.
+    Ok(codes
+        .into_iter()
+        .map(|code| {
+            let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+            format!("{}-{}", first, second)
+        })
+        .collect())
+}
+
+async fn use_recovery_code(
+    db: &SqlitePool,
+    user_id: Uuid,
+    user_name: &str,
+    code: &str,
+) -> Result<bool, AppError> {
+    let now = unix_now();
+    let code_hash = hash_recovery_code(code);
+    let used = sqlx::query!(
+        r#"
+        UPDATE user_recovery_codes
+        SET used_at = $1
+        WHERE user_id = $2 AND code_hash = $3 AND used_at IS NULL
+        "#,
+        now,
+        user_id,
+        code_hash
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+    if used > 0 {
+        tracing::info!(user = user_name, "Recovery code used");
+    }
+    Ok(used > 0)
+}
+
+async fn count_recovery_codes_left(db: &SqlitePool, user_id: Uuid) -> Result<i64, AppError> {
+    Ok(sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!: i64"
+        FROM user_recovery_codes
+        WHERE user_id = $1 AND used_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+/// Deletes the recovery codes of a user, e.g. when the user is deleted.
+pub async fn delete_recovery_codes(
+    conn: &mut SqliteConnection,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "DELETE FROM user_recovery_codes WHERE user_id = $1",
+        user_id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// The recovery code in `value` without separators, `None` when it cannot be one, e.g. a
+/// 6 digit code from the app.
+fn normalize_recovery_code(value: &str) -> Option<String> {
+    let code = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect::<String>();
+    (code.len() == RECOVERY_CODE_LENGTH && code.bytes().all(|byte| BASE32_ALPHABET.contains(&byte)))
+        .then_some(code)
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    Sha256::digest(code.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+async fn clear(conn: &mut SqliteConnection, user_id: Uuid) -> Result<(), AppError> {
+    delete_recovery_codes(&mut *conn, user_id).await?;
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_secret = NULL,
+            totp_pending_secret = NULL,
+            totp_enabled_at = NULL,
+            totp_last_step = NULL
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+async fn fetch_secrets(db: &SqlitePool, user_id: Uuid) -> Result<SecretsRow, AppError> {
+    sqlx::query_as!(
+        SecretsRow,
+        r#"
+        SELECT
+            name,
+            totp_secret,
+            totp_pending_secret,
+            totp_enabled_at,
+            totp_last_step
+        FROM users
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AppError::not_found_for("User", format!("No user exists for id: {}", user_id)))
+}
+
+/// The time step `code` was generated for, if it is valid now and newer than `last_step`.
+fn matching_step(secret: &[u8], code: &str, last_step: Option<i64>) -> Option<i64> {
+    let code = code
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+    if code.len() != CODE_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let code = code.parse::<u32>().ok()?;
+    let current = unix_now() / STEP_SECONDS;
+    (current - ALLOWED_DRIFT_STEPS..=current + ALLOWED_DRIFT_STEPS)
+        .filter(|step| last_step.is_none_or(|last_step| *step > last_step))
+        .find(|step| hotp(secret, *step as u64) == code)
+}
+
+/// RFC 4226 HOTP value for `counter`.
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac =
+        <Hmac<Sha1> as Mac>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    value % 10u32.pow(CODE_DIGITS)
+}
+
+/// Encrypts `secret` for `user_id`. The user id is bound as associated data, so a secret
+/// copied to another user's row does not decrypt.
+fn encrypt(state: &AppState, user_id: Uuid, secret: &[u8]) -> Result<Vec<u8>, AppError> {
+    state
+        .secret_cipher
+        .encrypt(user_id.as_bytes(), secret)
+        .map_err(AppError::internal)
+}
+
+fn decrypt(state: &AppState, user_id: Uuid, stored: &[u8]) -> Result<Vec<u8>, AppError> {
+    read_secret(&state.secret_cipher, &state.token_signer, user_id, stored).ok_or_else(|| {
+        AppError::conflict(
+            "The two-factor secret can no longer be read, probably because the encryption key \
+            changed. Ask an admin to reset two-factor authentication.",
+        )
+    })
+}
+
+fn read_secret(
+    cipher: &SecretCipher,
+    signer: &TokenSigner,
+    user_id: Uuid,
+    stored: &[u8],
+) -> Option<Vec<u8>> {
+    cipher
+        .decrypt(user_id.as_bytes(), stored)
+        .or_else(|| decrypt_legacy(signer, user_id, stored))
+}
+
+/// Secrets stored before they got their own key are a nonce and ciphertext, encrypted with a
+/// key derived from the token signing key.
+fn decrypt_legacy(signer: &TokenSigner, user_id: Uuid, stored: &[u8]) -> Option<Vec<u8>> {
+    if stored.len() <= NONCE_BYTES {
+        return None;
+    }
+    let (nonce, ciphertext) = stored.split_at(NONCE_BYTES);
+    let cipher = ChaCha20Poly1305::new(&signer.derive_key(LEGACY_KEY_PURPOSE).into());
+    cipher
+        .decrypt(
+            nonce.into(),
+            Payload {
+                msg: ciphertext,
+                aad: user_id.as_bytes(),
+            },
+        )
+        .ok()
+}
+
+/// Re-encrypts every stored secret not encrypted with the current key, so keys listed in
+/// `previous_encryption_keys` can be dropped after one start. Returns the number of secrets
+/// re-encrypted and the number no configured key could read.
+pub async fn reencrypt_secrets(
+    db: &SqlitePool,
+    cipher: &SecretCipher,
+    signer: &TokenSigner,
+) -> anyhow::Result<(usize, usize)> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            totp_secret,
+            totp_pending_secret
+        FROM users
+        WHERE totp_secret IS NOT NULL OR totp_pending_secret IS NOT NULL
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut reencrypted = 0;
+    let mut unreadable = 0;
+    for row in rows {
+        let mut reencrypt = |stored: Option<Vec<u8>>| -> anyhow::Result<Option<Vec<u8>>> {
+            let Some(stored) = stored else {
+                return Ok(None);
+            };
+            if cipher.is_current(&stored) {
+                return Ok(Some(stored));
+            }
+            match read_secret(cipher, signer, row.id, &stored) {
+                Some(secret) => {
+                    reencrypted += 1;
+                    Ok(Some(cipher.encrypt(row.id.as_bytes(), &secret)?))
+                }
+                None => {
+                    unreadable += 1;
+                    Ok(Some(stored))
+                }
+            }
+        };
+        let totp_secret = reencrypt(row.totp_secret)?;
+        let totp_pending_secret = reencrypt(row.totp_pending_secret)?;
+        sqlx::query!(
+            "UPDATE users SET totp_secret = $1, totp_pending_secret = $2 WHERE id = $3",
+            totp_secret,
+            totp_pending_secret,
+            row.id
+        )
+        .execute(db)
+        .await?;
+    }
+    Ok((reencrypted, unreadable))
+}
+
+/// RFC 4648 base32 without padding, as authenticator apps expect it.
+fn base32(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
@@ -0,0 +1,258 @@
+//! How much this instance is used: accounts, plans, executions per month and disk space. Meant
+//! for splitting the cost of a shared instance between departments, so it counts everything,
+//! archived executions included.
+
+use std::collections::BTreeMap;
+
+use axum::{Json, extract::State, response::Html};
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    AppError, AppState, CurrentUser, attachments,
+    authz::{Action, Resource, authorize},
+    format_unix_timestamp, templates, unix_now,
+};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UsageReport {
+    generated_at_unix: i64,
+    users: UserCounts,
+    group_count: i64,
+    plans: PlanCounts,
+    action_count: i64,
+    /// Executions per calendar month in server time, newest first.
+    months: Vec<MonthUsage>,
+    storage: StorageUsage,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserCounts {
+    total: i64,
+    disabled: i64,
+    admins: i64,
+    operators: i64,
+    viewers: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlanCounts {
+    active: i64,
+    /// Deleted plans that can still be restored.
+    deleted: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MonthUsage {
+    /// `YYYY-MM`.
+    month: String,
+    started_count: i64,
+    finished_count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StorageUsage {
+    /// The main database including its write-ahead log.
+    database_bytes: u64,
+    /// `None` when no archive database exists.
+    archive_bytes: Option<u64>,
+    /// Files attached to execution items, without their thumbnails.
+    attachment_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct UsagePageView {
+    report: UsageReport,
+    generated_display: String,
+    database_display: String,
+    archive_display: Option<String>,
+    attachments_display: String,
+    is_admin: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/usage",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Usage of this instance: users, plans, executions per month and storage", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
+pub async fn index(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+
+    let report = build_report(&state).await?;
+    let rendered = templates::render(
+        &state.jinja,
+        "usage.html",
+        UsagePageView {
+            generated_display: format_unix_timestamp(report.generated_at_unix),
+            database_display: format_bytes(report.storage.database_bytes),
+            archive_display: report.storage.archive_bytes.map(format_bytes),
+            attachments_display: format_bytes(report.storage.attachment_bytes),
+            report,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/usage.json",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Usage of this instance: users, plans, executions per month and storage", body = UsageReport),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
+pub async fn index_json(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Json<UsageReport>, AppError> {
+    authorize(
+        &current_user,
+        Action::AccessAdminPages,
+        Resource::Everything,
+    )?;
+
+    Ok(Json(build_report(&state).await?))
+}
+
+async fn build_report(state: &AppState) -> Result<UsageReport, AppError> {
+    let users = sqlx::query_as!(
+        UserCounts,
+        r#"
+        SELECT
+            COUNT(*) as "total!: i64",
+            COALESCE(SUM(is_disabled != 0), 0) as "disabled!: i64",
+            COALESCE(SUM(role = 'admin'), 0) as "admins!: i64",
+            COALESCE(SUM(role = 'operator'), 0) as "operators!: i64",
+            COALESCE(SUM(role = 'viewer'), 0) as "viewers!: i64"
+        FROM users
+        "#
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    let group_count = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count!: i64" FROM user_groups"#)
+        .fetch_one(&state.db)
+        .await?;
+
+    let plans = sqlx::query_as!(
+        PlanCounts,
+        r#"
+        SELECT
+            COALESCE(SUM(deleted_at IS NULL OR deleted_at <= 0), 0) as "active!: i64",
+            COALESCE(SUM(deleted_at > 0), 0) as "deleted!: i64"
+        FROM action_plans
+        "#
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    let action_count = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count!: i64" FROM actions"#)
+        .fetch_one(&state.db)
+        .await?;
+
+    let mut timestamps: Vec<(i64, Option<i64>)> = sqlx::query!(
+        r#"SELECT started as "started!", finished as "finished?" FROM action_plan_executions"#
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|row| (row.started, row.finished))
+    .collect();
+    if state.archive_enabled {
+        let archived: Vec<(i64, i64)> =
+            sqlx::query_as("SELECT started, finished FROM archive.action_plan_executions")
+                .fetch_all(&state.db)
+                .await?;
+        timestamps.extend(
+            archived
+                .into_iter()
+                .map(|(started, finished)| (started, Some(finished))),
+        );
+    }
+
+    let mut months: BTreeMap<String, MonthUsage> = BTreeMap::new();
+    let mut count = |timestamp: i64, finished: bool| {
+        let Some(datetime) = Local.timestamp_opt(timestamp, 0).single() else {
+            return;
+        };
+        let month = datetime.format("%Y-%m").to_string();
+        let usage = months.entry(month.clone()).or_insert(MonthUsage {
+            month,
+            started_count: 0,
+            finished_count: 0,
+        });
+        if finished {
+            usage.finished_count += 1;
+        } else {
+            usage.started_count += 1;
+        }
+    };
+    for (started, finished) in timestamps {
+        count(started, false);
+        if let Some(finished) = finished.filter(|value| *value > 0) {
+            count(finished, true);
+        }
+    }
+
+    let database_bytes = file_size(&state.config.db_path).await
+        + file_size(&format!("{}-wal", state.config.db_path)).await;
+    let archive_bytes = if state.archive_enabled {
+        Some(
+            file_size(&state.config.archive_db_path).await
+                + file_size(&format!("{}-wal", state.config.archive_db_path)).await,
+        )
+    } else {
+        None
+    };
+
+    Ok(UsageReport {
+        generated_at_unix: unix_now(),
+        users,
+        group_count,
+        plans,
+        action_count,
+        months: months.into_values().rev().collect(),
+        storage: StorageUsage {
+            database_bytes,
+            archive_bytes,
+            attachment_bytes: attachments::total_bytes(&state.db).await?,
+        },
+    })
+}
+
+/// Size of a file, 0 if it does not exist.
+async fn file_size(path: &str) -> u64 {
+    tokio::fs::metadata(path)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
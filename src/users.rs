@@ -2,8 +2,11 @@ use argon2::{
     Argon2,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
+use std::{net::IpAddr, sync::LazyLock};
+
 use axum::{
     extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
     response::{Html, IntoResponse, Redirect, Response},
 };
 use axum_extra::extract::{
@@ -11,35 +14,174 @@ use axum_extra::extract::{
     cookie::{Cookie, CookieJar, SameSite},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::{AppError, AppState, CurrentUser};
+use crate::{
+    AppError, AppState, CurrentUser,
+    audit::{self, AuditAction, AuditEntity, AuditEntry},
+    authz::{Action, Resource, Role, authorize},
+    auto_logout,
+    client_ip::ClientIp,
+    config::{Config, CookieSameSite},
+    emails, format_unix_timestamp, groups, invites, read_only, templates,
+    tokens::{self, TokenScope},
+    two_factor, unix_now,
+};
 
-pub const SESSION_COOKIE_NAME: &str = "maintenance_planner_session_id";
-const SESSION_DURATION_SECONDS: i64 = 60 * 60 * 24 * 30;
+const INVALID_LOGIN_MESSAGE: &str = "Invalid username or password.";
+const TOO_MANY_ATTEMPTS_MESSAGE: &str =
+    "Too many login attempts. Please wait a few minutes and try again.";
+const ACCOUNT_DISABLED_MESSAGE: &str = "This account is disabled. Ask an admin to enable it.";
+pub(crate) const ACCOUNT_LOCKED_MESSAGE: &str = "This account is locked after too many failed logins. Try again later or ask an admin to unlock it.";
+const LOGIN_EXPIRED_MESSAGE: &str = "The login took too long. Please sign in again.";
+const INVALID_CODE_MESSAGE: &str =
+    "The code did not match. Check the time on your phone and try again.";
+/// Time between the password and the code of a two-factor login.
+const LOGIN_CHALLENGE_VALIDITY_SECONDS: i64 = 5 * 60;
+/// Checked instead of a real hash for unknown names and accounts without a password, so the
+/// login takes as long as for an existing account.
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("not a real password").expect("hashing a fixed password"));
 
 #[derive(Debug, Clone)]
 pub struct User {
     pub id: Uuid,
     pub name: String,
-    pub is_admin: i64,
+    pub role: String,
     pub password_hash: String,
+    pub email: Option<String>,
+    pub email_verified_at: Option<i64>,
+    pub totp_enabled_at: Option<i64>,
 }
 
 impl User {
-    fn as_current_user(&self) -> CurrentUser {
-        CurrentUser {
-            id: self.id,
-            name: self.name.clone(),
-            is_admin: self.is_admin != 0,
+    fn role(&self) -> Role {
+        Role::from_db(&self.role)
+    }
+}
+
+/// The page `/` opens for a user, chosen on the account page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HomePage {
+    Plans,
+    Executions,
+    Calendar,
+    Reports,
+}
+
+impl HomePage {
+    pub const ALL: [HomePage; 4] = [
+        HomePage::Plans,
+        HomePage::Executions,
+        HomePage::Calendar,
+        HomePage::Reports,
+    ];
+
+    /// The value stored in `users.home_page`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HomePage::Plans => "plans",
+            HomePage::Executions => "executions",
+            HomePage::Calendar => "calendar",
+            HomePage::Reports => "reports",
+        }
+    }
+
+    /// Unknown values fall back to the plan list.
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "executions" => HomePage::Executions,
+            "calendar" => HomePage::Calendar,
+            "reports" => HomePage::Reports,
+            _ => HomePage::Plans,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HomePage::Plans => "Action Plans",
+            HomePage::Executions => "Executions",
+            HomePage::Calendar => "Calendar",
+            HomePage::Reports => "Reports",
+        }
+    }
+
+    pub fn path(self) -> &'static str {
+        match self {
+            HomePage::Plans => "/plans",
+            HomePage::Executions => "/executions",
+            HomePage::Calendar => "/calendar",
+            HomePage::Reports => "/reports",
+        }
+    }
+}
+
+/// The client a request comes from, compared against the one that created its session.
+pub struct SessionClient {
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl SessionClient {
+    pub fn new(ip_address: Option<IpAddr>, headers: &HeaderMap) -> Self {
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .chars()
+                    .take(MAX_USER_AGENT_LENGTH)
+                    .collect::<String>()
+            });
+        Self {
+            ip_address,
+            user_agent,
+        }
+    }
+
+    /// Whether this client may use a session created by the given address and user agent.
+    /// Sessions from before these were recorded are accepted.
+    fn matches(&self, config: &Config, ip_address: Option<&str>, user_agent: Option<&str>) -> bool {
+        if config.session_bind_user_agent
+            && let Some(user_agent) = user_agent
+            && self.user_agent.as_deref() != Some(user_agent)
+        {
+            return false;
+        }
+        if config.session_bind_ip
+            && let Some(created_from) = ip_address.and_then(|value| value.parse().ok())
+            && let Some(current) = self.ip_address
+            && !same_network(created_from, current)
+        {
+            return false;
         }
+        true
     }
 }
 
+/// Compares addresses by their /24 (IPv4) or /64 (IPv6) network, so clients that rotate
+/// through addresses of one provider or privacy extensions stay logged in.
+fn same_network(a: IpAddr, b: IpAddr) -> bool {
+    match (a.to_canonical(), b.to_canonical()) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => a.octets()[..3] == b.octets()[..3],
+        (IpAddr::V6(a), IpAddr::V6(b)) => a.segments()[..4] == b.segments()[..4],
+        _ => false,
+    }
+}
+
+/// `last_seen` is only written when it is older than this, so page loads do not each cost a write.
+const LAST_SEEN_RESOLUTION_SECONDS: i64 = 60;
+/// User agents are stored for display only; cap them so a client cannot bloat the table.
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
 #[derive(Debug, Serialize)]
 struct UserListView {
     users: Vec<UserListItem>,
+    invites: Vec<invites::PendingInvite>,
+    roles: Vec<RoleOption>,
     current_user_id: Uuid,
     is_admin: bool,
 }
@@ -48,9 +190,52 @@ struct UserListView {
 struct UserListItem {
     id: Uuid,
     name: String,
+    role_label: &'static str,
+    group_names: Vec<String>,
+    email: Option<String>,
+    email_verified: bool,
+    is_disabled: bool,
+    two_factor_enabled: bool,
+    /// Set while the account is locked after failed logins.
+    locked_until_display: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountView {
+    email: emails::EmailStatus,
+    two_factor_enabled: bool,
+    home_page: HomePage,
+    home_pages: Vec<HomePageOption>,
     is_admin: bool,
 }
 
+#[derive(Debug, Serialize)]
+struct HomePageOption {
+    value: &'static str,
+    label: &'static str,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct HomePageForm {
+    home_page: HomePage,
+}
+
+#[derive(Debug, Serialize)]
+struct UserSessionsView {
+    sessions: Vec<UserSessionItem>,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct UserSessionItem {
+    id: Uuid,
+    created_display: String,
+    last_seen_display: String,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    is_current: bool,
+}
+
 #[derive(Debug, Serialize)]
 struct DeleteUserConfirmView {
     id: Uuid,
@@ -65,30 +250,83 @@ struct LoginView {
     error_message: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+struct LoginTwoFactorView {
+    challenge: String,
+    remember_me: bool,
+    error_message: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 struct SetupView {
     has_error: bool,
     error_message: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct LoginForm {
     name: String,
     password: String,
+    /// Any value keeps the session for `session_duration_seconds` across browser restarts.
+    remember_me: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LoginTwoFactorForm {
+    /// Signed token handed out after the password was checked.
+    challenge: String,
+    /// The current code from the authenticator app.
+    code: String,
+    remember_me: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SetupForm {
     name: String,
     password: String,
     password_confirm: String,
+    /// Optional; a verification link is mailed to it.
+    #[serde(default)]
+    email: String,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CreateUserForm {
+#[derive(Debug, Serialize)]
+struct EditUserView {
+    id: Uuid,
     name: String,
-    password: String,
-    is_admin: Option<String>,
+    role: Role,
+    roles: Vec<RoleOption>,
+    email: Option<String>,
+    email_verified: bool,
+    two_factor_enabled: bool,
+    user_is_admin: bool,
+    is_current_user: bool,
+    is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct RoleOption {
+    value: &'static str,
+    label: &'static str,
+}
+
+fn role_options() -> Vec<RoleOption> {
+    Role::ALL
+        .into_iter()
+        .map(|role| RoleOption {
+            value: role.as_str(),
+            label: role.label(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditUserForm {
+    name: String,
+    role: Role,
+    /// Empty removes the address. A changed address is unverified until its link is opened.
+    #[serde(default)]
+    email: String,
 }
 
 pub async fn has_users(db: &SqlitePool) -> Result<bool, AppError> {
@@ -98,69 +336,334 @@ pub async fn has_users(db: &SqlitePool) -> Result<bool, AppError> {
     Ok(count > 0)
 }
 
+/// Loads the user of a session. `is_activity` is false for requests that must not keep the
+/// session alive, like the auto-logout countdown.
 pub async fn resolve_current_user_from_session(
     db: &SqlitePool,
     session_id: Uuid,
+    client: &SessionClient,
+    config: &Config,
+    is_activity: bool,
 ) -> Result<Option<CurrentUser>, AppError> {
-    let valid_since = unix_now().saturating_sub(SESSION_DURATION_SECONDS);
-    let user = sqlx::query_as!(
-        User,
+    let now = unix_now();
+    let cutoffs = session_cutoffs(config);
+    let user = sqlx::query!(
         r#"
         SELECT
             users.id as "id: uuid::Uuid",
             users.name,
-            users.is_admin,
-            users.password_hash
+            users.role,
+            user_sessions.last_seen,
+            user_sessions.ip_address,
+            user_sessions.user_agent,
+            (
+                SELECT MIN(user_groups.auto_logout_minutes)
+                FROM user_group_members
+                INNER JOIN user_groups ON user_groups.id = user_group_members.user_group
+                WHERE user_group_members.user_id = users.id
+                    AND user_groups.auto_logout_minutes > 0
+            ) as "auto_logout_minutes?: i64"
         FROM user_sessions
         INNER JOIN users ON users.id = user_sessions.user_id
         WHERE user_sessions.id = $1
-            AND user_sessions.created_at > $2
+            AND users.is_disabled = 0
+            AND user_sessions.created_at > CASE WHEN user_sessions.remember_me != 0 THEN $2 ELSE $3 END
+            AND user_sessions.last_seen > $4
         LIMIT 1
         "#,
         session_id,
-        valid_since
+        cutoffs.remembered_since,
+        cutoffs.short_since,
+        cutoffs.idle_since
     )
     .fetch_optional(db)
     .await?;
+    let Some(user) = user else {
+        return Ok(None);
+    };
+
+    // A session used from another client most likely has a stolen cookie, so it is ended
+    // rather than just refused; the real owner only has to log in again.
+    if !client.matches(
+        config,
+        user.ip_address.as_deref(),
+        user.user_agent.as_deref(),
+    ) {
+        tracing::warn!(
+            user = user.name,
+            address = ?client.ip_address,
+            "Session used from a different client, logging it out"
+        );
+        sqlx::query!("DELETE FROM user_sessions WHERE id = $1", session_id)
+            .execute(db)
+            .await?;
+        return Ok(None);
+    }
+
+    let auto_logout_seconds = auto_logout::limit_seconds(config, user.auto_logout_minutes);
+    if let Some(limit) = auto_logout_seconds
+        && now.saturating_sub(user.last_seen) >= limit
+    {
+        sqlx::query!("DELETE FROM user_sessions WHERE id = $1", session_id)
+            .execute(db)
+            .await?;
+        return Ok(None);
+    }
+
+    // Only bookkeeping, so it must not keep anyone from reading while the database is
+    // read-only. With an auto-logout every request counts, so the countdown is exact.
+    let resolution = if auto_logout_seconds.is_some() {
+        0
+    } else {
+        LAST_SEEN_RESOLUTION_SECONDS
+    };
+    if is_activity
+        && now.saturating_sub(user.last_seen) >= resolution
+        && !read_only::is_active()
+        && let Err(err) = sqlx::query!(
+            "UPDATE user_sessions SET last_seen = $1 WHERE id = $2",
+            now,
+            session_id
+        )
+        .execute(db)
+        .await
+    {
+        tracing::warn!("Updating the session's last use failed: {}", err);
+    }
 
-    Ok(user.map(|value| value.as_current_user()))
+    let role = Role::from_db(&user.role);
+    Ok(Some(CurrentUser {
+        id: user.id,
+        name: user.name,
+        role,
+        is_admin: role == Role::Admin,
+        session_id,
+        ip_address: client.ip_address,
+        auto_logout_seconds,
+    }))
 }
 
-pub async fn cleanup_expired_sessions(db: &SqlitePool) -> Result<u64, AppError> {
-    let valid_since = unix_now().saturating_sub(SESSION_DURATION_SECONDS);
-    let result = sqlx::query!(
-        "DELETE FROM user_sessions WHERE created_at <= $1",
-        valid_since
+async fn is_disabled(db: &SqlitePool, user_id: Uuid) -> Result<bool, AppError> {
+    let is_disabled = sqlx::query_scalar!("SELECT is_disabled FROM users WHERE id = $1", user_id)
+        .fetch_optional(db)
+        .await?;
+    Ok(is_disabled.is_some_and(|is_disabled| is_disabled != 0))
+}
+
+pub(crate) async fn is_locked(db: &SqlitePool, user_id: Uuid) -> Result<bool, AppError> {
+    let locked_until = sqlx::query_scalar!("SELECT locked_until FROM users WHERE id = $1", user_id)
+        .fetch_optional(db)
+        .await?
+        .flatten();
+    Ok(locked_until.is_some_and(|locked_until| locked_until > unix_now()))
+}
+
+/// Counts a failed login within the configured window and locks the account once the threshold
+/// is reached. Returns whether the account is now locked.
+pub(crate) async fn record_failed_login(
+    db: &SqlitePool,
+    config: &Config,
+    user_id: Uuid,
+) -> Result<bool, AppError> {
+    if config.lockout_threshold == 0 {
+        return Ok(false);
+    }
+
+    let now = unix_now();
+    let window_start = now.saturating_sub(config.lockout_window_seconds);
+    let failed_count = sqlx::query_scalar!(
+        r#"
+        UPDATE users SET
+            failed_login_count = CASE
+                WHEN failed_login_window_start > $2 THEN failed_login_count + 1
+                ELSE 1
+            END,
+            failed_login_window_start = CASE
+                WHEN failed_login_window_start > $2 THEN failed_login_window_start
+                ELSE $3
+            END
+        WHERE id = $1
+        RETURNING failed_login_count as "failed_login_count!: i64"
+        "#,
+        user_id,
+        window_start,
+        now
+    )
+    .fetch_one(db)
+    .await?;
+
+    if failed_count < i64::from(config.lockout_threshold) {
+        return Ok(false);
+    }
+
+    let locked_until = now.saturating_add(config.lockout_duration_seconds);
+    sqlx::query!(
+        "UPDATE users SET locked_until = $2, failed_login_count = 0, failed_login_window_start = 0 WHERE id = $1",
+        user_id,
+        locked_until
+    )
+    .execute(db)
+    .await?;
+    Ok(true)
+}
+
+pub(crate) async fn reset_failed_logins(db: &SqlitePool, user_id: Uuid) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE users SET locked_until = NULL, failed_login_count = 0, failed_login_window_start = 0 WHERE id = $1",
+        user_id
     )
     .execute(db)
     .await?;
+    Ok(())
+}
+
+pub async fn cleanup_expired_sessions(
+    conn: &mut SqliteConnection,
+    config: &Config,
+) -> Result<u64, AppError> {
+    let cutoffs = session_cutoffs(config);
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE created_at <= CASE WHEN remember_me != 0 THEN $1 ELSE $2 END
+            OR last_seen <= $3
+        "#,
+        cutoffs.remembered_since,
+        cutoffs.short_since,
+        cutoffs.idle_since
+    )
+    .execute(conn)
+    .await?;
     Ok(result.rows_affected())
 }
 
-pub fn read_session_cookie(jar: &CookieJar) -> Option<Uuid> {
-    jar.get(SESSION_COOKIE_NAME)
+/// Owner and last activity of up to `limit` sessions `cleanup_expired_sessions` would delete.
+pub async fn expired_session_samples(
+    conn: &mut SqliteConnection,
+    config: &Config,
+    limit: i64,
+) -> Result<Vec<String>, AppError> {
+    let cutoffs = session_cutoffs(config);
+    let sessions = sqlx::query!(
+        r#"
+        SELECT
+            users.name,
+            user_sessions.last_seen
+        FROM user_sessions
+        INNER JOIN users ON users.id = user_sessions.user_id
+        WHERE user_sessions.created_at <= CASE WHEN user_sessions.remember_me != 0 THEN $1 ELSE $2 END
+            OR user_sessions.last_seen <= $3
+        ORDER BY user_sessions.last_seen ASC
+        LIMIT $4
+        "#,
+        cutoffs.remembered_since,
+        cutoffs.short_since,
+        cutoffs.idle_since,
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(sessions
+        .into_iter()
+        .map(|session| {
+            format!(
+                "{} last seen {}",
+                session.name,
+                format_unix_timestamp(session.last_seen)
+            )
+        })
+        .collect())
+}
+
+/// Creation times at or before which remembered and short sessions have expired, and the
+/// `last_seen` time at or before which any session has been idle for too long.
+struct SessionCutoffs {
+    remembered_since: i64,
+    short_since: i64,
+    idle_since: i64,
+}
+
+fn session_cutoffs(config: &Config) -> SessionCutoffs {
+    let now = unix_now();
+    SessionCutoffs {
+        remembered_since: now.saturating_sub(config.session_duration_seconds),
+        short_since: now.saturating_sub(config.short_session_duration_seconds),
+        idle_since: if config.session_idle_timeout_seconds > 0 {
+            now.saturating_sub(config.session_idle_timeout_seconds)
+        } else {
+            i64::MIN
+        },
+    }
+}
+
+pub fn read_session_cookie(jar: &CookieJar, config: &Config) -> Option<Uuid> {
+    jar.get(&config.session_cookie_name())
         .and_then(|cookie| Uuid::parse_str(cookie.value()).ok())
 }
 
-fn require_admin(user: &CurrentUser) -> Result<(), AppError> {
-    if user.is_admin {
-        Ok(())
-    } else {
-        Err(AppError::forbidden(
-            "Only admin users can access this page.",
-        ))
+/// Session cookie with the configured name, path and domain, so several instances can share
+/// one host without overwriting each other's sessions.
+fn session_cookie(config: &Config, value: String) -> Cookie<'static> {
+    session_scoped_cookie(config, config.session_cookie_name(), value)
+}
+
+/// A cookie with the path, domain, `Secure` and `SameSite` settings of the session cookie.
+pub(crate) fn session_scoped_cookie(
+    config: &Config,
+    name: String,
+    value: String,
+) -> Cookie<'static> {
+    let same_site = match config.session_cookie_same_site {
+        CookieSameSite::Strict => SameSite::Strict,
+        CookieSameSite::Lax => SameSite::Lax,
+        CookieSameSite::None => SameSite::None,
+    };
+    let mut cookie = Cookie::build((name, value))
+        .path(config.session_cookie_path().to_string())
+        .http_only(true)
+        .secure(config.session_cookie_secure())
+        .same_site(same_site)
+        .build();
+    if let Some(domain) = config
+        .session_cookie_domain
+        .as_ref()
+        .filter(|_| !config.session_cookie_host_prefix)
+    {
+        cookie.set_domain(domain.clone());
     }
+    cookie
 }
 
+#[utoipa::path(
+    get,
+    path = "/login",
+    tag = "users",
+    responses(
+        (status = 200, description = "Login form", content_type = "text/html"),
+    )
+)]
 pub async fn login_get(State(state): State<AppState>) -> Result<Response, AppError> {
     if !has_users(&state.db).await? {
         return Ok(Redirect::to("/setup").into_response());
     }
-    render_login(&state, false).map(IntoResponse::into_response)
+    render_login(&state, None).map(IntoResponse::into_response)
 }
 
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "users",
+    request_body(content = LoginForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Sets the session cookie and redirects home"),
+        (status = 200, description = "Login form with an error message", content_type = "text/html"),
+        (status = 429, description = "Too many login attempts from this address or for this user", content_type = "text/html"),
+    )
+)]
 pub async fn login_post(
     State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
     jar: CookieJar,
     Form(form): Form<LoginForm>,
 ) -> Result<Response, AppError> {
@@ -169,14 +672,30 @@ pub async fn login_post(
     }
 
     let login_name = form.name.trim().to_string();
+    if !state
+        .login_rate_limiter
+        .try_acquire(&client_ip.to_string(), &login_name)
+    {
+        tracing::warn!(
+            user = login_name,
+            address = %client_ip,
+            "Login rate limit exceeded"
+        );
+        let page = render_login(&state, Some(TOO_MANY_ATTEMPTS_MESSAGE))?;
+        return Ok((StatusCode::TOO_MANY_REQUESTS, page).into_response());
+    }
+
     let user = sqlx::query_as!(
         User,
         r#"
         SELECT
             id as "id: uuid::Uuid",
             name,
-            is_admin,
-            password_hash
+            role,
+            password_hash,
+            email,
+            email_verified_at,
+            totp_enabled_at
         FROM users
         WHERE LOWER(name) = LOWER($1)
         LIMIT 1
@@ -186,34 +705,162 @@ pub async fn login_post(
     .fetch_optional(&state.db)
     .await?;
 
+    // The password is checked before anything about the account is looked at, and wrong
+    // passwords all get the same answer, so neither the answer nor its timing tells others
+    // whether the name exists or is locked.
+    let password_matches = verify_password(
+        user.as_ref()
+            .map_or(DUMMY_PASSWORD_HASH.as_str(), |user| &user.password_hash),
+        &form.password,
+    );
     let Some(user) = user else {
-        return render_login(&state, true).map(IntoResponse::into_response);
+        return render_login(&state, Some(INVALID_LOGIN_MESSAGE)).map(IntoResponse::into_response);
     };
+    let is_locked = is_locked(&state.db, user.id).await?;
+    if !password_matches {
+        if !is_locked && record_failed_login(&state.db, &state.config, user.id).await? {
+            tracing::warn!(
+                user = user.name,
+                "Account locked after repeated failed logins"
+            );
+        }
+        return render_login(&state, Some(INVALID_LOGIN_MESSAGE)).map(IntoResponse::into_response);
+    }
+
+    // Only revealed after the correct password, so it does not tell others the name exists.
+    if is_locked {
+        return render_login(&state, Some(ACCOUNT_LOCKED_MESSAGE)).map(IntoResponse::into_response);
+    }
+    reset_failed_logins(&state.db, user.id).await?;
+    if is_disabled(&state.db, user.id).await? {
+        return render_login(&state, Some(ACCOUNT_DISABLED_MESSAGE))
+            .map(IntoResponse::into_response);
+    }
 
-    if !verify_password(&user.password_hash, &form.password) {
-        return render_login(&state, true).map(IntoResponse::into_response);
+    let remember_me = form.remember_me.is_some();
+    if user.totp_enabled_at.is_some() {
+        let expires_at = unix_now().saturating_add(LOGIN_CHALLENGE_VALIDITY_SECONDS);
+        let challenge = state.token_signer.sign(
+            TokenScope::LoginChallenge,
+            Uuid::new_v4(),
+            user.id,
+            Some(expires_at),
+        );
+        return render_login_two_factor(&state, challenge, remember_me, None)
+            .map(IntoResponse::into_response);
     }
 
+    start_session(&state, client_ip, &headers, jar, user.id, remember_me).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/2fa",
+    tag = "users",
+    request_body(content = LoginTwoFactorForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Sets the session cookie and redirects home"),
+        (status = 200, description = "Code form or login form with an error message", content_type = "text/html"),
+        (status = 429, description = "Too many codes were tried for this login", content_type = "text/html"),
+    )
+)]
+pub async fn login_two_factor_post(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Form(form): Form<LoginTwoFactorForm>,
+) -> Result<Response, AppError> {
+    let verified = state
+        .token_signer
+        .verify(&state.db, &form.challenge, TokenScope::LoginChallenge)
+        .await?;
+    let Some(verified) = verified else {
+        return render_login(&state, Some(LOGIN_EXPIRED_MESSAGE)).map(IntoResponse::into_response);
+    };
+    if !tokens::record_request(&state.db, &state.config, verified.id).await? {
+        let page = render_login(&state, Some(TOO_MANY_ATTEMPTS_MESSAGE))?;
+        return Ok((StatusCode::TOO_MANY_REQUESTS, page).into_response());
+    }
+
+    let user_id = verified.subject;
+    if is_locked(&state.db, user_id).await? {
+        return render_login(&state, Some(ACCOUNT_LOCKED_MESSAGE)).map(IntoResponse::into_response);
+    }
+    if is_disabled(&state.db, user_id).await? {
+        return render_login(&state, Some(ACCOUNT_DISABLED_MESSAGE))
+            .map(IntoResponse::into_response);
+    }
+
+    let remember_me = form.remember_me.is_some();
+    if !two_factor::verify_code(&state, user_id, &form.code).await? {
+        // Wrong codes count like wrong passwords, so guessing runs into the same lockout.
+        if record_failed_login(&state.db, &state.config, user_id).await? {
+            tracing::warn!(%user_id, "Account locked after repeated failed logins");
+            return render_login(&state, Some(ACCOUNT_LOCKED_MESSAGE))
+                .map(IntoResponse::into_response);
+        }
+        return render_login_two_factor(
+            &state,
+            form.challenge,
+            remember_me,
+            Some(INVALID_CODE_MESSAGE),
+        )
+        .map(IntoResponse::into_response);
+    }
+    reset_failed_logins(&state.db, user_id).await?;
+    tokens::revoke(&state.db, verified.id).await?;
+
+    start_session(&state, client_ip, &headers, jar, user_id, remember_me).await
+}
+
+/// Creates a session for a user who passed every login check and sets its cookie.
+async fn start_session(
+    state: &AppState,
+    client_ip: IpAddr,
+    headers: &HeaderMap,
+    jar: CookieJar,
+    user_id: Uuid,
+    remember_me: bool,
+) -> Result<Response, AppError> {
     let session_id = Uuid::new_v4();
     let now = unix_now();
+    let client = SessionClient::new(Some(client_ip), headers);
+    let ip_address = client_ip.to_string();
+    let user_agent = client.user_agent;
     sqlx::query!(
-        "INSERT INTO user_sessions (id, user_id, created_at) VALUES ($1, $2, $3)",
+        r#"
+        INSERT INTO user_sessions (id, user_id, created_at, last_seen, ip_address, user_agent, remember_me)
+        VALUES ($1, $2, $3, $3, $4, $5, $6)
+        "#,
         session_id,
-        user.id,
-        now
+        user_id,
+        now,
+        ip_address,
+        user_agent,
+        remember_me
     )
     .execute(&state.db)
     .await?;
 
-    let cookie = Cookie::build((SESSION_COOKIE_NAME, session_id.to_string()))
-        .path("/")
-        .http_only(true)
-        .same_site(SameSite::Lax)
-        .build();
+    let mut cookie = session_cookie(&state.config, session_id.to_string());
+    if remember_me {
+        cookie.set_max_age(time::Duration::seconds(
+            state.config.session_duration_seconds,
+        ));
+    }
 
     Ok((jar.add(cookie), Redirect::to("/")).into_response())
 }
 
+#[utoipa::path(
+    get,
+    path = "/setup",
+    tag = "users",
+    responses(
+        (status = 200, description = "Initial admin setup form", content_type = "text/html"),
+    )
+)]
 pub async fn setup_get(State(state): State<AppState>) -> Result<Response, AppError> {
     if has_users(&state.db).await? {
         return Ok(Redirect::to("/login").into_response());
@@ -221,8 +868,18 @@ pub async fn setup_get(State(state): State<AppState>) -> Result<Response, AppErr
     render_setup(&state, None)
 }
 
+#[utoipa::path(
+    post,
+    path = "/setup",
+    tag = "users",
+    request_body(content = SetupForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Creates the first admin user and redirects to the login"),
+    )
+)]
 pub async fn setup_post(
     State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
     Form(form): Form<SetupForm>,
 ) -> Result<Response, AppError> {
     if has_users(&state.db).await? {
@@ -239,53 +896,270 @@ pub async fn setup_post(
     if form.password != form.password_confirm {
         return render_setup(&state, Some("Passwords do not match."));
     }
+    let Ok(email) = emails::normalize_address(&form.email) else {
+        return render_setup(
+            &state,
+            Some("Enter a valid email address or leave it empty."),
+        );
+    };
 
     let setup_user_id = Uuid::new_v4();
     let setup_created_at = unix_now();
     let setup_password_hash = hash_password(&form.password)?;
+    let mut tx = state.db.begin().await?;
     sqlx::query!(
-        "INSERT INTO users (id, name, is_admin, created_at, password_hash) VALUES ($1, $2, $3, $4, $5)",
+        "INSERT INTO users (id, name, role, created_at, password_hash) VALUES ($1, $2, 'admin', $3, $4)",
         setup_user_id,
         name,
-        1_i64,
         setup_created_at,
         setup_password_hash
     )
-    .execute(&state.db)
+    .execute(&mut *tx)
     .await?;
+    audit::record_as(
+        &mut *tx,
+        setup_user_id,
+        name,
+        Some(client_ip),
+        AuditEntry::new(
+            AuditEntity::User,
+            setup_user_id,
+            AuditAction::Create,
+            format!("Set up the first admin \"{}\"", name),
+        )
+        .change(None, Some(user_snapshot(name, Role::Admin, None))),
+    )
+    .await?;
+    tx.commit().await?;
+
+    if let Some(email) = email {
+        emails::set_address(&state.db, setup_user_id, Some(&email)).await?;
+        // The account exists either way; the link can be sent again from the email page.
+        if let Err(err) = emails::send_verification(&state, setup_user_id).await {
+            tracing::error!("Sending the verification mail failed: {}", err);
+        }
+    }
 
     Ok(Redirect::to("/login").into_response())
 }
 
+#[utoipa::path(
+    post,
+    path = "/logout",
+    tag = "users",
+    responses(
+        (status = 303, description = "Clears the session and redirects to the login"),
+    )
+)]
 pub async fn logout_post(
     State(state): State<AppState>,
     jar: CookieJar,
 ) -> Result<(CookieJar, Redirect), AppError> {
-    if let Some(session_id) = read_session_cookie(&jar) {
+    if let Some(session_id) = read_session_cookie(&jar, &state.config) {
         let _ = sqlx::query!("DELETE FROM user_sessions WHERE id = $1", session_id)
             .execute(&state.db)
             .await;
     }
 
-    let removal_cookie = Cookie::build((SESSION_COOKIE_NAME, "")).path("/").build();
+    let removal_cookie = session_cookie(&state.config, String::new());
 
     Ok((jar.remove(removal_cookie), Redirect::to("/login")))
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/me",
+    tag = "users",
+    responses(
+        (status = 200, description = "Account settings of the current user: email address and home page", content_type = "text/html"),
+    )
+)]
+pub async fn account_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    let rendered = templates::render(
+        &state.jinja,
+        "account.html",
+        AccountView {
+            email: emails::fetch_status(&state, current_user.id).await?,
+            two_factor_enabled: fetch_user(&state.db, current_user.id)
+                .await?
+                .totp_enabled_at
+                .is_some(),
+            home_page: fetch_home_page(&state.db, current_user.id).await?,
+            home_pages: HomePage::ALL
+                .into_iter()
+                .map(|home_page| HomePageOption {
+                    value: home_page.as_str(),
+                    label: home_page.label(),
+                })
+                .collect(),
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/me/home",
+    tag = "users",
+    request_body(content = HomePageForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Saves the page `/` opens and redirects to the account page"),
+    )
+)]
+pub async fn home_page_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Form(form): Form<HomePageForm>,
+) -> Result<Redirect, AppError> {
+    let home_page = form.home_page.as_str();
+    sqlx::query!(
+        "UPDATE users SET home_page = $1 WHERE id = $2",
+        home_page,
+        current_user.id
+    )
+    .execute(&state.db)
+    .await?;
+
+    Ok(Redirect::to("/users/me"))
+}
+
+pub async fn fetch_home_page(db: &SqlitePool, user_id: Uuid) -> Result<HomePage, AppError> {
+    let home_page = sqlx::query_scalar!("SELECT home_page FROM users WHERE id = $1", user_id)
+        .fetch_optional(db)
+        .await?;
+    Ok(home_page
+        .as_deref()
+        .map(HomePage::from_db)
+        .unwrap_or(HomePage::Plans))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/me/sessions",
+    tag = "users",
+    responses(
+        (status = 200, description = "Active sessions of the current user", content_type = "text/html"),
+    )
+)]
+pub async fn sessions_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    let cutoffs = session_cutoffs(&state.config);
+    let sessions = sqlx::query!(
+        r#"
+        SELECT
+            id as "id: uuid::Uuid",
+            created_at,
+            last_seen,
+            ip_address,
+            user_agent
+        FROM user_sessions
+        WHERE user_id = $1
+            AND created_at > CASE WHEN remember_me != 0 THEN $2 ELSE $3 END
+            AND last_seen > $4
+        ORDER BY last_seen DESC
+        "#,
+        current_user.id,
+        cutoffs.remembered_since,
+        cutoffs.short_since,
+        cutoffs.idle_since
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|session| UserSessionItem {
+        id: session.id,
+        created_display: format_unix_timestamp(session.created_at),
+        last_seen_display: format_unix_timestamp(session.last_seen),
+        ip_address: session.ip_address,
+        user_agent: session.user_agent,
+        is_current: session.id == current_user.session_id,
+    })
+    .collect();
+
+    let rendered = templates::render(
+        &state.jinja,
+        "user_sessions.html",
+        UserSessionsView {
+            sessions,
+            is_admin: current_user.is_admin,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/me/sessions/{id}/revoke",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "Session id")),
+    responses(
+        (status = 303, description = "Ends the session and redirects to the session list, or to the login page for the current session"),
+        (status = 403, description = "The session belongs to another user", content_type = "text/html"),
+    )
+)]
+pub async fn revoke_session_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    let owner = sqlx::query_scalar!(
+        r#"SELECT user_id as "user_id: uuid::Uuid" FROM user_sessions WHERE id = $1"#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?;
+    if let Some(owner) = owner {
+        authorize(
+            &current_user,
+            Action::RevokeSession,
+            Resource::Session { owner },
+        )?;
+        sqlx::query!("DELETE FROM user_sessions WHERE id = $1", id)
+            .execute(&state.db)
+            .await?;
+    }
+
+    if id == current_user.session_id {
+        return Ok(Redirect::to("/login"));
+    }
+    Ok(Redirect::to("/users/me/sessions"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    responses(
+        (status = 200, description = "User management page", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+    )
+)]
 pub async fn index(
     State(state): State<AppState>,
     current_user: CurrentUser,
+    headers: HeaderMap,
 ) -> Result<Html<String>, AppError> {
-    require_admin(&current_user)?;
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
 
-    let users = sqlx::query_as!(
-        User,
+    let users = sqlx::query!(
         r#"
         SELECT
             id as "id: uuid::Uuid",
             name,
-            is_admin,
-            password_hash
+            role,
+            email,
+            email_verified_at,
+            is_disabled,
+            locked_until,
+            totp_enabled_at
         FROM users
         ORDER BY name ASC
         "#
@@ -293,88 +1167,181 @@ pub async fn index(
     .fetch_all(&state.db)
     .await?;
 
+    let mut group_names = groups::fetch_group_names_by_user(&state.db).await?;
+    let now = unix_now();
     let view = UserListView {
         users: users
             .into_iter()
             .map(|user| UserListItem {
                 id: user.id,
                 name: user.name,
-                is_admin: user.is_admin != 0,
+                role_label: Role::from_db(&user.role).label(),
+                group_names: group_names.remove(&user.id).unwrap_or_default(),
+                email_verified: user.email.is_some() && user.email_verified_at.is_some(),
+                email: user.email,
+                is_disabled: user.is_disabled != 0,
+                two_factor_enabled: user.totp_enabled_at.is_some(),
+                locked_until_display: user
+                    .locked_until
+                    .filter(|locked_until| *locked_until > now)
+                    .map(format_unix_timestamp),
             })
             .collect(),
+        invites: invites::fetch_pending(&state, &headers).await?,
+        roles: role_options(),
         current_user_id: current_user.id,
         is_admin: true,
     };
 
-    let template = state
-        .jinja
-        .get_template("users.html")
-        .expect("template is loaded");
-    let rendered = template.render(view)?;
+    let rendered = templates::render(&state.jinja, "users.html", view)?;
 
     Ok(Html(rendered))
 }
 
-pub async fn create_post(
+#[utoipa::path(
+    post,
+    path = "/users/{id}/unlock",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 303, description = "Lifts a lockout after failed logins and redirects to the user list"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "User not found", content_type = "text/html"),
+    )
+)]
+pub async fn unlock_post(
     State(state): State<AppState>,
     current_user: CurrentUser,
-    Form(form): Form<CreateUserForm>,
+    Path(id): Path<Uuid>,
 ) -> Result<Redirect, AppError> {
-    require_admin(&current_user)?;
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let target = fetch_user(&state.db, id).await?;
+    reset_failed_logins(&state.db, id).await?;
+    audit::record(
+        &state.db,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::User,
+            id,
+            AuditAction::Update,
+            format!("Unlocked \"{}\" after failed logins", target.name),
+        ),
+    )
+    .await?;
 
-    let name = form.name.trim();
-    if name.is_empty() {
-        return Err(AppError::conflict("User name cannot be empty."));
-    }
-    if form.password.len() < 8 {
+    Ok(Redirect::to("/users"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/disable",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 303, description = "Blocks the user from logging in, ends their sessions and redirects to the user list"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "User not found", content_type = "text/html"),
+        (status = 409, description = "User cannot be disabled", content_type = "text/html"),
+    )
+)]
+pub async fn disable_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    if current_user.id == id {
         return Err(AppError::conflict(
-            "Password must be at least 8 characters.",
+            "You cannot disable your own active user.",
         ));
     }
+    let target = fetch_user(&state.db, id).await?;
+    if target.role() == Role::Admin && !is_disabled(&state.db, id).await? {
+        ensure_other_admin_exists(&state.db).await?;
+    }
 
-    let exists = sqlx::query_scalar!(
-        r#"
-        SELECT id as "id: uuid::Uuid"
-        FROM users
-        WHERE LOWER(name) = LOWER($1)
-        "#,
-        name
+    let mut tx = state.db.begin().await?;
+    sqlx::query!("UPDATE users SET is_disabled = 1 WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM user_sessions WHERE user_id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::User,
+            id,
+            AuditAction::Update,
+            format!("Disabled \"{}\"", target.name),
+        ),
     )
-    .fetch_optional(&state.db)
     .await?;
+    tx.commit().await?;
 
-    if exists.is_some() {
-        return Err(AppError::conflict("A user with this name already exists."));
-    }
+    Ok(Redirect::to("/users"))
+}
 
-    let created_user_id = Uuid::new_v4();
-    let created_is_admin = if form.is_admin.is_some() {
-        1_i64
-    } else {
-        0_i64
-    };
-    let created_at = unix_now();
-    let created_password_hash = hash_password(&form.password)?;
-    sqlx::query!(
-        "INSERT INTO users (id, name, is_admin, created_at, password_hash) VALUES ($1, $2, $3, $4, $5)",
-        created_user_id,
-        name,
-        created_is_admin,
-        created_at,
-        created_password_hash
+#[utoipa::path(
+    post,
+    path = "/users/{id}/enable",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 303, description = "Allows the user to log in again and redirects to the user list"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "User not found", content_type = "text/html"),
+    )
+)]
+pub async fn enable_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let target = fetch_user(&state.db, id).await?;
+    let mut tx = state.db.begin().await?;
+    sqlx::query!("UPDATE users SET is_disabled = 0 WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::User,
+            id,
+            AuditAction::Update,
+            format!("Enabled \"{}\"", target.name),
+        ),
     )
-    .execute(&state.db)
     .await?;
+    tx.commit().await?;
 
     Ok(Redirect::to("/users"))
 }
 
+#[utoipa::path(
+    post,
+    path = "/users/{id}/delete",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 303, description = "Deletes the user, removing them from their groups, and redirects to the user list"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "User not found", content_type = "text/html"),
+        (status = 409, description = "User cannot be deleted", content_type = "text/html"),
+    )
+)]
 pub async fn delete_post(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Path(id): Path<Uuid>,
 ) -> Result<Redirect, AppError> {
-    require_admin(&current_user)?;
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
 
     if current_user.id == id {
         return Err(AppError::conflict(
@@ -382,60 +1349,72 @@ pub async fn delete_post(
         ));
     }
 
-    let target = sqlx::query_as!(
-        User,
-        r#"
-        SELECT
-            id as "id: uuid::Uuid",
-            name,
-            is_admin,
-            password_hash
-        FROM users
-        WHERE id = $1
-        LIMIT 1
-        "#,
-        id
-    )
-    .fetch_optional(&state.db)
-    .await?;
+    let target = fetch_user(&state.db, id).await?;
 
-    let Some(target) = target else {
-        return Err(AppError::not_found_for(
-            "User",
-            format!("No user exists for id: {}", id),
-        ));
-    };
-
-    if target.is_admin != 0 {
-        let admin_count =
-            sqlx::query_scalar!("SELECT COUNT(*) as \"count!: i64\" FROM users WHERE is_admin = 1")
-                .fetch_one(&state.db)
-                .await?;
-
-        if admin_count <= 1 {
-            return Err(AppError::conflict("At least one admin user must remain."));
-        }
+    if target.role() == Role::Admin && !is_disabled(&state.db, id).await? {
+        ensure_other_admin_exists(&state.db).await?;
     }
 
     let mut tx = state.db.begin().await?;
     sqlx::query!("DELETE FROM user_sessions WHERE user_id = $1", id)
         .execute(&mut *tx)
         .await?;
+    sqlx::query!("DELETE FROM user_group_members WHERE user_id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM procedure_acknowledgments WHERE user_id = $1",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    two_factor::delete_recovery_codes(&mut tx, id).await?;
 
     sqlx::query!("DELETE FROM users WHERE id = $1", id)
         .execute(&mut *tx)
         .await?;
+    audit::record(
+        &mut *tx,
+        &current_user,
+        AuditEntry::new(
+            AuditEntity::User,
+            id,
+            AuditAction::Delete,
+            format!("Deleted \"{}\"", target.name),
+        )
+        .change(
+            Some(user_snapshot(
+                &target.name,
+                target.role(),
+                target.email.as_deref(),
+            )),
+            None,
+        ),
+    )
+    .await?;
     tx.commit().await?;
 
     Ok(Redirect::to("/users"))
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/{id}/delete",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 200, description = "Delete confirmation page", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "User not found", content_type = "text/html"),
+        (status = 409, description = "User cannot be deleted", content_type = "text/html"),
+    )
+)]
 pub async fn delete_get(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Path(id): Path<Uuid>,
 ) -> Result<Html<String>, AppError> {
-    require_admin(&current_user)?;
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
 
     if current_user.id == id {
         return Err(AppError::conflict(
@@ -443,60 +1422,203 @@ pub async fn delete_get(
         ));
     }
 
-    let target = sqlx::query_as!(
+    let target = fetch_user(&state.db, id).await?;
+
+    if target.role() == Role::Admin && !is_disabled(&state.db, id).await? {
+        ensure_other_admin_exists(&state.db).await?;
+    }
+
+    let rendered = templates::render(
+        &state.jinja,
+        "user_delete_confirm.html",
+        DeleteUserConfirmView {
+            id: target.id,
+            role: target.role().label().to_string(),
+            name: target.name,
+            show_users_link: true,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/edit",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 200, description = "User edit form", content_type = "text/html"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "User not found", content_type = "text/html"),
+    )
+)]
+pub async fn edit_get(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Html<String>, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let target = fetch_user(&state.db, id).await?;
+    let rendered = templates::render(
+        &state.jinja,
+        "user_edit.html",
+        EditUserView {
+            id: target.id,
+            role: target.role(),
+            roles: role_options(),
+            user_is_admin: target.role() == Role::Admin,
+            email_verified: target.email.is_some() && target.email_verified_at.is_some(),
+            two_factor_enabled: target.totp_enabled_at.is_some(),
+            email: target.email,
+            name: target.name,
+            is_current_user: target.id == current_user.id,
+            is_admin: true,
+        },
+    )?;
+
+    Ok(Html(rendered))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/edit",
+    tag = "users",
+    params(("id" = Uuid, Path, description = "User id")),
+    request_body(content = EditUserForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Saves the user and redirects to the user list"),
+        (status = 403, description = "Only admin users can access this endpoint", content_type = "text/html"),
+        (status = 404, description = "User not found", content_type = "text/html"),
+        (status = 409, description = "Invalid or duplicate name, invalid email address, or the last admin would be demoted", content_type = "text/html"),
+    )
+)]
+pub async fn edit_post(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Form(form): Form<EditUserForm>,
+) -> Result<Redirect, AppError> {
+    authorize(&current_user, Action::ManageUsers, Resource::Everything)?;
+
+    let target = fetch_user(&state.db, id).await?;
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Err(AppError::conflict("User name cannot be empty."));
+    }
+    let email = emails::normalize_address(&form.email)?;
+
+    let name_taken = sqlx::query_scalar!(
+        r#"
+        SELECT id as "id: uuid::Uuid"
+        FROM users
+        WHERE LOWER(name) = LOWER($1)
+            AND id != $2
+        "#,
+        name,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .is_some();
+    if name_taken {
+        return Err(AppError::conflict("A user with this name already exists."));
+    }
+
+    if target.role() == Role::Admin
+        && form.role != Role::Admin
+        && !is_disabled(&state.db, id).await?
+    {
+        ensure_other_admin_exists(&state.db).await?;
+    }
+
+    let role = form.role.as_str();
+    let before = user_snapshot(&target.name, target.role(), target.email.as_deref());
+    let after = user_snapshot(name, form.role, email.as_deref());
+    let mut tx = state.db.begin().await?;
+    sqlx::query!(
+        "UPDATE users SET name = $1, role = $2 WHERE id = $3",
+        name,
+        role,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    if before != after {
+        audit::record(
+            &mut *tx,
+            &current_user,
+            AuditEntry::new(
+                AuditEntity::User,
+                id,
+                AuditAction::Update,
+                format!("Edited \"{}\"", name),
+            )
+            .change(Some(before), Some(after)),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    let email_changed =
+        target.email.as_deref().map(str::to_lowercase) != email.as_deref().map(str::to_lowercase);
+    if email_changed {
+        emails::set_address(&state.db, id, email.as_deref()).await?;
+        emails::send_verification(&state, id).await?;
+    }
+
+    Ok(Redirect::to("/users"))
+}
+
+async fn fetch_user(db: &SqlitePool, id: Uuid) -> Result<User, AppError> {
+    sqlx::query_as!(
         User,
         r#"
         SELECT
             id as "id: uuid::Uuid",
             name,
-            is_admin,
-            password_hash
+            role,
+            password_hash,
+            email,
+            email_verified_at,
+            totp_enabled_at
         FROM users
         WHERE id = $1
         LIMIT 1
         "#,
         id
     )
-    .fetch_optional(&state.db)
-    .await?;
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AppError::not_found_for("User", format!("No user exists for id: {}", id)))
+}
 
-    let Some(target) = target else {
-        return Err(AppError::not_found_for(
-            "User",
-            format!("No user exists for id: {}", id),
-        ));
-    };
+/// The fields of a user an admin can change, for the audit log.
+pub(crate) fn user_snapshot(name: &str, role: Role, email: Option<&str>) -> String {
+    format!(
+        "Name: {}\nRole: {}\nEmail: {}",
+        name,
+        role.label(),
+        email.unwrap_or("none")
+    )
+}
 
-    if target.is_admin != 0 {
-        let admin_count =
-            sqlx::query_scalar!("SELECT COUNT(*) as \"count!: i64\" FROM users WHERE is_admin = 1")
-                .fetch_one(&state.db)
-                .await?;
+/// Fails unless another enabled admin remains when one admin is deleted, demoted or disabled.
+async fn ensure_other_admin_exists(db: &SqlitePool) -> Result<(), AppError> {
+    let admin_count = sqlx::query_scalar!(
+        "SELECT COUNT(*) as \"count!: i64\" FROM users WHERE role = 'admin' AND is_disabled = 0"
+    )
+    .fetch_one(db)
+    .await?;
 
-        if admin_count <= 1 {
-            return Err(AppError::conflict("At least one admin user must remain."));
-        }
+    if admin_count <= 1 {
+        return Err(AppError::conflict("At least one admin user must remain."));
     }
-
-    let template = state
-        .jinja
-        .get_template("user_delete_confirm.html")
-        .expect("template is loaded");
-    let rendered = template.render(DeleteUserConfirmView {
-        id: target.id,
-        name: target.name,
-        role: if target.is_admin != 0 {
-            "Admin".to_string()
-        } else {
-            "User".to_string()
-        },
-        show_users_link: true,
-    })?;
-
-    Ok(Html(rendered))
+    Ok(())
 }
 
-fn hash_password(password: &str) -> Result<String, AppError> {
+pub(crate) fn hash_password(password: &str) -> Result<String, AppError> {
     let salt = SaltString::generate(&mut OsRng);
     Argon2::default()
         .hash_password(password.as_bytes(), &salt)
@@ -504,48 +1626,60 @@ fn hash_password(password: &str) -> Result<String, AppError> {
         .map_err(|err| AppError::internal(anyhow::anyhow!(err.to_string())))
 }
 
+/// Accounts without a usable hash never match, but still cost a full check.
 fn verify_password(hash: &str, password: &str) -> bool {
-    let parsed = match PasswordHash::new(hash) {
-        Ok(parsed) => parsed,
-        Err(_) => return false,
+    let (parsed, usable) = match PasswordHash::new(hash) {
+        Ok(parsed) => (parsed, true),
+        Err(_) => (
+            PasswordHash::new(&DUMMY_PASSWORD_HASH).expect("the dummy hash parses"),
+            false,
+        ),
     };
 
     Argon2::default()
         .verify_password(password.as_bytes(), &parsed)
         .is_ok()
+        && usable
 }
 
-fn unix_now() -> i64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|duration| duration.as_secs() as i64)
-        .unwrap_or(0)
+fn render_login(state: &AppState, error_message: Option<&str>) -> Result<Html<String>, AppError> {
+    let rendered = templates::render(
+        &state.jinja,
+        "login.html",
+        LoginView {
+            has_error: error_message.is_some(),
+            error_message: error_message.map(str::to_string),
+        },
+    )?;
+    Ok(Html(rendered))
 }
 
-fn render_login(state: &AppState, has_error: bool) -> Result<Html<String>, AppError> {
-    let template = state
-        .jinja
-        .get_template("login.html")
-        .expect("template is loaded");
-    let rendered = template.render(LoginView {
-        has_error,
-        error_message: if has_error {
-            Some("Invalid username or password.".to_string())
-        } else {
-            None
+fn render_login_two_factor(
+    state: &AppState,
+    challenge: String,
+    remember_me: bool,
+    error_message: Option<&str>,
+) -> Result<Html<String>, AppError> {
+    let rendered = templates::render(
+        &state.jinja,
+        "login_2fa.html",
+        LoginTwoFactorView {
+            challenge,
+            remember_me,
+            error_message: error_message.map(str::to_string),
         },
-    })?;
+    )?;
     Ok(Html(rendered))
 }
 
 fn render_setup(state: &AppState, error_message: Option<&str>) -> Result<Response, AppError> {
-    let template = state
-        .jinja
-        .get_template("setup.html")
-        .expect("template is loaded");
-    let rendered = template.render(SetupView {
-        has_error: error_message.is_some(),
-        error_message: error_message.map(str::to_string),
-    })?;
+    let rendered = templates::render(
+        &state.jinja,
+        "setup.html",
+        SetupView {
+            has_error: error_message.is_some(),
+            error_message: error_message.map(str::to_string),
+        },
+    )?;
     Ok(Html(rendered).into_response())
 }
@@ -0,0 +1,91 @@
+//! Checks uploads with ClamAV before they are stored, as technicians attach files from USB
+//! sticks and vendor laptops. Files go to the clamd daemon at `clamd_address` with its
+//! `INSTREAM` command; without an address nothing is scanned.
+
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpStream, UnixStream},
+};
+
+use crate::config::Config;
+
+/// How long a scan may take, connecting included.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(60);
+/// Size of the chunks streamed to clamd, well below its default `StreamMaxLength`.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Why a file has to be quarantined: it was flagged, or it could not be scanned. `None` if it
+/// is clean or scanning is turned off.
+pub async fn scan(config: &Config, file_name: &str, bytes: &[u8]) -> Option<String> {
+    let address = config.clamd_address.as_deref()?;
+    let reply = match tokio::time::timeout(SCAN_TIMEOUT, send(address, bytes)).await {
+        Ok(Ok(reply)) => reply,
+        Ok(Err(err)) => {
+            tracing::warn!("Scanning \"{}\" failed: {:#}", file_name, err);
+            return Some(format!("Could not be scanned: {:#}", err));
+        }
+        Err(_) => {
+            tracing::warn!("Scanning \"{}\" timed out", file_name);
+            return Some("Could not be scanned: clamd did not answer in time".to_string());
+        }
+    };
+
+    let result = reply.strip_prefix("stream:").unwrap_or(&reply).trim();
+    if result == "OK" {
+        return None;
+    }
+    if let Some(signature) = result.strip_suffix("FOUND") {
+        tracing::warn!("ClamAV flagged \"{}\" as {}", file_name, signature.trim());
+        return Some(format!("ClamAV found {}", signature.trim()));
+    }
+    tracing::warn!(
+        "Scanning \"{}\" failed: clamd answered {}",
+        file_name,
+        reply
+    );
+    Some(format!("Could not be scanned: clamd answered {}", reply))
+}
+
+/// Streams `bytes` to clamd at a Unix socket path or `host:port` and returns its reply.
+async fn send(address: &str, bytes: &[u8]) -> anyhow::Result<String> {
+    if address.starts_with('/') {
+        let stream = UnixStream::connect(address)
+            .await
+            .with_context(|| format!("connecting to {} failed", address))?;
+        exchange(stream, bytes).await
+    } else {
+        let stream = TcpStream::connect(address)
+            .await
+            .with_context(|| format!("connecting to {} failed", address))?;
+        exchange(stream, bytes).await
+    }
+}
+
+async fn exchange<S>(mut stream: S, bytes: &[u8]) -> anyhow::Result<String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream.write_all(b"zINSTREAM\0").await?;
+    for chunk in bytes.chunks(CHUNK_BYTES) {
+        stream
+            .write_all(&(chunk.len() as u32).to_be_bytes())
+            .await?;
+        stream.write_all(chunk).await?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await?;
+    stream.flush().await?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+    let reply = String::from_utf8_lossy(&reply)
+        .trim_end_matches('\0')
+        .trim()
+        .to_string();
+    if reply.is_empty() {
+        bail!("clamd closed the connection without an answer");
+    }
+    Ok(reply)
+}
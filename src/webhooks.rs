@@ -0,0 +1,113 @@
+//! Outgoing notifications for other systems, such as a documentation pipeline mirroring
+//! procedures into a wiki. When `plan_webhook_url` is set, every saved plan is posted there as
+//! JSON with its Markdown export. Calls run in the background and are retried a few times, so
+//! a slow or unreachable receiver never holds up saving.
+
+use std::time::Duration;
+
+use axum::{
+    body::Bytes,
+    http::{HeaderName, Method, header},
+};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{AppState, action_plan, http_client, unix_now};
+
+/// Waits before the second and third attempt.
+const RETRY_DELAYS: [Duration; 2] = [Duration::from_secs(30), Duration::from_secs(5 * 60)];
+const TIMEOUT: Duration = Duration::from_secs(15);
+/// Receivers only need to acknowledge, so anything they send back is capped small.
+const MAX_RESPONSE_BYTES: usize = 64 * 1024;
+const SIGNATURE_HEADER: &str = "x-maintenance-planner-signature";
+
+#[derive(Serialize)]
+struct PlanPublished {
+    event: &'static str,
+    plan_id: Uuid,
+    plan_name: String,
+    /// Path of the Markdown export, relative to the base URL of this instance.
+    markdown_path: String,
+    markdown: String,
+    published_at: i64,
+}
+
+/// Posts the current version of the plan to `plan_webhook_url`, if set, in the background.
+pub fn plan_published(state: &AppState, plan_id: Uuid) {
+    let Some(url) = state.config.plan_webhook_url.clone() else {
+        return;
+    };
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(err) = send_plan_published(&state, &url, plan_id).await {
+            tracing::warn!(%plan_id, "Plan webhook failed: {:#}", err);
+        }
+    });
+}
+
+async fn send_plan_published(state: &AppState, url: &str, plan_id: Uuid) -> anyhow::Result<()> {
+    let plan_name = sqlx::query_scalar!("SELECT name FROM action_plans WHERE id = $1", plan_id)
+        .fetch_one(&state.db)
+        .await?;
+    let markdown = action_plan::plan_markdown(&state.db, plan_id)
+        .await
+        .map_err(|err| anyhow::anyhow!("rendering the plan failed: {}", err))?;
+    let body = Bytes::from(serde_json::to_vec(&PlanPublished {
+        event: "plan.published",
+        plan_id,
+        plan_name,
+        markdown_path: format!("{}/api/plans/{}/markdown", state.config.base_path, plan_id),
+        markdown,
+        published_at: unix_now(),
+    })?);
+
+    let mut headers = vec![(header::CONTENT_TYPE, "application/json".to_string())];
+    if let Some(secret) = &state.config.plan_webhook_secret {
+        headers.push((
+            HeaderName::from_static(SIGNATURE_HEADER),
+            format!("sha256={}", signature(secret, &body)),
+        ));
+    }
+
+    let mut delays = RETRY_DELAYS.iter();
+    loop {
+        let outcome = http_client::send(
+            Method::POST,
+            url,
+            &headers,
+            body.clone(),
+            MAX_RESPONSE_BYTES,
+            TIMEOUT,
+        )
+        .await
+        .and_then(|(status, _)| {
+            if status.is_success() {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("{} answered with {}", url, status))
+            }
+        });
+        match (outcome, delays.next()) {
+            (Ok(()), _) => return Ok(()),
+            (Err(err), Some(delay)) => {
+                tracing::debug!(%plan_id, "Plan webhook failed, retrying: {:#}", err);
+                tokio::time::sleep(*delay).await;
+            }
+            (Err(err), None) => return Err(err),
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 of the body, so receivers can tell the call came from this instance.
+fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}